@@ -0,0 +1,426 @@
+// Aerodynamic forces used by the vehicle models.
+//
+// Drag and downforce grow with the dynamic pressure 0.5 rho v^2 and are split between the
+// axles by the aero balance. Without a map the coefficients are constant. An AeroMap scales
+// them and shifts the balance as functions of ride height and pitch, interpolated bilinearly
+// and held constant outside the map. DRS or any other active-aero device is described by a
+// deployment between 0 (closed) and 1 (open), which removes a fraction of the drag and
+// downforce and moves the balance. Every force is returned with its derivatives so models
+// can use them in their Jacobians.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Aero {
+    pub air_density: f64,      // [kg/m^3]
+    pub frontal_area: f64,     // Reference area [m^2]
+    pub drag_coefficient: f64, // Cd at the map reference [-]
+    pub lift_coefficient: f64, // Cl at the map reference, positive pressing down [-]
+    pub balance: f64,          // Share of the downforce on the front axle [-]
+    pub map: Option<AeroMap>,
+    pub drs: Option<Drs>,
+}
+
+// Ride height and pitch sensitivity on a rectangular grid. The tables are row major with one
+// row per ride height and one column per pitch.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AeroMap {
+    ride_heights: Vec<f64>,    // [m]
+    pitches: Vec<f64>,         // Positive nose down [rad]
+    drag_scale: Vec<f64>,      // Multiplier on Cd [-]
+    downforce_scale: Vec<f64>, // Multiplier on Cl [-]
+    balance_shift: Vec<f64>,   // Added to the balance [-]
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Drs {
+    pub drag_reduction: f64,      // Fraction of the drag removed when open [-]
+    pub downforce_reduction: f64, // Fraction of the downforce removed when open [-]
+    pub balance_shift: f64,       // Added to the balance when open [-]
+}
+
+// Operating point of the aerodynamic surfaces
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AeroState {
+    pub ride_height: f64, // [m]
+    pub pitch: f64,       // [rad]
+    pub drs: f64,         // Deployment, 0 closed and 1 open [-]
+}
+
+// Forces with derivatives with respect to [v, ride_height, pitch, drs]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AeroForces {
+    pub drag: f64,      // [N]
+    pub downforce: f64, // [N]
+    pub balance: f64,   // [-]
+    pub d_drag: [f64; 4],
+    pub d_downforce: [f64; 4],
+    pub d_balance: [f64; 4],
+}
+
+// AERO IMPLEMENTATION ++++++++++++++++++++++++++
+impl Aero {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(
+        air_density: f64,
+        frontal_area: f64,
+        drag_coefficient: f64,
+        lift_coefficient: f64,
+        balance: f64,
+    ) -> Self {
+        return Aero {
+            air_density,
+            frontal_area,
+            drag_coefficient,
+            lift_coefficient,
+            balance,
+            map: None,
+            drs: None,
+        };
+    }
+
+    // Drag and downforce coefficients and balance at an operating point, each with
+    // derivatives with respect to [ride_height, pitch, drs]
+    fn coefficients(&self, state: &AeroState) -> [(f64, [f64; 3]); 3] {
+        let (drag_scale, downforce_scale, balance_shift) = match &self.map {
+            Some(map) => (
+                map.interpolate(&map.drag_scale, state.ride_height, state.pitch),
+                map.interpolate(&map.downforce_scale, state.ride_height, state.pitch),
+                map.interpolate(&map.balance_shift, state.ride_height, state.pitch),
+            ),
+            None => ((1.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 0.0, 0.0)),
+        };
+        let drs: Drs = self.drs.unwrap_or(Drs {
+            drag_reduction: 0.0,
+            downforce_reduction: 0.0,
+            balance_shift: 0.0,
+        });
+
+        let drag_open: f64 = 1.0 - drs.drag_reduction * state.drs;
+        let downforce_open: f64 = 1.0 - drs.downforce_reduction * state.drs;
+        let cd: f64 = self.drag_coefficient;
+        let cl: f64 = self.lift_coefficient;
+        return [
+            (
+                cd * drag_scale.0 * drag_open,
+                [
+                    cd * drag_scale.1 * drag_open,
+                    cd * drag_scale.2 * drag_open,
+                    -cd * drag_scale.0 * drs.drag_reduction,
+                ],
+            ),
+            (
+                cl * downforce_scale.0 * downforce_open,
+                [
+                    cl * downforce_scale.1 * downforce_open,
+                    cl * downforce_scale.2 * downforce_open,
+                    -cl * downforce_scale.0 * drs.downforce_reduction,
+                ],
+            ),
+            (
+                self.balance + balance_shift.0 + drs.balance_shift * state.drs,
+                [balance_shift.1, balance_shift.2, drs.balance_shift],
+            ),
+        ];
+    }
+
+    // 0.5 rho Cd A at an operating point, such that the drag is this factor times v^2 [kg/m]
+    pub fn drag_factor(&self, state: &AeroState) -> f64 {
+        return 0.5 * self.air_density * self.frontal_area * self.coefficients(state)[0].0;
+    }
+
+    // 0.5 rho Cl A at an operating point, such that the downforce is this factor times v^2
+    // [kg/m]
+    pub fn downforce_factor(&self, state: &AeroState) -> f64 {
+        return 0.5 * self.air_density * self.frontal_area * self.coefficients(state)[1].0;
+    }
+
+    // Forces at speed v [m/s] and an operating point
+    #[allow(dead_code)]
+    pub fn forces(&self, v: f64, state: &AeroState) -> AeroForces {
+        let [(cd, dcd), (cl, dcl), (balance, dbalance)] = self.coefficients(state);
+        let q: f64 = 0.5 * self.air_density * self.frontal_area * v.powi(2);
+        let dq_dv: f64 = self.air_density * self.frontal_area * v;
+        return AeroForces {
+            drag: q * cd,
+            downforce: q * cl,
+            balance,
+            d_drag: [dq_dv * cd, q * dcd[0], q * dcd[1], q * dcd[2]],
+            d_downforce: [dq_dv * cl, q * dcl[0], q * dcl[1], q * dcl[2]],
+            d_balance: [0.0, dbalance[0], dbalance[1], dbalance[2]],
+        };
+    }
+}
+
+// AEROFORCES IMPLEMENTATION ++++++++++++++++++++++++++
+impl AeroForces {
+    // Downforce on the front axle [N] with derivatives with respect to
+    // [v, ride_height, pitch, drs]
+    #[allow(dead_code)]
+    pub fn front_downforce(&self) -> (f64, [f64; 4]) {
+        let mut d: [f64; 4] = [0.0; 4];
+        for (k, d_k) in d.iter_mut().enumerate() {
+            *d_k = self.d_downforce[k] * self.balance + self.downforce * self.d_balance[k];
+        }
+        return (self.downforce * self.balance, d);
+    }
+
+    // Downforce on the rear axle [N] with derivatives with respect to
+    // [v, ride_height, pitch, drs]
+    #[allow(dead_code)]
+    pub fn rear_downforce(&self) -> (f64, [f64; 4]) {
+        let (front, d_front) = self.front_downforce();
+        let mut d: [f64; 4] = [0.0; 4];
+        for (k, d_k) in d.iter_mut().enumerate() {
+            *d_k = self.d_downforce[k] - d_front[k];
+        }
+        return (self.downforce - front, d);
+    }
+}
+
+// AEROMAP IMPLEMENTATION ++++++++++++++++++++++++++
+impl AeroMap {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(
+        ride_heights: Vec<f64>,
+        pitches: Vec<f64>,
+        drag_scale: Vec<f64>,
+        downforce_scale: Vec<f64>,
+        balance_shift: Vec<f64>,
+    ) -> Result<Self, String> {
+        for (axis, values) in [("ride height", &ride_heights), ("pitch", &pitches)] {
+            if values.len() < 2 {
+                return Err(format!("the {} axis needs at least two points", axis));
+            }
+            if values.iter().any(|v| !v.is_finite()) || values.windows(2).any(|w| w[1] <= w[0]) {
+                return Err(format!(
+                    "the {} axis must be finite and strictly increasing",
+                    axis
+                ));
+            }
+        }
+        let n: usize = ride_heights.len() * pitches.len();
+        for (table, values) in [
+            ("drag scale", &drag_scale),
+            ("downforce scale", &downforce_scale),
+            ("balance shift", &balance_shift),
+        ] {
+            if values.len() != n {
+                return Err(format!(
+                    "the {} table has {} entries but the axes need {}",
+                    table,
+                    values.len(),
+                    n
+                ));
+            }
+            if values.iter().any(|v| !v.is_finite()) {
+                return Err(format!("the {} table has a non-finite entry", table));
+            }
+        }
+
+        return Ok(AeroMap {
+            ride_heights,
+            pitches,
+            drag_scale,
+            downforce_scale,
+            balance_shift,
+        });
+    }
+
+    // Bilinear interpolation of a table with derivatives with respect to ride height and
+    // pitch, clamped to the map edges
+    fn interpolate(&self, table: &[f64], ride_height: f64, pitch: f64) -> (f64, f64, f64) {
+        let (i, a, da) = locate(&self.ride_heights, ride_height);
+        let (j, b, db) = locate(&self.pitches, pitch);
+        let n: usize = self.pitches.len();
+        let (f00, f01) = (table[i * n + j], table[i * n + j + 1]);
+        let (f10, f11) = (table[(i + 1) * n + j], table[(i + 1) * n + j + 1]);
+
+        let value: f64 = (1.0 - a) * ((1.0 - b) * f00 + b * f01) + a * ((1.0 - b) * f10 + b * f11);
+        let d_ride_height: f64 = da * ((1.0 - b) * (f10 - f00) + b * (f11 - f01));
+        let d_pitch: f64 = db * ((1.0 - a) * (f01 - f00) + a * (f11 - f10));
+        return (value, d_ride_height, d_pitch);
+    }
+}
+
+// Interval index, interpolation weight and derivative of the weight for a query on an axis.
+// Queries outside the axis are clamped and have zero derivative.
+fn locate(axis: &[f64], value: f64) -> (usize, f64, f64) {
+    let last: usize = axis.len() - 1;
+    if value <= axis[0] {
+        return (0, 0.0, 0.0);
+    }
+    if value >= axis[last] {
+        return (last - 1, 1.0, 0.0);
+    }
+    let i: usize = axis.partition_point(|x| *x <= value) - 1;
+    let width: f64 = axis[i + 1] - axis[i];
+    return (i, (value - axis[i]) / width, 1.0 / width);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_aero() -> Aero {
+        let mut aero: Aero = Aero::new(1.2, 1.1, 1.0, 3.0, 0.45);
+        aero.map = Some(
+            AeroMap::new(
+                vec![0.02, 0.04, 0.06],
+                vec![-0.01, 0.01],
+                vec![1.05, 1.0, 1.0, 1.0, 0.98, 0.96],
+                vec![1.1, 1.2, 1.0, 1.05, 0.85, 0.9],
+                vec![-0.02, 0.03, 0.0, 0.04, 0.02, 0.05],
+            )
+            .unwrap(),
+        );
+        aero.drs = Some(Drs {
+            drag_reduction: 0.25,
+            downforce_reduction: 0.3,
+            balance_shift: 0.05,
+        });
+        return aero;
+    }
+
+    #[test]
+    fn test_constant_coefficients() {
+        let aero: Aero = Aero::new(1.2, 1.0, 0.8, 2.5, 0.4);
+        let forces: AeroForces = aero.forces(30.0, &AeroState::default());
+
+        // q = 0.5 * 1.2 * 900 = 540 Pa
+        assert!((forces.drag - 540.0 * 0.8).abs() < 1e-9);
+        assert!((forces.downforce - 540.0 * 2.5).abs() < 1e-9);
+        assert!((forces.front_downforce().0 - 0.4 * 1350.0).abs() < 1e-9);
+        assert!((forces.rear_downforce().0 - 0.6 * 1350.0).abs() < 1e-9);
+        assert!((aero.drag_factor(&AeroState::default()) - 0.48).abs() < 1e-12);
+        assert!((aero.downforce_factor(&AeroState::default()) - 1.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_map_interpolation() {
+        let aero: Aero = example_aero();
+
+        // Grid points are reproduced exactly
+        let state = AeroState {
+            ride_height: 0.04,
+            pitch: 0.01,
+            drs: 0.0,
+        };
+        let forces: AeroForces = aero.forces(10.0, &state);
+        assert!((forces.downforce - 66.0 * 3.0 * 1.05).abs() < 1e-9);
+        assert!((forces.balance - 0.49).abs() < 1e-12);
+
+        // Outside the map the edge values are held
+        let low = AeroState {
+            ride_height: 0.0,
+            pitch: -0.05,
+            drs: 0.0,
+        };
+        let edge = AeroState {
+            ride_height: 0.02,
+            pitch: -0.01,
+            drs: 0.0,
+        };
+        assert_eq!(
+            aero.forces(10.0, &low).downforce,
+            aero.forces(10.0, &edge).downforce
+        );
+        assert_eq!(aero.forces(10.0, &low).d_downforce[1], 0.0);
+    }
+
+    #[test]
+    fn test_drs_reduces_drag_and_downforce() {
+        let aero: Aero = example_aero();
+        let closed = AeroState {
+            ride_height: 0.03,
+            pitch: 0.0,
+            drs: 0.0,
+        };
+        let open = AeroState { drs: 1.0, ..closed };
+        let f_closed: AeroForces = aero.forces(80.0, &closed);
+        let f_open: AeroForces = aero.forces(80.0, &open);
+        assert!((f_open.drag - 0.75 * f_closed.drag).abs() < 1e-9);
+        assert!((f_open.downforce - 0.7 * f_closed.downforce).abs() < 1e-9);
+        assert!((f_open.balance - f_closed.balance - 0.05).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_derivatives_match_forces() {
+        let aero: Aero = example_aero();
+        let (v, state) = (
+            45.0,
+            AeroState {
+                ride_height: 0.033,
+                pitch: 0.004,
+                drs: 0.4,
+            },
+        );
+        let forces: AeroForces = aero.forces(v, &state);
+        let h: f64 = 1e-7;
+
+        for k in 0..4 {
+            let perturb = |sign: f64| {
+                let mut v_k: f64 = v;
+                let mut state_k: AeroState = state;
+                match k {
+                    0 => v_k += sign * h,
+                    1 => state_k.ride_height += sign * h,
+                    2 => state_k.pitch += sign * h,
+                    _ => state_k.drs += sign * h,
+                }
+                return aero.forces(v_k, &state_k);
+            };
+            let (f_p, f_m) = (perturb(1.0), perturb(-1.0));
+            let fd = |p: f64, m: f64| (p - m) / (2.0 * h);
+
+            let cases: [(f64, f64); 4] = [
+                (forces.d_drag[k], fd(f_p.drag, f_m.drag)),
+                (forces.d_downforce[k], fd(f_p.downforce, f_m.downforce)),
+                (forces.d_balance[k], fd(f_p.balance, f_m.balance)),
+                (
+                    forces.front_downforce().1[k],
+                    fd(f_p.front_downforce().0, f_m.front_downforce().0),
+                ),
+            ];
+            for (exact, approx) in cases {
+                assert!((exact - approx).abs() < 1e-4 * (1.0 + exact.abs()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_map_validation() {
+        let axis = vec![0.0, 1.0];
+        let table = vec![1.0; 4];
+        assert!(AeroMap::new(
+            vec![0.0],
+            axis.clone(),
+            table.clone(),
+            table.clone(),
+            table.clone()
+        )
+        .is_err());
+        assert!(AeroMap::new(
+            vec![1.0, 0.0],
+            axis.clone(),
+            table.clone(),
+            table.clone(),
+            table.clone()
+        )
+        .is_err());
+        assert!(AeroMap::new(
+            axis.clone(),
+            axis.clone(),
+            vec![1.0; 3],
+            table.clone(),
+            table.clone()
+        )
+        .is_err());
+        assert!(AeroMap::new(
+            axis.clone(),
+            axis.clone(),
+            table.clone(),
+            table.clone(),
+            table
+        )
+        .is_ok());
+    }
+}
@@ -1,17 +1,26 @@
+use model::metadata::{csv_header, csv_row};
 use simulation_toolbox::erk::ExplicitRK;
+use simulation_toolbox::Model;
 use std::fs::File;
 use std::io::Write;
 
+mod aero;
 mod model;
+mod nlp;
+mod ocp;
+mod powertrain;
 mod track;
+mod tyre;
+mod vehicle;
 
 fn main() {
     let solver: ExplicitRK = ExplicitRK::rk4();
-    let point_mass: model::point_mass::PointMass =
-        model::point_mass::PointMass::new("PointMass1", 1.0);
+    let vehicle: vehicle::Vehicle = vehicle::Vehicle::read_from_file("vehicles/point_mass.toml");
 
-    let x0: Vec<f64> = vec![0.0, 0.0, 0.0, 0.0]; // Initial state: [x, y, vx, vy]
-    let u: Vec<f64> = vec![1.0, 0.0]; // Control input: [Fx, Fy]
+    // Start at rest with a unit first input, Fx for the point mass, sized for any model
+    let x0: Vec<f64> = vec![0.0; vehicle.n_x()];
+    let mut u: Vec<f64> = vec![0.0; vehicle.n_u()];
+    u[0] = 1.0;
     let t0: f64 = 0.0;
     let tf: f64 = 10.0;
     let dt: f64 = 0.1;
@@ -19,14 +28,14 @@ fn main() {
     let mut t: f64 = t0;
 
     let mut file: File = File::create("sim_out.csv").unwrap();
-    writeln!(file, "time,x,y,vx,vy").unwrap();
+    writeln!(file, "{}", csv_header(&vehicle)).unwrap();
 
     while t < tf {
         // Update the state using the solver
-        x = solver.step(&point_mass, &x, &u, t, dt);
+        x = solver.step(&vehicle, &x, &u, t, dt);
         t += dt;
 
-        writeln!(file, "{},{},{},{},{}", t, x[0], x[1], x[2], x[3]).unwrap();
+        writeln!(file, "{}", csv_row(t, &x)).unwrap();
     }
 
     let test_track: track::Track =
@@ -1 +1,11 @@
+pub mod autodiff;
+pub mod curvilinear;
+pub mod double_track;
+pub mod dynamic_bicycle;
+pub mod electric;
+pub mod extended_point_mass;
+pub mod finite_difference;
+pub mod kinematic_bicycle;
+pub mod metadata;
 pub mod point_mass;
+pub mod tyre_states;
@@ -0,0 +1,645 @@
+use simulation_toolbox::Model;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+// Forward-mode automatic differentiation for vehicle models.
+//
+// A model writes its dynamics once, generically over a Scalar, and implements Model::fun by
+// evaluating them with f64 and Model::jac with jacobian(), which evaluates them with dual
+// numbers, seeding one column of the row-major nx * (nx + nu) Jacobian per pass. hessian()
+// uses hyper-dual numbers to give exact second derivatives of a weighted sum of the
+// dynamics, as needed for the Hessian of an optimiser's Lagrangian.
+//
+// Branches in the dynamics should test Scalar::value(), and the derivatives are those of the
+// branch taken.
+
+#[allow(dead_code)]
+pub trait Scalar:
+    Copy
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + Add<f64, Output = Self>
+    + Sub<f64, Output = Self>
+    + Mul<f64, Output = Self>
+    + Div<f64, Output = Self>
+{
+    fn constant(value: f64) -> Self;
+    fn value(&self) -> f64;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn tan(self) -> Self;
+    fn atan(self) -> Self;
+    fn exp(self) -> Self;
+    fn ln(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn tanh(self) -> Self;
+    fn powi(self, n: i32) -> Self;
+
+    fn abs(self) -> Self {
+        return if self.value() < 0.0 { -self } else { self };
+    }
+
+    // Four-quadrant arctangent of self / x. The derivatives come from whichever of atan(y / x)
+    // and -atan(x / y) is well conditioned, offset by a constant to the atan2 value.
+    fn atan2(self, x: Self) -> Self {
+        let core: Self = match x.value().abs() >= self.value().abs() {
+            true => (self / x).atan(),
+            false => -(x / self).atan(),
+        };
+        let offset: f64 = f64::atan2(self.value(), x.value()) - core.value();
+        return core + offset;
+    }
+}
+
+// Model with dynamics written generically over the scalar type
+pub trait AutoDiffModel: Model {
+    fn dynamics<S: Scalar>(&self, x: &[S], u: &[S], t: f64) -> Vec<S>;
+}
+
+// Dual number value + derivative * eps with eps^2 = 0
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dual {
+    pub value: f64,
+    pub derivative: f64,
+}
+
+// Hyper-dual number with two independent directions, eps1^2 = eps2^2 = 0, whose eps1 eps2
+// part carries the second derivative
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HyperDual {
+    pub value: f64,
+    pub d1: f64,
+    pub d2: f64,
+    pub d12: f64,
+}
+
+// Row-major Jacobian of the dynamics with respect to [x, u]
+pub fn jacobian<M: AutoDiffModel + ?Sized>(model: &M, x: &[f64], u: &[f64], t: f64) -> Vec<f64> {
+    return dual_jacobian(|x, u| model.dynamics(x, u, t), x, u, model.n_x());
+}
+
+// Row-major Jacobian of f(x, u) with n_rows outputs with respect to [x, u], for functions
+// other than model dynamics such as costs and constraints written over a Scalar
+pub fn dual_jacobian<F: Fn(&[Dual], &[Dual]) -> Vec<Dual>>(
+    f: F,
+    x: &[f64],
+    u: &[f64],
+    n_rows: usize,
+) -> Vec<f64> {
+    let nx: usize = x.len();
+    let n_cols: usize = nx + u.len();
+    let mut jac: Vec<f64> = vec![0.0; n_rows * n_cols];
+
+    for col in 0..n_cols {
+        let seed = |i: usize, v: f64| Dual {
+            value: v,
+            derivative: if i == col { 1.0 } else { 0.0 },
+        };
+        let x_d: Vec<Dual> = x.iter().enumerate().map(|(i, v)| seed(i, *v)).collect();
+        let u_d: Vec<Dual> = u
+            .iter()
+            .enumerate()
+            .map(|(i, v)| seed(nx + i, *v))
+            .collect();
+        for (row, value) in f(&x_d, &u_d).iter().enumerate() {
+            jac[row * n_cols + col] = value.derivative;
+        }
+    }
+    return jac;
+}
+
+// Row-major Hessian of sum_i weights[i] * dx_i with respect to [x, u], symmetric and of size
+// (nx + nu) * (nx + nu)
+#[allow(dead_code)]
+pub fn hessian<M: AutoDiffModel + ?Sized>(
+    model: &M,
+    x: &[f64],
+    u: &[f64],
+    t: f64,
+    weights: &[f64],
+) -> Vec<f64> {
+    let nx: usize = model.n_x();
+    let n_cols: usize = nx + model.n_u();
+    let mut hess: Vec<f64> = vec![0.0; n_cols * n_cols];
+
+    for i in 0..n_cols {
+        for j in i..n_cols {
+            let seed = |k: usize, v: f64| HyperDual {
+                value: v,
+                d1: if k == i { 1.0 } else { 0.0 },
+                d2: if k == j { 1.0 } else { 0.0 },
+                d12: 0.0,
+            };
+            let x_h: Vec<HyperDual> = x.iter().enumerate().map(|(k, v)| seed(k, *v)).collect();
+            let u_h: Vec<HyperDual> = u
+                .iter()
+                .enumerate()
+                .map(|(k, v)| seed(nx + k, *v))
+                .collect();
+            let dx: Vec<HyperDual> = model.dynamics(&x_h, &u_h, t);
+            let value: f64 = dx.iter().zip(weights.iter()).map(|(d, w)| w * d.d12).sum();
+            hess[i * n_cols + j] = value;
+            hess[j * n_cols + i] = value;
+        }
+    }
+    return hess;
+}
+
+// F64 IMPLEMENTATION ++++++++++++++++++++++++++
+impl Scalar for f64 {
+    fn constant(value: f64) -> Self {
+        return value;
+    }
+
+    fn value(&self) -> f64 {
+        return *self;
+    }
+
+    fn sin(self) -> Self {
+        return f64::sin(self);
+    }
+
+    fn cos(self) -> Self {
+        return f64::cos(self);
+    }
+
+    fn tan(self) -> Self {
+        return f64::tan(self);
+    }
+
+    fn atan(self) -> Self {
+        return f64::atan(self);
+    }
+
+    fn exp(self) -> Self {
+        return f64::exp(self);
+    }
+
+    fn ln(self) -> Self {
+        return f64::ln(self);
+    }
+
+    fn sqrt(self) -> Self {
+        return f64::sqrt(self);
+    }
+
+    fn tanh(self) -> Self {
+        return f64::tanh(self);
+    }
+
+    fn powi(self, n: i32) -> Self {
+        return f64::powi(self, n);
+    }
+
+    fn abs(self) -> Self {
+        return f64::abs(self);
+    }
+
+    fn atan2(self, x: Self) -> Self {
+        return f64::atan2(self, x);
+    }
+}
+
+// DUAL IMPLEMENTATION ++++++++++++++++++++++++++
+impl Dual {
+    // f(self) given f(v) and f'(v)
+    #[allow(dead_code)]
+    fn chain(self, f: f64, df: f64) -> Self {
+        return Dual {
+            value: f,
+            derivative: df * self.derivative,
+        };
+    }
+}
+
+impl Scalar for Dual {
+    fn constant(value: f64) -> Self {
+        return Dual {
+            value,
+            derivative: 0.0,
+        };
+    }
+
+    fn value(&self) -> f64 {
+        return self.value;
+    }
+
+    fn sin(self) -> Self {
+        return self.chain(self.value.sin(), self.value.cos());
+    }
+
+    fn cos(self) -> Self {
+        return self.chain(self.value.cos(), -self.value.sin());
+    }
+
+    fn tan(self) -> Self {
+        let t: f64 = self.value.tan();
+        return self.chain(t, 1.0 + t * t);
+    }
+
+    fn atan(self) -> Self {
+        return self.chain(self.value.atan(), 1.0 / (1.0 + self.value.powi(2)));
+    }
+
+    fn exp(self) -> Self {
+        let e: f64 = self.value.exp();
+        return self.chain(e, e);
+    }
+
+    fn ln(self) -> Self {
+        return self.chain(self.value.ln(), 1.0 / self.value);
+    }
+
+    fn sqrt(self) -> Self {
+        let s: f64 = self.value.sqrt();
+        return self.chain(s, 0.5 / s);
+    }
+
+    fn tanh(self) -> Self {
+        let t: f64 = self.value.tanh();
+        return self.chain(t, 1.0 - t * t);
+    }
+
+    fn powi(self, n: i32) -> Self {
+        if n == 0 {
+            return Dual::constant(1.0);
+        }
+        return self.chain(self.value.powi(n), n as f64 * self.value.powi(n - 1));
+    }
+}
+
+impl Add for Dual {
+    type Output = Dual;
+    fn add(self, rhs: Dual) -> Dual {
+        return Dual {
+            value: self.value + rhs.value,
+            derivative: self.derivative + rhs.derivative,
+        };
+    }
+}
+
+impl Sub for Dual {
+    type Output = Dual;
+    fn sub(self, rhs: Dual) -> Dual {
+        return Dual {
+            value: self.value - rhs.value,
+            derivative: self.derivative - rhs.derivative,
+        };
+    }
+}
+
+impl Mul for Dual {
+    type Output = Dual;
+    fn mul(self, rhs: Dual) -> Dual {
+        return Dual {
+            value: self.value * rhs.value,
+            derivative: self.derivative * rhs.value + self.value * rhs.derivative,
+        };
+    }
+}
+
+impl Div for Dual {
+    type Output = Dual;
+    fn div(self, rhs: Dual) -> Dual {
+        return Dual {
+            value: self.value / rhs.value,
+            derivative: (self.derivative * rhs.value - self.value * rhs.derivative)
+                / rhs.value.powi(2),
+        };
+    }
+}
+
+impl Neg for Dual {
+    type Output = Dual;
+    fn neg(self) -> Dual {
+        return Dual {
+            value: -self.value,
+            derivative: -self.derivative,
+        };
+    }
+}
+
+impl Add<f64> for Dual {
+    type Output = Dual;
+    fn add(self, rhs: f64) -> Dual {
+        return self + Dual::constant(rhs);
+    }
+}
+
+impl Sub<f64> for Dual {
+    type Output = Dual;
+    fn sub(self, rhs: f64) -> Dual {
+        return self - Dual::constant(rhs);
+    }
+}
+
+impl Mul<f64> for Dual {
+    type Output = Dual;
+    fn mul(self, rhs: f64) -> Dual {
+        return Dual {
+            value: self.value * rhs,
+            derivative: self.derivative * rhs,
+        };
+    }
+}
+
+impl Div<f64> for Dual {
+    type Output = Dual;
+    fn div(self, rhs: f64) -> Dual {
+        return Dual {
+            value: self.value / rhs,
+            derivative: self.derivative / rhs,
+        };
+    }
+}
+
+// HYPERDUAL IMPLEMENTATION ++++++++++++++++++++++++++
+impl HyperDual {
+    // f(self) given f(v), f'(v) and f''(v)
+    fn chain(self, f: f64, df: f64, d2f: f64) -> Self {
+        return HyperDual {
+            value: f,
+            d1: df * self.d1,
+            d2: df * self.d2,
+            d12: df * self.d12 + d2f * self.d1 * self.d2,
+        };
+    }
+
+    fn recip(self) -> Self {
+        let v: f64 = self.value;
+        return self.chain(1.0 / v, -1.0 / v.powi(2), 2.0 / v.powi(3));
+    }
+}
+
+impl Scalar for HyperDual {
+    fn constant(value: f64) -> Self {
+        return HyperDual {
+            value,
+            d1: 0.0,
+            d2: 0.0,
+            d12: 0.0,
+        };
+    }
+
+    fn value(&self) -> f64 {
+        return self.value;
+    }
+
+    fn sin(self) -> Self {
+        let (s, c) = self.value.sin_cos();
+        return self.chain(s, c, -s);
+    }
+
+    fn cos(self) -> Self {
+        let (s, c) = self.value.sin_cos();
+        return self.chain(c, -s, -c);
+    }
+
+    fn tan(self) -> Self {
+        let t: f64 = self.value.tan();
+        let dt: f64 = 1.0 + t * t;
+        return self.chain(t, dt, 2.0 * t * dt);
+    }
+
+    fn atan(self) -> Self {
+        let v: f64 = self.value;
+        let d: f64 = 1.0 / (1.0 + v * v);
+        return self.chain(v.atan(), d, -2.0 * v * d * d);
+    }
+
+    fn exp(self) -> Self {
+        let e: f64 = self.value.exp();
+        return self.chain(e, e, e);
+    }
+
+    fn ln(self) -> Self {
+        let v: f64 = self.value;
+        return self.chain(v.ln(), 1.0 / v, -1.0 / v.powi(2));
+    }
+
+    fn sqrt(self) -> Self {
+        let s: f64 = self.value.sqrt();
+        return self.chain(s, 0.5 / s, -0.25 / (s * self.value));
+    }
+
+    fn tanh(self) -> Self {
+        let t: f64 = self.value.tanh();
+        let dt: f64 = 1.0 - t * t;
+        return self.chain(t, dt, -2.0 * t * dt);
+    }
+
+    fn powi(self, n: i32) -> Self {
+        if n == 0 {
+            return HyperDual::constant(1.0);
+        }
+        let v: f64 = self.value;
+        let n_f: f64 = n as f64;
+        return self.chain(
+            v.powi(n),
+            n_f * v.powi(n - 1),
+            n_f * (n_f - 1.0) * v.powi(n - 2),
+        );
+    }
+}
+
+impl Add for HyperDual {
+    type Output = HyperDual;
+    fn add(self, rhs: HyperDual) -> HyperDual {
+        return HyperDual {
+            value: self.value + rhs.value,
+            d1: self.d1 + rhs.d1,
+            d2: self.d2 + rhs.d2,
+            d12: self.d12 + rhs.d12,
+        };
+    }
+}
+
+impl Sub for HyperDual {
+    type Output = HyperDual;
+    fn sub(self, rhs: HyperDual) -> HyperDual {
+        return self + -rhs;
+    }
+}
+
+impl Mul for HyperDual {
+    type Output = HyperDual;
+    fn mul(self, rhs: HyperDual) -> HyperDual {
+        return HyperDual {
+            value: self.value * rhs.value,
+            d1: self.d1 * rhs.value + self.value * rhs.d1,
+            d2: self.d2 * rhs.value + self.value * rhs.d2,
+            d12: self.d12 * rhs.value + self.d1 * rhs.d2 + self.d2 * rhs.d1 + self.value * rhs.d12,
+        };
+    }
+}
+
+impl Div for HyperDual {
+    type Output = HyperDual;
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: HyperDual) -> HyperDual {
+        return self * rhs.recip();
+    }
+}
+
+impl Neg for HyperDual {
+    type Output = HyperDual;
+    fn neg(self) -> HyperDual {
+        return HyperDual {
+            value: -self.value,
+            d1: -self.d1,
+            d2: -self.d2,
+            d12: -self.d12,
+        };
+    }
+}
+
+impl Add<f64> for HyperDual {
+    type Output = HyperDual;
+    fn add(self, rhs: f64) -> HyperDual {
+        return HyperDual {
+            value: self.value + rhs,
+            ..self
+        };
+    }
+}
+
+impl Sub<f64> for HyperDual {
+    type Output = HyperDual;
+    fn sub(self, rhs: f64) -> HyperDual {
+        return HyperDual {
+            value: self.value - rhs,
+            ..self
+        };
+    }
+}
+
+impl Mul<f64> for HyperDual {
+    type Output = HyperDual;
+    fn mul(self, rhs: f64) -> HyperDual {
+        return HyperDual {
+            value: self.value * rhs,
+            d1: self.d1 * rhs,
+            d2: self.d2 * rhs,
+            d12: self.d12 * rhs,
+        };
+    }
+}
+
+impl Div<f64> for HyperDual {
+    type Output = HyperDual;
+    fn div(self, rhs: f64) -> HyperDual {
+        return self * (1.0 / rhs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::finite_difference::assert_jacobian;
+
+    // Nonlinear test model exercising the elementary functions
+    struct TestModel;
+
+    impl AutoDiffModel for TestModel {
+        fn dynamics<S: Scalar>(&self, x: &[S], u: &[S], _t: f64) -> Vec<S> {
+            return vec![
+                x[0].sin() * x[1].exp() + u[0].tanh() * x[2],
+                x[1].atan2(x[0]) + (x[0] * x[0] + 1.0).sqrt() / (x[1].cos() + 2.0),
+                (x[0].powi(3) - u[0]).abs() + (x[1] * 0.5).tan() * (x[2] + 3.0).ln() - x[0].atan(),
+            ];
+        }
+    }
+
+    impl Model for TestModel {
+        fn name(&self) -> &str {
+            return "TestModel";
+        }
+
+        fn n_x(&self) -> usize {
+            return 3;
+        }
+
+        fn n_u(&self) -> usize {
+            return 1;
+        }
+
+        fn fun(&self, x: &Vec<f64>, u: &Vec<f64>, t: f64) -> Vec<f64> {
+            return self.dynamics(x, u, t);
+        }
+
+        fn jac(&self, x: &Vec<f64>, u: &Vec<f64>, t: f64) -> Vec<f64> {
+            return jacobian(self, x, u, t);
+        }
+    }
+
+    #[test]
+    fn test_dual_arithmetic() {
+        // d/dx (x^2 + 3) / x at x = 2 is 1 - 3 / x^2 = 0.25
+        let x: Dual = Dual {
+            value: 2.0,
+            derivative: 1.0,
+        };
+        let y: Dual = (x * x + 3.0) / x;
+        assert_eq!(y.value, 3.5);
+        assert_eq!(y.derivative, 0.25);
+    }
+
+    #[test]
+    fn test_jacobian_matches_finite_differences() {
+        for (x, u) in [
+            (vec![0.3, -0.7, 1.0], vec![0.4]),
+            (vec![-1.2, 0.5, -0.5], vec![-2.0]),
+            (vec![0.1, 1.9, 2.0], vec![0.0]),
+        ] {
+            assert_jacobian(&TestModel, &x, &u, 0.0, 1e-7);
+        }
+    }
+
+    #[test]
+    fn test_atan2_quadrants() {
+        // Values match f64::atan2 in every quadrant and the derivatives are continuous
+        for (y, x) in [
+            (1.0, 2.0),
+            (2.0, -1.0),
+            (-1.0, -2.0),
+            (-2.0, 1.0),
+            (1.0, 0.0),
+        ] {
+            let yd: Dual = Dual {
+                value: y,
+                derivative: 1.0,
+            };
+            let xd: Dual = Dual::constant(x);
+            let a: Dual = yd.atan2(xd);
+            assert!((a.value - f64::atan2(y, x)).abs() < 1e-15);
+            assert!((a.derivative - x / (x * x + y * y)).abs() < 1e-15);
+        }
+    }
+
+    #[test]
+    fn test_hessian_matches_jacobian_differences() {
+        let (x, u) = (vec![0.3, -0.7, 1.0], vec![0.4]);
+        let weights: Vec<f64> = vec![1.0, -2.0, 0.5];
+        let hess: Vec<f64> = hessian(&TestModel, &x, &u, 0.0, &weights);
+
+        // Central differences of the weighted Jacobian rows
+        let z: Vec<f64> = vec![x[0], x[1], x[2], u[0]];
+        let h: f64 = 1e-6;
+        for j in 0..4 {
+            let (mut z_p, mut z_m) = (z.clone(), z.clone());
+            z_p[j] += h;
+            z_m[j] -= h;
+            let jac_p: Vec<f64> = jacobian(&TestModel, &z_p[..3], &z_p[3..], 0.0);
+            let jac_m: Vec<f64> = jacobian(&TestModel, &z_m[..3], &z_m[3..], 0.0);
+            for i in 0..4 {
+                let fd: f64 = (0..3)
+                    .map(|row| weights[row] * (jac_p[row * 4 + i] - jac_m[row * 4 + i]))
+                    .sum::<f64>()
+                    / (2.0 * h);
+                assert!((hess[i * 4 + j] - fd).abs() < 1e-6 * (1.0 + fd.abs()));
+            }
+        }
+    }
+}
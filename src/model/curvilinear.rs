@@ -0,0 +1,222 @@
+use crate::track::{Track, TrackFrame};
+use simulation_toolbox::Model;
+
+// Spatial-domain wrapper around a time-domain vehicle model, with the distance s along the
+// track centreline as the independent variable. The wrapped model must have the global pose
+// [x, y, psi] as its first three states and dynamics that are invariant to translations and
+// rotations of the global frame, as for the bicycle and double-track models. The pose is
+// replaced by the lateral offset n (positive to the left) and the heading relative to the
+// centreline xi, and time becomes a state, giving the states [t, n, xi, rest...] where rest
+// are the remaining states of the wrapped model. The inputs are unchanged and the "time"
+// argument of fun and jac is s.
+//
+// With the wrapped model evaluated in the local track frame, its first two derivatives are
+// the velocity along (v_t) and across (v_n) the centreline and
+//     ds/dt = v_t / (1 - n kappa(s)),
+// so every time derivative is divided by ds/dt and the heading loses the track rotation,
+//     dxi/ds = (dpsi/dt) / (ds/dt) - kappa(s).
+// The model is singular where v_t = 0 or n kappa = 1.
+pub struct Curvilinear<'a, M: Model> {
+    // Properties
+    name: String,
+    // Parameters
+    pub model: M,
+    pub track: &'a Track,
+}
+
+impl<'a, M: Model> Curvilinear<'a, M> {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(name: &str, model: M, track: &'a Track) -> Self {
+        return Curvilinear {
+            name: name.to_string(),
+            model,
+            track,
+        };
+    }
+
+    // State of the wrapped model in the local track frame, [0, 0, xi, rest...]
+    fn local_state(&self, x: &[f64]) -> Vec<f64> {
+        let mut x_local: Vec<f64> = x.to_vec();
+        x_local[0] = 0.0;
+        x_local[1] = 0.0;
+        return x_local;
+    }
+
+    // Distance s within the lap, wrapped on closed tracks and clamped on open ones
+    fn lap_distance(&self, s: f64) -> f64 {
+        return match self.track.is_closed() {
+            true => s.rem_euclid(self.track.length()),
+            false => s.clamp(0.0, self.track.length()),
+        };
+    }
+
+    // Global pose (x, y, psi) for the curvilinear state x at distance s
+    #[allow(dead_code)]
+    pub fn global_pose(&self, s: f64, x: &[f64]) -> (f64, f64, f64) {
+        let frame: &TrackFrame = &self.track.discretise(vec![self.lap_distance(s)])[0];
+        let (px, py) = frame.position();
+        let (lx, ly) = frame.lateral();
+        let (tx, ty) = frame.tangent();
+        return (px + x[1] * lx, py + x[1] * ly, f64::atan2(ty, tx) + x[2]);
+    }
+
+    // Curvilinear state for the global pose (x, y, psi) of a wrapped model state at time t,
+    // given the distance s of the closest centreline point
+    #[allow(dead_code)]
+    pub fn curvilinear_state(&self, s: f64, t: f64, x_global: &[f64]) -> Vec<f64> {
+        let frame: &TrackFrame = &self.track.discretise(vec![self.lap_distance(s)])[0];
+        let (px, py) = frame.position();
+        let (lx, ly) = frame.lateral();
+        let (tx, ty) = frame.tangent();
+
+        let mut x: Vec<f64> = x_global.to_vec();
+        x[0] = t;
+        x[1] = (x_global[0] - px) * lx + (x_global[1] - py) * ly;
+        let xi: f64 = x_global[2] - f64::atan2(ty, tx);
+        x[2] = f64::atan2(xi.sin(), xi.cos());
+        return x;
+    }
+}
+
+impl<M: Model> Model for Curvilinear<'_, M> {
+    fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    fn n_x(&self) -> usize {
+        return self.model.n_x(); // [t, n, xi, rest...]
+    }
+
+    fn n_u(&self) -> usize {
+        return self.model.n_u();
+    }
+
+    fn fun(&self, x: &Vec<f64>, u: &Vec<f64>, s: f64) -> Vec<f64> {
+        let kappa: f64 = self.track.curvature(s);
+        let dz: Vec<f64> = self.model.fun(&self.local_state(x), u, 0.0);
+
+        // dt/ds = (1 - n kappa) / v_t
+        let dt_ds: f64 = (1.0 - x[1] * kappa) / dz[0];
+
+        let mut dx: Vec<f64> = dz.iter().map(|dz_i| dz_i * dt_ds).collect();
+        dx[0] = dt_ds;
+        dx[2] -= kappa;
+        return dx;
+    }
+
+    fn jac(&self, x: &Vec<f64>, u: &Vec<f64>, s: f64) -> Vec<f64> {
+        let nx = self.n_x();
+        let nu = self.n_u();
+        let n_cols: usize = nx + nu;
+        let mut jac: Vec<f64> = vec![0.0; nx * n_cols];
+
+        let kappa: f64 = self.track.curvature(s);
+        let x_local: Vec<f64> = self.local_state(x);
+        let dz: Vec<f64> = self.model.fun(&x_local, u, 0.0);
+        let jac_z: Vec<f64> = self.model.jac(&x_local, u, 0.0);
+
+        // q = dt/ds = (1 - n kappa) / v_t and its derivatives
+        let a: f64 = 1.0 - x[1] * kappa;
+        let q: f64 = a / dz[0];
+        let dq_dn: f64 = -kappa / dz[0];
+        let mut dq: Vec<f64> = (0..n_cols).map(|j| -a / dz[0].powi(2) * jac_z[j]).collect();
+        // The wrapped model does not depend on its position, which is fixed at the origin
+        dq[0] = 0.0;
+        dq[1] = dq_dn;
+
+        // Jacobian is expected to be row major
+        jac[..n_cols].copy_from_slice(&dq); // dx0/dxj
+        for (i, dz_i) in dz.iter().enumerate().skip(1) {
+            let row: usize = i * n_cols;
+            for j in 2..n_cols {
+                jac[row + j] = jac_z[row + j] * q + dz_i * dq[j]; // dxi/dxj
+            }
+            jac[row + 1] = dz_i * dq_dn; // dxi/dx1
+        }
+
+        return jac;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::dynamic_bicycle::tests::saturating_model;
+    use crate::model::finite_difference::assert_jacobian;
+    use crate::model::kinematic_bicycle::KinematicBicycle;
+
+    #[test]
+    fn test_curvilinear_straight() {
+        // On a straight the spatial derivatives are the time derivatives divided by v_t
+        let track: Track = Track::straight(200.0, 6.0);
+        let model = Curvilinear::new("Spatial", saturating_model(), &track);
+
+        let x: Vec<f64> = vec![1.0, 0.5, 0.02, 20.0, 0.3, 0.1];
+        let u: Vec<f64> = vec![0.03, 1000.0];
+        let dx = model.fun(&x, &u, 50.0);
+        let dz = saturating_model().fun(&vec![0.0, 0.0, 0.02, 20.0, 0.3, 0.1], &u, 0.0);
+        assert!((dx[0] - 1.0 / dz[0]).abs() < 1e-12);
+        for (dx_i, dz_i) in dx.iter().zip(dz.iter()).skip(1) {
+            assert!((dx_i - dz_i / dz[0]).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_curvilinear_steady_cornering() {
+        // Kinematic bicycle following the centreline of a circle with v_t = v and a yaw
+        // rate equal to v kappa, so n and xi stay constant
+        let track: Track = Track::circle(50.0, 6.0);
+        let (wheelbase, rear_to_cg) = (2.5, 1.0);
+        let model = Curvilinear::new(
+            "Spatial",
+            KinematicBicycle::new("Kinematic", wheelbase, rear_to_cg),
+            &track,
+        );
+
+        // With the CG on the centreline the course angle equals the track direction
+        let kappa: f64 = track.curvature(10.0);
+        let beta: f64 = f64::asin(kappa * rear_to_cg);
+        let delta: f64 = f64::atan(f64::tan(beta) * wheelbase / rear_to_cg);
+        let x: Vec<f64> = vec![0.0, 0.0, -beta, 15.0];
+        let dx = model.fun(&x, &vec![delta, 0.0], 10.0);
+        assert!((dx[0] - 1.0 / 15.0).abs() < 1e-12);
+        assert!(dx[1].abs() < 1e-12);
+        assert!(dx[2].abs() < 1e-12);
+        assert!(dx[3].abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_curvilinear_global_pose() {
+        let track: Track = Track::circle(50.0, 6.0);
+        let model = Curvilinear::new("Spatial", saturating_model(), &track);
+
+        // A quarter lap in, one metre to the left of the centreline and heading along it
+        let s: f64 = track.length() / 4.0;
+        let (x, y, psi) = model.global_pose(s, &[0.0, 1.0, 0.0, 20.0, 0.0, 0.0]);
+        assert!(x.abs() < 0.1);
+        assert!((y - 49.0).abs() < 0.1);
+        assert!((psi - std::f64::consts::PI).abs() < 1e-2);
+
+        // Converting back gives the same offset and heading
+        let x_curv: Vec<f64> = model.curvilinear_state(s, 3.0, &[x, y, psi, 20.0, 0.0, 0.0]);
+        assert_eq!(x_curv[0], 3.0);
+        assert!((x_curv[1] - 1.0).abs() < 1e-9);
+        assert!(x_curv[2].abs() < 1e-9);
+
+        // A lap later the distance wraps to the same point
+        let x_next: Vec<f64> =
+            model.curvilinear_state(s + track.length(), 3.0, &[x, y, psi, 20.0, 0.0, 0.0]);
+        assert!((x_next[1] - x_curv[1]).abs() < 1e-9);
+        assert!((x_next[2] - x_curv[2]).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_curvilinear_jacobian_matches_fun() {
+        let track: Track = Track::circle(50.0, 6.0);
+        let model = Curvilinear::new("Spatial", saturating_model(), &track);
+        let x: Vec<f64> = vec![4.0, 1.5, 0.05, 18.0, 0.4, 0.3];
+        let u: Vec<f64> = vec![0.06, 800.0];
+        let s: f64 = 37.0;
+        assert_jacobian(&model, &x, &u, s, 1e-6);
+    }
+}
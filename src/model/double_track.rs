@@ -0,0 +1,557 @@
+use crate::model::tyre_states::GripScaled;
+use crate::tyre::TyreLaw;
+use simulation_toolbox::Model;
+
+// Four-wheel vehicle model with quasi-static load transfer. The body has the same planar
+// states as the dynamic bicycle, but every wheel has its own slip angle and normal load.
+// Longitudinal and lateral load transfer follow from the accelerations a_x = F_x / m and
+// a_y = v_x r, which keeps the loads explicit in the state. The lateral transfer is split
+// between the axles by the front roll-stiffness share. Axle torques are divided equally
+// between the left and right wheel as with an open differential, and wheel slip is not
+// modelled, so the longitudinal tyre force is the wheel torque divided by the wheel radius.
+// Beyond wheel lift the load transfer would make a load negative, so every load is blended
+// smoothly into MIN_WHEEL_LOAD and a lifted wheel keeps a small load instead of producing
+// grip from a negative one.
+// The grip multiplier of GripScaled scales all four lateral tyre forces.
+pub struct DoubleTrack {
+    // Properties
+    name: String,
+    // Parameters
+    pub parameters: DoubleTrackParameters,
+    pub front_tyre: Box<dyn TyreLaw>, // Tyre on each front wheel
+    pub rear_tyre: Box<dyn TyreLaw>,  // Tyre on each rear wheel
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DoubleTrackParameters {
+    pub mass: f64,             // Vehicle mass [kg]
+    pub yaw_inertia: f64,      // Moment of inertia about the vertical axis [kg m^2]
+    pub front_to_cg: f64,      // Distance from front axle to centre of gravity [m]
+    pub rear_to_cg: f64,       // Distance from rear axle to centre of gravity [m]
+    pub front_track: f64,      // Distance between the front wheels [m]
+    pub rear_track: f64,       // Distance between the rear wheels [m]
+    pub cg_height: f64,        // Height of the centre of gravity [m]
+    pub front_roll_share: f64, // Front share of the total roll stiffness [-]
+    pub wheel_radius: f64,     // Loaded wheel radius [m]
+}
+
+const GRAVITY: f64 = 9.81; // [m/s^2]
+const N_COLS: usize = 9; // Columns of the Jacobian, [x, y, psi, vx, vy, r, delta, Tf, Tr]
+const MIN_WHEEL_LOAD: f64 = 10.0; // Load of a lifted wheel [N]
+const LOAD_BLEND: f64 = 50.0; // Half-width of the blend into the minimum load [N]
+
+// Wheels in the order front left, front right, rear left, rear right
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Wheel {
+    FrontLeft,
+    FrontRight,
+    RearLeft,
+    RearRight,
+}
+
+const WHEELS: [Wheel; 4] = [
+    Wheel::FrontLeft,
+    Wheel::FrontRight,
+    Wheel::RearLeft,
+    Wheel::RearRight,
+];
+
+impl Wheel {
+    fn is_front(&self) -> bool {
+        return matches!(self, Wheel::FrontLeft | Wheel::FrontRight);
+    }
+
+    // +1 for the left wheels, which lie on the positive body y side
+    fn side(&self) -> f64 {
+        return match self {
+            Wheel::FrontLeft | Wheel::RearLeft => 1.0,
+            Wheel::FrontRight | Wheel::RearRight => -1.0,
+        };
+    }
+}
+
+impl DoubleTrack {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(
+        name: &str,
+        parameters: DoubleTrackParameters,
+        front_tyre: Box<dyn TyreLaw>,
+        rear_tyre: Box<dyn TyreLaw>,
+    ) -> Self {
+        return DoubleTrack {
+            name: name.to_string(),
+            parameters,
+            front_tyre,
+            rear_tyre,
+        };
+    }
+
+    // Position of the wheel contact patch relative to the CG in the body frame [m]
+    fn wheel_position(&self, wheel: Wheel) -> (f64, f64) {
+        let p: &DoubleTrackParameters = &self.parameters;
+        return if wheel.is_front() {
+            (p.front_to_cg, wheel.side() * p.front_track / 2.0)
+        } else {
+            (-p.rear_to_cg, wheel.side() * p.rear_track / 2.0)
+        };
+    }
+
+    fn tyre(&self, wheel: Wheel) -> &dyn TyreLaw {
+        return if wheel.is_front() {
+            self.front_tyre.as_ref()
+        } else {
+            self.rear_tyre.as_ref()
+        };
+    }
+
+    // Normal load on each wheel [N] and its derivatives with respect to [vx, r, Tf, Tr]
+    fn loads(&self, x: &[f64], u: &[f64]) -> [(f64, [f64; 4]); 4] {
+        let p: &DoubleTrackParameters = &self.parameters;
+        let wheelbase: f64 = p.front_to_cg + p.rear_to_cg;
+        let weight: f64 = p.mass * GRAVITY;
+        let vx: f64 = x[3];
+        let r: f64 = x[5];
+
+        // Quasi-static lateral acceleration
+        let ay: f64 = vx * r;
+
+        // Longitudinal transfer per wheel m a_x h / (2 L), positive towards the rear
+        let k_long: f64 = p.cg_height / (2.0 * wheelbase * p.wheel_radius);
+        let long: f64 = k_long * (u[1] + u[2]);
+
+        // Lateral transfer per axle, positive towards the right wheels
+        let k_lat_f: f64 = p.front_roll_share * p.mass * p.cg_height / p.front_track;
+        let k_lat_r: f64 = (1.0 - p.front_roll_share) * p.mass * p.cg_height / p.rear_track;
+
+        let mut loads: [(f64, [f64; 4]); 4] = [(0.0, [0.0; 4]); 4];
+        for (i, wheel) in WHEELS.iter().enumerate() {
+            let (static_load, long_sign, k_lat) = if wheel.is_front() {
+                (weight * p.rear_to_cg / (2.0 * wheelbase), -1.0, k_lat_f)
+            } else {
+                (weight * p.front_to_cg / (2.0 * wheelbase), 1.0, k_lat_r)
+            };
+            let lat_sign: f64 = -wheel.side();
+            let (fz, dfz_dload) =
+                clamp_load(static_load + long_sign * long + lat_sign * k_lat * ay);
+            loads[i] = (
+                fz,
+                [
+                    dfz_dload * lat_sign * k_lat * r,
+                    dfz_dload * lat_sign * k_lat * vx,
+                    dfz_dload * long_sign * k_long,
+                    dfz_dload * long_sign * k_long,
+                ],
+            );
+        }
+        return loads;
+    }
+
+    // Normal load on each wheel [N] in the order FL, FR, RL, RR
+    #[allow(dead_code)]
+    pub fn wheel_loads(&self, x: &[f64], u: &[f64]) -> [f64; 4] {
+        return self.loads(x, u).map(|(fz, _)| fz);
+    }
+
+    // Slip angle of each wheel [rad] in the order FL, FR, RL, RR
+    #[allow(dead_code)]
+    pub fn slip_angles(&self, x: &[f64], delta: f64) -> [f64; 4] {
+        return WHEELS.map(|wheel| self.slip_angle(wheel, x, delta).0);
+    }
+
+    // Slip angle of one wheel and its derivatives with respect to [vx, vy, r]
+    fn slip_angle(&self, wheel: Wheel, x: &[f64], delta: f64) -> (f64, [f64; 3]) {
+        let (px, py) = self.wheel_position(wheel);
+        let vx_w: f64 = x[3] - x[5] * py;
+        let vy_w: f64 = x[4] + x[5] * px;
+        let den: f64 = vx_w.powi(2) + vy_w.powi(2);
+
+        // d atan2(vy_w, vx_w) = (vx_w dvy_w - vy_w dvx_w) / den
+        let datan: [f64; 3] = [-vy_w / den, vx_w / den, (vx_w * px + vy_w * py) / den];
+        let steer: f64 = if wheel.is_front() { delta } else { 0.0 };
+        return (steer - f64::atan2(vy_w, vx_w), datan.map(|d| -d));
+    }
+
+    // Body frame forces of one wheel and their derivatives with respect to all states and
+    // inputs, given the wheel load with its derivatives and the grip multiplier
+    fn wheel_forces(
+        &self,
+        wheel: Wheel,
+        x: &[f64],
+        u: &[f64],
+        load: &(f64, [f64; 4]),
+        grip: f64,
+    ) -> (f64, f64, [f64; N_COLS], [f64; N_COLS]) {
+        let p: &DoubleTrackParameters = &self.parameters;
+        let (fz, dfz) = *load;
+        let (alpha, dalpha) = self.slip_angle(wheel, x, u[0]);
+        let tyre: &dyn TyreLaw = self.tyre(wheel);
+
+        // Wheel frame forces
+        let fx_w: f64 = if wheel.is_front() { u[1] } else { u[2] } / (2.0 * p.wheel_radius);
+        let fy_w: f64 = grip * tyre.lateral_force(alpha, fz);
+        let c_alpha: f64 = grip * tyre.lateral_force_dalpha(alpha, fz);
+        let c_fz: f64 = grip * tyre.lateral_force_dfz(alpha, fz);
+
+        let mut dfx_w: [f64; N_COLS] = [0.0; N_COLS];
+        let mut dfy_w: [f64; N_COLS] = [0.0; N_COLS];
+        dfx_w[if wheel.is_front() { 7 } else { 8 }] = 1.0 / (2.0 * p.wheel_radius);
+        dfy_w[3] = c_alpha * dalpha[0] + c_fz * dfz[0];
+        dfy_w[4] = c_alpha * dalpha[1];
+        dfy_w[5] = c_alpha * dalpha[2] + c_fz * dfz[1];
+        dfy_w[6] = if wheel.is_front() { c_alpha } else { 0.0 };
+        dfy_w[7] = c_fz * dfz[2];
+        dfy_w[8] = c_fz * dfz[3];
+
+        if !wheel.is_front() {
+            return (fx_w, fy_w, dfx_w, dfy_w);
+        }
+
+        // Rotate the front wheel forces by the steering angle
+        let (sin_delta, cos_delta) = u[0].sin_cos();
+        let fx: f64 = fx_w * cos_delta - fy_w * sin_delta;
+        let fy: f64 = fx_w * sin_delta + fy_w * cos_delta;
+        let mut dfx: [f64; N_COLS] = [0.0; N_COLS];
+        let mut dfy: [f64; N_COLS] = [0.0; N_COLS];
+        for j in 0..N_COLS {
+            dfx[j] = dfx_w[j] * cos_delta - dfy_w[j] * sin_delta;
+            dfy[j] = dfx_w[j] * sin_delta + dfy_w[j] * cos_delta;
+        }
+        dfx[6] -= fy;
+        dfy[6] += fx;
+        return (fx, fy, dfx, dfy);
+    }
+
+    // Lateral slip work Fy alpha of one wheel at unit grip and its derivatives with respect
+    // to all states and inputs
+    fn lateral_work(
+        &self,
+        wheel: Wheel,
+        x: &[f64],
+        u: &[f64],
+        load: &(f64, [f64; 4]),
+    ) -> (f64, [f64; N_COLS]) {
+        let (fz, dfz) = *load;
+        let (alpha, dalpha) = self.slip_angle(wheel, x, u[0]);
+        let tyre: &dyn TyreLaw = self.tyre(wheel);
+        let fy: f64 = tyre.lateral_force(alpha, fz);
+        let c_alpha: f64 = tyre.lateral_force_dalpha(alpha, fz);
+        let c_fz: f64 = tyre.lateral_force_dfz(alpha, fz);
+
+        // d(Fy alpha) = (c_alpha alpha + Fy) dalpha + alpha c_fz dfz
+        let dwork_dalpha: f64 = c_alpha * alpha + fy;
+        let dwork_dfz: f64 = alpha * c_fz;
+        let mut dwork: [f64; N_COLS] = [0.0; N_COLS];
+        dwork[3] = dwork_dalpha * dalpha[0] + dwork_dfz * dfz[0];
+        dwork[4] = dwork_dalpha * dalpha[1];
+        dwork[5] = dwork_dalpha * dalpha[2] + dwork_dfz * dfz[1];
+        dwork[6] = if wheel.is_front() { dwork_dalpha } else { 0.0 };
+        dwork[7] = dwork_dfz * dfz[2];
+        dwork[8] = dwork_dfz * dfz[3];
+        return (fy * alpha, dwork);
+    }
+
+    fn dynamics(&self, x: &[f64], u: &[f64], grip: f64) -> Vec<f64> {
+        let mut dx: Vec<f64> = vec![0.0; self.n_x()];
+        let p: &DoubleTrackParameters = &self.parameters;
+        let psi: f64 = x[2];
+        let vx: f64 = x[3];
+        let vy: f64 = x[4];
+        let r: f64 = x[5];
+
+        // Sum of the wheel forces and their moment about the CG
+        let loads = self.loads(x, u);
+        let (mut fx, mut fy, mut mz) = (0.0, 0.0, 0.0);
+        for (wheel, load) in WHEELS.iter().zip(loads.iter()) {
+            let (fx_i, fy_i, _, _) = self.wheel_forces(*wheel, x, u, load, grip);
+            let (px, py) = self.wheel_position(*wheel);
+            fx += fx_i;
+            fy += fy_i;
+            mz += px * fy_i - py * fx_i;
+        }
+
+        // Body velocity rotated to the global frame
+        dx[0] = vx * psi.cos() - vy * psi.sin();
+        dx[1] = vx * psi.sin() + vy * psi.cos();
+        dx[2] = r;
+
+        // Newton-Euler equations in the rotating body frame
+        dx[3] = fx / p.mass + vy * r;
+        dx[4] = fy / p.mass - vx * r;
+        dx[5] = mz / p.yaw_inertia;
+
+        return dx;
+    }
+
+    fn dynamics_jac(&self, x: &[f64], u: &[f64], grip: f64) -> Vec<f64> {
+        let nx = self.n_x();
+        let nu = self.n_u();
+        let mut jac: Vec<f64> = vec![0.0; nx * (nx + nu)];
+        let p: &DoubleTrackParameters = &self.parameters;
+
+        let psi: f64 = x[2];
+        let vx: f64 = x[3];
+        let vy: f64 = x[4];
+        let r: f64 = x[5];
+        let (sin_psi, cos_psi) = psi.sin_cos();
+
+        // Jacobian is expected to be row major
+        jac[2] = -vx * sin_psi - vy * cos_psi; // dx0/dx2
+        jac[3] = cos_psi; // dx0/dx3
+        jac[4] = -sin_psi; // dx0/dx4
+
+        jac[N_COLS + 2] = vx * cos_psi - vy * sin_psi; // dx1/dx2
+        jac[N_COLS + 3] = sin_psi; // dx1/dx3
+        jac[N_COLS + 4] = cos_psi; // dx1/dx4
+
+        jac[2 * N_COLS + 5] = 1.0; // dx2/dx5
+
+        // Rows of dx3, dx4 and dx5 accumulate the derivatives of every wheel force
+        let loads = self.loads(x, u);
+        for (wheel, load) in WHEELS.iter().zip(loads.iter()) {
+            let (_, _, dfx_i, dfy_i) = self.wheel_forces(*wheel, x, u, load, grip);
+            let (px, py) = self.wheel_position(*wheel);
+            for j in 0..N_COLS {
+                jac[3 * N_COLS + j] += dfx_i[j] / p.mass; // dx3/dxj
+                jac[4 * N_COLS + j] += dfy_i[j] / p.mass; // dx4/dxj
+                jac[5 * N_COLS + j] += (px * dfy_i[j] - py * dfx_i[j]) / p.yaw_inertia;
+                // dx5/dxj
+            }
+        }
+
+        // Coriolis terms of the rotating frame
+        jac[3 * N_COLS + 4] += r; // dx3/dx4
+        jac[3 * N_COLS + 5] += vy; // dx3/dx5
+        jac[4 * N_COLS + 3] -= r; // dx4/dx3
+        jac[4 * N_COLS + 5] -= vx; // dx4/dx5
+
+        return jac;
+    }
+}
+
+// Load above MIN_WHEEL_LOAD, equal to the given load more than LOAD_BLEND above it and
+// joined quadratically below, and its derivative with respect to the given load
+fn clamp_load(load: f64) -> (f64, f64) {
+    let d: f64 = load - MIN_WHEEL_LOAD;
+    return if d >= LOAD_BLEND {
+        (load, 1.0)
+    } else if d <= -LOAD_BLEND {
+        (MIN_WHEEL_LOAD, 0.0)
+    } else {
+        (
+            MIN_WHEEL_LOAD + (d + LOAD_BLEND).powi(2) / (4.0 * LOAD_BLEND),
+            (d + LOAD_BLEND) / (2.0 * LOAD_BLEND),
+        )
+    };
+}
+
+impl Model for DoubleTrack {
+    fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    fn n_x(&self) -> usize {
+        return 6; // [x, y, psi, vx, vy, r]
+    }
+
+    fn n_u(&self) -> usize {
+        return 3; // [delta, Tf, Tr]
+    }
+
+    fn fun(&self, x: &Vec<f64>, u: &Vec<f64>, _t: f64) -> Vec<f64> {
+        return self.dynamics(x, u, 1.0);
+    }
+
+    fn jac(&self, x: &Vec<f64>, u: &Vec<f64>, _t: f64) -> Vec<f64> {
+        return self.dynamics_jac(x, u, 1.0);
+    }
+}
+
+impl GripScaled for DoubleTrack {
+    fn fun_with_grip(&self, x: &[f64], u: &[f64], grip: f64) -> Vec<f64> {
+        return self.dynamics(x, u, grip);
+    }
+
+    fn jac_with_grip(&self, x: &[f64], u: &[f64], grip: f64) -> (Vec<f64>, Vec<f64>) {
+        // The dynamics are affine in the grip, so the derivative is the lateral force share
+        let with_tyres: Vec<f64> = self.dynamics(x, u, 1.0);
+        let without_tyres: Vec<f64> = self.dynamics(x, u, 0.0);
+        let dgrip: Vec<f64> = with_tyres
+            .iter()
+            .zip(without_tyres.iter())
+            .map(|(a, b)| a - b)
+            .collect();
+        return (self.dynamics_jac(x, u, grip), dgrip);
+    }
+
+    // Lateral sliding power sum(Fy alpha vx) of the four wheels, ignoring longitudinal slip
+    fn sliding_power(&self, x: &[f64], u: &[f64], grip: f64) -> (f64, Vec<f64>, f64) {
+        let vx: f64 = x[3];
+        let loads = self.loads(x, u);
+        let mut work: f64 = 0.0;
+        let mut dwork: [f64; N_COLS] = [0.0; N_COLS];
+        for (wheel, load) in WHEELS.iter().zip(loads.iter()) {
+            let (work_i, dwork_i) = self.lateral_work(*wheel, x, u, load);
+            work += work_i;
+            for (d, d_i) in dwork.iter_mut().zip(dwork_i.iter()) {
+                *d += d_i;
+            }
+        }
+
+        let mut dpower: Vec<f64> = dwork.iter().map(|d| grip * vx * d).collect();
+        dpower[3] += grip * work; // dP/dvx
+        return (grip * work * vx, dpower, work * vx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::finite_difference::assert_jacobian;
+    use crate::tyre::{LinearTyre, SaturatingTyre};
+
+    fn parameters() -> DoubleTrackParameters {
+        return DoubleTrackParameters {
+            mass: 1500.0,
+            yaw_inertia: 2500.0,
+            front_to_cg: 1.2,
+            rear_to_cg: 1.4,
+            front_track: 1.6,
+            rear_track: 1.5,
+            cg_height: 0.5,
+            front_roll_share: 0.55,
+            wheel_radius: 0.3,
+        };
+    }
+
+    fn saturating_model() -> DoubleTrack {
+        return DoubleTrack::new(
+            "TestDoubleTrack",
+            parameters(),
+            Box::new(SaturatingTyre::new(40000.0, 1.3)),
+            Box::new(SaturatingTyre::new(45000.0, 1.3)),
+        );
+    }
+
+    #[test]
+    fn test_double_track_static_loads() {
+        let model = saturating_model();
+        let x: Vec<f64> = vec![0.0, 0.0, 0.0, 20.0, 0.0, 0.0];
+        let u: Vec<f64> = vec![0.0, 0.0, 0.0];
+
+        // Without acceleration the loads are the static axle loads split evenly
+        let loads: [f64; 4] = model.wheel_loads(&x, &u);
+        let weight: f64 = 1500.0 * 9.81;
+        assert!((loads[0] - weight * 1.4 / 5.2).abs() < 1e-9);
+        assert!((loads[1] - loads[0]).abs() < 1e-9);
+        assert!((loads[2] - weight * 1.2 / 5.2).abs() < 1e-9);
+        assert!((loads[3] - loads[2]).abs() < 1e-9);
+
+        // Driving straight without torque leaves the vehicle coasting
+        let dx = model.fun(&x, &u, 0.0);
+        assert!((dx[0] - 20.0).abs() < 1e-12);
+        assert!(dx[1..].iter().all(|d| d.abs() < 1e-12));
+        assert!(model.slip_angles(&x, 0.0).iter().all(|a| a.abs() < 1e-12));
+    }
+
+    #[test]
+    fn test_double_track_load_transfer() {
+        let model = saturating_model();
+        let weight: f64 = 1500.0 * 9.81;
+
+        // Accelerating shifts load to the rear, by m a_x h / L in total
+        let x: Vec<f64> = vec![0.0, 0.0, 0.0, 20.0, 0.0, 0.0];
+        let u: Vec<f64> = vec![0.0, 0.0, 1500.0 * 0.3 * 3.0];
+        let loads: [f64; 4] = model.wheel_loads(&x, &u);
+        let transfer: f64 = 1500.0 * 3.0 * 0.5 / 2.6;
+        assert!((loads[2] + loads[3] - weight * 1.2 / 2.6 - transfer).abs() < 1e-9);
+        assert!((loads.iter().sum::<f64>() - weight).abs() < 1e-9);
+
+        // Turning left shifts load to the right wheels, split by roll stiffness
+        let x: Vec<f64> = vec![0.0, 0.0, 0.0, 20.0, 0.0, 0.4];
+        let u: Vec<f64> = vec![0.0, 0.0, 0.0];
+        let loads: [f64; 4] = model.wheel_loads(&x, &u);
+        let lateral: f64 = 1500.0 * 8.0 * 0.5;
+        assert!(((loads[1] - loads[0]) * 1.6 / 2.0 - 0.55 * lateral).abs() < 1e-9);
+        assert!(((loads[3] - loads[2]) * 1.5 / 2.0 - 0.45 * lateral).abs() < 1e-9);
+        assert!((loads.iter().sum::<f64>() - weight).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_double_track_wheel_lift() {
+        let model = saturating_model();
+        let u: Vec<f64> = vec![0.05, 0.0, 0.0];
+
+        // Past about 1.6 g both left wheels lift and keep the minimum load
+        let x: Vec<f64> = vec![0.0, 0.0, 0.0, 20.0, 0.5, 1.2];
+        let loads: [f64; 4] = model.wheel_loads(&x, &u);
+        assert_eq!(loads[0], MIN_WHEEL_LOAD);
+        assert_eq!(loads[2], MIN_WHEEL_LOAD);
+        assert!(model.fun(&x, &u, 0.0).iter().all(|d| d.is_finite()));
+        assert!(model.jac(&x, &u, 0.0).iter().all(|d| d.is_finite()));
+        assert_jacobian(&model, &x, &u, 0.0, 1e-6);
+
+        // Within the blend into the minimum load the loads stay smooth
+        let x: Vec<f64> = vec![0.0, 0.0, 0.0, 20.0, 0.5, 0.76];
+        let loads: [f64; 4] = model.wheel_loads(&x, &u);
+        assert!(loads[0] > MIN_WHEEL_LOAD && loads[0] < MIN_WHEEL_LOAD + LOAD_BLEND);
+        assert!(loads[2] > MIN_WHEEL_LOAD && loads[2] < MIN_WHEEL_LOAD + LOAD_BLEND);
+        assert_jacobian(&model, &x, &u, 0.0, 1e-6);
+    }
+
+    #[test]
+    fn test_double_track_matches_bicycle() {
+        // With linear tyres and no load sensitivity, a narrow double-track model with
+        // half the axle stiffness per wheel reproduces the dynamic bicycle model
+        let mut p: DoubleTrackParameters = parameters();
+        p.front_track = 1e-6;
+        p.rear_track = 1e-6;
+        let model = DoubleTrack::new(
+            "Narrow",
+            p,
+            Box::new(LinearTyre::new(40000.0)),
+            Box::new(LinearTyre::new(45000.0)),
+        );
+        let bicycle = crate::model::dynamic_bicycle::DynamicBicycle::new(
+            "Bicycle",
+            1500.0,
+            2500.0,
+            1.2,
+            1.4,
+            Box::new(LinearTyre::new(80000.0)),
+            Box::new(LinearTyre::new(90000.0)),
+        );
+
+        let x: Vec<f64> = vec![1.0, 2.0, 0.3, 18.0, 0.5, 0.2];
+        let dx = model.fun(&x, &vec![0.05, 0.0, 600.0], 0.0);
+        let dx_bicycle = bicycle.fun(&x, &vec![0.05, 2000.0], 0.0);
+        for i in 0..6 {
+            assert!((dx[i] - dx_bicycle[i]).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_double_track_simulation() {
+        // Same integration loop as for the point mass, steering into a left turn
+        let model = saturating_model();
+        let solver: simulation_toolbox::erk::ExplicitRK =
+            simulation_toolbox::erk::ExplicitRK::rk4();
+        let mut x: Vec<f64> = vec![0.0, 0.0, 0.0, 15.0, 0.0, 0.0];
+        let u: Vec<f64> = vec![0.05, 0.0, 300.0];
+        let mut t: f64 = 0.0;
+        while t < 2.0 {
+            x = solver.step(&model, &x, &u, t, 0.01);
+            t += 0.01;
+        }
+        assert!(x.iter().all(|v| v.is_finite()));
+        assert!(x[1] > 0.0 && x[2] > 0.0 && x[5] > 0.0);
+    }
+
+    #[test]
+    fn test_double_track_jacobian_matches_fun() {
+        let model = saturating_model();
+        let x: Vec<f64> = vec![3.0, -2.0, 0.7, 18.0, 0.8, 0.3];
+        for u in [vec![0.06, 400.0, 900.0], vec![-0.2, -1500.0, -800.0]] {
+            assert_jacobian(&model, &x, &u, 0.0, 1e-5);
+        }
+    }
+}
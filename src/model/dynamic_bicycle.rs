@@ -0,0 +1,395 @@
+use crate::model::tyre_states::GripScaled;
+use crate::tyre::TyreLaw;
+use simulation_toolbox::Model;
+
+// Dynamic single-track model with the velocity expressed in the body frame at the centre of
+// gravity. Lateral axle forces follow from the axle slip angles through a tyre law, the
+// longitudinal force acts at the rear axle and the normal loads are the static axle loads.
+// The slip angles are singular at vx = 0, so the model is only valid while driving forward.
+// The grip multiplier of GripScaled scales both lateral axle forces.
+pub struct DynamicBicycle {
+    // Properties
+    name: String,
+    // Parameters
+    pub mass: f64,                    // Vehicle mass [kg]
+    pub yaw_inertia: f64,             // Moment of inertia about the vertical axis [kg m^2]
+    pub front_to_cg: f64,             // Distance from front axle to centre of gravity [m]
+    pub rear_to_cg: f64,              // Distance from rear axle to centre of gravity [m]
+    pub front_tyre: Box<dyn TyreLaw>, // Lumped front axle tyre
+    pub rear_tyre: Box<dyn TyreLaw>,  // Lumped rear axle tyre
+}
+
+const GRAVITY: f64 = 9.81; // [m/s^2]
+
+// Slip angle of one axle and its derivatives with respect to [vx, vy, r]
+struct AxleSlip {
+    alpha: f64,
+    dalpha: [f64; 3],
+}
+
+impl DynamicBicycle {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(
+        name: &str,
+        mass: f64,
+        yaw_inertia: f64,
+        front_to_cg: f64,
+        rear_to_cg: f64,
+        front_tyre: Box<dyn TyreLaw>,
+        rear_tyre: Box<dyn TyreLaw>,
+    ) -> Self {
+        return DynamicBicycle {
+            name: name.to_string(),
+            mass,
+            yaw_inertia,
+            front_to_cg,
+            rear_to_cg,
+            front_tyre,
+            rear_tyre,
+        };
+    }
+
+    // Static normal loads on the front and rear axle [N]
+    pub fn axle_loads(&self) -> (f64, f64) {
+        let wheelbase: f64 = self.front_to_cg + self.rear_to_cg;
+        let weight: f64 = self.mass * GRAVITY;
+        return (
+            weight * self.rear_to_cg / wheelbase,
+            weight * self.front_to_cg / wheelbase,
+        );
+    }
+
+    // Front and rear slip angles [rad] for the state x and steering angle delta
+    pub fn slip_angles(&self, x: &[f64], delta: f64) -> (f64, f64) {
+        let (front, rear) = self.axle_slips(x, delta);
+        return (front.alpha, rear.alpha);
+    }
+
+    // Front and rear lateral axle forces [N] for the state x and steering angle delta
+    pub fn axle_forces(&self, x: &[f64], delta: f64) -> (f64, f64) {
+        let (alpha_f, alpha_r) = self.slip_angles(x, delta);
+        let (fz_f, fz_r) = self.axle_loads();
+        return (
+            self.front_tyre.lateral_force(alpha_f, fz_f),
+            self.rear_tyre.lateral_force(alpha_r, fz_r),
+        );
+    }
+
+    fn axle_slips(&self, x: &[f64], delta: f64) -> (AxleSlip, AxleSlip) {
+        let vx: f64 = x[3];
+        let vy: f64 = x[4];
+        let r: f64 = x[5];
+
+        // alpha_f = delta - atan2(vy + lf r, vx)
+        let vy_f: f64 = vy + self.front_to_cg * r;
+        let den_f: f64 = vx.powi(2) + vy_f.powi(2);
+        let front: AxleSlip = AxleSlip {
+            alpha: delta - f64::atan2(vy_f, vx),
+            dalpha: [vy_f / den_f, -vx / den_f, -self.front_to_cg * vx / den_f],
+        };
+
+        // alpha_r = -atan2(vy - lr r, vx)
+        let vy_r: f64 = vy - self.rear_to_cg * r;
+        let den_r: f64 = vx.powi(2) + vy_r.powi(2);
+        let rear: AxleSlip = AxleSlip {
+            alpha: -f64::atan2(vy_r, vx),
+            dalpha: [vy_r / den_r, -vx / den_r, self.rear_to_cg * vx / den_r],
+        };
+
+        return (front, rear);
+    }
+
+    fn dynamics(&self, x: &[f64], u: &[f64], grip: f64) -> Vec<f64> {
+        let mut dx: Vec<f64> = vec![0.0; self.n_x()];
+        let psi: f64 = x[2];
+        let vx: f64 = x[3];
+        let vy: f64 = x[4];
+        let r: f64 = x[5];
+        let delta: f64 = u[0];
+        let fx: f64 = u[1];
+        let (fy_f, fy_r) = self.axle_forces(x, delta);
+        let (fy_f, fy_r) = (grip * fy_f, grip * fy_r);
+
+        // Body velocity rotated to the global frame
+        dx[0] = vx * psi.cos() - vy * psi.sin();
+        dx[1] = vx * psi.sin() + vy * psi.cos();
+        dx[2] = r;
+
+        // Newton-Euler equations in the rotating body frame
+        dx[3] = (fx - fy_f * delta.sin()) / self.mass + vy * r;
+        dx[4] = (fy_r + fy_f * delta.cos()) / self.mass - vx * r;
+        dx[5] = (self.front_to_cg * fy_f * delta.cos() - self.rear_to_cg * fy_r) / self.yaw_inertia;
+
+        return dx;
+    }
+
+    fn dynamics_jac(&self, x: &[f64], u: &[f64], grip: f64) -> Vec<f64> {
+        let nx = self.n_x();
+        let nu = self.n_u();
+        let mut jac: Vec<f64> = vec![0.0; nx * (nx + nu)];
+
+        let psi: f64 = x[2];
+        let vx: f64 = x[3];
+        let vy: f64 = x[4];
+        let r: f64 = x[5];
+        let delta: f64 = u[0];
+        let (sin_psi, cos_psi) = psi.sin_cos();
+        let (sin_delta, cos_delta) = delta.sin_cos();
+        let m: f64 = self.mass;
+        let iz: f64 = self.yaw_inertia;
+        let lf: f64 = self.front_to_cg;
+        let lr: f64 = self.rear_to_cg;
+
+        // Axle forces and their slopes, chained through the slip angle derivatives
+        let (front, rear) = self.axle_slips(x, delta);
+        let (fz_f, fz_r) = self.axle_loads();
+        let fy_f: f64 = grip * self.front_tyre.lateral_force(front.alpha, fz_f);
+        let c_f: f64 = grip * self.front_tyre.lateral_force_dalpha(front.alpha, fz_f);
+        let c_r: f64 = grip * self.rear_tyre.lateral_force_dalpha(rear.alpha, fz_r);
+        let dfy_f: [f64; 3] = front.dalpha.map(|d| c_f * d); // dFyf/d[vx, vy, r]
+        let dfy_r: [f64; 3] = rear.dalpha.map(|d| c_r * d); // dFyr/d[vx, vy, r]
+
+        // Jacobian is expected to be row major
+        jac[0] = 0.0; // dx0/dx0
+        jac[1] = 0.0; // dx0/dx1
+        jac[2] = -vx * sin_psi - vy * cos_psi; // dx0/dx2
+        jac[3] = cos_psi; // dx0/dx3
+        jac[4] = -sin_psi; // dx0/dx4
+        jac[5] = 0.0; // dx0/dx5
+        jac[6] = 0.0; // dx0/du0
+        jac[7] = 0.0; // dx0/du1
+
+        jac[8] = 0.0; // dx1/dx0
+        jac[9] = 0.0; // dx1/dx1
+        jac[10] = vx * cos_psi - vy * sin_psi; // dx1/dx2
+        jac[11] = sin_psi; // dx1/dx3
+        jac[12] = cos_psi; // dx1/dx4
+        jac[13] = 0.0; // dx1/dx5
+        jac[14] = 0.0; // dx1/du0
+        jac[15] = 0.0; // dx1/du1
+
+        jac[16] = 0.0; // dx2/dx0
+        jac[17] = 0.0; // dx2/dx1
+        jac[18] = 0.0; // dx2/dx2
+        jac[19] = 0.0; // dx2/dx3
+        jac[20] = 0.0; // dx2/dx4
+        jac[21] = 1.0; // dx2/dx5
+        jac[22] = 0.0; // dx2/du0
+        jac[23] = 0.0; // dx2/du1
+
+        jac[24] = 0.0; // dx3/dx0
+        jac[25] = 0.0; // dx3/dx1
+        jac[26] = 0.0; // dx3/dx2
+        jac[27] = -dfy_f[0] * sin_delta / m; // dx3/dx3
+        jac[28] = -dfy_f[1] * sin_delta / m + r; // dx3/dx4
+        jac[29] = -dfy_f[2] * sin_delta / m + vy; // dx3/dx5
+        jac[30] = -(c_f * sin_delta + fy_f * cos_delta) / m; // dx3/du0
+        jac[31] = 1.0 / m; // dx3/du1
+
+        jac[32] = 0.0; // dx4/dx0
+        jac[33] = 0.0; // dx4/dx1
+        jac[34] = 0.0; // dx4/dx2
+        jac[35] = (dfy_r[0] + dfy_f[0] * cos_delta) / m - r; // dx4/dx3
+        jac[36] = (dfy_r[1] + dfy_f[1] * cos_delta) / m; // dx4/dx4
+        jac[37] = (dfy_r[2] + dfy_f[2] * cos_delta) / m - vx; // dx4/dx5
+        jac[38] = (c_f * cos_delta - fy_f * sin_delta) / m; // dx4/du0
+        jac[39] = 0.0; // dx4/du1
+
+        jac[40] = 0.0; // dx5/dx0
+        jac[41] = 0.0; // dx5/dx1
+        jac[42] = 0.0; // dx5/dx2
+        jac[43] = (lf * dfy_f[0] * cos_delta - lr * dfy_r[0]) / iz; // dx5/dx3
+        jac[44] = (lf * dfy_f[1] * cos_delta - lr * dfy_r[1]) / iz; // dx5/dx4
+        jac[45] = (lf * dfy_f[2] * cos_delta - lr * dfy_r[2]) / iz; // dx5/dx5
+        jac[46] = lf * (c_f * cos_delta - fy_f * sin_delta) / iz; // dx5/du0
+        jac[47] = 0.0; // dx5/du1
+
+        return jac;
+    }
+}
+
+impl Model for DynamicBicycle {
+    fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    fn n_x(&self) -> usize {
+        return 6; // [x, y, psi, vx, vy, r]
+    }
+
+    fn n_u(&self) -> usize {
+        return 2; // [delta, Fx]
+    }
+
+    fn fun(&self, x: &Vec<f64>, u: &Vec<f64>, _t: f64) -> Vec<f64> {
+        return self.dynamics(x, u, 1.0);
+    }
+
+    fn jac(&self, x: &Vec<f64>, u: &Vec<f64>, _t: f64) -> Vec<f64> {
+        return self.dynamics_jac(x, u, 1.0);
+    }
+}
+
+impl GripScaled for DynamicBicycle {
+    fn fun_with_grip(&self, x: &[f64], u: &[f64], grip: f64) -> Vec<f64> {
+        return self.dynamics(x, u, grip);
+    }
+
+    fn jac_with_grip(&self, x: &[f64], u: &[f64], grip: f64) -> (Vec<f64>, Vec<f64>) {
+        // The dynamics are affine in the grip, so the derivative is the tyre force share
+        let with_tyres: Vec<f64> = self.dynamics(x, u, 1.0);
+        let without_tyres: Vec<f64> = self.dynamics(x, u, 0.0);
+        let dgrip: Vec<f64> = with_tyres
+            .iter()
+            .zip(without_tyres.iter())
+            .map(|(a, b)| a - b)
+            .collect();
+        return (self.dynamics_jac(x, u, grip), dgrip);
+    }
+
+    // Lateral sliding power sum(Fy alpha vx) of both axles, ignoring longitudinal slip
+    fn sliding_power(&self, x: &[f64], u: &[f64], grip: f64) -> (f64, Vec<f64>, f64) {
+        let vx: f64 = x[3];
+        let (front, rear) = self.axle_slips(x, u[0]);
+        let (fz_f, fz_r) = self.axle_loads();
+        let fy_f: f64 = self.front_tyre.lateral_force(front.alpha, fz_f);
+        let fy_r: f64 = self.rear_tyre.lateral_force(rear.alpha, fz_r);
+        let c_f: f64 = self.front_tyre.lateral_force_dalpha(front.alpha, fz_f);
+        let c_r: f64 = self.rear_tyre.lateral_force_dalpha(rear.alpha, fz_r);
+
+        // d(Fy alpha)/dalpha for each axle
+        let work: f64 = fy_f * front.alpha + fy_r * rear.alpha;
+        let dwork_f: f64 = c_f * front.alpha + fy_f;
+        let dwork_r: f64 = c_r * rear.alpha + fy_r;
+
+        let mut dpower: Vec<f64> = vec![0.0; 8];
+        for k in 0..3 {
+            dpower[3 + k] = grip * vx * (dwork_f * front.dalpha[k] + dwork_r * rear.dalpha[k]);
+        }
+        dpower[3] += grip * work; // dP/dvx
+        dpower[6] = grip * vx * dwork_f; // dP/ddelta
+        return (grip * work * vx, dpower, work * vx);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::model::finite_difference::assert_jacobian;
+    use crate::tyre::{LinearTyre, SaturatingTyre};
+
+    fn linear_model() -> DynamicBicycle {
+        return DynamicBicycle::new(
+            "TestDynamicBicycle",
+            1500.0,
+            2500.0,
+            1.2,
+            1.4,
+            Box::new(LinearTyre::new(80000.0)),
+            Box::new(LinearTyre::new(90000.0)),
+        );
+    }
+
+    pub fn saturating_model() -> DynamicBicycle {
+        return DynamicBicycle::new(
+            "TestDynamicBicycle",
+            1500.0,
+            2500.0,
+            1.2,
+            1.4,
+            Box::new(SaturatingTyre::new(80000.0, 1.3)),
+            Box::new(SaturatingTyre::new(90000.0, 1.3)),
+        );
+    }
+
+    #[test]
+    fn test_dynamic_bicycle_straight() {
+        let model = linear_model();
+
+        // Driving straight along y with no slip gives no lateral forces
+        let x: Vec<f64> = vec![0.0, 0.0, std::f64::consts::FRAC_PI_2, 20.0, 0.0, 0.0];
+        let u: Vec<f64> = vec![0.0, 3000.0];
+        let dx = model.fun(&x, &u, 0.0);
+        assert!(dx[0].abs() < 1e-12);
+        assert!((dx[1] - 20.0).abs() < 1e-12);
+        assert!(dx[2].abs() < 1e-12);
+        assert!((dx[3] - 2.0).abs() < 1e-12);
+        assert!(dx[4].abs() < 1e-12);
+        assert!(dx[5].abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_dynamic_bicycle_slip_angles() {
+        let model = linear_model();
+
+        // Steering alone gives a front slip angle equal to the steering angle
+        let x: Vec<f64> = vec![0.0, 0.0, 0.0, 20.0, 0.0, 0.0];
+        let (alpha_f, alpha_r) = model.slip_angles(&x, 0.05);
+        assert!((alpha_f - 0.05).abs() < 1e-12);
+        assert!(alpha_r.abs() < 1e-12);
+
+        // A positive yaw rate gives opposite slip angles on the two axles
+        let x: Vec<f64> = vec![0.0, 0.0, 0.0, 20.0, 0.0, 0.5];
+        let (alpha_f, alpha_r) = model.slip_angles(&x, 0.0);
+        assert!((alpha_f + f64::atan(1.2 * 0.5 / 20.0)).abs() < 1e-12);
+        assert!((alpha_r - f64::atan(1.4 * 0.5 / 20.0)).abs() < 1e-12);
+
+        // Static loads sum to the vehicle weight and balance about the CG
+        let (fz_f, fz_r) = model.axle_loads();
+        assert!((fz_f + fz_r - 1500.0 * 9.81).abs() < 1e-9);
+        assert!((fz_f * 1.2 - fz_r * 1.4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_dynamic_bicycle_steady_state_yaw_rate() {
+        let model = linear_model();
+
+        // Integrate a constant small steering input to steady state and compare with the
+        // linear yaw rate gain v / (L + K v^2) * delta
+        let (vx, delta) = (20.0, 0.01);
+        let mut x: Vec<f64> = vec![0.0, 0.0, 0.0, vx, 0.0, 0.0];
+        let u: Vec<f64> = vec![delta, 0.0];
+        let dt: f64 = 1e-3;
+        for _ in 0..5000 {
+            let dx = model.fun(&x, &u, 0.0);
+            for i in 0..6 {
+                x[i] += dt * dx[i];
+            }
+            x[3] = vx; // Hold the speed so only the lateral dynamics settle
+        }
+
+        let wheelbase: f64 = 2.6;
+        let understeer: f64 = 1500.0 * (1.4 / 80000.0 - 1.2 / 90000.0) / wheelbase;
+        let expected: f64 = vx / (wheelbase + understeer * vx.powi(2)) * delta;
+        assert!((x[5] - expected).abs() < 1e-3 * expected);
+    }
+
+    #[test]
+    fn test_dynamic_bicycle_saturation() {
+        let linear = linear_model();
+        let saturating = saturating_model();
+
+        // Both tyre laws agree for small slip, but the saturating axle force is bounded
+        let x: Vec<f64> = vec![0.0, 0.0, 0.0, 20.0, 0.0, 0.0];
+        let (small_lin, _) = linear.axle_forces(&x, 1e-4);
+        let (small_sat, _) = saturating.axle_forces(&x, 1e-4);
+        assert!((small_lin - small_sat).abs() < 1e-3);
+
+        let (fz_f, _) = saturating.axle_loads();
+        let (large_sat, _) = saturating.axle_forces(&x, 0.5);
+        assert!(large_sat < 1.3 * fz_f);
+        assert!(large_sat > 0.99 * 1.3 * fz_f);
+    }
+
+    #[test]
+    fn test_dynamic_bicycle_jacobian_matches_fun() {
+        let x: Vec<f64> = vec![3.0, -2.0, 0.7, 18.0, 0.8, 0.3];
+        let u: Vec<f64> = vec![0.06, 1500.0];
+        assert_jacobian(&linear_model(), &x, &u, 0.0, 1e-5);
+        assert_jacobian(&saturating_model(), &x, &u, 0.0, 1e-5);
+
+        // Deep in the saturated region
+        let u: Vec<f64> = vec![0.3, -2000.0];
+        assert_jacobian(&saturating_model(), &x, &u, 0.0, 1e-5);
+    }
+}
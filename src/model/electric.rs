@@ -0,0 +1,408 @@
+use crate::model::double_track::DoubleTrack;
+use crate::model::dynamic_bicycle::DynamicBicycle;
+use crate::model::extended_point_mass::ExtendedPointMass;
+use crate::model::point_mass::PointMass;
+use crate::powertrain::battery::{Battery, BatteryCurrent};
+use simulation_toolbox::Model;
+
+// Mechanical power delivered to the road by the inputs of a vehicle model, used to size the
+// electrical power drawn from the battery. The gradient is with respect to the model states
+// followed by its inputs.
+pub trait WheelPower {
+    fn wheel_power(&self, x: &[f64], u: &[f64]) -> (f64, Vec<f64>);
+}
+
+// Wrapper adding the battery state of charge, and optionally the pack temperature, as extra
+// states after those of the wrapped model, giving [rest..., soc] or [rest..., soc, T]. The
+// inputs are unchanged. Driving draws the wheel power divided by the drive efficiency from
+// the battery, and braking returns the wheel power times the regeneration efficiency, so
+// the battery power has a kink at zero wheel power.
+//
+// The battery current, regeneration current, voltage sag and motor power limits are exposed
+// as constraints g(x, u) <= 0. The motor limit bounds the wheel power while driving only, so
+// braking is bounded by the regeneration current alone and may exceed the motor rating.
+// Energy limits per lap or per stint are bounds on the state of charge at the end of the
+// interval, or on the difference of Battery::energy between its start and end.
+pub struct Electric<M: Model + WheelPower> {
+    // Properties
+    name: String,
+    // Parameters
+    pub model: M,
+    pub battery: Battery,
+    pub drive_efficiency: f64, // Battery to wheel efficiency [-]
+    pub regen_efficiency: f64, // Wheel to battery efficiency [-]
+    pub max_motor_power: f64,  // Motor power at the wheels, infinite without a limit [W]
+    pub thermal: bool,         // Add the pack temperature as a state
+}
+
+impl<M: Model + WheelPower> Electric<M> {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(
+        name: &str,
+        model: M,
+        battery: Battery,
+        drive_efficiency: f64,
+        regen_efficiency: f64,
+    ) -> Self {
+        return Electric {
+            name: name.to_string(),
+            model,
+            battery,
+            drive_efficiency,
+            regen_efficiency,
+            max_motor_power: f64::INFINITY,
+            thermal: false,
+        };
+    }
+
+    // Number of states added to the wrapped model
+    fn n_extra(&self) -> usize {
+        return if self.thermal { 2 } else { 1 };
+    }
+
+    // Column of the full Jacobian for column j of the wrapped model Jacobian
+    fn column(&self, j: usize) -> usize {
+        return if j < self.model.n_x() {
+            j
+        } else {
+            j + self.n_extra()
+        };
+    }
+
+    // Battery terminal power [W] and its gradient over the wrapped model states and inputs
+    pub fn battery_power(&self, x: &[f64], u: &[f64]) -> (f64, Vec<f64>) {
+        let nz: usize = self.model.n_x();
+        let (power, dpower) = self.model.wheel_power(&x[..nz], u);
+        let factor: f64 = match power >= 0.0 {
+            true => 1.0 / self.drive_efficiency,
+            false => self.regen_efficiency,
+        };
+        return (
+            power * factor,
+            dpower.iter().map(|dp| dp * factor).collect(),
+        );
+    }
+
+    // Battery current with derivatives, and the gradient of the current over the wrapped model
+    // states and inputs
+    fn current(&self, x: &[f64], u: &[f64]) -> (BatteryCurrent, Vec<f64>) {
+        let soc: f64 = x[self.model.n_x()];
+        let (power, dpower) = self.battery_power(x, u);
+        let current: BatteryCurrent = self.battery.current(power, soc);
+        let dcurrent: Vec<f64> = dpower.iter().map(|dp| current.d_power * dp).collect();
+        return (current, dcurrent);
+    }
+
+    #[allow(dead_code)]
+    pub fn n_constraints(&self) -> usize {
+        return 4; // [discharge current, charge current, voltage, motor power]
+    }
+
+    // Constraint values, feasible when every entry is <= 0, scaled by the current, voltage
+    // and power limits so that the entries are dimensionless
+    #[allow(dead_code)]
+    pub fn constraints(&self, x: &[f64], u: &[f64]) -> Vec<f64> {
+        let b: &Battery = &self.battery;
+        let nz: usize = self.model.n_x();
+        let soc: f64 = x[nz];
+        let (current, _) = self.current(x, u);
+        let voltage: f64 = b.terminal_voltage(current.current, soc);
+        let (wheel_power, _) = self.model.wheel_power(&x[..nz], u);
+        return vec![
+            current.current / b.max_discharge_current - 1.0,
+            -current.current / b.max_charge_current - 1.0,
+            1.0 - voltage / b.min_voltage,
+            wheel_power / self.max_motor_power - 1.0,
+        ];
+    }
+
+    // Row-major Jacobian of the constraints with respect to the states and inputs
+    #[allow(dead_code)]
+    pub fn constraints_jac(&self, x: &[f64], u: &[f64]) -> Vec<f64> {
+        let b: &Battery = &self.battery;
+        let nz: usize = self.model.n_x();
+        let n_cols: usize = self.n_x() + self.n_u();
+        let mut jac: Vec<f64> = vec![0.0; self.n_constraints() * n_cols];
+        let (current, dcurrent) = self.current(x, u);
+
+        for (j, di) in dcurrent.iter().enumerate() {
+            let col: usize = self.column(j);
+            jac[col] = di / b.max_discharge_current; // dg0/dxj
+            jac[n_cols + col] = -di / b.max_charge_current; // dg1/dxj
+            jac[2 * n_cols + col] = b.internal_resistance * di / b.min_voltage; // dg2/dxj
+        }
+
+        // V = V_oc(soc) - R I(soc)
+        let dv_dsoc: f64 = b.ocv_full - b.ocv_empty - b.internal_resistance * current.d_soc;
+        jac[nz] = current.d_soc / b.max_discharge_current; // dg0/dsoc
+        jac[n_cols + nz] = -current.d_soc / b.max_charge_current; // dg1/dsoc
+        jac[2 * n_cols + nz] = -dv_dsoc / b.min_voltage; // dg2/dsoc
+
+        // Wheel power over the motor limit
+        let (_, dpower) = self.model.wheel_power(&x[..nz], u);
+        for (j, dp) in dpower.iter().enumerate() {
+            jac[3 * n_cols + self.column(j)] = dp / self.max_motor_power; // dg3/dxj
+        }
+
+        return jac;
+    }
+}
+
+impl<M: Model + WheelPower> Model for Electric<M> {
+    fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    fn n_x(&self) -> usize {
+        return self.model.n_x() + self.n_extra(); // [rest..., soc, T]
+    }
+
+    fn n_u(&self) -> usize {
+        return self.model.n_u();
+    }
+
+    fn fun(&self, x: &Vec<f64>, u: &Vec<f64>, t: f64) -> Vec<f64> {
+        let nz: usize = self.model.n_x();
+        let mut dx: Vec<f64> = self.model.fun(&x[..nz].to_vec(), u, t);
+        let (current, _) = self.current(x, u);
+
+        dx.push(self.battery.soc_rate(current.current));
+        if self.thermal {
+            dx.push(self.battery.temperature_rate(current.current, x[nz + 1]));
+        }
+        return dx;
+    }
+
+    fn jac(&self, x: &Vec<f64>, u: &Vec<f64>, t: f64) -> Vec<f64> {
+        let b: &Battery = &self.battery;
+        let nz: usize = self.model.n_x();
+        let nx = self.n_x();
+        let nu = self.n_u();
+        let n_cols: usize = nx + nu;
+        let mut jac: Vec<f64> = vec![0.0; nx * n_cols];
+
+        // Wrapped model rows, with its input columns shifted past the added states
+        let jac_z: Vec<f64> = self.model.jac(&x[..nz].to_vec(), u, t);
+        for i in 0..nz {
+            for j in 0..nz + nu {
+                jac[i * n_cols + self.column(j)] = jac_z[i * (nz + nu) + j]; // dxi/dxj
+            }
+        }
+
+        // dsoc/dt = -I / Q
+        let (current, dcurrent) = self.current(x, u);
+        let dsoc_di: f64 = b.soc_rate(1.0);
+        let row: usize = nz * n_cols;
+        for (j, di) in dcurrent.iter().enumerate() {
+            jac[row + self.column(j)] = dsoc_di * di; // dsoc/dxj
+        }
+        jac[row + nz] = dsoc_di * current.d_soc; // dsoc/dsoc
+
+        // dT/dt = (R I^2 - h (T - T_amb)) / C
+        if self.thermal {
+            let row: usize = (nz + 1) * n_cols;
+            let dtemp_di: f64 = 2.0 * b.internal_resistance * current.current / b.thermal_mass;
+            for (j, di) in dcurrent.iter().enumerate() {
+                jac[row + self.column(j)] = dtemp_di * di; // dT/dxj
+            }
+            jac[row + nz] = dtemp_di * current.d_soc; // dT/dsoc
+            jac[row + nz + 1] = -b.cooling / b.thermal_mass; // dT/dT
+        }
+
+        return jac;
+    }
+}
+
+// WHEEL POWER IMPLEMENTATIONS ++++++++++++++++++++++++++
+impl WheelPower for PointMass {
+    // P = F . v
+    fn wheel_power(&self, x: &[f64], u: &[f64]) -> (f64, Vec<f64>) {
+        let power: f64 = u[0] * x[2] + u[1] * x[3];
+        return (power, vec![0.0, 0.0, u[0], u[1], x[2], x[3]]);
+    }
+}
+
+impl WheelPower for ExtendedPointMass {
+    // P = F . v
+    fn wheel_power(&self, x: &[f64], u: &[f64]) -> (f64, Vec<f64>) {
+        let power: f64 = u[0] * x[2] + u[1] * x[3];
+        return (power, vec![0.0, 0.0, u[0], u[1], x[2], x[3]]);
+    }
+}
+
+impl WheelPower for DynamicBicycle {
+    // P = Fx vx with the drive force along the vehicle axis
+    fn wheel_power(&self, x: &[f64], u: &[f64]) -> (f64, Vec<f64>) {
+        let power: f64 = u[1] * x[3];
+        return (power, vec![0.0, 0.0, 0.0, u[1], 0.0, 0.0, 0.0, x[3]]);
+    }
+}
+
+impl WheelPower for DoubleTrack {
+    // P = (Tf + Tr) vx / R, neglecting the wheel slip and the steering of the front wheels
+    fn wheel_power(&self, x: &[f64], u: &[f64]) -> (f64, Vec<f64>) {
+        let r: f64 = self.parameters.wheel_radius;
+        let power: f64 = (u[1] + u[2]) * x[3] / r;
+        return (
+            power,
+            vec![
+                0.0,
+                0.0,
+                0.0,
+                (u[1] + u[2]) / r,
+                0.0,
+                0.0,
+                0.0,
+                x[3] / r,
+                x[3] / r,
+            ],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::finite_difference::{
+        assert_jacobian, compare_jacobians, finite_difference_jac,
+    };
+    use simulation_toolbox::erk::ExplicitRK;
+
+    fn battery() -> Battery {
+        return Battery {
+            capacity: 7.0,
+            ocv_empty: 480.0,
+            ocv_full: 588.0,
+            internal_resistance: 0.3,
+            max_discharge_current: 200.0,
+            max_charge_current: 60.0,
+            min_voltage: 420.0,
+            thermal_mass: 30000.0,
+            cooling: 20.0,
+            ambient_temperature: 25.0,
+        };
+    }
+
+    fn model() -> Electric<PointMass> {
+        let mut model = Electric::new(
+            "Electric",
+            PointMass::new("Mass", 300.0),
+            battery(),
+            0.9,
+            0.7,
+        );
+        model.thermal = true;
+        return model;
+    }
+
+    #[test]
+    fn test_electric_drive_and_regen() {
+        let model = model();
+        let x: Vec<f64> = vec![0.0, 0.0, 20.0, 0.0, 0.8, 30.0];
+
+        // The wrapped dynamics are unchanged
+        let dx = model.fun(&x, &vec![1500.0, 0.0], 0.0);
+        assert_eq!(dx[..4], [20.0, 0.0, 5.0, 0.0]);
+
+        // Driving draws 30 kW / 0.9 from the battery
+        let (power, _) = model.battery_power(&x, &[1500.0, 0.0]);
+        assert!((power - 30e3 / 0.9).abs() < 1e-9);
+        assert!(dx[4] < 0.0);
+
+        // Braking returns 70% of the wheel power and charges the battery
+        let (power, _) = model.battery_power(&x, &[-1500.0, 0.0]);
+        assert!((power + 30e3 * 0.7).abs() < 1e-9);
+        assert!(model.fun(&x, &vec![-1500.0, 0.0], 0.0)[4] > 0.0);
+
+        // Coasting only cools the pack
+        let dx = model.fun(&x, &vec![0.0, 0.0], 0.0);
+        assert_eq!(dx[4], 0.0);
+        assert!(dx[5] < 0.0);
+    }
+
+    #[test]
+    fn test_electric_energy_over_a_run() {
+        // Accelerate at constant force and compare the drop in stored energy with the
+        // mechanical work divided by the drive efficiency, plus the resistive losses
+        let mut model = model();
+        model.thermal = false;
+        let rk4 = ExplicitRK::rk4();
+        let u: Vec<f64> = vec![600.0, 0.0];
+        let mut x: Vec<f64> = vec![0.0, 0.0, 1.0, 0.0, 0.9];
+        let dt: f64 = 0.01;
+        for i in 0..1000 {
+            x = rk4.step(&model, &x, &u, i as f64 * dt, dt);
+        }
+        let work: f64 = 600.0 * x[0];
+        let used: f64 = model.battery.energy(0.9) - model.battery.energy(x[4]);
+        assert!(used > work / 0.9);
+        assert!(used < 1.02 * work / 0.9);
+    }
+
+    #[test]
+    fn test_electric_jacobian_matches_fun() {
+        let model = model();
+        assert_jacobian(
+            &model,
+            &[3.0, 1.0, 18.0, 6.0, 0.6, 40.0],
+            &[2000.0, 500.0],
+            0.0,
+            1e-6,
+        );
+        assert_jacobian(
+            &model,
+            &[3.0, 1.0, 18.0, 6.0, 0.6, 40.0],
+            &[-2000.0, 500.0],
+            0.0,
+            1e-6,
+        );
+    }
+
+    #[test]
+    fn test_electric_constraints() {
+        let model = model();
+        let x: Vec<f64> = vec![0.0, 0.0, 25.0, 0.0, 0.5, 30.0];
+
+        // Moderate power is feasible
+        assert!(model
+            .constraints(&x, &[1000.0, 0.0])
+            .iter()
+            .all(|g| *g <= 0.0));
+
+        // Hard acceleration exceeds the discharge current and sags the voltage
+        let g = model.constraints(&x, &[6000.0, 0.0]);
+        assert!(g[0] > 0.0);
+        assert!(g[2] > 0.0);
+
+        // Hard braking exceeds the regeneration current
+        assert!(model.constraints(&x, &[-5000.0, 0.0])[1] > 0.0);
+
+        // Jacobian against central differences
+        let u: Vec<f64> = vec![2500.0, 300.0];
+        let jac = model.constraints_jac(&x, &u);
+        let fd = finite_difference_jac(|x, u| model.constraints(x, u), &x, &u, 4);
+        let check = compare_jacobians(&jac, &fd, 6, 2, 1e-6);
+        assert!(check.passed(), "{}", check);
+    }
+
+    #[test]
+    fn test_electric_motor_power_limit() {
+        let mut model = model();
+        let x: Vec<f64> = vec![0.0, 0.0, 25.0, 0.0, 0.5, 30.0];
+
+        // Without a limit the motor never binds
+        assert_eq!(model.constraints(&x, &[1e6, 0.0])[3], -1.0);
+
+        // At 25 m/s an 80 kW motor allows 3.2 kN, while braking is not limited by it
+        model.max_motor_power = 80e3;
+        assert!(model.constraints(&x, &[3100.0, 0.0])[3] < 0.0);
+        assert!(model.constraints(&x, &[3300.0, 0.0])[3] > 0.0);
+        assert!(model.constraints(&x, &[-5000.0, 0.0])[3] < 0.0);
+
+        let u: Vec<f64> = vec![2500.0, 300.0];
+        let jac = model.constraints_jac(&x, &u);
+        let fd = finite_difference_jac(|x, u| model.constraints(x, u), &x, &u, 4);
+        let check = compare_jacobians(&jac, &fd, 6, 2, 1e-6);
+        assert!(check.passed(), "{}", check);
+    }
+}
@@ -0,0 +1,498 @@
+use crate::aero::{Aero, AeroState};
+use crate::model::tyre_states::GripScaled;
+use crate::powertrain::Powertrain;
+use simulation_toolbox::Model;
+
+// Point mass with aerodynamic drag and downforce, rolling resistance, an engine power limit
+// and a friction ellipse, giving the "g-g-v" lap time model. The inputs are the tyre forces
+// in the global frame. The dynamics only add the resistive forces, while the power limit and
+// the friction ellipse bound the inputs and are exposed as constraints g(x, u) <= 0. With a
+// powertrain the power limit is replaced by its maximum tractive force at the current speed.
+// With an aero model the drag and downforce factors come from it at aero_state, which can be
+// updated between steps, e.g. opening the DRS inside the aero zones of the track. The grip
+// multiplier of GripScaled scales both friction coefficients.
+pub struct ExtendedPointMass {
+    // Properties
+    name: String,
+    // Parameters
+    pub parameters: ExtendedPointMassParameters,
+    pub powertrain: Option<Powertrain>,
+    pub aero: Option<Aero>,
+    pub aero_state: AeroState,
+    pub slip_at_limit: f64, // Slip ratio and slip angle at the friction limit [-]
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtendedPointMassParameters {
+    pub mass: f64,               // Vehicle mass [kg]
+    pub drag_factor: f64,        // 0.5 rho Cd A [kg/m]
+    pub downforce_factor: f64,   // 0.5 rho Cl A [kg/m]
+    pub rolling_resistance: f64, // Rolling resistance coefficient [-]
+    pub max_power: f64,          // Engine power at the wheels [W]
+    pub mu_longitudinal: f64,    // Longitudinal friction coefficient [-]
+    pub mu_lateral: f64,         // Lateral friction coefficient [-]
+}
+
+const GRAVITY: f64 = 9.81; // [m/s^2]
+
+// The direction of travel is undefined at standstill, so the speed is regularised as
+// sqrt(vx^2 + vy^2 + eps^2). The constraints are only meaningful while moving.
+const SPEED_EPSILON: f64 = 0.1; // [m/s]
+
+impl ExtendedPointMass {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(name: &str, parameters: ExtendedPointMassParameters) -> Self {
+        return ExtendedPointMass {
+            name: name.to_string(),
+            parameters,
+            powertrain: None,
+            aero: None,
+            aero_state: AeroState::default(),
+            slip_at_limit: 0.1,
+        };
+    }
+
+    fn speed(&self, x: &[f64]) -> f64 {
+        return f64::sqrt(x[2].powi(2) + x[3].powi(2) + SPEED_EPSILON.powi(2));
+    }
+
+    // 0.5 rho Cd A [kg/m] from the aero model if present
+    fn drag_factor(&self) -> f64 {
+        return match &self.aero {
+            Some(aero) => aero.drag_factor(&self.aero_state),
+            None => self.parameters.drag_factor,
+        };
+    }
+
+    // 0.5 rho Cl A [kg/m] from the aero model if present
+    fn downforce_factor(&self) -> f64 {
+        return match &self.aero {
+            Some(aero) => aero.downforce_factor(&self.aero_state),
+            None => self.parameters.downforce_factor,
+        };
+    }
+
+    // Normal load, weight plus downforce, at speed v [N]
+    pub fn normal_load(&self, v: f64) -> f64 {
+        let p: &ExtendedPointMassParameters = &self.parameters;
+        return p.mass * GRAVITY + self.downforce_factor() * v.powi(2);
+    }
+
+    // Resistance coefficient c(v) such that the resistive force is -c(v) * velocity, and its
+    // derivative with respect to the speed
+    fn resistance(&self, v: f64) -> (f64, f64) {
+        let p: &ExtendedPointMassParameters = &self.parameters;
+        let drag_factor: f64 = self.drag_factor();
+        let c: f64 = drag_factor * v + p.rolling_resistance * self.normal_load(v) / v;
+        let dc_dv: f64 = drag_factor
+            + p.rolling_resistance * (self.downforce_factor() - p.mass * GRAVITY / v.powi(2));
+        return (c, dc_dv);
+    }
+
+    #[allow(dead_code)]
+    pub fn n_constraints(&self) -> usize {
+        return 2; // [power, friction ellipse]
+    }
+
+    // Constraint values, feasible when every entry is <= 0. The power constraint is scaled
+    // by the maximum power and the tractive force constraint by the weight, so that both
+    // entries are dimensionless.
+    #[allow(dead_code)]
+    pub fn constraints(&self, x: &[f64], u: &[f64]) -> Vec<f64> {
+        return self.constraints_with_grip(x, u, 1.0);
+    }
+
+    // Constraint values with both friction coefficients scaled by the grip multiplier
+    pub fn constraints_with_grip(&self, x: &[f64], u: &[f64], grip: f64) -> Vec<f64> {
+        let p: &ExtendedPointMassParameters = &self.parameters;
+        let v: f64 = self.speed(x);
+        let n: f64 = self.normal_load(v);
+
+        // Force split into the direction of travel and perpendicular to it
+        let f_long: f64 = (u[0] * x[2] + u[1] * x[3]) / v;
+        let f_lat: f64 = (u[1] * x[2] - u[0] * x[3]) / v;
+
+        let drive: f64 = match &self.powertrain {
+            Some(powertrain) => (f_long - powertrain.max_tractive_force(v).0) / (p.mass * GRAVITY),
+            None => (u[0] * x[2] + u[1] * x[3]) / p.max_power - 1.0,
+        };
+        return vec![
+            drive,
+            ((f_long / (p.mu_longitudinal * n)).powi(2) + (f_lat / (p.mu_lateral * n)).powi(2))
+                / grip.powi(2)
+                - 1.0,
+        ];
+    }
+
+    // Row-major Jacobian of the constraints with respect to [x, y, vx, vy, Fx, Fy]
+    #[allow(dead_code)]
+    pub fn constraints_jac(&self, x: &[f64], u: &[f64]) -> Vec<f64> {
+        return self.constraints_jac_with_grip(x, u, 1.0).0;
+    }
+
+    // Jacobian of the constraints with the grip multiplier and their derivative with respect
+    // to the grip
+    pub fn constraints_jac_with_grip(
+        &self,
+        x: &[f64],
+        u: &[f64],
+        grip: f64,
+    ) -> (Vec<f64>, Vec<f64>) {
+        let p: &ExtendedPointMassParameters = &self.parameters;
+        let n_cols: usize = self.n_x() + self.n_u();
+        let mut jac: Vec<f64> = vec![0.0; self.n_constraints() * n_cols];
+        let (vx, vy, fx, fy) = (x[2], x[3], u[0], u[1]);
+        let v: f64 = self.speed(x);
+        let n: f64 = self.normal_load(v);
+        let (f_long, f_lat, df_long, df_lat) = self.force_split(x, u);
+
+        match &self.powertrain {
+            // Tractive force
+            Some(powertrain) => {
+                let (_, dforce_dv) = powertrain.max_tractive_force(v);
+                let weight: f64 = p.mass * GRAVITY;
+                jac[2] = (df_long[0] - dforce_dv * vx / v) / weight; // dg0/dx2
+                jac[3] = (df_long[1] - dforce_dv * vy / v) / weight; // dg0/dx3
+                jac[4] = df_long[2] / weight; // dg0/du0
+                jac[5] = df_long[3] / weight; // dg0/du1
+            }
+            // Power
+            None => {
+                jac[2] = fx / p.max_power; // dg0/dx2
+                jac[3] = fy / p.max_power; // dg0/dx3
+                jac[4] = vx / p.max_power; // dg0/du0
+                jac[5] = vy / p.max_power; // dg0/du1
+            }
+        }
+
+        // Friction ellipse
+        let a: f64 = f_long / (p.mu_longitudinal * n);
+        let b: f64 = f_lat / (p.mu_lateral * n);
+        let dn: [f64; 4] = self.normal_load_gradient(x);
+        let grip_squared: f64 = grip.powi(2);
+        for k in 0..4 {
+            let da: f64 = df_long[k] / (p.mu_longitudinal * n) - a * dn[k] / n;
+            let db: f64 = df_lat[k] / (p.mu_lateral * n) - b * dn[k] / n;
+            jac[n_cols + 2 + k] = (2.0 * a * da + 2.0 * b * db) / grip_squared; // dg1/d[vx, vy, Fx, Fy]
+        }
+
+        let dgrip: Vec<f64> = vec![0.0, -2.0 * (a.powi(2) + b.powi(2)) / grip.powi(3)];
+        return (jac, dgrip);
+    }
+
+    // Input force split into the direction of travel and perpendicular to it, with the
+    // derivatives of both over [vx, vy, Fx, Fy]
+    fn force_split(&self, x: &[f64], u: &[f64]) -> (f64, f64, [f64; 4], [f64; 4]) {
+        let (vx, vy, fx, fy) = (x[2], x[3], u[0], u[1]);
+        let v: f64 = self.speed(x);
+        let f_long: f64 = (fx * vx + fy * vy) / v;
+        let f_lat: f64 = (fy * vx - fx * vy) / v;
+        let df_long: [f64; 4] = [
+            fx / v - f_long * vx / v.powi(2),
+            fy / v - f_long * vy / v.powi(2),
+            vx / v,
+            vy / v,
+        ];
+        let df_lat: [f64; 4] = [
+            fy / v - f_lat * vx / v.powi(2),
+            -fx / v - f_lat * vy / v.powi(2),
+            -vy / v,
+            vx / v,
+        ];
+        return (f_long, f_lat, df_long, df_lat);
+    }
+
+    // Derivatives of the normal load over [vx, vy, Fx, Fy]
+    fn normal_load_gradient(&self, x: &[f64]) -> [f64; 4] {
+        let c: f64 = self.downforce_factor();
+        return [2.0 * c * x[2], 2.0 * c * x[3], 0.0, 0.0];
+    }
+}
+
+impl Model for ExtendedPointMass {
+    fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    fn n_x(&self) -> usize {
+        return 4; // [x, y, vx, vy]
+    }
+
+    fn n_u(&self) -> usize {
+        return 2; // [Fx, Fy]
+    }
+
+    fn fun(&self, x: &Vec<f64>, u: &Vec<f64>, _t: f64) -> Vec<f64> {
+        let mut dx: Vec<f64> = vec![0.0; self.n_x()];
+        let m: f64 = self.parameters.mass;
+        let (c, _) = self.resistance(self.speed(x));
+
+        // dx/dt = v
+        dx[0] = x[2];
+        dx[1] = x[3];
+
+        // dv/dt = (F - c(v) v) / m with drag and rolling resistance opposing the motion
+        dx[2] = (u[0] - c * x[2]) / m;
+        dx[3] = (u[1] - c * x[3]) / m;
+
+        return dx;
+    }
+
+    fn jac(&self, x: &Vec<f64>, _u: &Vec<f64>, _t: f64) -> Vec<f64> {
+        let nx = self.n_x();
+        let nu = self.n_u();
+        let mut jac: Vec<f64> = vec![0.0; nx * (nx + nu)];
+
+        let m: f64 = self.parameters.mass;
+        let (vx, vy) = (x[2], x[3]);
+        let v: f64 = self.speed(x);
+        let (c, dc_dv) = self.resistance(v);
+
+        // Jacobian is expected to be row major
+        jac[0] = 0.0; // dx0/dx0
+        jac[1] = 0.0; // dx0/dx1
+        jac[2] = 1.0; // dx0/dx2
+        jac[3] = 0.0; // dx0/dx3
+        jac[4] = 0.0; // dx0/du0
+        jac[5] = 0.0; // dx0/du1
+
+        jac[6] = 0.0; // dx1/dx0
+        jac[7] = 0.0; // dx1/dx1
+        jac[8] = 0.0; // dx1/dx2
+        jac[9] = 1.0; // dx1/dx3
+        jac[10] = 0.0; // dx1/du0
+        jac[11] = 0.0; // dx1/du1
+
+        jac[12] = 0.0; // dx2/dx0
+        jac[13] = 0.0; // dx2/dx1
+        jac[14] = -(c + dc_dv * vx * vx / v) / m; // dx2/dx2
+        jac[15] = -dc_dv * vx * vy / v / m; // dx2/dx3
+        jac[16] = 1.0 / m; // dx2/du0
+        jac[17] = 0.0; // dx2/du1
+
+        jac[18] = 0.0; // dx3/dx0
+        jac[19] = 0.0; // dx3/dx1
+        jac[20] = -dc_dv * vx * vy / v / m; // dx3/dx2
+        jac[21] = -(c + dc_dv * vy * vy / v) / m; // dx3/dx3
+        jac[22] = 0.0; // dx3/du0
+        jac[23] = 1.0 / m; // dx3/du1
+
+        return jac;
+    }
+}
+
+// The inputs are the tyre forces, so the grip only enters the friction ellipse of the
+// constraints. Without a slip model the tyres are taken to slide in each direction at a
+// fraction of the speed growing linearly with the use of that friction coefficient, up to
+// slip_at_limit at the limit, so that
+//     P = slip_at_limit v (F_long^2 / mu_long + F_lat^2 / mu_lat) / (grip N).
+impl GripScaled for ExtendedPointMass {
+    fn fun_with_grip(&self, x: &[f64], u: &[f64], _grip: f64) -> Vec<f64> {
+        return self.fun(&x.to_vec(), &u.to_vec(), 0.0);
+    }
+
+    fn jac_with_grip(&self, x: &[f64], u: &[f64], _grip: f64) -> (Vec<f64>, Vec<f64>) {
+        return (
+            self.jac(&x.to_vec(), &u.to_vec(), 0.0),
+            vec![0.0; self.n_x()],
+        );
+    }
+
+    fn sliding_power(&self, x: &[f64], u: &[f64], grip: f64) -> (f64, Vec<f64>, f64) {
+        let p: &ExtendedPointMassParameters = &self.parameters;
+        let v: f64 = self.speed(x);
+        let n: f64 = self.normal_load(v);
+        let (f_long, f_lat, df_long, df_lat) = self.force_split(x, u);
+        let dn: [f64; 4] = self.normal_load_gradient(x);
+        let dv: [f64; 4] = [x[2] / v, x[3] / v, 0.0, 0.0];
+
+        // q = F_long^2 / mu_long + F_lat^2 / mu_lat
+        let q: f64 = f_long.powi(2) / p.mu_longitudinal + f_lat.powi(2) / p.mu_lateral;
+        let factor: f64 = self.slip_at_limit / grip;
+        let power: f64 = factor * v * q / n;
+
+        let mut dpower: Vec<f64> = vec![0.0; self.n_x() + self.n_u()];
+        for k in 0..4 {
+            let dq: f64 = 2.0 * f_long * df_long[k] / p.mu_longitudinal
+                + 2.0 * f_lat * df_lat[k] / p.mu_lateral;
+            dpower[2 + k] = factor * (dv[k] * q + v * dq - v * q * dn[k] / n) / n;
+            // dP/d[vx, vy, Fx, Fy]
+        }
+        return (power, dpower, -power / grip);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aero::Drs;
+    use crate::model::finite_difference::{
+        assert_jacobian, compare_jacobians, finite_difference_jac,
+    };
+    use crate::track::{AeroZone, Track};
+
+    fn example_model() -> ExtendedPointMass {
+        return ExtendedPointMass::new(
+            "TestExtendedPointMass",
+            ExtendedPointMassParameters {
+                mass: 800.0,
+                drag_factor: 0.9,
+                downforce_factor: 2.0,
+                rolling_resistance: 0.015,
+                max_power: 400e3,
+                mu_longitudinal: 1.6,
+                mu_lateral: 1.8,
+            },
+        );
+    }
+
+    #[test]
+    fn test_extended_point_mass_coasting() {
+        let model = example_model();
+
+        // Coasting at speed decelerates by drag plus rolling resistance on the normal load
+        let v: f64 = 50.0;
+        let x: Vec<f64> = vec![0.0, 0.0, v, 0.0];
+        let dx = model.fun(&x, &vec![0.0, 0.0], 0.0);
+        let v_reg: f64 = f64::sqrt(v * v + 0.01);
+        let resistance: f64 =
+            0.9 * v_reg * v + 0.015 * (800.0 * 9.81 + 2.0 * v_reg.powi(2)) * v / v_reg;
+        assert!((dx[0] - v).abs() < 1e-12);
+        assert!((dx[2] + resistance / 800.0).abs() < 1e-9);
+        assert!(dx[3].abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_aero_drs() {
+        // The aero model replaces the constant factors, and opening the DRS on the straight
+        // reduces the drag and the downforce
+        let mut model = example_model();
+        let mut aero: Aero = Aero::new(1.2, 1.0, 1.5, 3.3, 0.45);
+        aero.drs = Some(Drs {
+            drag_reduction: 0.3,
+            downforce_reduction: 0.4,
+            balance_shift: 0.0,
+        });
+        model.aero = Some(aero);
+        let x: Vec<f64> = vec![0.0, 0.0, 60.0, 0.0];
+        let closed = model.fun(&x, &vec![0.0, 0.0], 0.0);
+        assert!((model.normal_load(60.0) - (800.0 * 9.81 + 0.6 * 3.3 * 3600.0)).abs() < 1e-9);
+
+        let mut track: Track = Track::straight(500.0, 10.0);
+        track.aero_zones.push(AeroZone {
+            start: 100.0,
+            end: 400.0,
+        });
+        model.aero_state.drs = if track.in_aero_zone(250.0) { 1.0 } else { 0.0 };
+        let open = model.fun(&x, &vec![0.0, 0.0], 0.0);
+        assert!(open[2] > closed[2]);
+        assert!((model.normal_load(60.0) - (800.0 * 9.81 + 0.6 * 0.6 * 3.3 * 3600.0)).abs() < 1e-9);
+
+        let u: Vec<f64> = vec![3000.0, 5000.0];
+        let x: Vec<f64> = vec![1.0, 2.0, 30.0, -12.0];
+        assert_jacobian(&model, &x, &u, 0.0, 1e-6);
+    }
+
+    #[test]
+    fn test_extended_point_mass_jacobian_matches_fun() {
+        let model = example_model();
+        let x: Vec<f64> = vec![1.0, 2.0, 30.0, -12.0];
+        let u: Vec<f64> = vec![3000.0, 5000.0];
+        assert_jacobian(&model, &x, &u, 0.0, 1e-6);
+    }
+
+    #[test]
+    fn test_power_limit() {
+        let model = example_model();
+
+        // At 80 m/s the power limit allows 5 kN of tractive force
+        let x: Vec<f64> = vec![0.0, 0.0, 80.0, 0.0];
+        assert!(model.constraints(&x, &[4900.0, 0.0])[0] < 0.0);
+        assert!(model.constraints(&x, &[5100.0, 0.0])[0] > 0.0);
+
+        // Braking and lateral forces are not limited by the engine
+        assert!(model.constraints(&x, &[-20000.0, 15000.0])[0] < 0.0);
+    }
+
+    #[test]
+    fn test_powertrain_limit() {
+        let mut model = example_model();
+        let map = crate::powertrain::TorqueMap::from_csv_str("0, 400\n6000, 400\n12000, 200\n");
+        let powertrain: Powertrain = Powertrain::new(
+            crate::powertrain::MotorKind::Electric,
+            map.unwrap(),
+            vec![8.0],
+            1.0,
+            0.9,
+            0.3,
+        )
+        .unwrap();
+        model.powertrain = Some(powertrain.clone());
+
+        // The tractive force limit replaces the power limit
+        let x: Vec<f64> = vec![0.0, 0.0, 30.0, 0.0];
+        let (f_max, _) = powertrain.max_tractive_force(f64::sqrt(900.0 + 0.01));
+        assert!(model.constraints(&x, &[0.99 * f_max, 0.0])[0] < 0.0);
+        assert!(model.constraints(&x, &[1.01 * f_max, 0.0])[0] > 0.0);
+
+        let x: Vec<f64> = vec![1.0, 2.0, 30.0, -12.0];
+        let u: Vec<f64> = vec![3000.0, 5000.0];
+        let jac = model.constraints_jac(&x, &u);
+        let fd = finite_difference_jac(|x, u| model.constraints(x, u), &x, &u, 2);
+        let check = compare_jacobians(&jac, &fd, 4, 2, 1e-6);
+        assert!(check.passed(), "{}", check);
+    }
+
+    #[test]
+    fn test_friction_ellipse_grows_with_downforce() {
+        let model = example_model();
+        let weight: f64 = 800.0 * 9.81;
+
+        // At low speed the lateral limit is close to mu times the weight
+        let slow: Vec<f64> = vec![0.0, 0.0, 5.0, 0.0];
+        assert!(model.constraints(&slow, &[0.0, 0.99 * 1.8 * weight])[1] < 0.0);
+        assert!(model.constraints(&slow, &[0.0, 1.1 * 1.8 * weight])[1] > 0.0);
+
+        // At speed the downforce allows more grip in both directions
+        let fast: Vec<f64> = vec![0.0, 0.0, 60.0, 0.0];
+        assert!(model.constraints(&fast, &[0.0, 1.5 * 1.8 * weight])[1] < 0.0);
+        assert!(model.constraints(&fast, &[-1.5 * 1.6 * weight, 0.0])[1] < 0.0);
+
+        // Combined forces on the ellipse boundary
+        let n: f64 = model.normal_load(f64::sqrt(3600.0 + 0.01));
+        let (a, b) = (0.6 * 1.6 * n, 0.8 * 1.8 * n);
+        assert!(model.constraints(&fast, &[a, b])[1].abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_constraints_jac_matches_constraints() {
+        let model = example_model();
+        let x: Vec<f64> = vec![1.0, 2.0, 30.0, -12.0];
+        let u: Vec<f64> = vec![3000.0, 5000.0];
+        let jac = model.constraints_jac(&x, &u);
+        let fd = finite_difference_jac(|x, u| model.constraints(x, u), &x, &u, 2);
+        let check = compare_jacobians(&jac, &fd, 4, 2, 1e-6);
+        assert!(check.passed(), "{}", check);
+    }
+
+    #[test]
+    fn test_sliding_power() {
+        let model = example_model();
+
+        // On the lateral friction limit the tyres slide at slip_at_limit times the speed
+        let x: Vec<f64> = vec![0.0, 0.0, 40.0, 0.0];
+        let v: f64 = f64::sqrt(1600.0 + 0.01);
+        let f_lat: f64 = 1.8 * model.normal_load(v);
+        let (power, _, dpower_dgrip) = model.sliding_power(&x, &[0.0, f_lat * v / 40.0], 1.0);
+        assert!((power - 0.1 * v * f_lat).abs() < 1e-6 * power);
+        assert!((dpower_dgrip + power).abs() < 1e-6 * power);
+        assert_eq!(model.sliding_power(&x, &[0.0, 0.0], 1.0).0, 0.0);
+
+        let x: Vec<f64> = vec![1.0, 2.0, 30.0, -12.0];
+        let u: Vec<f64> = vec![3000.0, 5000.0];
+        let (_, dpower, _) = model.sliding_power(&x, &u, 0.8);
+        let fd = finite_difference_jac(|x, u| vec![model.sliding_power(x, u, 0.8).0], &x, &u, 1);
+        let check = compare_jacobians(&dpower, &fd, 4, 2, 1e-6);
+        assert!(check.passed(), "{}", check);
+    }
+}
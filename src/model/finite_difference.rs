@@ -0,0 +1,306 @@
+use simulation_toolbox::Model;
+
+// Central finite-difference checks of hand-written Jacobians. The step for each variable is
+// relative to its magnitude, h_j = STEP * max(1, |z_j|), and the relative error of an entry
+// is |exact - fd| / (1 + |exact|), so small entries are compared absolutely and large ones
+// relatively. Columns and rows are named as in the row-major nx * (nx + nu) layout, x0 to
+// x(nx-1) for states and u0 to u(nu-1) for inputs, and dxi for the rows.
+
+const STEP: f64 = 1e-6;
+
+// Largest number of mismatching entries listed in a report
+const MAX_REPORTED: usize = 10;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct JacobianEntry {
+    pub row: usize,
+    pub col: usize,
+    pub exact: f64,
+    pub finite_difference: f64,
+    pub relative_error: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct JacobianCheck {
+    pub n_x: usize,
+    pub n_u: usize,
+    pub max_relative_error: f64,
+    pub mismatches: Vec<JacobianEntry>, // Entries above the tolerance, worst first
+}
+
+// Row-major central-difference Jacobian of f(x, u) with n_rows outputs over [x, u]
+pub fn finite_difference_jac<F: Fn(&[f64], &[f64]) -> Vec<f64>>(
+    f: F,
+    x: &[f64],
+    u: &[f64],
+    n_rows: usize,
+) -> Vec<f64> {
+    let n_cols: usize = x.len() + u.len();
+    let mut jac: Vec<f64> = vec![0.0; n_rows * n_cols];
+    for col in 0..n_cols {
+        let (mut x_p, mut x_m, mut u_p, mut u_m) = (x.to_vec(), x.to_vec(), u.to_vec(), u.to_vec());
+        let h: f64 = if col < x.len() {
+            let h: f64 = STEP * x[col].abs().max(1.0);
+            x_p[col] += h;
+            x_m[col] -= h;
+            h
+        } else {
+            let h: f64 = STEP * u[col - x.len()].abs().max(1.0);
+            u_p[col - x.len()] += h;
+            u_m[col - x.len()] -= h;
+            h
+        };
+        let (f_p, f_m) = (f(&x_p, &u_p), f(&x_m, &u_m));
+        for row in 0..n_rows {
+            jac[row * n_cols + col] = (f_p[row] - f_m[row]) / (2.0 * h);
+        }
+    }
+    return jac;
+}
+
+// Compare two row-major Jacobians over [x, u] with n_x states, listing the entries whose
+// relative error exceeds the tolerance
+pub fn compare_jacobians(
+    exact: &[f64],
+    approx: &[f64],
+    n_x: usize,
+    n_u: usize,
+    tolerance: f64,
+) -> JacobianCheck {
+    let n_cols: usize = n_x + n_u;
+    let mut max_relative_error: f64 = 0.0;
+    let mut mismatches: Vec<JacobianEntry> = Vec::new();
+    for (i, (e, a)) in exact.iter().zip(approx.iter()).enumerate() {
+        let relative_error: f64 = (e - a).abs() / (1.0 + e.abs());
+        // NaN compares false, so it is caught explicitly
+        if relative_error > max_relative_error || relative_error.is_nan() {
+            max_relative_error = relative_error;
+        }
+        if relative_error > tolerance || relative_error.is_nan() {
+            mismatches.push(JacobianEntry {
+                row: i / n_cols,
+                col: i % n_cols,
+                exact: *e,
+                finite_difference: *a,
+                relative_error,
+            });
+        }
+    }
+    mismatches.sort_by(|a, b| b.relative_error.total_cmp(&a.relative_error));
+    return JacobianCheck {
+        n_x,
+        n_u,
+        max_relative_error,
+        mismatches,
+    };
+}
+
+// Check Model::jac against central differences of Model::fun at (x, u, t)
+pub fn check_jacobian<M: Model + ?Sized>(
+    model: &M,
+    x: &[f64],
+    u: &[f64],
+    t: f64,
+    tolerance: f64,
+) -> JacobianCheck {
+    let fd: Vec<f64> = finite_difference_jac(
+        |x, u| model.fun(&x.to_vec(), &u.to_vec(), t),
+        x,
+        u,
+        model.n_x(),
+    );
+    let exact: Vec<f64> = model.jac(&x.to_vec(), &u.to_vec(), t);
+    return compare_jacobians(&exact, &fd, model.n_x(), model.n_u(), tolerance);
+}
+
+// Check Model::jac at n_points pseudo-random points drawn uniformly from the bounds of each
+// state, input and the time, reproducible through the seed. Returns the check with the
+// largest error.
+#[allow(dead_code)]
+pub fn check_jacobian_random<M: Model + ?Sized>(
+    model: &M,
+    x_bounds: &[(f64, f64)],
+    u_bounds: &[(f64, f64)],
+    t_bounds: (f64, f64),
+    n_points: usize,
+    seed: u64,
+    tolerance: f64,
+) -> JacobianCheck {
+    let mut state: u64 = seed.max(1);
+    let mut sample = |(lower, upper): (f64, f64)| -> f64 {
+        // xorshift64 mapped to [0, 1)
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        return lower + (upper - lower) * (state >> 11) as f64 / (1u64 << 53) as f64;
+    };
+
+    let mut worst: Option<JacobianCheck> = None;
+    for _ in 0..n_points {
+        let x: Vec<f64> = x_bounds.iter().map(|b| sample(*b)).collect();
+        let u: Vec<f64> = u_bounds.iter().map(|b| sample(*b)).collect();
+        let t: f64 = sample(t_bounds);
+        let check: JacobianCheck = check_jacobian(model, &x, &u, t, tolerance);
+        let is_worse: bool = match &worst {
+            Some(w) => {
+                check.max_relative_error > w.max_relative_error || check.max_relative_error.is_nan()
+            }
+            None => true,
+        };
+        if is_worse {
+            worst = Some(check);
+        }
+    }
+    return worst.expect("at least one point is needed");
+}
+
+// Panic with the report if Model::jac differs from central differences at (x, u, t)
+#[allow(dead_code)]
+pub fn assert_jacobian<M: Model + ?Sized>(model: &M, x: &[f64], u: &[f64], t: f64, tolerance: f64) {
+    let check: JacobianCheck = check_jacobian(model, x, u, t, tolerance);
+    assert!(
+        check.passed(),
+        "{} Jacobian mismatch\n{}",
+        model.name(),
+        check
+    );
+}
+
+// JACOBIANCHECK IMPLEMENTATION ++++++++++++++++++++++++
+impl JacobianCheck {
+    pub fn passed(&self) -> bool {
+        return self.mismatches.is_empty();
+    }
+
+    // Name of a column, x<j> for states and u<j> for inputs
+    pub fn column_name(&self, col: usize) -> String {
+        return match col < self.n_x {
+            true => format!("x{}", col),
+            false => format!("u{}", col - self.n_x),
+        };
+    }
+}
+
+impl std::fmt::Display for JacobianCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} mismatching entries, max relative error {:e}",
+            self.mismatches.len(),
+            self.max_relative_error
+        )?;
+        for entry in self.mismatches.iter().take(MAX_REPORTED) {
+            writeln!(
+                f,
+                "  dx{}/d{}: exact {:e}, finite difference {:e}, relative error {:e}",
+                entry.row,
+                self.column_name(entry.col),
+                entry.exact,
+                entry.finite_difference,
+                entry.relative_error
+            )?;
+        }
+        if self.mismatches.len() > MAX_REPORTED {
+            writeln!(f, "  ... and {} more", self.mismatches.len() - MAX_REPORTED)?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::point_mass::PointMass;
+
+    // Point mass with a deliberately wrong entry dx2/du0
+    struct WrongJacobian {
+        model: PointMass,
+    }
+
+    impl Model for WrongJacobian {
+        fn name(&self) -> &str {
+            return "WrongJacobian";
+        }
+
+        fn n_x(&self) -> usize {
+            return self.model.n_x();
+        }
+
+        fn n_u(&self) -> usize {
+            return self.model.n_u();
+        }
+
+        fn fun(&self, x: &Vec<f64>, u: &Vec<f64>, t: f64) -> Vec<f64> {
+            return self.model.fun(x, u, t);
+        }
+
+        fn jac(&self, x: &Vec<f64>, u: &Vec<f64>, t: f64) -> Vec<f64> {
+            let mut jac: Vec<f64> = self.model.jac(x, u, t);
+            jac[16] *= 2.0;
+            return jac;
+        }
+    }
+
+    #[test]
+    fn test_finite_difference_jac() {
+        // f = [x0 * u0, x0^2] has the Jacobian [[u0, x0], [2 x0, 0]]
+        let f = |x: &[f64], u: &[f64]| vec![x[0] * u[0], x[0].powi(2)];
+        let jac: Vec<f64> = finite_difference_jac(f, &[3.0], &[-2.0], 2);
+        let expected: [f64; 4] = [-2.0, 3.0, 6.0, 0.0];
+        for (a, b) in jac.iter().zip(expected.iter()) {
+            assert!((a - b).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn test_correct_jacobian_passes() {
+        let model: PointMass = PointMass::new("Mass", 2.0);
+        let check: JacobianCheck =
+            check_jacobian(&model, &[1.0, 2.0, 3.0, 4.0], &[5.0, 6.0], 0.0, 1e-6);
+        assert!(check.passed());
+        assert!(check.max_relative_error < 1e-8);
+        assert_jacobian(&model, &[1.0, 2.0, 3.0, 4.0], &[5.0, 6.0], 0.0, 1e-6);
+    }
+
+    #[test]
+    fn test_wrong_entry_is_reported() {
+        let model = WrongJacobian {
+            model: PointMass::new("Mass", 2.0),
+        };
+        let check: JacobianCheck =
+            check_jacobian(&model, &[1.0, 2.0, 3.0, 4.0], &[5.0, 6.0], 0.0, 1e-6);
+        assert!(!check.passed());
+        assert_eq!(check.mismatches.len(), 1);
+        let entry: &JacobianEntry = &check.mismatches[0];
+        assert_eq!((entry.row, entry.col), (2, 4));
+        assert_eq!(check.column_name(entry.col), "u0");
+        assert!((entry.relative_error - 0.5 / 2.0).abs() < 1e-6);
+
+        let report: String = check.to_string();
+        assert!(report.starts_with("1 mismatching entries"));
+        assert!(report.contains("dx2/du0: exact 1e0"));
+    }
+
+    #[test]
+    fn test_random_points() {
+        let model = WrongJacobian {
+            model: PointMass::new("Mass", 2.0),
+        };
+        let bounds: Vec<(f64, f64)> = vec![(-10.0, 10.0); 4];
+        let check: JacobianCheck =
+            check_jacobian_random(&model, &bounds, &[(-1.0, 1.0); 2], (0.0, 1.0), 5, 42, 1e-6);
+        assert!(!check.passed());
+
+        let correct: PointMass = PointMass::new("Mass", 2.0);
+        let check: JacobianCheck = check_jacobian_random(
+            &correct,
+            &bounds,
+            &[(-1.0, 1.0); 2],
+            (0.0, 1.0),
+            5,
+            42,
+            1e-6,
+        );
+        assert!(check.passed());
+    }
+}
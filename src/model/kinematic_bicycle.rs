@@ -0,0 +1,184 @@
+use crate::model::metadata::{unbounded, ModelMetadata, Variable};
+use simulation_toolbox::Model;
+
+// Kinematic single-track model with the velocity defined at the centre of gravity.
+// The slip angle at the CG follows from the geometry alone, beta = atan(l_r / L * tan(delta)),
+// so the model is only valid at low lateral acceleration where tyre slip is negligible.
+pub struct KinematicBicycle {
+    // Properties
+    name: String,
+    // Parameters
+    pub wheelbase: f64,  // Distance between front and rear axle [m]
+    pub rear_to_cg: f64, // Distance from rear axle to centre of gravity [m]
+}
+
+impl KinematicBicycle {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(name: &str, wheelbase: f64, rear_to_cg: f64) -> Self {
+        return KinematicBicycle {
+            name: name.to_string(),
+            wheelbase,
+            rear_to_cg,
+        };
+    }
+
+    // Slip angle at the CG and its derivative with respect to the steering angle
+    fn slip_angle(&self, delta: f64) -> (f64, f64) {
+        let k: f64 = self.rear_to_cg / self.wheelbase;
+        let tan_delta: f64 = delta.tan();
+        let beta: f64 = f64::atan(k * tan_delta);
+        let dbeta_ddelta: f64 = k * (1.0 + tan_delta.powi(2)) / (1.0 + (k * tan_delta).powi(2));
+        return (beta, dbeta_ddelta);
+    }
+}
+
+impl Model for KinematicBicycle {
+    fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    fn n_x(&self) -> usize {
+        return 4; // [x, y, psi, v]
+    }
+
+    fn n_u(&self) -> usize {
+        return 2; // [delta, a]
+    }
+
+    fn fun(&self, x: &Vec<f64>, u: &Vec<f64>, _t: f64) -> Vec<f64> {
+        let mut dx: Vec<f64> = vec![0.0; self.n_x()];
+        let psi: f64 = x[2];
+        let v: f64 = x[3];
+        let (beta, _) = self.slip_angle(u[0]);
+
+        // Velocity of the CG in the global frame
+        dx[0] = v * f64::cos(psi + beta);
+        dx[1] = v * f64::sin(psi + beta);
+
+        // Yaw rate from the rear axle kinematics
+        dx[2] = v / self.rear_to_cg * beta.sin();
+
+        // dv/dt = a
+        dx[3] = u[1];
+
+        return dx;
+    }
+
+    fn jac(&self, x: &Vec<f64>, u: &Vec<f64>, _t: f64) -> Vec<f64> {
+        let nx = self.n_x();
+        let nu = self.n_u();
+        let mut jac: Vec<f64> = vec![0.0; nx * (nx + nu)];
+
+        let psi: f64 = x[2];
+        let v: f64 = x[3];
+        let (beta, dbeta_ddelta) = self.slip_angle(u[0]);
+        let cos_course: f64 = f64::cos(psi + beta);
+        let sin_course: f64 = f64::sin(psi + beta);
+
+        // Jacobian is expected to be row major
+        jac[0] = 0.0; // dx0/dx0
+        jac[1] = 0.0; // dx0/dx1
+        jac[2] = -v * sin_course; // dx0/dx2
+        jac[3] = cos_course; // dx0/dx3
+        jac[4] = -v * sin_course * dbeta_ddelta; // dx0/du0
+        jac[5] = 0.0; // dx0/du1
+
+        jac[6] = 0.0; // dx1/dx0
+        jac[7] = 0.0; // dx1/dx1
+        jac[8] = v * cos_course; // dx1/dx2
+        jac[9] = sin_course; // dx1/dx3
+        jac[10] = v * cos_course * dbeta_ddelta; // dx1/du0
+        jac[11] = 0.0; // dx1/du1
+
+        jac[12] = 0.0; // dx2/dx0
+        jac[13] = 0.0; // dx2/dx1
+        jac[14] = 0.0; // dx2/dx2
+        jac[15] = beta.sin() / self.rear_to_cg; // dx2/dx3
+        jac[16] = v / self.rear_to_cg * beta.cos() * dbeta_ddelta; // dx2/du0
+        jac[17] = 0.0; // dx2/du1
+
+        jac[18] = 0.0; // dx3/dx0
+        jac[19] = 0.0; // dx3/dx1
+        jac[20] = 0.0; // dx3/dx2
+        jac[21] = 0.0; // dx3/dx3
+        jac[22] = 0.0; // dx3/du0
+        jac[23] = 1.0; // dx3/du1
+
+        return jac;
+    }
+}
+
+impl ModelMetadata for KinematicBicycle {
+    fn states(&self) -> Vec<Variable> {
+        return vec![
+            unbounded("x", "m", 100.0),
+            unbounded("y", "m", 100.0),
+            unbounded("psi", "rad", 1.0),
+            // The slip angle and the spatial form are singular at standstill
+            Variable::new("v", "m/s", 10.0, 0.0, f64::INFINITY),
+        ];
+    }
+
+    fn inputs(&self) -> Vec<Variable> {
+        // tan(delta) is singular at a right angle
+        let max_steer: f64 = std::f64::consts::FRAC_PI_2;
+        return vec![
+            Variable::new("delta", "rad", 0.1, -max_steer, max_steer),
+            unbounded("a", "m/s^2", 10.0),
+        ];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::finite_difference::assert_jacobian;
+
+    #[test]
+    fn test_kinematic_bicycle_dynamics() {
+        let model = KinematicBicycle::new("TestBicycle", 2.5, 1.0);
+
+        // Driving straight along y
+        let x: Vec<f64> = vec![0.0, 0.0, std::f64::consts::FRAC_PI_2, 10.0];
+        let u: Vec<f64> = vec![0.0, 2.0];
+        let dx = model.fun(&x, &u, 0.0);
+        assert!(dx[0].abs() < 1e-12);
+        assert!((dx[1] - 10.0).abs() < 1e-12);
+        assert!(dx[2].abs() < 1e-12);
+        assert!((dx[3] - 2.0).abs() < 1e-12);
+
+        // Steady-state turning: yaw rate is v * tan(delta) * cos(beta) / L
+        let delta: f64 = 0.1;
+        let x: Vec<f64> = vec![0.0, 0.0, 0.0, 10.0];
+        let u: Vec<f64> = vec![delta, 0.0];
+        let dx = model.fun(&x, &u, 0.0);
+        let beta: f64 = f64::atan(0.4 * delta.tan());
+        assert!((dx[2] - 10.0 * delta.tan() * beta.cos() / 2.5).abs() < 1e-12);
+        assert!((dx[1] - 10.0 * beta.sin()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_kinematic_bicycle_jacobian() {
+        let model = KinematicBicycle::new("TestBicycle", 2.5, 1.0);
+        let x: Vec<f64> = vec![0.0, 0.0, 0.0, 10.0];
+        let u: Vec<f64> = vec![0.0, 1.0];
+
+        // With zero steering and heading the Jacobian is known in closed form
+        let jac = model.jac(&x, &u, 0.0);
+        let expected_jac: Vec<f64> = vec![
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 10.0, 0.0, 4.0, 0.0, 0.0, 0.0, 0.0, 0.0, 4.0,
+            0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+        ];
+        for (j, e) in jac.iter().zip(expected_jac.iter()) {
+            assert!((j - e).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_kinematic_bicycle_jacobian_matches_fun() {
+        let model = KinematicBicycle::new("TestBicycle", 2.7, 1.2);
+        let x: Vec<f64> = vec![3.0, -2.0, 0.7, 15.0];
+        let u: Vec<f64> = vec![0.15, -1.0];
+        assert_jacobian(&model, &x, &u, 0.0, 1e-6);
+    }
+}
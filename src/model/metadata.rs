@@ -0,0 +1,138 @@
+use simulation_toolbox::Model;
+
+// Descriptions of the states and inputs of a model, so writers, plotting and the optimiser
+// can handle any model without hard-coding names and index positions.
+//
+// The scale is the nominal magnitude of a variable, used to normalise it for the optimiser,
+// and the bounds are the physically admissible range, infinite where there is none.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variable {
+    pub name: String,
+    pub unit: String,
+    pub scale: f64, // Nominal magnitude [unit]
+    pub lower: f64, // Lower bound [unit]
+    pub upper: f64, // Upper bound [unit]
+}
+
+#[allow(dead_code)]
+pub trait ModelMetadata: Model {
+    // One description per state, in state order
+    fn states(&self) -> Vec<Variable>;
+
+    // One description per input, in input order
+    fn inputs(&self) -> Vec<Variable>;
+
+    fn state_names(&self) -> Vec<String> {
+        return self.states().into_iter().map(|v| v.name).collect();
+    }
+
+    fn input_names(&self) -> Vec<String> {
+        return self.inputs().into_iter().map(|v| v.name).collect();
+    }
+
+    fn state_bounds(&self) -> Vec<(f64, f64)> {
+        return self.states().iter().map(|v| (v.lower, v.upper)).collect();
+    }
+
+    fn input_bounds(&self) -> Vec<(f64, f64)> {
+        return self.inputs().iter().map(|v| (v.lower, v.upper)).collect();
+    }
+
+    fn state_scales(&self) -> Vec<f64> {
+        return self.states().iter().map(|v| v.scale).collect();
+    }
+
+    fn input_scales(&self) -> Vec<f64> {
+        return self.inputs().iter().map(|v| v.scale).collect();
+    }
+}
+
+// Variable without bounds
+#[allow(dead_code)]
+pub fn unbounded(name: &str, unit: &str, scale: f64) -> Variable {
+    return Variable::new(name, unit, scale, f64::NEG_INFINITY, f64::INFINITY);
+}
+
+// Placeholder descriptions <prefix>0 to <prefix>(n-1) for models without metadata
+#[allow(dead_code)]
+pub fn generic_variables(prefix: &str, n: usize) -> Vec<Variable> {
+    return (0..n)
+        .map(|i| unbounded(&format!("{}{}", prefix, i), "-", 1.0))
+        .collect();
+}
+
+// CSV header of a time history, time followed by the state names
+pub fn csv_header<M: ModelMetadata + ?Sized>(model: &M) -> String {
+    let mut columns: Vec<String> = vec![String::from("time")];
+    columns.extend(model.state_names());
+    return columns.join(",");
+}
+
+// CSV row of a time history matching csv_header
+pub fn csv_row(t: f64, x: &[f64]) -> String {
+    let mut columns: Vec<String> = vec![t.to_string()];
+    columns.extend(x.iter().map(|v| v.to_string()));
+    return columns.join(",");
+}
+
+// VARIABLE IMPLEMENTATION ++++++++++++++++++++++++++
+impl Variable {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(name: &str, unit: &str, scale: f64, lower: f64, upper: f64) -> Self {
+        return Variable {
+            name: name.to_string(),
+            unit: unit.to_string(),
+            scale,
+            lower,
+            upper,
+        };
+    }
+
+    // Axis label, e.g. "vx [m/s]"
+    #[allow(dead_code)]
+    pub fn label(&self) -> String {
+        return format!("{} [{}]", self.name, self.unit);
+    }
+
+    // Whether a value lies within the bounds
+    #[allow(dead_code)]
+    pub fn contains(&self, value: f64) -> bool {
+        return value >= self.lower && value <= self.upper;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::point_mass::PointMass;
+
+    #[test]
+    fn test_point_mass_metadata() {
+        let pm: PointMass = PointMass::new("TestMass", 2.0);
+        assert_eq!(pm.states().len(), pm.n_x());
+        assert_eq!(pm.inputs().len(), pm.n_u());
+        assert_eq!(pm.state_names(), vec!["x", "y", "vx", "vy"]);
+        assert_eq!(pm.input_names(), vec!["Fx", "Fy"]);
+        assert_eq!(pm.states()[2].label(), "vx [m/s]");
+        assert_eq!(pm.inputs()[0].label(), "Fx [N]");
+        assert!(pm.state_scales().iter().all(|s| *s > 0.0));
+        assert!(pm.input_scales().iter().all(|s| *s > 0.0));
+        assert!(pm.state_bounds().iter().all(|(lower, upper)| lower < upper));
+    }
+
+    #[test]
+    fn test_csv() {
+        let pm: PointMass = PointMass::new("TestMass", 2.0);
+        assert_eq!(csv_header(&pm), "time,x,y,vx,vy");
+        assert_eq!(csv_row(0.5, &[1.0, 2.5, -3.0, 0.0]), "0.5,1,2.5,-3,0");
+    }
+
+    #[test]
+    fn test_generic_variables() {
+        let variables: Vec<Variable> = generic_variables("x", 3);
+        assert_eq!(variables[2].name, "x2");
+        assert!(variables[0].contains(1e12));
+        assert!(!Variable::new("v", "m/s", 10.0, 0.0, 50.0).contains(-1.0));
+    }
+}
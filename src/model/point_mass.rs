@@ -1,3 +1,5 @@
+use crate::model::autodiff::{jacobian, AutoDiffModel, Scalar};
+use crate::model::metadata::{unbounded, ModelMetadata, Variable};
 use simulation_toolbox::Model;
 
 pub struct PointMass {
@@ -30,61 +32,52 @@ impl Model for PointMass {
         return 2; // [Fx, Fy]
     }
 
-    fn fun(&self, x: &Vec<f64>, u: &Vec<f64>, _t: f64) -> Vec<f64> {
-        let mut dx: Vec<f64> = vec![0.0; self.n_x()];
+    fn fun(&self, x: &Vec<f64>, u: &Vec<f64>, t: f64) -> Vec<f64> {
+        return self.dynamics(x, u, t);
+    }
 
-        // dx/dt = v
-        dx[0] = x[2];
-        dx[1] = x[3];
+    fn jac(&self, x: &Vec<f64>, u: &Vec<f64>, t: f64) -> Vec<f64> {
+        return jacobian(self, x, u, t);
+    }
+}
 
-        // dv/dt = F/m
-        dx[2] = u[0] / self.mass;
-        dx[3] = u[1] / self.mass;
+impl AutoDiffModel for PointMass {
+    fn dynamics<S: Scalar>(&self, x: &[S], u: &[S], _t: f64) -> Vec<S> {
+        return vec![
+            // dx/dt = v
+            x[2],
+            x[3],
+            // dv/dt = F/m
+            u[0] / self.mass,
+            u[1] / self.mass,
+        ];
+    }
+}
 
-        return dx;
+impl ModelMetadata for PointMass {
+    fn states(&self) -> Vec<Variable> {
+        return vec![
+            unbounded("x", "m", 100.0),
+            unbounded("y", "m", 100.0),
+            unbounded("vx", "m/s", 10.0),
+            unbounded("vy", "m/s", 10.0),
+        ];
     }
 
-    fn jac(&self, _x: &Vec<f64>, _u: &Vec<f64>, _t: f64) -> Vec<f64> {
-        let nx = self.n_x();
-        let nu = self.n_u();
-        let mut jac: Vec<f64> = vec![0.0; nx * (nx + nu)];
-
-        // Jacobian is expected to be row major
-        jac[0] = 0.0; // dx0/dx0
-        jac[1] = 0.0; // dx0/dx1
-        jac[2] = 1.0; // dx0/dx2
-        jac[3] = 0.0; // dx0/dx3
-        jac[4] = 0.0; // dx0/du0
-        jac[5] = 0.0; // dx0/du1
-
-        jac[6] = 0.0; // dx1/dx0
-        jac[7] = 0.0; // dx1/dx1
-        jac[8] = 0.0; // dx1/dx2
-        jac[9] = 1.0; // dx1/dx3
-        jac[10] = 0.0; // dx1/du0
-        jac[11] = 0.0; // dx1/du1
-
-        jac[12] = 0.0; // dx2/dx0
-        jac[13] = 0.0; // dx2/dx1
-        jac[14] = 0.0; // dx2/dx2
-        jac[15] = 0.0; // dx2/dx3
-        jac[16] = 1.0 / self.mass; // dx2/du0
-        jac[17] = 0.0; // dx2/du1
-
-        jac[18] = 0.0; // dx3/dx0
-        jac[19] = 0.0; // dx3/dx1
-        jac[20] = 0.0; // dx3/dx2
-        jac[21] = 0.0; // dx3/dx3
-        jac[22] = 0.0; // dx3/du0
-        jac[23] = 1.0 / self.mass; // dx3/du1
-
-        return jac;
+    fn inputs(&self) -> Vec<Variable> {
+        // Forces scaled by the weight
+        return vec![
+            unbounded("Fx", "N", self.mass * 9.81),
+            unbounded("Fy", "N", self.mass * 9.81),
+        ];
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::autodiff::hessian;
+    use crate::model::finite_difference::assert_jacobian;
 
     #[test]
     fn test_point_mass_dynamics() {
@@ -111,4 +104,18 @@ mod tests {
         ];
         assert_eq!(jac, expected_jac); // Expected Jacobian
     }
+
+    #[test]
+    fn test_point_mass_jacobian_matches_fun() {
+        let pm = PointMass::new("TestMass", 2.0);
+        assert_jacobian(&pm, &[0.5, -1.0, 3.0, 2.0], &[4.0, -2.0], 0.0, 1e-6);
+    }
+
+    #[test]
+    fn test_point_mass_hessian() {
+        // The dynamics are linear, so all second derivatives vanish
+        let pm = PointMass::new("TestMass", 2.0);
+        let hess: Vec<f64> = hessian(&pm, &[0.5, -1.0, 3.0, 2.0], &[4.0, -2.0], 0.0, &[1.0; 4]);
+        assert_eq!(hess, vec![0.0; 36]);
+    }
 }
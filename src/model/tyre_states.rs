@@ -0,0 +1,403 @@
+use crate::model::curvilinear::Curvilinear;
+use crate::model::extended_point_mass::ExtendedPointMass;
+use crate::tyre::condition::TyreCondition;
+use simulation_toolbox::erk::ExplicitRK;
+use simulation_toolbox::Model;
+
+// Vehicle model whose tyre forces can be scaled by a grip multiplier, e.g. from tyre
+// temperature and wear, and which reports the power dissipated by sliding in its tyres.
+// Gradients are with respect to the model states followed by its inputs.
+pub trait GripScaled {
+    fn fun_with_grip(&self, x: &[f64], u: &[f64], grip: f64) -> Vec<f64>;
+    // Jacobian of the dynamics and their derivative with respect to the grip
+    fn jac_with_grip(&self, x: &[f64], u: &[f64], grip: f64) -> (Vec<f64>, Vec<f64>);
+    // Sliding power [W], its gradient and its derivative with respect to the grip
+    fn sliding_power(&self, x: &[f64], u: &[f64], grip: f64) -> (f64, Vec<f64>, f64);
+}
+
+// Wrapper adding the tyre temperature and wear as extra states after those of the wrapped
+// model, giving [rest..., T, wear]. The inputs are unchanged. The grip multiplier from the
+// tyre condition scales the tyre forces of the wrapped model, and the sliding power feeds
+// back into the heating and wear.
+pub struct TyreStates<M: Model + GripScaled> {
+    // Properties
+    name: String,
+    // Parameters
+    pub model: M,
+    pub condition: TyreCondition,
+}
+
+// Summary of one lap of a stint
+#[derive(Clone, Debug, PartialEq)]
+pub struct LapRecord {
+    pub lap: usize,
+    pub lap_time: f64,    // [s]
+    pub temperature: f64, // At the end of the lap [degC]
+    pub wear: f64,        // At the end of the lap [-]
+    pub grip: f64,        // At the end of the lap [-]
+}
+
+impl<M: Model + GripScaled> TyreStates<M> {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(name: &str, model: M, condition: TyreCondition) -> Self {
+        return TyreStates {
+            name: name.to_string(),
+            model,
+            condition,
+        };
+    }
+
+    // Grip multiplier for the state x
+    pub fn grip(&self, x: &[f64]) -> f64 {
+        let nz: usize = self.model.n_x();
+        return self.condition.grip(x[nz], x[nz + 1]).0;
+    }
+}
+
+// The tyre condition of the extended point mass acts on its friction ellipse
+impl TyreStates<ExtendedPointMass> {
+    // Constraints of the wrapped model at the grip of the tyre condition, feasible <= 0
+    #[allow(dead_code)]
+    pub fn constraints(&self, x: &[f64], u: &[f64]) -> Vec<f64> {
+        let nz: usize = self.model.n_x();
+        return self.model.constraints_with_grip(&x[..nz], u, self.grip(x));
+    }
+
+    // Row-major Jacobian of the constraints with respect to [rest..., T, wear, u]
+    #[allow(dead_code)]
+    pub fn constraints_jac(&self, x: &[f64], u: &[f64]) -> Vec<f64> {
+        let nz: usize = self.model.n_x();
+        let n_cols: usize = self.n_x() + self.n_u();
+        let (grip, dgrip_dtemp, dgrip_dwear) = self.condition.grip(x[nz], x[nz + 1]);
+        let (jac_z, dg_dgrip) = self.model.constraints_jac_with_grip(&x[..nz], u, grip);
+
+        let n_g: usize = dg_dgrip.len();
+        let mut jac: Vec<f64> = vec![0.0; n_g * n_cols];
+        for (i, dgi_dgrip) in dg_dgrip.iter().enumerate() {
+            let (row, row_z) = (i * n_cols, i * (nz + self.n_u()));
+            jac[row..row + nz].copy_from_slice(&jac_z[row_z..row_z + nz]); // dgi/dxj
+            jac[row + nz] = dgi_dgrip * dgrip_dtemp; // dgi/dT
+            jac[row + nz + 1] = dgi_dgrip * dgrip_dwear; // dgi/dwear
+            jac[row + nz + 2..row + n_cols]
+                .copy_from_slice(&jac_z[row_z + nz..row_z + nz + self.n_u()]); // dgi/duj
+        }
+        return jac;
+    }
+}
+
+impl<M: Model + GripScaled> Model for TyreStates<M> {
+    fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    fn n_x(&self) -> usize {
+        return self.model.n_x() + 2; // [rest..., T, wear]
+    }
+
+    fn n_u(&self) -> usize {
+        return self.model.n_u();
+    }
+
+    fn fun(&self, x: &Vec<f64>, u: &Vec<f64>, _t: f64) -> Vec<f64> {
+        let nz: usize = self.model.n_x();
+        let grip: f64 = self.grip(x);
+        let mut dx: Vec<f64> = self.model.fun_with_grip(&x[..nz], u, grip);
+        let (power, _, _) = self.model.sliding_power(&x[..nz], u, grip);
+
+        dx.push(self.condition.temperature_rate(power, x[nz]));
+        dx.push(self.condition.wear_rate(power));
+        return dx;
+    }
+
+    fn jac(&self, x: &Vec<f64>, u: &Vec<f64>, _t: f64) -> Vec<f64> {
+        let c: &TyreCondition = &self.condition;
+        let nz: usize = self.model.n_x();
+        let nx = self.n_x();
+        let nu = self.n_u();
+        let n_cols: usize = nx + nu;
+        let mut jac: Vec<f64> = vec![0.0; nx * n_cols];
+
+        let (grip, dgrip_dtemp, dgrip_dwear) = c.grip(x[nz], x[nz + 1]);
+        let (jac_z, dz_dgrip) = self.model.jac_with_grip(&x[..nz], u, grip);
+        let (_, dpower, dpower_dgrip) = self.model.sliding_power(&x[..nz], u, grip);
+
+        // Column of the full Jacobian for column j of the wrapped model Jacobian
+        let column = |j: usize| if j < nz { j } else { j + 2 };
+
+        // Wrapped model rows, with the grip chained through the added states
+        for i in 0..nz {
+            for j in 0..nz + nu {
+                jac[i * n_cols + column(j)] = jac_z[i * (nz + nu) + j]; // dxi/dxj
+            }
+            jac[i * n_cols + nz] = dz_dgrip[i] * dgrip_dtemp; // dxi/dT
+            jac[i * n_cols + nz + 1] = dz_dgrip[i] * dgrip_dwear; // dxi/dwear
+        }
+
+        // dT/dt = (eta P - h (T - T_amb)) / C and dwear/dt = k P
+        let dtemp_dp: f64 = c.heating_fraction / c.heat_capacity;
+        let dwear_dp: f64 = c.wear_rate;
+        let (row_t, row_w) = (nz * n_cols, (nz + 1) * n_cols);
+        for (j, dp) in dpower.iter().enumerate() {
+            jac[row_t + column(j)] = dtemp_dp * dp; // dT/dxj
+            jac[row_w + column(j)] = dwear_dp * dp; // dwear/dxj
+        }
+        jac[row_t + nz] = dtemp_dp * dpower_dgrip * dgrip_dtemp - c.cooling / c.heat_capacity; // dT/dT
+        jac[row_t + nz + 1] = dtemp_dp * dpower_dgrip * dgrip_dwear; // dT/dwear
+        jac[row_w + nz] = dwear_dp * dpower_dgrip * dgrip_dtemp; // dwear/dT
+        jac[row_w + nz + 1] = dwear_dp * dpower_dgrip * dgrip_dwear; // dwear/dwear
+
+        return jac;
+    }
+}
+
+// STINT SIMULATION ++++++++++++++++++++++++++
+// Simulate n_laps of a closed track in the spatial domain with steps of ds, starting from the
+// curvilinear state x0 = [t, n, xi, rest..., T, wear] at the start line. The controller gives
+// the inputs for a distance s along the stint and the current state. Returns one record per
+// lap, or an error if the track is open or the state stops being finite.
+#[allow(dead_code)]
+pub fn simulate_stint<M, F>(
+    model: &Curvilinear<'_, TyreStates<M>>,
+    x0: &[f64],
+    controller: F,
+    n_laps: usize,
+    ds: f64,
+) -> Result<Vec<LapRecord>, String>
+where
+    M: Model + GripScaled,
+    F: Fn(f64, &[f64]) -> Vec<f64>,
+{
+    if !model.track.is_closed() {
+        return Err("a stint needs a closed track".to_string());
+    }
+    let length: f64 = model.track.length();
+    let n_steps: usize = f64::ceil(length / ds) as usize;
+    let ds_lap: f64 = length / n_steps as f64;
+    let nx: usize = model.n_x();
+    let rk4: ExplicitRK = ExplicitRK::rk4();
+
+    let mut records: Vec<LapRecord> = Vec::with_capacity(n_laps);
+    let mut x: Vec<f64> = x0.to_vec();
+    for lap in 0..n_laps {
+        let lap_start: f64 = x[0];
+        for step in 0..n_steps {
+            let s: f64 = lap as f64 * length + step as f64 * ds_lap;
+            let u: Vec<f64> = controller(s, &x);
+            x = rk4.step(model, &x, &u, s, ds_lap);
+        }
+        if x.iter().any(|x_i| !x_i.is_finite()) {
+            return Err(format!("the state is not finite after lap {}", lap + 1));
+        }
+        records.push(LapRecord {
+            lap: lap + 1,
+            lap_time: x[0] - lap_start,
+            temperature: x[nx - 2],
+            wear: x[nx - 1],
+            grip: model.model.grip(&x[..nx]),
+        });
+    }
+    return Ok(records);
+}
+
+// Stint degradation curve as CSV with one row per lap
+#[allow(dead_code)]
+pub fn stint_to_csv_string(records: &[LapRecord]) -> String {
+    let mut csv: String = String::from("lap,lap_time,temperature,wear,grip\n");
+    for record in records {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\n",
+            record.lap, record.lap_time, record.temperature, record.wear, record.grip
+        ));
+    }
+    return csv;
+}
+
+#[allow(dead_code)]
+pub fn write_stint_csv(file_path: &str, records: &[LapRecord]) {
+    match std::fs::write(file_path, stint_to_csv_string(records)) {
+        Ok(_) => {}
+        Err(e) => panic!("Failed to write stint file {}: {}", file_path, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::double_track::{DoubleTrack, DoubleTrackParameters};
+    use crate::model::dynamic_bicycle::tests::saturating_model;
+    use crate::model::extended_point_mass::ExtendedPointMassParameters;
+    use crate::model::finite_difference::{
+        assert_jacobian, compare_jacobians, finite_difference_jac,
+    };
+    use crate::track::Track;
+    use crate::tyre::SaturatingTyre;
+
+    fn condition() -> TyreCondition {
+        return TyreCondition {
+            heat_capacity: 20000.0,
+            heating_fraction: 0.5,
+            cooling: 40.0,
+            ambient_temperature: 25.0,
+            optimal_temperature: 90.0,
+            temperature_window: 30.0,
+            max_thermal_loss: 0.4,
+            wear_rate: 2e-7,
+            wear_grip_loss: 0.3,
+        };
+    }
+
+    #[test]
+    fn test_tyre_states_grip_scales_forces() {
+        let model = TyreStates::new("Tyres", saturating_model(), condition());
+        let x: Vec<f64> = vec![0.0, 0.0, 0.0, 20.0, 0.5, 0.3, 90.0, 0.0];
+        let u: Vec<f64> = vec![0.05, 0.0];
+
+        // At the optimal temperature and unworn the wrapped model is unchanged
+        let dx = model.fun(&x, &u, 0.0);
+        let dz = saturating_model().fun(&x[..6].to_vec(), &u, 0.0);
+        assert_eq!(dx[..6], dz[..]);
+        let (power, _, _) = model.model.sliding_power(&x[..6], &u, 1.0);
+        assert!(power > 0.0);
+        assert_eq!(dx[6], condition().temperature_rate(power, 90.0));
+        assert_eq!(dx[7], condition().wear_rate(power));
+
+        // Worn tyres give less lateral acceleration
+        let mut x_worn: Vec<f64> = x.clone();
+        x_worn[7] = 0.5;
+        let dx_worn = model.fun(&x_worn, &u, 0.0);
+        assert!((dx_worn[4] + 20.0 * 0.3) < (dx[4] + 20.0 * 0.3));
+    }
+
+    #[test]
+    fn test_tyre_states_jacobian_matches_fun() {
+        let model = TyreStates::new("Tyres", saturating_model(), condition());
+        let x: Vec<f64> = vec![1.0, 2.0, 0.2, 18.0, 0.4, 0.3, 70.0, 0.2];
+        let u: Vec<f64> = vec![0.06, 800.0];
+        assert_jacobian(&model, &x, &u, 0.0, 1e-5);
+    }
+
+    #[test]
+    fn test_tyre_states_double_track() {
+        let parameters = DoubleTrackParameters {
+            mass: 1500.0,
+            yaw_inertia: 2500.0,
+            front_to_cg: 1.2,
+            rear_to_cg: 1.4,
+            front_track: 1.6,
+            rear_track: 1.5,
+            cg_height: 0.5,
+            front_roll_share: 0.55,
+            wheel_radius: 0.3,
+        };
+        let double_track = || {
+            DoubleTrack::new(
+                "DoubleTrack",
+                parameters.clone(),
+                Box::new(SaturatingTyre::new(40000.0, 1.3)),
+                Box::new(SaturatingTyre::new(45000.0, 1.3)),
+            )
+        };
+        let model = TyreStates::new("Tyres", double_track(), condition());
+
+        // Unchanged at full grip, and worn tyres give less lateral acceleration
+        let x: Vec<f64> = vec![0.0, 0.0, 0.0, 20.0, 0.5, 0.3, 90.0, 0.0];
+        let u: Vec<f64> = vec![0.05, 200.0, 400.0];
+        let dx = model.fun(&x, &u, 0.0);
+        assert_eq!(dx[..6], double_track().fun(&x[..6].to_vec(), &u, 0.0)[..]);
+        assert!(model.model.sliding_power(&x[..6], &u, 1.0).0 > 0.0);
+        let mut x_worn: Vec<f64> = x.clone();
+        x_worn[7] = 0.5;
+        assert!(model.fun(&x_worn, &u, 0.0)[4] < dx[4]);
+
+        let x: Vec<f64> = vec![1.0, 2.0, 0.2, 18.0, 0.4, 0.3, 70.0, 0.2];
+        assert_jacobian(&model, &x, &[0.06, 400.0, 900.0], 0.0, 1e-5);
+    }
+
+    #[test]
+    fn test_tyre_states_extended_point_mass() {
+        let point_mass = ExtendedPointMass::new(
+            "PointMass",
+            ExtendedPointMassParameters {
+                mass: 800.0,
+                drag_factor: 0.9,
+                downforce_factor: 2.0,
+                rolling_resistance: 0.015,
+                max_power: 400e3,
+                mu_longitudinal: 1.6,
+                mu_lateral: 1.8,
+            },
+        );
+        let model = TyreStates::new("Tyres", point_mass, condition());
+        let x: Vec<f64> = vec![1.0, 2.0, 30.0, -12.0, 70.0, 0.2];
+        let u: Vec<f64> = vec![3000.0, 5000.0];
+        assert_jacobian(&model, &x, &u, 0.0, 1e-6);
+
+        // Cold and worn tyres shrink the friction ellipse
+        let fresh: Vec<f64> = vec![0.0, 0.0, 30.0, 0.0, 90.0, 0.0];
+        let worn: Vec<f64> = vec![0.0, 0.0, 30.0, 0.0, 70.0, 0.2];
+        let u_limit: Vec<f64> = vec![0.0, 0.95 * 1.8 * (800.0 * 9.81 + 2.0 * 900.0)];
+        assert!(model.grip(&worn) < 0.95);
+        assert!(model.constraints(&fresh, &u_limit)[1] < 0.0);
+        assert!(model.constraints(&worn, &u_limit)[1] > 0.0);
+
+        // Cornering at the limit heats and wears the tyres
+        let dx = model.fun(&fresh, &u_limit, 0.0);
+        assert!(dx[4] > 0.0);
+        assert!(dx[5] > 0.0);
+        assert_eq!(
+            model.constraints(&fresh, &u),
+            model.model.constraints(&fresh[..4], &u)
+        );
+
+        let jac = model.constraints_jac(&x, &u);
+        let fd = finite_difference_jac(|x, u| model.constraints(x, u), &x, &u, 2);
+        let check = compare_jacobians(&jac, &fd, 6, 2, 1e-6);
+        assert!(check.passed(), "{}", check);
+    }
+
+    #[test]
+    fn test_stint_on_circle() {
+        let track: Track = Track::circle(50.0, 6.0);
+        let model = Curvilinear::new(
+            "Spatial",
+            TyreStates::new("Tyres", saturating_model(), condition()),
+            &track,
+        );
+
+        // Follow the centreline at 15 m/s with feedforward steering and feedback on the
+        // offset, heading and speed
+        let kappa: f64 = track.curvature(10.0);
+        let controller = |_s: f64, x: &[f64]| -> Vec<f64> {
+            let delta: f64 = 2.6 * kappa - 0.05 * x[1] - 0.5 * x[2];
+            let fx: f64 = 2000.0 * (15.0 - x[3]);
+            return vec![delta, fx];
+        };
+        let x0: Vec<f64> = vec![0.0, 0.0, 0.0, 15.0, 0.0, 15.0 * kappa, 25.0, 0.0];
+        let records: Vec<LapRecord> = simulate_stint(&model, &x0, controller, 3, 1.0).unwrap();
+
+        // The tyres warm up and wear every lap, and each lap takes about 2 pi R / v with the
+        // speed settling slightly below the target
+        assert_eq!(records.len(), 3);
+        for (i, record) in records.iter().enumerate() {
+            assert_eq!(record.lap, i + 1);
+            assert!((record.lap_time * 15.0 / track.length() - 1.0).abs() < 0.05);
+            if i > 0 {
+                assert!(record.temperature > records[i - 1].temperature);
+                assert!(record.wear > records[i - 1].wear);
+            }
+        }
+
+        let csv: String = stint_to_csv_string(&records);
+        assert!(csv.starts_with("lap,lap_time,temperature,wear,grip\n1,"));
+        assert_eq!(csv.lines().count(), 4);
+
+        // Open tracks cannot be lapped
+        let straight: Track = Track::straight(100.0, 6.0);
+        let open = Curvilinear::new(
+            "Spatial",
+            TyreStates::new("Tyres", saturating_model(), condition()),
+            &straight,
+        );
+        assert!(simulate_stint(&open, &x0, controller, 1, 1.0).is_err());
+    }
+}
@@ -0,0 +1,541 @@
+pub mod interior_point;
+#[cfg(feature = "ipopt")]
+pub mod ipopt;
+pub mod ldl;
+pub mod sqp;
+
+use crate::nlp::interior_point::InteriorPoint;
+use crate::nlp::sqp::Sqp;
+
+// Solver-agnostic description of a nonlinear program,
+//     min  f(z)
+//     s.t. z_L <= z <= z_U,
+//          c_L <= c(z) <= c_U,
+// with equality constraints where c_L = c_U and infinite bounds where a side is absent.
+//
+// The constraint Jacobian and the Hessian of the Lagrangian sigma f(z) + lambda^T c(z) are
+// sparse, with a fixed structure of (row, col) entries and values returned in the same
+// order. The Hessian lists the lower triangle only, row >= col. Repeated entries add up.
+
+#[allow(dead_code)]
+pub trait Nlp {
+    // Number of variables n_z
+    fn n_variables(&self) -> usize;
+
+    // Number of constraints n_c
+    fn n_constraints(&self) -> usize;
+
+    // Variable bounds (z_L, z_U)
+    fn variable_bounds(&self) -> (Vec<f64>, Vec<f64>);
+
+    // Constraint bounds (c_L, c_U)
+    fn constraint_bounds(&self) -> (Vec<f64>, Vec<f64>);
+
+    // Objective f(z)
+    fn objective(&self, z: &[f64]) -> f64;
+
+    // Objective gradient
+    fn gradient(&self, z: &[f64]) -> Vec<f64>;
+
+    // Constraint functions c(z)
+    fn constraints(&self, z: &[f64]) -> Vec<f64>;
+
+    // (row, col) entries of the constraint Jacobian
+    fn jacobian_structure(&self) -> Vec<(usize, usize)>;
+
+    // Constraint Jacobian values in the order of jacobian_structure
+    fn jacobian(&self, z: &[f64]) -> Vec<f64>;
+
+    // (row, col) entries of the lower triangle of the Hessian of the Lagrangian
+    fn hessian_structure(&self) -> Vec<(usize, usize)>;
+
+    // Hessian of sigma f(z) + lambda^T c(z) in the order of hessian_structure
+    fn hessian(&self, z: &[f64], sigma: f64, lambda: &[f64]) -> Vec<f64>;
+}
+
+// Common interface of the NLP algorithms, so that problems are posed once and solved with
+// any backend
+#[allow(dead_code)]
+pub trait NlpSolver {
+    // Name of the algorithm
+    fn name(&self) -> &str;
+
+    // Options shared by all backends
+    fn options(&self) -> &SolverOptions;
+
+    // Solve the NLP from the starting point z0
+    fn solve(&self, nlp: &dyn Nlp, z0: &[f64]) -> Solution;
+}
+
+// Available NLP algorithms
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    InteriorPoint,
+    Sqp,
+    #[cfg(feature = "ipopt")]
+    Ipopt, // Installed IPOPT library
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct SolverOptions {
+    pub tolerance: f64, // Convergence tolerance on the scaled KKT error
+    pub max_iterations: usize,
+    pub verbose: bool, // Print the iteration log
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum Status {
+    Solved,
+    MaxIterations,
+    Failed(String),
+}
+
+// One line of the iteration log
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct Iteration {
+    pub iteration: usize,
+    pub objective: f64,
+    pub primal_infeasibility: f64,
+    pub dual_infeasibility: f64,
+    pub barrier: f64,        // Barrier parameter mu, 0 for solvers without one
+    pub step_norm: f64,      // Max norm of the primal step
+    pub regularisation: f64, // Hessian regularisation delta_w
+    pub primal_step: f64,    // Primal step size alpha
+    pub dual_step: f64,      // Bound multiplier step size
+    pub line_search_trials: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct Solution {
+    pub status: Status,
+    pub z: Vec<f64>,
+    pub objective: f64,
+    pub constraints: Vec<f64>,
+    pub lambda: Vec<f64>,            // Constraint multipliers
+    pub lower_multipliers: Vec<f64>, // Multipliers of the lower variable bounds
+    pub upper_multipliers: Vec<f64>, // Multipliers of the upper variable bounds
+    pub iterations: usize,
+    pub primal_infeasibility: f64, // Max constraint violation
+    pub dual_infeasibility: f64,   // Max norm of the Lagrangian gradient
+    pub complementarity: f64,      // Max bound complementarity
+    pub log: Vec<Iteration>,
+}
+
+// SOLVEROPTIONS IMPLEMENTATION ++++++++++++++++++++++++
+impl Default for SolverOptions {
+    fn default() -> Self {
+        return SolverOptions {
+            tolerance: 1e-8,
+            max_iterations: 200,
+            verbose: false,
+        };
+    }
+}
+
+// BACKEND IMPLEMENTATION ++++++++++++++++++++++++++++++
+impl Backend {
+    // Solver of this backend with the given options
+    #[allow(dead_code)]
+    pub fn solver(&self, options: SolverOptions) -> Box<dyn NlpSolver> {
+        return match self {
+            Backend::InteriorPoint => Box::new(InteriorPoint::new(options)),
+            Backend::Sqp => Box::new(Sqp::new(options)),
+            #[cfg(feature = "ipopt")]
+            Backend::Ipopt => Box::new(ipopt::Ipopt::new(options)),
+        };
+    }
+}
+
+// SOLUTION IMPLEMENTATION +++++++++++++++++++++++++++++
+impl Solution {
+    // Solution at z with the given multipliers, evaluating the KKT residuals of the NLP there
+    pub fn new(
+        nlp: &dyn Nlp,
+        status: Status,
+        z: Vec<f64>,
+        multipliers: (Vec<f64>, Vec<f64>, Vec<f64>), // (lambda, lower, upper)
+        log: Vec<Iteration>,
+    ) -> Self {
+        let (lambda, lower_multipliers, upper_multipliers) = multipliers;
+        let (z_lower, z_upper) = nlp.variable_bounds();
+        let (c_lower, c_upper) = nlp.constraint_bounds();
+        let constraints: Vec<f64> = nlp.constraints(&z);
+
+        // Lagrangian gradient grad f + J^T lambda - z_l + z_u
+        let mut dual: Vec<f64> = nlp.gradient(&z);
+        for ((row, col), value) in nlp.jacobian_structure().iter().zip(nlp.jacobian(&z).iter()) {
+            dual[*col] += value * lambda[*row];
+        }
+        for (i, d) in dual.iter_mut().enumerate() {
+            *d += upper_multipliers[i] - lower_multipliers[i];
+        }
+
+        let violation = |v: f64, l: f64, u: f64| -> f64 { (l - v).max(v - u).max(0.0) };
+        let primal_infeasibility: f64 = (0..constraints.len())
+            .map(|r| violation(constraints[r], c_lower[r], c_upper[r]))
+            .chain((0..z.len()).map(|i| violation(z[i], z_lower[i], z_upper[i])))
+            .fold(0.0, f64::max);
+
+        // Products of the distances to the bounds and their multipliers, where negative
+        // constraint multipliers belong to the lower bounds. Infinite bounds have zero
+        // multipliers and are skipped
+        let product = |distance: f64, multiplier: f64| -> f64 {
+            return match multiplier == 0.0 {
+                true => 0.0,
+                false => (distance * multiplier).abs(),
+            };
+        };
+        let complementarity: f64 = (0..z.len())
+            .map(|i| {
+                product(z[i] - z_lower[i], lower_multipliers[i])
+                    .max(product(z_upper[i] - z[i], upper_multipliers[i]))
+            })
+            .chain(
+                (0..constraints.len())
+                    .filter(|r| c_lower[*r] != c_upper[*r])
+                    .map(|r| {
+                        product(constraints[r] - c_lower[r], lambda[r].min(0.0))
+                            .max(product(c_upper[r] - constraints[r], lambda[r].max(0.0)))
+                    }),
+            )
+            .fold(0.0, f64::max);
+
+        return Solution {
+            status,
+            objective: nlp.objective(&z),
+            constraints,
+            lambda,
+            lower_multipliers,
+            upper_multipliers,
+            iterations: log.len().saturating_sub(1),
+            primal_infeasibility,
+            dual_infeasibility: dual.iter().fold(0.0, |max, d| max.max(d.abs())),
+            complementarity,
+            log,
+            z,
+        };
+    }
+}
+
+// ITERATION IMPLEMENTATION ++++++++++++++++++++++++++++
+impl Iteration {
+    // Column headings matching the Display format
+    pub fn header() -> String {
+        return format!(
+            "{:>4} {:>14} {:>9} {:>9} {:>6} {:>9} {:>6} {:>9} {:>9} {:>2}",
+            "iter",
+            "objective",
+            "inf_pr",
+            "inf_du",
+            "lg(mu)",
+            "||d||",
+            "lg(rg)",
+            "alpha_du",
+            "alpha_pr",
+            "ls"
+        );
+    }
+}
+
+impl std::fmt::Display for Iteration {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let log10 = |v: f64| -> String {
+            return match v > 0.0 {
+                true => format!("{:.1}", v.log10()),
+                false => String::from("-"),
+            };
+        };
+        return write!(
+            f,
+            "{:>4} {:>14.7e} {:>9.2e} {:>9.2e} {:>6} {:>9.2e} {:>6} {:>9.2e} {:>9.2e} {:>2}",
+            self.iteration,
+            self.objective,
+            self.primal_infeasibility,
+            self.dual_infeasibility,
+            log10(self.barrier),
+            self.step_norm,
+            log10(self.regularisation),
+            self.dual_step,
+            self.primal_step,
+            self.line_search_trials
+        );
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::model::point_mass::PointMass;
+    use crate::ocp::transcription::Transcription;
+    use crate::ocp::{FrictionCircle, MinimumTime};
+    use simulation_toolbox::Model;
+
+    // Dense NLP from closures for the small test problems, with the Hessian of the
+    // Lagrangian from central differences of its gradient
+    pub struct DenseNlp {
+        pub n: usize,
+        pub m: usize,
+        pub variable_bounds: (Vec<f64>, Vec<f64>),
+        pub constraint_bounds: (Vec<f64>, Vec<f64>),
+        pub objective: fn(&[f64]) -> f64,
+        pub constraints: fn(&[f64]) -> Vec<f64>,
+    }
+
+    impl DenseNlp {
+        fn differentiate<F: Fn(&[f64]) -> Vec<f64>>(&self, f: F, z: &[f64]) -> Vec<Vec<f64>> {
+            let h: f64 = 1e-6;
+            return (0..self.n)
+                .map(|j| {
+                    let (mut z_p, mut z_m) = (z.to_vec(), z.to_vec());
+                    z_p[j] += h;
+                    z_m[j] -= h;
+                    let (f_p, f_m) = (f(&z_p), f(&z_m));
+                    f_p.iter()
+                        .zip(f_m.iter())
+                        .map(|(p, m)| (p - m) / (2.0 * h))
+                        .collect()
+                })
+                .collect();
+        }
+
+        fn lagrangian_gradient(&self, z: &[f64], sigma: f64, lambda: &[f64]) -> Vec<f64> {
+            let jac: Vec<f64> = self.jacobian(z);
+            let mut gradient: Vec<f64> = self.gradient(z).iter().map(|g| sigma * g).collect();
+            for (k, (row, col)) in self.jacobian_structure().iter().enumerate() {
+                gradient[*col] += jac[k] * lambda[*row];
+            }
+            return gradient;
+        }
+    }
+
+    impl Nlp for DenseNlp {
+        fn n_variables(&self) -> usize {
+            return self.n;
+        }
+
+        fn n_constraints(&self) -> usize {
+            return self.m;
+        }
+
+        fn variable_bounds(&self) -> (Vec<f64>, Vec<f64>) {
+            return self.variable_bounds.clone();
+        }
+
+        fn constraint_bounds(&self) -> (Vec<f64>, Vec<f64>) {
+            return self.constraint_bounds.clone();
+        }
+
+        fn objective(&self, z: &[f64]) -> f64 {
+            return (self.objective)(z);
+        }
+
+        fn gradient(&self, z: &[f64]) -> Vec<f64> {
+            return self
+                .differentiate(|z| vec![(self.objective)(z)], z)
+                .iter()
+                .map(|column| column[0])
+                .collect();
+        }
+
+        fn constraints(&self, z: &[f64]) -> Vec<f64> {
+            return (self.constraints)(z);
+        }
+
+        fn jacobian_structure(&self) -> Vec<(usize, usize)> {
+            return (0..self.m)
+                .flat_map(|r| (0..self.n).map(move |c| (r, c)))
+                .collect();
+        }
+
+        fn jacobian(&self, z: &[f64]) -> Vec<f64> {
+            let columns: Vec<Vec<f64>> = self.differentiate(self.constraints, z);
+            return self
+                .jacobian_structure()
+                .iter()
+                .map(|(r, c)| columns[*c][*r])
+                .collect();
+        }
+
+        fn hessian_structure(&self) -> Vec<(usize, usize)> {
+            return (0..self.n)
+                .flat_map(|r| (0..=r).map(move |c| (r, c)))
+                .collect();
+        }
+
+        fn hessian(&self, z: &[f64], sigma: f64, lambda: &[f64]) -> Vec<f64> {
+            let columns: Vec<Vec<f64>> =
+                self.differentiate(|z| self.lagrangian_gradient(z, sigma, lambda), z);
+            return self
+                .hessian_structure()
+                .iter()
+                .map(|(r, c)| 0.5 * (columns[*c][*r] + columns[*r][*c]))
+                .collect();
+        }
+    }
+
+    pub fn rosenbrock() -> DenseNlp {
+        return DenseNlp {
+            n: 2,
+            m: 0,
+            variable_bounds: (vec![f64::NEG_INFINITY; 2], vec![f64::INFINITY; 2]),
+            constraint_bounds: (vec![], vec![]),
+            objective: |z| 100.0 * (z[1] - z[0] * z[0]).powi(2) + (1.0 - z[0]).powi(2),
+            constraints: |_| vec![],
+        };
+    }
+
+    // Hock-Schittkowski problem 71 with an inequality, an equality and bounds, solved from
+    // (1, 5, 5, 1)
+    pub fn hs071() -> DenseNlp {
+        return DenseNlp {
+            n: 4,
+            m: 2,
+            variable_bounds: (vec![1.0; 4], vec![5.0; 4]),
+            constraint_bounds: (vec![25.0, 40.0], vec![f64::INFINITY, 40.0]),
+            objective: |z| z[0] * z[3] * (z[0] + z[1] + z[2]) + z[2],
+            constraints: |z| vec![z[0] * z[1] * z[2] * z[3], z.iter().map(|z| z * z).sum()],
+        };
+    }
+
+    // min (x - 1)^2 + (y - 2.5)^2 + z x over a polygon, with z fixed at 3
+    pub fn polygon_qp() -> DenseNlp {
+        return DenseNlp {
+            n: 3,
+            m: 3,
+            variable_bounds: (vec![0.0, 0.0, 3.0], vec![f64::INFINITY, f64::INFINITY, 3.0]),
+            constraint_bounds: (
+                vec![-2.0, f64::NEG_INFINITY, -2.0],
+                vec![f64::INFINITY, 6.0, f64::INFINITY],
+            ),
+            objective: |z| (z[0] - 1.0).powi(2) + (z[1] - 2.5).powi(2) + z[2] * z[0],
+            constraints: |z| vec![z[0] - 2.0 * z[1], z[0] + 2.0 * z[1], -z[0] + 2.0 * z[1]],
+        };
+    }
+
+    // Point mass with the final time as an extra constant state, on the unit time horizon
+    pub struct FreeTime {
+        pub model: PointMass,
+    }
+
+    impl Model for FreeTime {
+        fn name(&self) -> &str {
+            return "FreeTime";
+        }
+
+        fn n_x(&self) -> usize {
+            return 5; // [x, y, vx, vy, T]
+        }
+
+        fn n_u(&self) -> usize {
+            return 2;
+        }
+
+        fn fun(&self, x: &Vec<f64>, u: &Vec<f64>, t: f64) -> Vec<f64> {
+            let mut dx: Vec<f64> = self.model.fun(&x[..4].to_vec(), u, t);
+            dx.iter_mut().for_each(|dx| *dx *= x[4]);
+            dx.push(0.0);
+            return dx;
+        }
+
+        fn jac(&self, x: &Vec<f64>, u: &Vec<f64>, t: f64) -> Vec<f64> {
+            let dx: Vec<f64> = self.model.fun(&x[..4].to_vec(), u, t);
+            let jac_pm: Vec<f64> = self.model.jac(&x[..4].to_vec(), u, t);
+            let mut jac: Vec<f64> = vec![0.0; 5 * 7];
+            for r in 0..4 {
+                for c in 0..4 {
+                    jac[r * 7 + c] = x[4] * jac_pm[r * 6 + c]; // dxr/dxc
+                }
+                jac[r * 7 + 4] = dx[r]; // dxr/dT
+                jac[r * 7 + 5] = x[4] * jac_pm[r * 6 + 4]; // dxr/du0
+                jac[r * 7 + 6] = x[4] * jac_pm[r * 6 + 5]; // dxr/du1
+            }
+            return jac;
+        }
+    }
+
+    #[test]
+    fn test_backends() {
+        // The same problem through every backend, with matching solutions and multipliers
+        let nlp: DenseNlp = hs071();
+        let expected: [f64; 4] = [1.0, 4.7429994, 3.8211503, 1.3794082];
+        let mut lambdas: Vec<Vec<f64>> = Vec::new();
+        for backend in [Backend::InteriorPoint, Backend::Sqp] {
+            let solver: Box<dyn NlpSolver> = backend.solver(SolverOptions::default());
+            let solution: Solution = solver.solve(&nlp, &[1.0, 5.0, 5.0, 1.0]);
+            assert_eq!(solution.status, Status::Solved, "{}", solver.name());
+            assert!((solution.objective - 17.0140173).abs() < 1e-6);
+            for (z, e) in solution.z.iter().zip(expected.iter()) {
+                assert!((z - e).abs() < 1e-5);
+            }
+            assert!(solution.dual_infeasibility <= solver.options().tolerance * 10.0);
+            lambdas.push(solution.lambda);
+        }
+        for (a, b) in lambdas[0].iter().zip(lambdas[1].iter()) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_solution_residuals() {
+        // At the optimum (0.2, 1.1, 3) of the polygon QP the first edge is active with
+        // lambda = -1.4 and the fixed variable has the lower bound multiplier 0.2
+        let nlp: DenseNlp = polygon_qp();
+        let multipliers = (vec![-1.4, 0.0, 0.0], vec![0.0, 0.0, 0.2], vec![0.0; 3]);
+        let solution = Solution::new(
+            &nlp,
+            Status::Solved,
+            vec![0.2, 1.1, 3.0],
+            multipliers,
+            Vec::new(),
+        );
+        assert!(solution.primal_infeasibility < 1e-12);
+        assert!(solution.dual_infeasibility < 1e-6);
+        assert!(solution.complementarity < 1e-12);
+        assert!((solution.objective - 3.2).abs() < 1e-12);
+
+        // Off the active edge the complementarity picks up the first constraint
+        let multipliers = (vec![-1.4, 0.0, 0.0], vec![0.0, 0.0, 0.2], vec![0.0; 3]);
+        let solution = Solution::new(
+            &nlp,
+            Status::Solved,
+            vec![0.2, 1.0, 3.0],
+            multipliers,
+            Vec::new(),
+        );
+        assert!((solution.complementarity - 0.28).abs() < 1e-12);
+        assert!(solution.dual_infeasibility > 0.1);
+    }
+
+    #[test]
+    fn test_backends_minimum_time() {
+        // The transcribed rest to rest problem of the interior-point tests, unchanged between
+        // backends
+        let model = FreeTime {
+            model: PointMass::new("Mass", 2.0),
+        };
+        let mesh: Vec<f64> = (0..=10).map(|k| k as f64 / 10.0).collect();
+        let mut problem = Transcription::new(&model, &mesh, 3);
+        problem.costs.push(Box::new(MinimumTime::new(4)));
+        problem
+            .constraints
+            .push(Box::new(FrictionCircle::new((0, 1), 10.0)));
+        problem.boundary.initial = vec![Some(0.0), Some(0.0), Some(0.0), Some(0.0), None];
+        problem.boundary.terminal = vec![Some(10.0), Some(0.0), Some(0.0), Some(0.0), None];
+        problem.state_bounds[4] = (0.1, 100.0);
+        problem.input_scales = vec![10.0, 10.0];
+        let z0: Vec<f64> = problem.pack(|t| (vec![10.0 * t, 0.0, 1.0, 0.0, 5.0], vec![0.0, 0.0]));
+
+        for backend in [Backend::InteriorPoint, Backend::Sqp] {
+            let solution: Solution = backend
+                .solver(SolverOptions::default())
+                .solve(&problem, &z0);
+            assert_eq!(solution.status, Status::Solved);
+            assert!((problem.state(&solution.z, 0)[4] - 2.0 * 2f64.sqrt()).abs() < 1e-3);
+        }
+    }
+}
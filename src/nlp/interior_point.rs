@@ -0,0 +1,802 @@
+use crate::nlp::ldl::{Inertia, Ldl};
+use crate::nlp::{Iteration, Nlp, NlpSolver, Solution, SolverOptions, Status};
+
+// Primal-dual interior-point method for sparse NLPs, following the design of IPOPT.
+//
+// Fixed variables (z_L = z_U) are removed, and every inequality row gets a slack s with
+// c(z) - s = 0 and c_L <= s <= c_U, leaving equality constraints h(w) = 0 on the variables
+// w = [free z, s] with bounds only. The bounds are handled by a logarithmic barrier with
+// parameter mu, which is decreased monotonically once the barrier problem is solved to
+// within a multiple of mu. Every iteration solves the primal-dual Newton system
+//     [W + Sigma + delta_w I   J^T       ] [dw     ]     [grad phi + J^T lambda]
+//     [J                       -delta_c I] [dlambda] = - [h                    ]
+// with the sparse LDL^T factorisation, increasing the regularisation delta_w until the
+// inertia shows a descent direction. Steps are kept inside the bounds by the fraction to
+// the boundary rule and accepted by an Armijo backtracking line search on the l1 exact
+// penalty merit function of the barrier problem, with second order corrections of rejected
+// full steps.
+
+const BOUND_PUSH: f64 = 1e-2; // Relative distance of the starting point from the bounds
+const INITIAL_BARRIER: f64 = 0.1;
+const BARRIER_LINEAR_DECREASE: f64 = 0.2;
+const BARRIER_SUPERLINEAR_POWER: f64 = 1.5;
+const BARRIER_TOLERANCE_FACTOR: f64 = 10.0;
+const MIN_FRACTION_TO_BOUNDARY: f64 = 0.99;
+const ARMIJO: f64 = 1e-4;
+const MAX_LINE_SEARCH_TRIALS: usize = 40;
+const MAX_SECOND_ORDER_CORRECTIONS: usize = 4;
+const SECOND_ORDER_REDUCTION: f64 = 0.99; // Infeasibility decrease needed to continue
+const PENALTY_MARGIN: f64 = 0.1; // Share of the infeasibility reduction kept in the merit
+const CONSTRAINT_REGULARISATION: f64 = 1e-9;
+const FIRST_REGULARISATION: f64 = 1e-4;
+const MIN_REGULARISATION: f64 = 1e-20;
+const MAX_REGULARISATION: f64 = 1e40;
+const MULTIPLIER_SAFEGUARD: f64 = 1e10;
+const MAX_SCALING: f64 = 100.0; // Multiplier size above which the KKT error is scaled
+
+pub struct InteriorPoint {
+    // Parameters
+    pub options: SolverOptions,
+}
+
+// NLP in the variables w = [free z, s] with the constraints h(w) = 0
+struct Reformulation<'a> {
+    nlp: &'a dyn Nlp,
+    z_fixed: Vec<f64>, // Values of the fixed variables, free entries unused
+    free: Vec<usize>,  // Index in z of every free variable
+    w_index: Vec<Option<usize>>, // Index in w of every variable of z
+    slack: Vec<Option<usize>>, // Index in w of the slack of every constraint
+    rhs: Vec<f64>,     // Right-hand side of the equality constraints
+    lower: Vec<f64>,   // Bounds on w
+    upper: Vec<f64>,
+    jacobian_structure: Vec<(usize, usize)>,
+    hessian_structure: Vec<(usize, usize)>,
+}
+
+// Primal and constraint multiplier steps
+type Step = (Vec<f64>, Vec<f64>);
+
+// Values of the reformulated problem at a point
+struct Evaluation {
+    objective: f64,
+    gradient: Vec<f64>,
+    residual: Vec<f64>,
+    jacobian: Vec<f64>, // Values of the NLP Jacobian
+}
+
+// REFORMULATION IMPLEMENTATION ++++++++++++++++++++++++
+impl<'a> Reformulation<'a> {
+    fn new(nlp: &'a dyn Nlp) -> Self {
+        let (z_lower, z_upper) = nlp.variable_bounds();
+        let (c_lower, c_upper) = nlp.constraint_bounds();
+
+        let mut w_index: Vec<Option<usize>> = vec![None; z_lower.len()];
+        let (mut free, mut lower, mut upper) = (Vec::new(), Vec::new(), Vec::new());
+        for (i, (l, u)) in z_lower.iter().zip(z_upper.iter()).enumerate() {
+            if l != u {
+                w_index[i] = Some(free.len());
+                free.push(i);
+                lower.push(*l);
+                upper.push(*u);
+            }
+        }
+        let mut slack: Vec<Option<usize>> = vec![None; c_lower.len()];
+        for (r, (l, u)) in c_lower.iter().zip(c_upper.iter()).enumerate() {
+            if l != u {
+                slack[r] = Some(lower.len());
+                lower.push(*l);
+                upper.push(*u);
+            }
+        }
+
+        return Reformulation {
+            nlp,
+            z_fixed: z_lower,
+            free,
+            w_index,
+            slack,
+            rhs: c_lower,
+            lower,
+            upper,
+            jacobian_structure: nlp.jacobian_structure(),
+            hessian_structure: nlp.hessian_structure(),
+        };
+    }
+
+    fn n_w(&self) -> usize {
+        return self.lower.len();
+    }
+
+    fn m(&self) -> usize {
+        return self.rhs.len();
+    }
+
+    fn z(&self, w: &[f64]) -> Vec<f64> {
+        let mut z: Vec<f64> = self.z_fixed.clone();
+        for (k, i) in self.free.iter().enumerate() {
+            z[*i] = w[k];
+        }
+        return z;
+    }
+
+    // Objective and constraint residuals, None where they are not finite
+    fn values(&self, w: &[f64]) -> Option<(f64, Vec<f64>)> {
+        let z: Vec<f64> = self.z(w);
+        let objective: f64 = self.nlp.objective(&z);
+        let residual: Vec<f64> = self.residual(w, self.nlp.constraints(&z));
+        return match objective.is_finite() && residual.iter().all(|r| r.is_finite()) {
+            true => Some((objective, residual)),
+            false => None,
+        };
+    }
+
+    fn residual(&self, w: &[f64], constraints: Vec<f64>) -> Vec<f64> {
+        return constraints
+            .iter()
+            .enumerate()
+            .map(|(r, c)| match self.slack[r] {
+                Some(k) => c - w[k],
+                None => c - self.rhs[r],
+            })
+            .collect();
+    }
+
+    fn evaluate(&self, w: &[f64]) -> Evaluation {
+        let z: Vec<f64> = self.z(w);
+        let gradient_z: Vec<f64> = self.nlp.gradient(&z);
+        let mut gradient: Vec<f64> = vec![0.0; self.n_w()];
+        for (k, i) in self.free.iter().enumerate() {
+            gradient[k] = gradient_z[*i];
+        }
+        return Evaluation {
+            objective: self.nlp.objective(&z),
+            gradient,
+            residual: self.residual(w, self.nlp.constraints(&z)),
+            jacobian: self.nlp.jacobian(&z),
+        };
+    }
+
+    // J^T lambda in w
+    fn jacobian_transpose_product(&self, jacobian: &[f64], lambda: &[f64]) -> Vec<f64> {
+        let mut product: Vec<f64> = vec![0.0; self.n_w()];
+        for ((row, col), value) in self.jacobian_structure.iter().zip(jacobian.iter()) {
+            if let Some(k) = self.w_index[*col] {
+                product[k] += value * lambda[*row];
+            }
+        }
+        for (r, slack) in self.slack.iter().enumerate() {
+            if let Some(k) = slack {
+                product[*k] -= lambda[r];
+            }
+        }
+        return product;
+    }
+
+    // Structure of the KKT matrix, the diagonal followed by the Hessian, Jacobian and slack
+    // entries
+    fn kkt_structure(&self) -> Vec<(usize, usize)> {
+        let n_w: usize = self.n_w();
+        let mut structure: Vec<(usize, usize)> = (0..n_w + self.m()).map(|i| (i, i)).collect();
+        for (i, j) in self.hessian_structure.iter() {
+            if let (Some(a), Some(b)) = (self.w_index[*i], self.w_index[*j]) {
+                structure.push((a, b));
+            }
+        }
+        for (row, col) in self.jacobian_structure.iter() {
+            if let Some(k) = self.w_index[*col] {
+                structure.push((n_w + row, k));
+            }
+        }
+        for (r, slack) in self.slack.iter().enumerate() {
+            if let Some(k) = slack {
+                structure.push((n_w + r, *k));
+            }
+        }
+        return structure;
+    }
+
+    // KKT values in the order of kkt_structure
+    fn kkt_values(
+        &self,
+        hessian: &[f64],
+        jacobian: &[f64],
+        sigma: &[f64],
+        delta_w: f64,
+        delta_c: f64,
+    ) -> Vec<f64> {
+        let mut values: Vec<f64> = sigma.iter().map(|s| s + delta_w).collect();
+        values.extend(vec![-delta_c; self.m()]);
+        for ((i, j), value) in self.hessian_structure.iter().zip(hessian.iter()) {
+            if self.w_index[*i].is_some() && self.w_index[*j].is_some() {
+                values.push(*value);
+            }
+        }
+        for ((_, col), value) in self.jacobian_structure.iter().zip(jacobian.iter()) {
+            if self.w_index[*col].is_some() {
+                values.push(*value);
+            }
+        }
+        values.extend(vec![-1.0; self.slack.iter().flatten().count()]);
+        return values;
+    }
+
+    // Barrier function -mu sum ln(w - l) - mu sum ln(u - w) and its gradient
+    fn barrier(&self, w: &[f64], mu: f64) -> (f64, Vec<f64>) {
+        let mut value: f64 = 0.0;
+        let mut gradient: Vec<f64> = vec![0.0; w.len()];
+        for (k, w_k) in w.iter().enumerate() {
+            if self.lower[k].is_finite() {
+                value -= mu * (w_k - self.lower[k]).ln();
+                gradient[k] -= mu / (w_k - self.lower[k]);
+            }
+            if self.upper[k].is_finite() {
+                value -= mu * (self.upper[k] - w_k).ln();
+                gradient[k] += mu / (self.upper[k] - w_k);
+            }
+        }
+        return (value, gradient);
+    }
+
+    // Starting point moved strictly inside the bounds
+    fn push_inside(&self, k: usize, value: f64) -> f64 {
+        let (l, u) = (self.lower[k], self.upper[k]);
+        let range: f64 = u - l;
+        let mut value: f64 = value;
+        if l.is_finite() {
+            value = value.max(l + (BOUND_PUSH * l.abs().max(1.0)).min(BOUND_PUSH * range));
+        }
+        if u.is_finite() {
+            value = value.min(u - (BOUND_PUSH * u.abs().max(1.0)).min(BOUND_PUSH * range));
+        }
+        return value;
+    }
+}
+
+// Largest step in (0, 1] keeping v + alpha dv at least the fraction tau of the way from
+// the bounds, for the distances to the lower and upper bounds
+fn fraction_to_boundary(distances: &[(f64, f64)], direction: &[f64], tau: f64) -> f64 {
+    let mut alpha: f64 = 1.0;
+    for ((to_lower, to_upper), d) in distances.iter().zip(direction.iter()) {
+        if *d < 0.0 && to_lower.is_finite() {
+            alpha = alpha.min(-tau * to_lower / d);
+        }
+        if *d > 0.0 && to_upper.is_finite() {
+            alpha = alpha.min(tau * to_upper / d);
+        }
+    }
+    return alpha;
+}
+
+fn max_norm(v: &[f64]) -> f64 {
+    return v.iter().fold(0.0, |max, v| max.max(v.abs()));
+}
+
+fn one_norm(v: &[f64]) -> f64 {
+    return v.iter().map(|v| v.abs()).sum();
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    return a.iter().zip(b.iter()).map(|(a, b)| a * b).sum();
+}
+
+// INTERIORPOINT IMPLEMENTATION ++++++++++++++++++++++++
+impl InteriorPoint {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(options: SolverOptions) -> Self {
+        return InteriorPoint { options };
+    }
+
+    // Second order corrections of a rejected trial step, resolving the Newton system with the
+    // constraint residual at the trial point added to the right-hand side. Gives the step
+    // size and the corrected primal and multiplier steps when a correction is accepted
+    #[allow(clippy::too_many_arguments)]
+    fn second_order_correction<T, A>(
+        &self,
+        problem: &Reformulation,
+        ldl: &Ldl,
+        rhs: &[f64],
+        w: &[f64],
+        w_trial: &[f64],
+        distances: &[(f64, f64)],
+        alpha: f64,
+        tau: f64,
+        trial: &T,
+        sufficient: &A,
+    ) -> Option<(f64, Step)>
+    where
+        T: Fn(&[f64]) -> Option<(f64, Vec<f64>)>,
+        A: Fn(f64, f64) -> bool,
+    {
+        let n_w: usize = problem.n_w();
+        let infeasibility: f64 = one_norm(&rhs[n_w..]);
+        let (_, mut trial_residual) = trial(w_trial)?;
+        let mut trial_infeasibility: f64 = one_norm(&trial_residual);
+        if trial_infeasibility < infeasibility {
+            return None;
+        }
+
+        // Constraint residual accumulated over the corrections
+        let mut residual: Vec<f64> = (0..rhs.len() - n_w)
+            .map(|r| -alpha * rhs[n_w + r] + trial_residual[r])
+            .collect();
+        for _ in 0..MAX_SECOND_ORDER_CORRECTIONS {
+            let mut corrected_rhs: Vec<f64> = rhs[..n_w].to_vec();
+            corrected_rhs.extend(residual.iter().map(|r| -r));
+            let correction: Vec<f64> = ldl.solve(&corrected_rhs);
+            let alpha_correction: f64 = fraction_to_boundary(distances, &correction[..n_w], tau);
+            let w_corrected: Vec<f64> = (0..n_w)
+                .map(|k| w[k] + alpha_correction * correction[k])
+                .collect();
+
+            let (trial_merit, corrected_residual) = trial(&w_corrected)?;
+            if sufficient(trial_merit, alpha) {
+                let (dw, dlambda) = correction.split_at(n_w);
+                return Some((alpha_correction, (dw.to_vec(), dlambda.to_vec())));
+            }
+            let corrected_infeasibility: f64 = one_norm(&corrected_residual);
+            if corrected_infeasibility > SECOND_ORDER_REDUCTION * trial_infeasibility {
+                return None;
+            }
+            trial_infeasibility = corrected_infeasibility;
+            trial_residual = corrected_residual;
+            residual = (0..residual.len())
+                .map(|r| alpha_correction * residual[r] + trial_residual[r])
+                .collect();
+        }
+        return None;
+    }
+
+    // Solution in the variables of the NLP, with the bound multipliers of fixed variables
+    // from the Lagrangian gradient
+    #[allow(clippy::too_many_arguments)]
+    fn solution(
+        &self,
+        problem: &Reformulation,
+        status: Status,
+        w: &[f64],
+        lambda: &[f64],
+        z_l: &[f64],
+        z_u: &[f64],
+        eval: &Evaluation,
+        log: Vec<Iteration>,
+    ) -> Solution {
+        let nlp: &dyn Nlp = problem.nlp;
+        let z: Vec<f64> = problem.z(w);
+        let mut lagrangian_gradient: Vec<f64> = nlp.gradient(&z);
+        for ((row, col), value) in problem.jacobian_structure.iter().zip(eval.jacobian.iter()) {
+            lagrangian_gradient[*col] += value * lambda[*row];
+        }
+
+        let mut lower_multipliers: Vec<f64> = vec![0.0; z.len()];
+        let mut upper_multipliers: Vec<f64> = vec![0.0; z.len()];
+        for (i, index) in problem.w_index.iter().enumerate() {
+            match index {
+                Some(k) => {
+                    lower_multipliers[i] = z_l[*k];
+                    upper_multipliers[i] = z_u[*k];
+                }
+                None => {
+                    lower_multipliers[i] = lagrangian_gradient[i].max(0.0);
+                    upper_multipliers[i] = (-lagrangian_gradient[i]).max(0.0);
+                }
+            }
+        }
+        let multipliers = (lambda.to_vec(), lower_multipliers, upper_multipliers);
+        return Solution::new(nlp, status, z, multipliers, log);
+    }
+}
+
+impl NlpSolver for InteriorPoint {
+    fn name(&self) -> &str {
+        return "interior point";
+    }
+
+    fn options(&self) -> &SolverOptions {
+        return &self.options;
+    }
+
+    fn solve(&self, nlp: &dyn Nlp, z0: &[f64]) -> Solution {
+        let problem: Reformulation = Reformulation::new(nlp);
+        let (n_w, m) = (problem.n_w(), problem.m());
+        let (lower, upper) = (&problem.lower, &problem.upper);
+        let tolerance: f64 = self.options.tolerance;
+
+        // Starting point inside the bounds with unit bound multipliers
+        let mut w: Vec<f64> = problem.free.iter().map(|i| z0[*i]).collect();
+        let c0: Vec<f64> = nlp.constraints(&problem.z(&w));
+        for (r, slack) in problem.slack.iter().enumerate() {
+            if slack.is_some() {
+                w.push(c0[r]);
+            }
+        }
+        w = w
+            .iter()
+            .enumerate()
+            .map(|(k, w)| problem.push_inside(k, *w))
+            .collect();
+        let mut lambda: Vec<f64> = vec![0.0; m];
+        let mut z_l: Vec<f64> = lower
+            .iter()
+            .map(|l| if l.is_finite() { 1.0 } else { 0.0 })
+            .collect();
+        let mut z_u: Vec<f64> = upper
+            .iter()
+            .map(|u| if u.is_finite() { 1.0 } else { 0.0 })
+            .collect();
+        let mut mu: f64 = INITIAL_BARRIER;
+        let mut penalty: f64 = 1.0;
+        let mut delta_w_last: f64 = 0.0;
+
+        let mut ldl: Ldl = Ldl::analyse(n_w + m, &problem.kkt_structure());
+        let mut log: Vec<Iteration> = Vec::new();
+        let mut status: Status = Status::MaxIterations;
+        let mut last_step: (f64, f64, f64, f64, usize) = (0.0, 0.0, 0.0, 0.0, 0);
+        if self.options.verbose {
+            println!("{}", Iteration::header());
+        }
+
+        let mut eval: Evaluation = problem.evaluate(&w);
+        let mut iteration: usize = 0;
+        loop {
+            // KKT error of the barrier problem, scaled for large multipliers
+            let dual: Vec<f64> = {
+                let jt_lambda: Vec<f64> =
+                    problem.jacobian_transpose_product(&eval.jacobian, &lambda);
+                (0..n_w)
+                    .map(|k| eval.gradient[k] + jt_lambda[k] - z_l[k] + z_u[k])
+                    .collect()
+            };
+            let complementarity = |mu: f64| -> f64 {
+                let mut max: f64 = 0.0;
+                for k in 0..n_w {
+                    if lower[k].is_finite() {
+                        max = max.max(((w[k] - lower[k]) * z_l[k] - mu).abs());
+                    }
+                    if upper[k].is_finite() {
+                        max = max.max(((upper[k] - w[k]) * z_u[k] - mu).abs());
+                    }
+                }
+                return max;
+            };
+            let bound_sum: f64 = one_norm(&z_l) + one_norm(&z_u);
+            let s_d: f64 = ((one_norm(&lambda) + bound_sum) / ((m + 2 * n_w).max(1) as f64))
+                .max(MAX_SCALING)
+                / MAX_SCALING;
+            let s_c: f64 = (bound_sum / ((2 * n_w).max(1) as f64)).max(MAX_SCALING) / MAX_SCALING;
+            let (primal_infeasibility, dual_infeasibility) =
+                (max_norm(&eval.residual), max_norm(&dual));
+            let error = |mu: f64| -> f64 {
+                return (dual_infeasibility / s_d)
+                    .max(primal_infeasibility)
+                    .max(complementarity(mu) / s_c);
+            };
+
+            let (step_norm, delta_w, alpha_primal, alpha_dual, trials) = last_step;
+            let line: Iteration = Iteration {
+                iteration,
+                objective: eval.objective,
+                primal_infeasibility,
+                dual_infeasibility,
+                barrier: mu,
+                step_norm,
+                regularisation: delta_w,
+                primal_step: alpha_primal,
+                dual_step: alpha_dual,
+                line_search_trials: trials,
+            };
+            if self.options.verbose {
+                println!("{}", line);
+            }
+            log.push(line);
+
+            if error(0.0) <= tolerance {
+                status = Status::Solved;
+                break;
+            }
+            if iteration >= self.options.max_iterations {
+                break;
+            }
+            if !eval.objective.is_finite() || eval.gradient.iter().any(|g| !g.is_finite()) {
+                status = Status::Failed(String::from("non-finite objective or gradient"));
+                break;
+            }
+
+            // Decrease the barrier parameter while its problem is solved accurately enough
+            let min_barrier: f64 = tolerance / 10.0;
+            while mu > min_barrier && error(mu) <= BARRIER_TOLERANCE_FACTOR * mu {
+                mu = (BARRIER_LINEAR_DECREASE * mu)
+                    .min(mu.powf(BARRIER_SUPERLINEAR_POWER))
+                    .max(min_barrier);
+            }
+            let tau: f64 = MIN_FRACTION_TO_BOUNDARY.max(1.0 - mu);
+
+            // Newton system
+            let z: Vec<f64> = problem.z(&w);
+            let hessian: Vec<f64> = nlp.hessian(&z, 1.0, &lambda);
+            let (barrier_value, barrier_gradient) = problem.barrier(&w, mu);
+            let phi_gradient: Vec<f64> = (0..n_w)
+                .map(|k| eval.gradient[k] + barrier_gradient[k])
+                .collect();
+            let sigma: Vec<f64> = (0..n_w)
+                .map(|k| {
+                    let mut s: f64 = 0.0;
+                    if lower[k].is_finite() {
+                        s += z_l[k] / (w[k] - lower[k]);
+                    }
+                    if upper[k].is_finite() {
+                        s += z_u[k] / (upper[k] - w[k]);
+                    }
+                    s
+                })
+                .collect();
+            let jt_lambda: Vec<f64> = problem.jacobian_transpose_product(&eval.jacobian, &lambda);
+            let mut rhs: Vec<f64> = (0..n_w)
+                .map(|k| -(phi_gradient[k] + jt_lambda[k]))
+                .collect();
+            rhs.extend(eval.residual.iter().map(|h| -h));
+
+            // Inertia correction, increasing delta_w until the matrix has n_w positive and
+            // m negative eigenvalues
+            let mut delta_w: f64 = 0.0;
+            loop {
+                let values: Vec<f64> = problem.kkt_values(
+                    &hessian,
+                    &eval.jacobian,
+                    &sigma,
+                    delta_w,
+                    CONSTRAINT_REGULARISATION,
+                );
+                let correct: bool = match ldl.factorise(&values) {
+                    Ok(Inertia { positive, negative }) => positive == n_w && negative == m,
+                    Err(_) => false,
+                };
+                if correct {
+                    break;
+                }
+                delta_w = match (delta_w == 0.0, delta_w_last == 0.0) {
+                    (true, true) => FIRST_REGULARISATION,
+                    (true, false) => (delta_w_last / 3.0).max(MIN_REGULARISATION),
+                    (false, true) => 100.0 * delta_w,
+                    (false, false) => 8.0 * delta_w,
+                };
+                if delta_w > MAX_REGULARISATION {
+                    break;
+                }
+            }
+            if delta_w > MAX_REGULARISATION {
+                status = Status::Failed(String::from("inertia correction failed"));
+                break;
+            }
+            if delta_w > 0.0 {
+                delta_w_last = delta_w;
+            }
+
+            let solution: Vec<f64> = ldl.solve(&rhs);
+            let (dw, dlambda) = solution.split_at(n_w);
+            let distances: Vec<(f64, f64)> = (0..n_w)
+                .map(|k| (w[k] - lower[k], upper[k] - w[k]))
+                .collect();
+            let alpha_max: f64 = fraction_to_boundary(&distances, dw, tau);
+
+            // Penalty large enough for the step to descend on the merit function
+            let infeasibility: f64 = one_norm(&eval.residual);
+            let directional: f64 = dot(&phi_gradient, dw);
+            if infeasibility > 0.0 {
+                let new_lambda: Vec<f64> = (0..m).map(|r| lambda[r] + dlambda[r]).collect();
+                let curvature: f64 = (dot(&eval.residual, &new_lambda) - directional).max(0.0);
+                let required: f64 =
+                    (directional + 0.5 * curvature) / ((1.0 - PENALTY_MARGIN) * infeasibility);
+                penalty = penalty.max(required);
+            }
+            let merit_slope: f64 = directional - penalty * infeasibility;
+            let merit: f64 = eval.objective + barrier_value + penalty * infeasibility;
+            // Merit function and constraint residual at a trial point, None where not finite
+            let trial = |w_trial: &[f64]| -> Option<(f64, Vec<f64>)> {
+                let (objective, residual) = problem.values(w_trial)?;
+                let trial_merit: f64 =
+                    objective + problem.barrier(w_trial, mu).0 + penalty * one_norm(&residual);
+                return Some((trial_merit, residual));
+            };
+            let sufficient = |trial_merit: f64, alpha: f64| -> bool {
+                return trial_merit <= merit + ARMIJO * alpha * merit_slope.min(0.0);
+            };
+
+            // Backtracking line search on the merit function, with second order corrections
+            // of the full step against the Maratos effect
+            let mut alpha: f64 = alpha_max;
+            let mut trials: usize = 1;
+            let mut step: Step = (dw.to_vec(), dlambda.to_vec());
+            loop {
+                let w_trial: Vec<f64> = (0..n_w).map(|k| w[k] + alpha * dw[k]).collect();
+                if let Some((trial_merit, _)) = trial(&w_trial) {
+                    if sufficient(trial_merit, alpha) {
+                        break;
+                    }
+                }
+                if trials == 1 && infeasibility > 0.0 {
+                    if let Some(corrected) = self.second_order_correction(
+                        &problem,
+                        &ldl,
+                        &rhs,
+                        &w,
+                        &w_trial,
+                        &distances,
+                        alpha,
+                        tau,
+                        &trial,
+                        &sufficient,
+                    ) {
+                        (alpha, step) = corrected;
+                        break;
+                    }
+                }
+                if trials >= MAX_LINE_SEARCH_TRIALS {
+                    break;
+                }
+                alpha /= 2.0;
+                trials += 1;
+            }
+            let (dw, dlambda) = (&step.0, &step.1);
+
+            // Bound multiplier steps and their fraction to the boundary
+            let mut dz_l: Vec<f64> = vec![0.0; n_w];
+            let mut dz_u: Vec<f64> = vec![0.0; n_w];
+            for k in 0..n_w {
+                if lower[k].is_finite() {
+                    let d: f64 = w[k] - lower[k];
+                    dz_l[k] = (mu - z_l[k] * d - z_l[k] * dw[k]) / d;
+                }
+                if upper[k].is_finite() {
+                    let d: f64 = upper[k] - w[k];
+                    dz_u[k] = (mu - z_u[k] * d + z_u[k] * dw[k]) / d;
+                }
+            }
+            let multiplier_distances: Vec<(f64, f64)> = z_l
+                .iter()
+                .map(|z| (*z, f64::INFINITY))
+                .chain(z_u.iter().map(|z| (*z, f64::INFINITY)))
+                .collect();
+            let dz_bounds: Vec<f64> = dz_l.iter().chain(dz_u.iter()).copied().collect();
+            let alpha_dual: f64 = fraction_to_boundary(&multiplier_distances, &dz_bounds, tau);
+
+            // Step, keeping the bound multipliers within a factor of mu / distance
+            for k in 0..n_w {
+                w[k] += alpha * dw[k];
+            }
+            for r in 0..m {
+                lambda[r] += alpha * dlambda[r];
+            }
+            for k in 0..n_w {
+                if lower[k].is_finite() {
+                    let d: f64 = w[k] - lower[k];
+                    z_l[k] = (z_l[k] + alpha_dual * dz_l[k])
+                        .min(MULTIPLIER_SAFEGUARD * mu / d)
+                        .max(mu / (MULTIPLIER_SAFEGUARD * d));
+                }
+                if upper[k].is_finite() {
+                    let d: f64 = upper[k] - w[k];
+                    z_u[k] = (z_u[k] + alpha_dual * dz_u[k])
+                        .min(MULTIPLIER_SAFEGUARD * mu / d)
+                        .max(mu / (MULTIPLIER_SAFEGUARD * d));
+                }
+            }
+            last_step = (alpha * max_norm(dw), delta_w, alpha, alpha_dual, trials);
+            eval = problem.evaluate(&w);
+            iteration += 1;
+        }
+
+        return self.solution(&problem, status, &w, &lambda, &z_l, &z_u, &eval, log);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::point_mass::PointMass;
+    use crate::nlp::tests::{hs071, polygon_qp, rosenbrock, DenseNlp, FreeTime};
+    use crate::ocp::transcription::Transcription;
+    use crate::ocp::{FrictionCircle, MinimumTime};
+
+    fn solver() -> InteriorPoint {
+        return InteriorPoint::new(SolverOptions {
+            tolerance: 1e-8,
+            max_iterations: 200,
+            verbose: false,
+        });
+    }
+
+    #[test]
+    fn test_rosenbrock() {
+        let nlp: DenseNlp = rosenbrock();
+        let solution: Solution = solver().solve(&nlp, &[-1.2, 1.0]);
+        assert_eq!(solution.status, Status::Solved);
+        assert!((solution.z[0] - 1.0).abs() < 1e-5);
+        assert!((solution.z[1] - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_hs071() {
+        let nlp: DenseNlp = hs071();
+        let solution: Solution = solver().solve(&nlp, &[1.0, 5.0, 5.0, 1.0]);
+        assert_eq!(solution.status, Status::Solved);
+        assert!((solution.objective - 17.0140173).abs() < 1e-6);
+        let expected: [f64; 4] = [1.0, 4.7429994, 3.8211503, 1.3794082];
+        for (z, e) in solution.z.iter().zip(expected.iter()) {
+            assert!((z - e).abs() < 1e-5);
+        }
+        // z0 is at its lower bound with a positive multiplier
+        assert!(solution.lower_multipliers[0] > 0.1);
+        assert!(solution.primal_infeasibility < 1e-8);
+        assert!(solution.iterations < 30);
+    }
+
+    #[test]
+    fn test_quadratic_program_with_fixed_variable() {
+        let nlp: DenseNlp = polygon_qp();
+        let solution: Solution = solver().solve(&nlp, &[2.0, 0.0, 3.0]);
+        assert_eq!(solution.status, Status::Solved);
+        // The fixed variable adds 3 x, moving the optimum onto the edge y = 1 + x / 2
+        assert!((solution.z[0] - 0.2).abs() < 1e-6);
+        assert!((solution.z[1] - 1.1).abs() < 1e-6);
+        assert_eq!(solution.z[2], 3.0);
+        // The Lagrangian gradient in the fixed variable is x
+        assert!((solution.lower_multipliers[2] - 0.2).abs() < 1e-6);
+        assert_eq!(solution.upper_multipliers[2], 0.0);
+        assert!(solution.lower_multipliers[0] < 1e-6);
+        assert!(solution.lambda[0].abs() > 0.1);
+        assert!(solution.lambda[1].abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_iteration_log() {
+        let nlp = DenseNlp {
+            n: 1,
+            m: 0,
+            variable_bounds: (vec![0.0], vec![f64::INFINITY]),
+            constraint_bounds: (vec![], vec![]),
+            objective: |z| (z[0] + 1.0).powi(2),
+            constraints: |_| vec![],
+        };
+        let solution: Solution = solver().solve(&nlp, &[1.0]);
+        assert_eq!(solution.status, Status::Solved);
+        assert!(solution.z[0] < 1e-7);
+        assert_eq!(solution.log.len(), solution.iterations + 1);
+        assert_eq!(solution.log[0].primal_step, 0.0);
+        assert!(solution.log.last().unwrap().barrier < 1e-8);
+        assert!(Iteration::header().starts_with("iter"));
+
+        let limited: Solution = InteriorPoint::new(SolverOptions {
+            max_iterations: 2,
+            ..SolverOptions::default()
+        })
+        .solve(&nlp, &[1.0]);
+        assert_eq!(limited.status, Status::MaxIterations);
+        assert_eq!(limited.iterations, 2);
+    }
+
+    #[test]
+    fn test_minimum_time_point_mass() {
+        // Rest to rest over 10 m with |F| <= 10 N and m = 2 kg, bang-bang in 2 sqrt(2) s
+        let model = FreeTime {
+            model: PointMass::new("Mass", 2.0),
+        };
+        let mesh: Vec<f64> = (0..=10).map(|k| k as f64 / 10.0).collect();
+        let mut problem = Transcription::new(&model, &mesh, 3);
+        problem.costs.push(Box::new(MinimumTime::new(4)));
+        problem
+            .constraints
+            .push(Box::new(FrictionCircle::new((0, 1), 10.0)));
+        problem.boundary.initial = vec![Some(0.0), Some(0.0), Some(0.0), Some(0.0), None];
+        problem.boundary.terminal = vec![Some(10.0), Some(0.0), Some(0.0), Some(0.0), None];
+        problem.state_bounds[4] = (0.1, 100.0);
+        problem.input_scales = vec![10.0, 10.0];
+
+        let z0: Vec<f64> = problem.pack(|t| (vec![10.0 * t, 0.0, 1.0, 0.0, 5.0], vec![0.0, 0.0]));
+        let solution: Solution = solver().solve(&problem, &z0);
+        assert_eq!(solution.status, Status::Solved);
+        let lap_time: f64 = problem.state(&solution.z, 0)[4];
+        assert!((lap_time - 2.0 * 2f64.sqrt()).abs() < 1e-3);
+        assert!(solution.iterations < 30);
+        assert!(solution.primal_infeasibility < 1e-8);
+    }
+}
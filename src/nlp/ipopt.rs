@@ -0,0 +1,348 @@
+use crate::nlp::{Iteration, Nlp, NlpSolver, Solution, SolverOptions, Status};
+use std::cell::RefCell;
+use std::ffi::{c_void, CString};
+use std::os::raw::{c_char, c_int};
+use std::slice;
+
+// Binding to a locally installed IPOPT through its C interface (IpStdCInterface.h), built
+// with the "ipopt" feature and linked against libipopt.
+//
+// The NLP callbacks forward to the Nlp trait, whose conventions match those of IPOPT: the
+// Lagrangian is sigma f(z) + lambda^T c(z), the Hessian is given as its lower triangle and
+// repeated entries add up. Infinite bounds are passed through, which IPOPT treats as
+// absent. The iteration log is collected by the intermediate callback.
+
+type Number = f64;
+type Index = c_int;
+type IpoptProblem = *mut c_void;
+type UserData = *mut c_void;
+
+type EvalF = extern "C" fn(Index, *const Number, bool, *mut Number, UserData) -> bool;
+type EvalGradF = extern "C" fn(Index, *const Number, bool, *mut Number, UserData) -> bool;
+type EvalG = extern "C" fn(Index, *const Number, bool, Index, *mut Number, UserData) -> bool;
+type EvalJacG = extern "C" fn(
+    Index,
+    *const Number,
+    bool,
+    Index,
+    Index,
+    *mut Index,
+    *mut Index,
+    *mut Number,
+    UserData,
+) -> bool;
+type EvalH = extern "C" fn(
+    Index,
+    *const Number,
+    bool,
+    Number,
+    Index,
+    *const Number,
+    bool,
+    Index,
+    *mut Index,
+    *mut Index,
+    *mut Number,
+    UserData,
+) -> bool;
+type Intermediate = extern "C" fn(
+    Index,
+    Index,
+    Number,
+    Number,
+    Number,
+    Number,
+    Number,
+    Number,
+    Number,
+    Number,
+    Index,
+    UserData,
+) -> bool;
+
+#[link(name = "ipopt")]
+extern "C" {
+    #[allow(clippy::too_many_arguments)]
+    fn CreateIpoptProblem(
+        n: Index,
+        x_l: *const Number,
+        x_u: *const Number,
+        m: Index,
+        g_l: *const Number,
+        g_u: *const Number,
+        nele_jac: Index,
+        nele_hess: Index,
+        index_style: Index,
+        eval_f: EvalF,
+        eval_g: EvalG,
+        eval_grad_f: EvalGradF,
+        eval_jac_g: EvalJacG,
+        eval_h: EvalH,
+    ) -> IpoptProblem;
+    fn FreeIpoptProblem(problem: IpoptProblem);
+    fn AddIpoptNumOption(problem: IpoptProblem, keyword: *const c_char, value: Number) -> bool;
+    fn AddIpoptIntOption(problem: IpoptProblem, keyword: *const c_char, value: Index) -> bool;
+    fn AddIpoptStrOption(
+        problem: IpoptProblem,
+        keyword: *const c_char,
+        value: *const c_char,
+    ) -> bool;
+    fn SetIntermediateCallback(problem: IpoptProblem, callback: Intermediate) -> bool;
+    #[allow(clippy::too_many_arguments)]
+    fn IpoptSolve(
+        problem: IpoptProblem,
+        x: *mut Number,
+        g: *mut Number,
+        obj_val: *mut Number,
+        mult_g: *mut Number,
+        mult_x_l: *mut Number,
+        mult_x_u: *mut Number,
+        user_data: UserData,
+    ) -> c_int;
+}
+
+// ApplicationReturnStatus values
+const SOLVE_SUCCEEDED: c_int = 0;
+const MAXIMUM_ITERATIONS_EXCEEDED: c_int = -1;
+
+pub struct Ipopt {
+    // Parameters
+    pub options: SolverOptions,
+}
+
+// State shared with the callbacks through the user data pointer
+struct Callbacks<'a> {
+    nlp: &'a dyn Nlp,
+    jacobian_structure: Vec<(usize, usize)>,
+    hessian_structure: Vec<(usize, usize)>,
+    log: RefCell<Vec<Iteration>>,
+}
+
+// Callbacks behind the user data pointer
+fn callbacks<'a>(user_data: UserData) -> &'a Callbacks<'a> {
+    return unsafe { &*(user_data as *const Callbacks) };
+}
+
+fn variables<'a>(n: Index, x: *const Number) -> &'a [f64] {
+    return unsafe { slice::from_raw_parts(x, n as usize) };
+}
+
+// Copy values into an IPOPT array, false where any is not finite
+fn write(values: &[f64], target: *mut Number) -> bool {
+    let target: &mut [f64] = unsafe { slice::from_raw_parts_mut(target, values.len()) };
+    target.copy_from_slice(values);
+    return values.iter().all(|v| v.is_finite());
+}
+
+// Write a sparsity structure as zero-based (row, col) indices
+fn write_structure(structure: &[(usize, usize)], rows: *mut Index, cols: *mut Index) {
+    let rows: &mut [Index] = unsafe { slice::from_raw_parts_mut(rows, structure.len()) };
+    let cols: &mut [Index] = unsafe { slice::from_raw_parts_mut(cols, structure.len()) };
+    for (k, (row, col)) in structure.iter().enumerate() {
+        rows[k] = *row as Index;
+        cols[k] = *col as Index;
+    }
+}
+
+extern "C" fn eval_f(
+    n: Index,
+    x: *const Number,
+    _new_x: bool,
+    obj_value: *mut Number,
+    user_data: UserData,
+) -> bool {
+    let objective: f64 = callbacks(user_data).nlp.objective(variables(n, x));
+    return write(&[objective], obj_value);
+}
+
+extern "C" fn eval_grad_f(
+    n: Index,
+    x: *const Number,
+    _new_x: bool,
+    grad_f: *mut Number,
+    user_data: UserData,
+) -> bool {
+    return write(&callbacks(user_data).nlp.gradient(variables(n, x)), grad_f);
+}
+
+extern "C" fn eval_g(
+    n: Index,
+    x: *const Number,
+    _new_x: bool,
+    _m: Index,
+    g: *mut Number,
+    user_data: UserData,
+) -> bool {
+    return write(&callbacks(user_data).nlp.constraints(variables(n, x)), g);
+}
+
+extern "C" fn eval_jac_g(
+    n: Index,
+    x: *const Number,
+    _new_x: bool,
+    _m: Index,
+    _nele_jac: Index,
+    rows: *mut Index,
+    cols: *mut Index,
+    values: *mut Number,
+    user_data: UserData,
+) -> bool {
+    let data: &Callbacks = callbacks(user_data);
+    if values.is_null() {
+        write_structure(&data.jacobian_structure, rows, cols);
+        return true;
+    }
+    return write(&data.nlp.jacobian(variables(n, x)), values);
+}
+
+extern "C" fn eval_h(
+    n: Index,
+    x: *const Number,
+    _new_x: bool,
+    obj_factor: Number,
+    m: Index,
+    lambda: *const Number,
+    _new_lambda: bool,
+    _nele_hess: Index,
+    rows: *mut Index,
+    cols: *mut Index,
+    values: *mut Number,
+    user_data: UserData,
+) -> bool {
+    let data: &Callbacks = callbacks(user_data);
+    if values.is_null() {
+        write_structure(&data.hessian_structure, rows, cols);
+        return true;
+    }
+    let lambda: &[f64] = variables(m, lambda);
+    return write(
+        &data.nlp.hessian(variables(n, x), obj_factor, lambda),
+        values,
+    );
+}
+
+extern "C" fn intermediate(
+    _alg_mod: Index,
+    iter_count: Index,
+    obj_value: Number,
+    inf_pr: Number,
+    inf_du: Number,
+    mu: Number,
+    d_norm: Number,
+    regularization_size: Number,
+    alpha_du: Number,
+    alpha_pr: Number,
+    ls_trials: Index,
+    user_data: UserData,
+) -> bool {
+    callbacks(user_data).log.borrow_mut().push(Iteration {
+        iteration: iter_count as usize,
+        objective: obj_value,
+        primal_infeasibility: inf_pr,
+        dual_infeasibility: inf_du,
+        barrier: mu,
+        step_norm: d_norm,
+        regularisation: regularization_size,
+        primal_step: alpha_pr,
+        dual_step: alpha_du,
+        line_search_trials: ls_trials as usize,
+    });
+    return true;
+}
+
+// IPOPT IMPLEMENTATION ++++++++++++++++++++++++++++++++
+impl Ipopt {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(options: SolverOptions) -> Self {
+        return Ipopt { options };
+    }
+}
+
+impl NlpSolver for Ipopt {
+    fn name(&self) -> &str {
+        return "IPOPT";
+    }
+
+    fn options(&self) -> &SolverOptions {
+        return &self.options;
+    }
+
+    fn solve(&self, nlp: &dyn Nlp, z0: &[f64]) -> Solution {
+        let (n, m) = (nlp.n_variables(), nlp.n_constraints());
+        let (z_lower, z_upper) = nlp.variable_bounds();
+        let (c_lower, c_upper) = nlp.constraint_bounds();
+        let data: Callbacks = Callbacks {
+            nlp,
+            jacobian_structure: nlp.jacobian_structure(),
+            hessian_structure: nlp.hessian_structure(),
+            log: RefCell::new(Vec::new()),
+        };
+
+        let mut z: Vec<f64> = z0.to_vec();
+        let mut constraints: Vec<f64> = vec![0.0; m];
+        let mut objective: f64 = 0.0;
+        let mut lambda: Vec<f64> = vec![0.0; m];
+        let mut lower_multipliers: Vec<f64> = vec![0.0; n];
+        let mut upper_multipliers: Vec<f64> = vec![0.0; n];
+        let code: c_int = unsafe {
+            let problem: IpoptProblem = CreateIpoptProblem(
+                n as Index,
+                z_lower.as_ptr(),
+                z_upper.as_ptr(),
+                m as Index,
+                c_lower.as_ptr(),
+                c_upper.as_ptr(),
+                data.jacobian_structure.len() as Index,
+                data.hessian_structure.len() as Index,
+                0, // Zero-based indices
+                eval_f,
+                eval_g,
+                eval_grad_f,
+                eval_jac_g,
+                eval_h,
+            );
+            if problem.is_null() {
+                return Solution::new(
+                    nlp,
+                    Status::Failed(String::from("IPOPT problem creation failed")),
+                    z,
+                    (lambda, lower_multipliers, upper_multipliers),
+                    Vec::new(),
+                );
+            }
+
+            let keyword = |name: &str| -> CString { CString::new(name).unwrap() };
+            AddIpoptNumOption(problem, keyword("tol").as_ptr(), self.options.tolerance);
+            AddIpoptIntOption(
+                problem,
+                keyword("max_iter").as_ptr(),
+                self.options.max_iterations as Index,
+            );
+            let print_level: Index = if self.options.verbose { 5 } else { 0 };
+            AddIpoptIntOption(problem, keyword("print_level").as_ptr(), print_level);
+            AddIpoptStrOption(problem, keyword("sb").as_ptr(), keyword("yes").as_ptr());
+            SetIntermediateCallback(problem, intermediate);
+
+            let code: c_int = IpoptSolve(
+                problem,
+                z.as_mut_ptr(),
+                constraints.as_mut_ptr(),
+                &mut objective,
+                lambda.as_mut_ptr(),
+                lower_multipliers.as_mut_ptr(),
+                upper_multipliers.as_mut_ptr(),
+                &data as *const Callbacks as UserData,
+            );
+            FreeIpoptProblem(problem);
+            code
+        };
+
+        let status: Status = match code {
+            SOLVE_SUCCEEDED => Status::Solved,
+            MAXIMUM_ITERATIONS_EXCEEDED => Status::MaxIterations,
+            _ => Status::Failed(format!("IPOPT return status {}", code)),
+        };
+        let multipliers = (lambda, lower_multipliers, upper_multipliers);
+        return Solution::new(nlp, status, z, multipliers, data.log.into_inner());
+    }
+}
@@ -0,0 +1,326 @@
+// Sparse LDL^T factorisation of symmetric, possibly indefinite matrices.
+//
+// The matrix is given once as a structure of (row, col) entries from either triangle, with
+// repeated entries summed, and can then be factorised for any values on that structure.
+// The rows and columns are ordered by reverse Cuthill-McKee to keep the profile small, and
+// the factorisation is up-looking along the elimination tree, as in QDLDL. Only 1x1 pivots
+// are used, so the factorisation exists for quasi-definite matrices such as a regularised
+// KKT system, and fails with a zero pivot where a 2x2 pivot would have been needed. The
+// signs of the pivots give the inertia of the matrix, which is nonsingular when the
+// factorisation succeeds.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Inertia {
+    pub positive: usize,
+    pub negative: usize,
+}
+
+pub struct Ldl {
+    n: usize,
+    permutation: Vec<usize>, // Original index of every permuted index
+    // Upper triangle of the permuted matrix in compressed sparse columns
+    col_ptr: Vec<usize>,
+    row_idx: Vec<usize>,
+    slots: Vec<usize>, // Position in row_idx of every structure entry
+    etree: Vec<Option<usize>>,
+    // Unit lower triangular factor in compressed sparse columns and the pivots
+    l_ptr: Vec<usize>,
+    l_idx: Vec<usize>,
+    l_val: Vec<f64>,
+    d: Vec<f64>,
+    d_inv: Vec<f64>,
+}
+
+// Reverse Cuthill-McKee ordering of the graph of the off-diagonal entries
+fn reverse_cuthill_mckee(n: usize, structure: &[(usize, usize)]) -> Vec<usize> {
+    let mut adjacency: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for (i, j) in structure.iter().filter(|(i, j)| i != j) {
+        adjacency[*i].push(*j);
+        adjacency[*j].push(*i);
+    }
+    for neighbours in adjacency.iter_mut() {
+        neighbours.sort_unstable();
+        neighbours.dedup();
+    }
+
+    let mut visited: Vec<bool> = vec![false; n];
+    let mut order: Vec<usize> = Vec::with_capacity(n);
+    while order.len() < n {
+        // Start every connected component from an unvisited node of minimum degree
+        let start: usize = (0..n)
+            .filter(|i| !visited[*i])
+            .min_by_key(|i| adjacency[*i].len())
+            .unwrap();
+        visited[start] = true;
+        let mut head: usize = order.len();
+        order.push(start);
+        while head < order.len() {
+            let node: usize = order[head];
+            head += 1;
+            let mut next: Vec<usize> = adjacency[node]
+                .iter()
+                .copied()
+                .filter(|i| !visited[*i])
+                .collect();
+            next.sort_by_key(|i| adjacency[*i].len());
+            for i in next {
+                visited[i] = true;
+                order.push(i);
+            }
+        }
+    }
+    order.reverse();
+    return order;
+}
+
+// LDL IMPLEMENTATION ++++++++++++++++++++++++++++++++++
+impl Ldl {
+    // Symbolic analysis of an n * n structure, which need not contain the diagonal
+    pub fn analyse(n: usize, structure: &[(usize, usize)]) -> Self {
+        let permutation: Vec<usize> = reverse_cuthill_mckee(n, structure);
+        let mut position: Vec<usize> = vec![0; n];
+        for (new, old) in permutation.iter().enumerate() {
+            position[*old] = new;
+        }
+
+        // Upper triangle (row <= col) of the permuted structure, always with the diagonal
+        let upper = |(i, j): (usize, usize)| -> (usize, usize) {
+            let (a, b) = (position[i], position[j]);
+            return (a.min(b), a.max(b));
+        };
+        let mut columns: Vec<Vec<usize>> = (0..n).map(|j| vec![j]).collect();
+        for entry in structure.iter() {
+            let (row, col) = upper(*entry);
+            columns[col].push(row);
+        }
+        let mut col_ptr: Vec<usize> = vec![0];
+        let mut row_idx: Vec<usize> = Vec::new();
+        for column in columns.iter_mut() {
+            column.sort_unstable();
+            column.dedup();
+            row_idx.extend(column.iter());
+            col_ptr.push(row_idx.len());
+        }
+        let slots: Vec<usize> = structure
+            .iter()
+            .map(|entry| {
+                let (row, col) = upper(*entry);
+                let rows: &[usize] = &row_idx[col_ptr[col]..col_ptr[col + 1]];
+                col_ptr[col] + rows.binary_search(&row).unwrap()
+            })
+            .collect();
+
+        // Elimination tree and the number of entries in every column of L
+        let mut etree: Vec<Option<usize>> = vec![None; n];
+        let mut l_count: Vec<usize> = vec![0; n];
+        let mut work: Vec<usize> = vec![usize::MAX; n];
+        for j in 0..n {
+            work[j] = j;
+            for row in row_idx[col_ptr[j]..col_ptr[j + 1]].iter() {
+                let mut i: usize = *row;
+                while work[i] != j {
+                    if etree[i].is_none() {
+                        etree[i] = Some(j);
+                    }
+                    l_count[i] += 1;
+                    work[i] = j;
+                    i = etree[i].unwrap();
+                }
+            }
+        }
+        let mut l_ptr: Vec<usize> = vec![0];
+        for count in l_count.iter() {
+            l_ptr.push(l_ptr.last().unwrap() + count);
+        }
+        let nnz_l: usize = *l_ptr.last().unwrap();
+
+        return Ldl {
+            n,
+            permutation,
+            col_ptr,
+            row_idx,
+            slots,
+            etree,
+            l_ptr,
+            l_idx: vec![0; nnz_l],
+            l_val: vec![0.0; nnz_l],
+            d: vec![0.0; n],
+            d_inv: vec![0.0; n],
+        };
+    }
+
+    // Numeric factorisation for the values of the structure entries, giving the inertia
+    pub fn factorise(&mut self, values: &[f64]) -> Result<Inertia, String> {
+        let n: usize = self.n;
+        let mut a: Vec<f64> = vec![0.0; self.row_idx.len()];
+        for (slot, value) in self.slots.iter().zip(values.iter()) {
+            a[*slot] += value;
+        }
+
+        let mut y: Vec<f64> = vec![0.0; n];
+        let mut marked: Vec<bool> = vec![false; n];
+        let mut next_in_col: Vec<usize> = self.l_ptr[..n].to_vec();
+        let mut pattern: Vec<usize> = Vec::with_capacity(n);
+        let mut stack: Vec<usize> = Vec::with_capacity(n);
+
+        for k in 0..n {
+            // Nonzero pattern of row k of L, the reach of column k in the elimination tree,
+            // in topological order
+            pattern.clear();
+            self.d[k] = 0.0;
+            let column = self.col_ptr[k]..self.col_ptr[k + 1];
+            for (i, a_ik) in self.row_idx[column.clone()].iter().zip(a[column].iter()) {
+                let i: usize = *i;
+                if i == k {
+                    self.d[k] = *a_ik;
+                    continue;
+                }
+                y[i] = *a_ik;
+                let mut next: Option<usize> = Some(i);
+                stack.clear();
+                while let Some(node) = next {
+                    if node >= k || marked[node] {
+                        break;
+                    }
+                    marked[node] = true;
+                    stack.push(node);
+                    next = self.etree[node];
+                }
+                while let Some(node) = stack.pop() {
+                    pattern.push(node);
+                }
+            }
+
+            // Sparse triangular solve for row k of L and the pivot
+            for &c in pattern.iter().rev() {
+                let y_c: f64 = y[c];
+                for p in self.l_ptr[c]..next_in_col[c] {
+                    y[self.l_idx[p]] -= self.l_val[p] * y_c;
+                }
+                let l_kc: f64 = y_c * self.d_inv[c];
+                self.l_idx[next_in_col[c]] = k;
+                self.l_val[next_in_col[c]] = l_kc;
+                self.d[k] -= y_c * l_kc;
+                next_in_col[c] += 1;
+                y[c] = 0.0;
+                marked[c] = false;
+            }
+
+            if self.d[k] == 0.0 || !self.d[k].is_finite() {
+                return Err(format!("zero pivot in row {}", self.permutation[k]));
+            }
+            self.d_inv[k] = 1.0 / self.d[k];
+        }
+
+        return Ok(Inertia {
+            positive: self.d.iter().filter(|d| **d > 0.0).count(),
+            negative: self.d.iter().filter(|d| **d < 0.0).count(),
+        });
+    }
+
+    // Solution of A x = b with the last factorisation
+    pub fn solve(&self, b: &[f64]) -> Vec<f64> {
+        let n: usize = self.n;
+        let mut x: Vec<f64> = self.permutation.iter().map(|i| b[*i]).collect();
+        for i in 0..n {
+            for p in self.l_ptr[i]..self.l_ptr[i + 1] {
+                x[self.l_idx[p]] -= self.l_val[p] * x[i];
+            }
+        }
+        for (x_i, d_inv) in x.iter_mut().zip(self.d_inv.iter()) {
+            *x_i *= d_inv;
+        }
+        for i in (0..n).rev() {
+            for p in self.l_ptr[i]..self.l_ptr[i + 1] {
+                x[i] -= self.l_val[p] * x[self.l_idx[p]];
+            }
+        }
+        let mut solution: Vec<f64> = vec![0.0; n];
+        for (new, old) in self.permutation.iter().enumerate() {
+            solution[*old] = x[new];
+        }
+        return solution;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Product of a symmetric matrix given by lower triangle entries and a vector
+    fn multiply(structure: &[(usize, usize)], values: &[f64], x: &[f64]) -> Vec<f64> {
+        let mut y: Vec<f64> = vec![0.0; x.len()];
+        for ((i, j), v) in structure.iter().zip(values.iter()) {
+            y[*i] += v * x[*j];
+            if i != j {
+                y[*j] += v * x[*i];
+            }
+        }
+        return y;
+    }
+
+    #[test]
+    fn test_indefinite_kkt_system() {
+        // [H J^T; J -delta] with H = diag(2, 3, 1) and J = [[1, 1, 0], [0, 1, 1]]
+        let structure: Vec<(usize, usize)> = vec![
+            (0, 0),
+            (1, 1),
+            (2, 2),
+            (3, 0),
+            (3, 1),
+            (4, 1),
+            (4, 2),
+            (3, 3),
+            (4, 4),
+        ];
+        let values: Vec<f64> = vec![2.0, 3.0, 1.0, 1.0, 1.0, 1.0, 1.0, -1e-8, -1e-8];
+        let mut ldl: Ldl = Ldl::analyse(5, &structure);
+        let inertia: Inertia = ldl.factorise(&values).unwrap();
+        assert_eq!((inertia.positive, inertia.negative), (3, 2));
+
+        let b: Vec<f64> = vec![1.0, -2.0, 0.5, 3.0, 1.0];
+        let x: Vec<f64> = ldl.solve(&b);
+        let residual: Vec<f64> = multiply(&structure, &values, &x);
+        for (r, b) in residual.iter().zip(b.iter()) {
+            assert!((r - b).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_banded_matrix_with_repeated_entries() {
+        // Tridiagonal [-1 4 -1] matrix with a corner coupling, each diagonal given twice
+        let n: usize = 40;
+        let mut structure: Vec<(usize, usize)> = Vec::new();
+        let mut values: Vec<f64> = Vec::new();
+        for i in 0..n {
+            structure.extend([(i, i), (i, i)]);
+            values.extend([2.0, 2.0]);
+            if i > 0 {
+                structure.push((i - 1, i));
+                values.push(-1.0);
+            }
+        }
+        structure.push((n - 1, 0));
+        values.push(-1.0);
+
+        let mut ldl: Ldl = Ldl::analyse(n, &structure);
+        assert_eq!(ldl.factorise(&values).unwrap().positive, n);
+        let b: Vec<f64> = (0..n).map(|i| (i as f64).sin()).collect();
+        let x: Vec<f64> = ldl.solve(&b);
+        let residual: Vec<f64> = multiply(&structure, &values, &x);
+        for (r, b) in residual.iter().zip(b.iter()) {
+            assert!((r - b).abs() < 1e-12);
+        }
+
+        // Refactorising with other values on the same structure
+        let negated: Vec<f64> = values.iter().map(|v| -v).collect();
+        assert_eq!(ldl.factorise(&negated).unwrap().negative, n);
+    }
+
+    #[test]
+    fn test_zero_pivot() {
+        // [0 1; 1 0] needs a 2x2 pivot
+        let mut ldl: Ldl = Ldl::analyse(2, &[(1, 0)]);
+        assert!(ldl.factorise(&[1.0]).is_err());
+    }
+}
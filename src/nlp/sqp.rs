@@ -0,0 +1,460 @@
+use crate::nlp::interior_point::InteriorPoint;
+use crate::nlp::ldl::{Inertia, Ldl};
+use crate::nlp::{Iteration, Nlp, NlpSolver, Solution, SolverOptions, Status};
+
+// Line-search sequential quadratic programming.
+//
+// Every iteration minimises the quadratic model
+//     min  g^T d + 1/2 d^T (W + delta I) d
+//     s.t. z_L - z <= d <= z_U - z,
+//          c_L - c <= J d <= c_U - c
+// of the NLP at z, with the exact Hessian of the Lagrangian W. The regularisation delta is
+// increased until W + delta I is positive definite on the null space of the equality
+// constraints and fixed variables, which keeps the subproblem bounded. The quadratic
+// subproblems are solved with the interior-point method, and the step is accepted by an
+// Armijo backtracking line search on the l1 exact penalty merit function
+//     f(z) + nu sum max(c_L - c(z), c(z) - c_U, 0),
+// with a second order correction of rejected full steps. The variable bounds are kept by
+// every step, since they are linear.
+
+const ARMIJO: f64 = 1e-4;
+const MAX_LINE_SEARCH_TRIALS: usize = 30;
+const PENALTY_MARGIN: f64 = 1.1; // Penalty over the largest constraint multiplier
+const SUBPROBLEM_TOLERANCE_FACTOR: f64 = 0.1; // Subproblem tolerance relative to the NLP
+const FIRST_REGULARISATION: f64 = 1e-4;
+const MAX_REGULARISATION: f64 = 1e20;
+const CONSTRAINT_REGULARISATION: f64 = 1e-9;
+
+pub struct Sqp {
+    // Parameters
+    pub options: SolverOptions,
+}
+
+// Quadratic model of the NLP in the step d
+struct Subproblem {
+    gradient: Vec<f64>,
+    jacobian_structure: Vec<(usize, usize)>,
+    jacobian: Vec<f64>,
+    hessian_structure: Vec<(usize, usize)>, // NLP Hessian followed by the diagonal
+    hessian: Vec<f64>,
+    variable_bounds: (Vec<f64>, Vec<f64>),
+    constraint_bounds: (Vec<f64>, Vec<f64>),
+}
+
+// SUBPROBLEM IMPLEMENTATION +++++++++++++++++++++++++++
+impl Subproblem {
+    // Model at z, with the Hessian of the Lagrangian regularised by delta
+    fn new(nlp: &dyn Nlp, z: &[f64], lambda: &[f64], delta: f64) -> Self {
+        let (z_lower, z_upper) = nlp.variable_bounds();
+        let (c_lower, c_upper) = nlp.constraint_bounds();
+        let c: Vec<f64> = nlp.constraints(z);
+        let shift = |bounds: &[f64], values: &[f64]| -> Vec<f64> {
+            return bounds
+                .iter()
+                .zip(values.iter())
+                .map(|(b, v)| b - v)
+                .collect();
+        };
+
+        let n: usize = z.len();
+        let mut hessian_structure: Vec<(usize, usize)> = nlp.hessian_structure();
+        let mut hessian: Vec<f64> = nlp.hessian(z, 1.0, lambda);
+        hessian_structure.extend((0..n).map(|i| (i, i)));
+        hessian.extend(vec![delta; n]);
+
+        return Subproblem {
+            gradient: nlp.gradient(z),
+            jacobian_structure: nlp.jacobian_structure(),
+            jacobian: nlp.jacobian(z),
+            hessian_structure,
+            hessian,
+            variable_bounds: (shift(&z_lower, z), shift(&z_upper, z)),
+            constraint_bounds: (shift(&c_lower, &c), shift(&c_upper, &c)),
+        };
+    }
+
+    // (W + delta I) d
+    fn hessian_product(&self, d: &[f64]) -> Vec<f64> {
+        let mut product: Vec<f64> = vec![0.0; d.len()];
+        for ((i, j), value) in self.hessian_structure.iter().zip(self.hessian.iter()) {
+            product[*i] += value * d[*j];
+            if i != j {
+                product[*j] += value * d[*i];
+            }
+        }
+        return product;
+    }
+}
+
+impl Nlp for Subproblem {
+    fn n_variables(&self) -> usize {
+        return self.gradient.len();
+    }
+
+    fn n_constraints(&self) -> usize {
+        return self.constraint_bounds.0.len();
+    }
+
+    fn variable_bounds(&self) -> (Vec<f64>, Vec<f64>) {
+        return self.variable_bounds.clone();
+    }
+
+    fn constraint_bounds(&self) -> (Vec<f64>, Vec<f64>) {
+        return self.constraint_bounds.clone();
+    }
+
+    fn objective(&self, d: &[f64]) -> f64 {
+        let product: Vec<f64> = self.hessian_product(d);
+        return (0..d.len())
+            .map(|i| d[i] * (self.gradient[i] + 0.5 * product[i]))
+            .sum();
+    }
+
+    fn gradient(&self, d: &[f64]) -> Vec<f64> {
+        let product: Vec<f64> = self.hessian_product(d);
+        return (0..d.len())
+            .map(|i| self.gradient[i] + product[i])
+            .collect();
+    }
+
+    fn constraints(&self, d: &[f64]) -> Vec<f64> {
+        let mut c: Vec<f64> = vec![0.0; self.n_constraints()];
+        for ((row, col), value) in self.jacobian_structure.iter().zip(self.jacobian.iter()) {
+            c[*row] += value * d[*col];
+        }
+        return c;
+    }
+
+    fn jacobian_structure(&self) -> Vec<(usize, usize)> {
+        return self.jacobian_structure.clone();
+    }
+
+    fn jacobian(&self, _d: &[f64]) -> Vec<f64> {
+        return self.jacobian.clone();
+    }
+
+    fn hessian_structure(&self) -> Vec<(usize, usize)> {
+        return self.hessian_structure.clone();
+    }
+
+    fn hessian(&self, _d: &[f64], sigma: f64, _lambda: &[f64]) -> Vec<f64> {
+        return self.hessian.iter().map(|h| sigma * h).collect();
+    }
+}
+
+// Sum of the constraint violations
+fn violation(c: &[f64], bounds: &(Vec<f64>, Vec<f64>)) -> f64 {
+    return (0..c.len())
+        .map(|r| (bounds.0[r] - c[r]).max(c[r] - bounds.1[r]).max(0.0))
+        .sum();
+}
+
+fn max_norm(v: &[f64]) -> f64 {
+    return v.iter().fold(0.0, |max, v| max.max(v.abs()));
+}
+
+// SQP IMPLEMENTATION ++++++++++++++++++++++++++++++++++
+impl Sqp {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(options: SolverOptions) -> Self {
+        return Sqp { options };
+    }
+
+    // Solver of the quadratic subproblems
+    fn subproblem_solver(&self) -> InteriorPoint {
+        return InteriorPoint::new(SolverOptions {
+            tolerance: self.options.tolerance * SUBPROBLEM_TOLERANCE_FACTOR,
+            max_iterations: self.options.max_iterations,
+            verbose: false,
+        });
+    }
+
+    // Regularisation making the Hessian positive definite on the null space of the equality
+    // constraints and fixed variables, from the inertia of
+    //     [W + delta I   A^T       ]
+    //     [A             -delta_c I]
+    fn regularisation(
+        &self,
+        nlp: &dyn Nlp,
+        z: &[f64],
+        lambda: &[f64],
+        last: f64,
+    ) -> Result<f64, String> {
+        let n: usize = z.len();
+        let (z_lower, z_upper) = nlp.variable_bounds();
+        let (c_lower, c_upper) = nlp.constraint_bounds();
+        let equality: Vec<usize> = (0..c_lower.len())
+            .filter(|r| c_lower[*r] == c_upper[*r])
+            .collect();
+        let fixed: Vec<usize> = (0..n).filter(|i| z_lower[*i] == z_upper[*i]).collect();
+        let mut row_of: Vec<Option<usize>> = vec![None; c_lower.len()];
+        for (k, r) in equality.iter().enumerate() {
+            row_of[*r] = Some(n + k);
+        }
+        let m: usize = equality.len() + fixed.len();
+
+        // Diagonal, Hessian, equality rows and fixed variable rows
+        let mut structure: Vec<(usize, usize)> = (0..n + m).map(|i| (i, i)).collect();
+        structure.extend(nlp.hessian_structure());
+        let mut values: Vec<f64> = vec![0.0; n];
+        values.extend(vec![-CONSTRAINT_REGULARISATION; m]);
+        values.extend(nlp.hessian(z, 1.0, lambda));
+        let jacobian: Vec<f64> = nlp.jacobian(z);
+        for ((row, col), value) in nlp.jacobian_structure().iter().zip(jacobian.iter()) {
+            if let Some(r) = row_of[*row] {
+                structure.push((r, *col));
+                values.push(*value);
+            }
+        }
+        for (k, i) in fixed.iter().enumerate() {
+            structure.push((n + equality.len() + k, *i));
+            values.push(1.0);
+        }
+
+        let mut ldl: Ldl = Ldl::analyse(n + m, &structure);
+        let mut delta: f64 = 0.0;
+        loop {
+            for value in values.iter_mut().take(n) {
+                *value = delta;
+            }
+            if let Ok(Inertia { positive, negative }) = ldl.factorise(&values) {
+                if positive == n && negative == m {
+                    return Ok(delta);
+                }
+            }
+            delta = match delta == 0.0 {
+                true => FIRST_REGULARISATION.max(last / 3.0),
+                false => 10.0 * delta,
+            };
+            if delta > MAX_REGULARISATION {
+                return Err(String::from("Hessian regularisation failed"));
+            }
+        }
+    }
+}
+
+impl NlpSolver for Sqp {
+    fn name(&self) -> &str {
+        return "SQP";
+    }
+
+    fn options(&self) -> &SolverOptions {
+        return &self.options;
+    }
+
+    fn solve(&self, nlp: &dyn Nlp, z0: &[f64]) -> Solution {
+        let (z_lower, z_upper) = nlp.variable_bounds();
+        let constraint_bounds: (Vec<f64>, Vec<f64>) = nlp.constraint_bounds();
+        let (n, m) = (nlp.n_variables(), nlp.n_constraints());
+        let subproblem_solver: InteriorPoint = self.subproblem_solver();
+
+        // Starting point projected onto the bounds, with zero multipliers
+        let mut z: Vec<f64> = (0..n)
+            .map(|i| z0[i].max(z_lower[i]).min(z_upper[i]))
+            .collect();
+        let mut lambda: Vec<f64> = vec![0.0; m];
+        let mut z_l: Vec<f64> = vec![0.0; n];
+        let mut z_u: Vec<f64> = vec![0.0; n];
+        let mut penalty: f64 = 0.0;
+        let mut delta: f64 = 0.0;
+
+        let mut log: Vec<Iteration> = Vec::new();
+        let mut status: Status = Status::MaxIterations;
+        let mut last_step: (f64, f64, usize) = (0.0, 0.0, 0);
+        if self.options.verbose {
+            println!("{}", Iteration::header());
+        }
+        let mut iteration: usize = 0;
+        loop {
+            let current: Solution = Solution::new(
+                nlp,
+                Status::Solved,
+                z.clone(),
+                (lambda.clone(), z_l.clone(), z_u.clone()),
+                Vec::new(),
+            );
+            let (step_norm, alpha, trials) = last_step;
+            let line: Iteration = Iteration {
+                iteration,
+                objective: current.objective,
+                primal_infeasibility: current.primal_infeasibility,
+                dual_infeasibility: current.dual_infeasibility,
+                barrier: 0.0,
+                step_norm,
+                regularisation: delta,
+                primal_step: alpha,
+                dual_step: alpha,
+                line_search_trials: trials,
+            };
+            if self.options.verbose {
+                println!("{}", line);
+            }
+            log.push(line);
+
+            let error: f64 = current
+                .primal_infeasibility
+                .max(current.dual_infeasibility)
+                .max(current.complementarity);
+            if error <= self.options.tolerance {
+                status = Status::Solved;
+                break;
+            }
+            if iteration >= self.options.max_iterations {
+                break;
+            }
+            if !error.is_finite() {
+                status = Status::Failed(String::from("non-finite KKT error"));
+                break;
+            }
+
+            // Quadratic subproblem
+            delta = match self.regularisation(nlp, &z, &lambda, delta) {
+                Ok(delta) => delta,
+                Err(message) => {
+                    status = Status::Failed(message);
+                    break;
+                }
+            };
+            let subproblem: Subproblem = Subproblem::new(nlp, &z, &lambda, delta);
+            let qp: Solution = subproblem_solver.solve(&subproblem, &vec![0.0; n]);
+            if qp.status != Status::Solved {
+                status = Status::Failed(format!("quadratic subproblem {:?}", qp.status));
+                break;
+            }
+            let d: &[f64] = &qp.z;
+
+            // l1 merit function, with a penalty above the subproblem multipliers
+            penalty = penalty.max(PENALTY_MARGIN * max_norm(&qp.lambda));
+            let infeasibility: f64 = violation(&current.constraints, &constraint_bounds);
+            let merit: f64 = current.objective + penalty * infeasibility;
+            let slope: f64 = (0..n).map(|i| subproblem.gradient[i] * d[i]).sum::<f64>()
+                - penalty * infeasibility;
+            let trial = |z_trial: &[f64]| -> f64 {
+                let trial_merit: f64 = nlp.objective(z_trial)
+                    + penalty * violation(&nlp.constraints(z_trial), &constraint_bounds);
+                return match trial_merit.is_finite() {
+                    true => trial_merit,
+                    false => f64::INFINITY,
+                };
+            };
+            let sufficient = |trial_merit: f64, alpha: f64| -> bool {
+                return trial_merit <= merit + ARMIJO * alpha * slope.min(0.0);
+            };
+
+            let mut alpha: f64 = 1.0;
+            let mut trials: usize = 1;
+            let mut step: Vec<f64> = d.to_vec();
+            loop {
+                let z_trial: Vec<f64> = (0..n).map(|i| z[i] + alpha * d[i]).collect();
+                if sufficient(trial(&z_trial), alpha) {
+                    break;
+                }
+                if trials == 1 {
+                    // Second order correction, resolving the subproblem with the constraints
+                    // shifted by their curvature along the step
+                    let mut corrected: Subproblem = Subproblem::new(nlp, &z, &lambda, delta);
+                    let linearised: Vec<f64> = subproblem.constraints(d);
+                    let c_trial: Vec<f64> = nlp.constraints(&z_trial);
+                    for (r, (lower, upper)) in corrected
+                        .constraint_bounds
+                        .0
+                        .iter_mut()
+                        .zip(corrected.constraint_bounds.1.iter_mut())
+                        .enumerate()
+                    {
+                        let curvature: f64 = c_trial[r] - current.constraints[r] - linearised[r];
+                        *lower -= curvature;
+                        *upper -= curvature;
+                    }
+                    let correction: Solution = subproblem_solver.solve(&corrected, d);
+                    if correction.status == Status::Solved {
+                        let z_corrected: Vec<f64> =
+                            (0..n).map(|i| z[i] + correction.z[i]).collect();
+                        if sufficient(trial(&z_corrected), 1.0) {
+                            step = correction.z;
+                            break;
+                        }
+                    }
+                }
+                if trials >= MAX_LINE_SEARCH_TRIALS {
+                    break;
+                }
+                alpha /= 2.0;
+                trials += 1;
+            }
+
+            // Step, moving the multipliers towards those of the subproblem
+            for i in 0..n {
+                z[i] = (z[i] + alpha * step[i]).max(z_lower[i]).min(z_upper[i]);
+                z_l[i] += alpha * (qp.lower_multipliers[i] - z_l[i]);
+                z_u[i] += alpha * (qp.upper_multipliers[i] - z_u[i]);
+            }
+            for (lambda_r, qp_lambda_r) in lambda.iter_mut().zip(qp.lambda.iter()) {
+                *lambda_r += alpha * (qp_lambda_r - *lambda_r);
+            }
+            last_step = (alpha * max_norm(&step), alpha, trials);
+            iteration += 1;
+        }
+
+        return Solution::new(nlp, status, z, (lambda, z_l, z_u), log);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nlp::tests::{hs071, polygon_qp, rosenbrock, DenseNlp};
+
+    #[test]
+    fn test_rosenbrock() {
+        let nlp: DenseNlp = rosenbrock();
+        let solution: Solution = Sqp::new(SolverOptions::default()).solve(&nlp, &[-1.2, 1.0]);
+        assert_eq!(solution.status, Status::Solved);
+        assert!((solution.z[0] - 1.0).abs() < 1e-6);
+        assert!((solution.z[1] - 1.0).abs() < 1e-6);
+        assert!(solution.iterations < 30);
+    }
+
+    #[test]
+    fn test_hs071() {
+        let nlp: DenseNlp = hs071();
+        let solution: Solution =
+            Sqp::new(SolverOptions::default()).solve(&nlp, &[1.0, 5.0, 5.0, 1.0]);
+        assert_eq!(solution.status, Status::Solved);
+        assert!((solution.objective - 17.0140173).abs() < 1e-6);
+        assert!(solution.lower_multipliers[0] > 0.1);
+        assert!(solution.iterations < 20);
+    }
+
+    #[test]
+    fn test_nonconvex_regularisation() {
+        // min x^2 - y^2 on x + y = 1 is linear along the constraint, decreasing towards the
+        // bound x = 0, so the Hessian needs regularising on the constraint null space
+        let nlp = DenseNlp {
+            n: 2,
+            m: 1,
+            variable_bounds: (vec![0.0; 2], vec![2.0; 2]),
+            constraint_bounds: (vec![1.0], vec![1.0]),
+            objective: |z| z[0] * z[0] - z[1] * z[1],
+            constraints: |z| vec![z[0] + z[1]],
+        };
+        let solution: Solution = Sqp::new(SolverOptions::default()).solve(&nlp, &[1.0, 0.0]);
+        assert_eq!(solution.status, Status::Solved);
+        assert!(solution.z[0].abs() < 1e-6);
+        assert!((solution.z[1] - 1.0).abs() < 1e-6);
+        assert!(solution.log.iter().any(|line| line.regularisation > 0.0));
+    }
+
+    #[test]
+    fn test_quadratic_program() {
+        // The model is exact, so the first full step solves the problem
+        let nlp: DenseNlp = polygon_qp();
+        let solution: Solution = Sqp::new(SolverOptions::default()).solve(&nlp, &[2.0, 0.0, 3.0]);
+        assert_eq!(solution.status, Status::Solved);
+        assert!((solution.z[0] - 0.2).abs() < 1e-6);
+        assert!((solution.z[1] - 1.1).abs() < 1e-6);
+        assert!((solution.lambda[0] + 1.4).abs() < 1e-6);
+        assert!((solution.log[1].objective - 3.2).abs() < 1e-6);
+        assert!(solution.iterations < 5);
+    }
+}
@@ -0,0 +1,261 @@
+pub mod collocation;
+pub mod lap;
+pub mod transcription;
+
+use crate::model::autodiff::{dual_jacobian, Dual, Scalar};
+use crate::track::Track;
+
+// Building blocks of the optimal control problem in the design document,
+//     min  J = int_t0^tf L(x, u, t) dt + Phi(x(tf))
+//     s.t. g_L(t) <= g(x, u, t) <= g_U(t),
+// where t is the independent variable of the model, the distance s for spatial-domain
+// models. Gradients and Jacobians are row-major over [x, u] as for the model Jacobians, and
+// can be written by hand or computed with dual_jacobian from a function over a Scalar.
+// Infinite bounds mark a constraint side that is absent.
+
+#[allow(dead_code)]
+pub trait Cost {
+    // Lagrange integrand L(x, u, t)
+    fn lagrange(&self, _x: &[f64], _u: &[f64], _t: f64) -> f64 {
+        return 0.0;
+    }
+
+    // Gradient of the Lagrange integrand with respect to [x, u]
+    fn lagrange_grad(&self, x: &[f64], u: &[f64], _t: f64) -> Vec<f64> {
+        return vec![0.0; x.len() + u.len()];
+    }
+
+    // Mayer term Phi(x(tf))
+    fn mayer(&self, _x: &[f64]) -> f64 {
+        return 0.0;
+    }
+
+    // Gradient of the Mayer term with respect to x
+    fn mayer_grad(&self, x: &[f64]) -> Vec<f64> {
+        return vec![0.0; x.len()];
+    }
+}
+
+#[allow(dead_code)]
+pub trait Constraint {
+    // Number of constraint functions n_g
+    fn n_g(&self) -> usize;
+
+    // Constraint functions g(x, u, t)
+    fn values(&self, x: &[f64], u: &[f64], t: f64) -> Vec<f64>;
+
+    // Row-major n_g * (nx + nu) Jacobian of g with respect to [x, u]
+    fn jac(&self, x: &[f64], u: &[f64], t: f64) -> Vec<f64>;
+
+    // Lower and upper bounds (g_L, g_U) at t
+    fn bounds(&self, t: f64) -> (Vec<f64>, Vec<f64>);
+}
+
+// Minimum time as the Mayer term on the time state of a spatial-domain model, such as
+// state 0 of Curvilinear
+#[allow(dead_code)]
+pub struct MinimumTime {
+    // Parameters
+    pub time_state: usize,
+}
+
+// Quadratic control effort, L = sum_i w_i u_i^2
+#[allow(dead_code)]
+pub struct ControlEffort {
+    // Parameters
+    pub weights: Vec<f64>, // One weight per input
+}
+
+// Lateral offset within the track edges less a margin, |n| <= w(s) / 2 - margin, for
+// spatial-domain models with the offset n as a state. Where the margin exceeds half the
+// width the half-width is clamped to zero, pinning n to the centreline.
+#[allow(dead_code)]
+pub struct TrackLimits<'a> {
+    // Parameters
+    pub track: &'a Track,
+    pub offset_state: usize,
+    pub margin: f64, // Distance kept from the edges, e.g. half the vehicle width [m]
+}
+
+// Friction circle on a pair of force inputs, (Fx / F_max)^2 + (Fy / F_max)^2 <= 1
+#[allow(dead_code)]
+pub struct FrictionCircle {
+    // Parameters
+    pub force_inputs: (usize, usize),
+    pub max_force: f64, // mu * m * g [N]
+}
+
+// MINIMUMTIME IMPLEMENTATION ++++++++++++++++++++++++++
+impl MinimumTime {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(time_state: usize) -> Self {
+        return MinimumTime { time_state };
+    }
+}
+
+impl Cost for MinimumTime {
+    fn mayer(&self, x: &[f64]) -> f64 {
+        return x[self.time_state];
+    }
+
+    fn mayer_grad(&self, x: &[f64]) -> Vec<f64> {
+        let mut grad: Vec<f64> = vec![0.0; x.len()];
+        grad[self.time_state] = 1.0;
+        return grad;
+    }
+}
+
+// CONTROLEFFORT IMPLEMENTATION ++++++++++++++++++++++++
+impl ControlEffort {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(weights: Vec<f64>) -> Self {
+        return ControlEffort { weights };
+    }
+}
+
+impl Cost for ControlEffort {
+    fn lagrange(&self, _x: &[f64], u: &[f64], _t: f64) -> f64 {
+        return u
+            .iter()
+            .zip(self.weights.iter())
+            .map(|(u, w)| w * u * u)
+            .sum();
+    }
+
+    fn lagrange_grad(&self, x: &[f64], u: &[f64], _t: f64) -> Vec<f64> {
+        let mut grad: Vec<f64> = vec![0.0; x.len() + u.len()];
+        for (i, (u_i, w)) in u.iter().zip(self.weights.iter()).enumerate() {
+            grad[x.len() + i] = 2.0 * w * u_i; // dL/dui
+        }
+        return grad;
+    }
+}
+
+// TRACKLIMITS IMPLEMENTATION ++++++++++++++++++++++++++
+impl<'a> TrackLimits<'a> {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(track: &'a Track, offset_state: usize, margin: f64) -> Self {
+        return TrackLimits {
+            track,
+            offset_state,
+            margin,
+        };
+    }
+}
+
+impl Constraint for TrackLimits<'_> {
+    fn n_g(&self) -> usize {
+        return 1;
+    }
+
+    fn values(&self, x: &[f64], _u: &[f64], _t: f64) -> Vec<f64> {
+        return vec![x[self.offset_state]];
+    }
+
+    fn jac(&self, x: &[f64], u: &[f64], _t: f64) -> Vec<f64> {
+        let mut jac: Vec<f64> = vec![0.0; x.len() + u.len()];
+        jac[self.offset_state] = 1.0; // dg/dn
+        return jac;
+    }
+
+    fn bounds(&self, s: f64) -> (Vec<f64>, Vec<f64>) {
+        let half_width: f64 = (self.track.width(s) / 2.0 - self.margin).max(0.0);
+        return (vec![-half_width], vec![half_width]);
+    }
+}
+
+// FRICTIONCIRCLE IMPLEMENTATION +++++++++++++++++++++++
+impl FrictionCircle {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(force_inputs: (usize, usize), max_force: f64) -> Self {
+        return FrictionCircle {
+            force_inputs,
+            max_force,
+        };
+    }
+
+    // Utilisation of the friction circle, 1 at the limit
+    #[allow(dead_code)]
+    fn utilisation<S: Scalar>(&self, u: &[S]) -> S {
+        let (i, j) = self.force_inputs;
+        return (u[i] / self.max_force).powi(2) + (u[j] / self.max_force).powi(2);
+    }
+}
+
+impl Constraint for FrictionCircle {
+    fn n_g(&self) -> usize {
+        return 1;
+    }
+
+    fn values(&self, _x: &[f64], u: &[f64], _t: f64) -> Vec<f64> {
+        return vec![self.utilisation(u)];
+    }
+
+    fn jac(&self, x: &[f64], u: &[f64], _t: f64) -> Vec<f64> {
+        return dual_jacobian(|_x, u: &[Dual]| vec![self.utilisation(u)], x, u, 1);
+    }
+
+    fn bounds(&self, _t: f64) -> (Vec<f64>, Vec<f64>) {
+        return (vec![f64::NEG_INFINITY], vec![1.0]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::finite_difference::finite_difference_jac;
+
+    fn assert_close(a: &[f64], b: &[f64]) {
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert!((a - b).abs() < 1e-6 * (1.0 + b.abs()), "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_minimum_time() {
+        let cost: MinimumTime = MinimumTime::new(0);
+        let x: Vec<f64> = vec![12.5, 0.3, -0.1];
+        assert_eq!(cost.mayer(&x), 12.5);
+        assert_eq!(cost.mayer_grad(&x), vec![1.0, 0.0, 0.0]);
+        assert_eq!(cost.lagrange(&x, &[1.0], 0.0), 0.0);
+        assert_eq!(cost.lagrange_grad(&x, &[1.0], 0.0), vec![0.0; 4]);
+    }
+
+    #[test]
+    fn test_control_effort() {
+        let cost: ControlEffort = ControlEffort::new(vec![1.0, 0.5]);
+        let (x, u) = (vec![1.0, 2.0], vec![3.0, -4.0]);
+        assert_eq!(cost.lagrange(&x, &u, 0.0), 9.0 + 8.0);
+        let fd: Vec<f64> = finite_difference_jac(|x, u| vec![cost.lagrange(x, u, 0.0)], &x, &u, 1);
+        assert_close(&cost.lagrange_grad(&x, &u, 0.0), &fd);
+    }
+
+    #[test]
+    fn test_track_limits() {
+        let track: Track = Track::straight(100.0, 6.0);
+        let constraint: TrackLimits = TrackLimits::new(&track, 1, 0.8);
+        let (lower, upper) = constraint.bounds(50.0);
+        assert!((lower[0] + 2.2).abs() < 1e-9);
+        assert!((upper[0] - 2.2).abs() < 1e-9);
+
+        let (x, u) = (vec![0.0, 1.5, 0.1], vec![0.0, 0.0]);
+        assert_eq!(constraint.values(&x, &u, 50.0), vec![1.5]);
+        assert_eq!(constraint.jac(&x, &u, 50.0), vec![0.0, 1.0, 0.0, 0.0, 0.0]);
+
+        // A margin wider than half the track keeps the bounds ordered
+        let constraint: TrackLimits = TrackLimits::new(&track, 1, 4.0);
+        assert_eq!(constraint.bounds(50.0), (vec![0.0], vec![0.0]));
+    }
+
+    #[test]
+    fn test_friction_circle() {
+        let constraint: FrictionCircle = FrictionCircle::new((0, 1), 5000.0);
+        let (x, u) = (vec![0.0; 4], vec![3000.0, -4000.0]);
+        assert!((constraint.values(&x, &u, 0.0)[0] - 1.0).abs() < 1e-12);
+        assert_eq!(constraint.bounds(0.0), (vec![f64::NEG_INFINITY], vec![1.0]));
+
+        let fd: Vec<f64> = finite_difference_jac(|x, u| constraint.values(x, u, 0.0), &x, &u, 1);
+        assert_close(&constraint.jac(&x, &u, 0.0), &fd);
+    }
+}
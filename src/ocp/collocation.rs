@@ -0,0 +1,308 @@
+// Flipped Legendre-Gauss-Radau (fLGR) collocation on [-1, 1] and its mapping to mesh
+// intervals [tau_k, tau_k+1].
+//
+// The N_c fLGR points are the roots of P_{N_c-1}(zeta) - P_{N_c}(zeta), where P_n is the
+// Legendre polynomial of degree n. They lie in (-1, 1] and include the right end, so
+// consecutive intervals share the point where one ends and the next begins. The quadrature
+// over them is exact for polynomials up to degree 2 N_c - 2. The state polynomial
+// interpolates the N_c + 1 nodes zeta_0 = -1 followed by the fLGR points, and the
+// differentiation matrix D_ij = L_j'(zeta_i) gives the polynomial derivative at every node.
+
+const NEWTON_TOLERANCE: f64 = 1e-15;
+const NEWTON_MAX_ITERATIONS: usize = 100;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collocation {
+    pub degree: usize,             // Number of collocation points N_c
+    pub nodes: Vec<f64>,           // -1 followed by the N_c fLGR points, ascending
+    pub weights: Vec<f64>,         // Quadrature weights of the fLGR points
+    pub differentiation: Vec<f64>, // Row-major (N_c + 1) * (N_c + 1), D_ij = L_j'(zeta_i)
+    barycentric: Vec<f64>,         // Barycentric weights 1 / prod_k!=j (zeta_j - zeta_k)
+}
+
+// Collocation mapped to the interval [start, end], with the nodes in the independent
+// variable and the weights and differentiation matrix scaled by the interval length
+#[derive(Clone, Debug, PartialEq)]
+pub struct IntervalCollocation {
+    pub start: f64,
+    pub end: f64,
+    pub nodes: Vec<f64>,
+    pub weights: Vec<f64>,
+    pub differentiation: Vec<f64>,
+}
+
+// Legendre polynomials (P_n, P_{n-1}) and the derivative P_n' at x
+fn legendre(n: usize, x: f64) -> (f64, f64, f64) {
+    let (mut p, mut p_prev) = (1.0, 0.0);
+    let (mut dp, mut dp_prev) = (0.0, 0.0);
+    for k in 1..=n {
+        let k_f: f64 = k as f64;
+        let p_next: f64 = ((2.0 * k_f - 1.0) * x * p - (k_f - 1.0) * p_prev) / k_f;
+        let dp_next: f64 = ((2.0 * k_f - 1.0) * (p + x * dp) - (k_f - 1.0) * dp_prev) / k_f;
+        (p_prev, p) = (p, p_next);
+        (dp_prev, dp) = (dp, dp_next);
+    }
+    return (p, p_prev, dp);
+}
+
+// COLLOCATION IMPLEMENTATION ++++++++++++++++++++++++++
+impl Collocation {
+    // fLGR collocation with n_c >= 1 points
+    #[allow(dead_code)]
+    pub fn flgr(n_c: usize) -> Self {
+        assert!(n_c >= 1, "at least one collocation point is needed");
+        let n_f: f64 = n_c as f64;
+
+        // Newton iterations on P_{N-1} - P_N from the flipped Chebyshev-Gauss-Radau points
+        let mut points: Vec<f64> = vec![1.0];
+        for i in 1..n_c {
+            let mut zeta: f64 = (2.0 * std::f64::consts::PI * i as f64 / (2.0 * n_f - 1.0)).cos();
+            for _ in 0..NEWTON_MAX_ITERATIONS {
+                let (p_n, _, dp_n) = legendre(n_c, zeta);
+                let (p_m, _, dp_m) = legendre(n_c - 1, zeta);
+                let step: f64 = (p_m - p_n) / (dp_m - dp_n);
+                zeta -= step;
+                if step.abs() < NEWTON_TOLERANCE {
+                    break;
+                }
+            }
+            points.push(zeta);
+        }
+        points.sort_by(|a, b| a.total_cmp(b));
+
+        // w = 2 / N^2 at zeta = 1 and (1 + zeta) / (N P_{N-1}(zeta))^2 elsewhere
+        let weights: Vec<f64> = points
+            .iter()
+            .map(|zeta| match *zeta == 1.0 {
+                true => 2.0 / n_f.powi(2),
+                false => (1.0 + zeta) / (n_f * legendre(n_c - 1, *zeta).0).powi(2),
+            })
+            .collect();
+
+        let mut nodes: Vec<f64> = vec![-1.0];
+        nodes.extend(points);
+
+        let n: usize = nodes.len();
+        let barycentric: Vec<f64> = (0..n)
+            .map(|j| {
+                let product: f64 = (0..n)
+                    .filter(|k| *k != j)
+                    .map(|k| nodes[j] - nodes[k])
+                    .product();
+                1.0 / product
+            })
+            .collect();
+
+        // D_ij = (b_j / b_i) / (zeta_i - zeta_j) and the rows sum to zero
+        let mut differentiation: Vec<f64> = vec![0.0; n * n];
+        for i in 0..n {
+            let mut diagonal: f64 = 0.0;
+            for j in (0..n).filter(|j| *j != i) {
+                let d_ij: f64 = barycentric[j] / barycentric[i] / (nodes[i] - nodes[j]);
+                differentiation[i * n + j] = d_ij;
+                diagonal -= d_ij;
+            }
+            differentiation[i * n + i] = diagonal;
+        }
+
+        return Collocation {
+            degree: n_c,
+            nodes,
+            weights,
+            differentiation,
+            barycentric,
+        };
+    }
+
+    // Lagrange basis polynomials L_i(zeta) of the nodes
+    pub fn lagrange_basis(&self, zeta: f64) -> Vec<f64> {
+        if let Some(i) = self.nodes.iter().position(|node| *node == zeta) {
+            let mut basis: Vec<f64> = vec![0.0; self.nodes.len()];
+            basis[i] = 1.0;
+            return basis;
+        }
+        // Barycentric form L_i = l(zeta) b_i / (zeta - zeta_i), l(zeta) = prod (zeta - zeta_k)
+        let l: f64 = self.nodes.iter().map(|node| zeta - node).product();
+        return self
+            .nodes
+            .iter()
+            .zip(self.barycentric.iter())
+            .map(|(node, b)| l * b / (zeta - node))
+            .collect();
+    }
+
+    // Value at zeta of the polynomial through the node values
+    #[allow(dead_code)]
+    pub fn interpolate(&self, values: &[f64], zeta: f64) -> f64 {
+        return self
+            .lagrange_basis(zeta)
+            .iter()
+            .zip(values.iter())
+            .map(|(l, v)| l * v)
+            .sum();
+    }
+
+    // Collocation on the interval [start, end] through zeta = 2 (t - start) / (end - start) - 1
+    #[allow(dead_code)]
+    pub fn interval(&self, start: f64, end: f64) -> IntervalCollocation {
+        let half: f64 = (end - start) / 2.0;
+        return IntervalCollocation {
+            start,
+            end,
+            nodes: self
+                .nodes
+                .iter()
+                .map(|z| start + (z + 1.0) * half)
+                .collect(),
+            weights: self.weights.iter().map(|w| w * half).collect(),
+            differentiation: self.differentiation.iter().map(|d| d / half).collect(),
+        };
+    }
+}
+
+// INTERVALCOLLOCATION IMPLEMENTATION ++++++++++++++++++
+impl IntervalCollocation {
+    // Quadrature of the values at the fLGR points, excluding the start node
+    #[allow(dead_code)]
+    pub fn integrate(&self, values: &[f64]) -> f64 {
+        return self
+            .weights
+            .iter()
+            .zip(values.iter())
+            .map(|(w, v)| w * v)
+            .sum();
+    }
+
+    // Derivatives at every node of the polynomial through the node values
+    #[allow(dead_code)]
+    pub fn differentiate(&self, values: &[f64]) -> Vec<f64> {
+        let n: usize = self.nodes.len();
+        return (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| self.differentiation[i * n + j] * values[j])
+                    .sum()
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_points() {
+        // N_c = 2 gives the flipped LGR points -1/3 and 1 with weights 3/2 and 1/2
+        let collocation: Collocation = Collocation::flgr(2);
+        let expected: [f64; 3] = [-1.0, -1.0 / 3.0, 1.0];
+        for (node, e) in collocation.nodes.iter().zip(expected.iter()) {
+            assert!((node - e).abs() < 1e-14);
+        }
+        assert!((collocation.weights[0] - 1.5).abs() < 1e-14);
+        assert!((collocation.weights[1] - 0.5).abs() < 1e-14);
+
+        // N_c = 3 gives (-1 -+ sqrt(6)) / 5 and 1
+        let collocation: Collocation = Collocation::flgr(3);
+        assert!((collocation.nodes[1] - (-1.0 - 6f64.sqrt()) / 5.0).abs() < 1e-14);
+        assert!((collocation.nodes[2] - (-1.0 + 6f64.sqrt()) / 5.0).abs() < 1e-14);
+        assert_eq!(collocation.nodes[3], 1.0);
+    }
+
+    #[test]
+    fn test_quadrature_exactness() {
+        for n_c in 1..=8 {
+            let collocation: Collocation = Collocation::flgr(n_c);
+            let points: &[f64] = &collocation.nodes[1..];
+            assert!((collocation.weights.iter().sum::<f64>() - 2.0).abs() < 1e-13);
+
+            // Exact up to degree 2 N_c - 2
+            for degree in 0..=(2 * n_c - 2) {
+                let quadrature: f64 = points
+                    .iter()
+                    .zip(collocation.weights.iter())
+                    .map(|(z, w)| w * z.powi(degree as i32))
+                    .sum();
+                let exact: f64 = match degree % 2 {
+                    0 => 2.0 / (degree as f64 + 1.0),
+                    _ => 0.0,
+                };
+                assert!(
+                    (quadrature - exact).abs() < 1e-12,
+                    "N_c {} degree {}",
+                    n_c,
+                    degree
+                );
+            }
+
+            // but not for degree 2 N_c - 1
+            let degree: i32 = 2 * n_c as i32 - 1;
+            let quadrature: f64 = points
+                .iter()
+                .zip(collocation.weights.iter())
+                .map(|(z, w)| w * z.powi(degree))
+                .sum();
+            assert!(quadrature.abs() > 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_lagrange_basis() {
+        let collocation: Collocation = Collocation::flgr(4);
+        for (i, node) in collocation.nodes.iter().enumerate() {
+            let basis: Vec<f64> = collocation.lagrange_basis(*node);
+            for (j, l) in basis.iter().enumerate() {
+                assert_eq!(*l, if i == j { 1.0 } else { 0.0 });
+            }
+        }
+        // The basis is a partition of unity and reproduces polynomials of degree N_c
+        let basis: Vec<f64> = collocation.lagrange_basis(0.3);
+        assert!((basis.iter().sum::<f64>() - 1.0).abs() < 1e-13);
+        let values: Vec<f64> = collocation.nodes.iter().map(|z| z.powi(4) - z).collect();
+        assert!((collocation.interpolate(&values, 0.3) - (0.3f64.powi(4) - 0.3)).abs() < 1e-13);
+    }
+
+    #[test]
+    fn test_polynomial_differentiation() {
+        for n_c in 1..=8 {
+            let collocation: Collocation = Collocation::flgr(n_c);
+            let n: usize = n_c + 1;
+            // Exact for polynomials up to degree N_c
+            for degree in 0..=n_c as i32 {
+                let values: Vec<f64> = collocation.nodes.iter().map(|z| z.powi(degree)).collect();
+                for i in 0..n {
+                    let derivative: f64 = (0..n)
+                        .map(|j| collocation.differentiation[i * n + j] * values[j])
+                        .sum();
+                    let exact: f64 = match degree {
+                        0 => 0.0,
+                        _ => degree as f64 * collocation.nodes[i].powi(degree - 1),
+                    };
+                    assert!((derivative - exact).abs() < 1e-11);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_interval_mapping() {
+        let collocation: Collocation = Collocation::flgr(3);
+        let interval: IntervalCollocation = collocation.interval(2.0, 5.0);
+        assert_eq!(interval.nodes[0], 2.0);
+        assert_eq!(interval.nodes[3], 5.0);
+
+        // Integral of t^2 over [2, 5] is 39, using the fLGR points only
+        let values: Vec<f64> = interval.nodes[1..].iter().map(|t| t * t).collect();
+        assert!((interval.integrate(&values) - 39.0).abs() < 1e-12);
+
+        // Derivative of t^3 - 2 t is 3 t^2 - 2
+        let values: Vec<f64> = interval.nodes.iter().map(|t| t.powi(3) - 2.0 * t).collect();
+        for (derivative, t) in interval
+            .differentiate(&values)
+            .iter()
+            .zip(interval.nodes.iter())
+        {
+            assert!((derivative - (3.0 * t * t - 2.0)).abs() < 1e-11);
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use crate::model::curvilinear::Curvilinear;
+use crate::model::metadata::ModelMetadata;
+use crate::nlp::{Backend, NlpSolver, Solution, SolverOptions};
+use crate::ocp::transcription::{Trajectory, Transcription};
+use crate::ocp::{Constraint, MinimumTime, TrackLimits};
+use crate::track::Track;
+
+// Minimum-time lap in the spatial domain, posed with the Curvilinear form of a vehicle model
+// and solved by direct collocation.
+//
+// The horizon is the track length on a uniform mesh, with the lap time as the Mayer cost and
+// the track edges less a margin as path constraints on the offset n. The time starts at zero.
+// On closed tracks every state except time is periodic, so the lap starts and ends in the
+// same condition as in a flying lap. Open tracks need a fixed initial state, and a closed
+// track may be given one too. Vehicle limits such as grip or power are supplied as further
+// path constraints over the curvilinear states and the inputs.
+//
+// Bounds and scales come from the metadata of the wrapped model, with the time scaled by the
+// lap time of the guess, the offset by the track width and the relative heading bounded to
+// a right angle either way, which keeps the vehicle moving along the track. The initial
+// guess holds a state and input of the wrapped model along the centreline.
+
+#[allow(dead_code)]
+pub struct LapOptions<'a> {
+    pub backend: Backend,
+    pub solver: SolverOptions,
+    pub intervals: usize,                // Mesh intervals over the track length
+    pub collocation_points: usize,       // fLGR points per interval
+    pub margin: f64,                     // Distance kept from the track edges [m]
+    pub guess: (Vec<f64>, Vec<f64>),     // Wrapped model state and input, pose ignored
+    pub initial_state: Option<Vec<f64>>, // Curvilinear state at s = 0, time ignored
+    pub constraints: Vec<Box<dyn Constraint + 'a>>, // Vehicle limits over [x, u]
+}
+
+#[allow(dead_code)]
+pub struct Lap {
+    pub lap_time: f64,                // [s]
+    pub distance: Vec<f64>,           // Distance s along the centreline at the nodes [m]
+    pub racing_line: Vec<(f64, f64)>, // Global position (x, y) of the vehicle [m]
+    pub heading: Vec<f64>,            // Global heading psi [rad]
+    pub speed: Vec<f64>,              // [m/s]
+    pub trajectory: Trajectory,       // Curvilinear states [t, n, xi, rest...] and inputs
+    pub solution: Solution,
+}
+
+// LAPOPTIONS IMPLEMENTATION ++++++++++++++++++++++++++
+impl<'a> LapOptions<'a> {
+    // Defaults of the solver and mesh around the initial guess, without vehicle limits
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(guess: (Vec<f64>, Vec<f64>)) -> Self {
+        return LapOptions {
+            backend: Backend::InteriorPoint,
+            solver: SolverOptions::default(),
+            intervals: 50,
+            collocation_points: 3,
+            margin: 0.0,
+            guess,
+            initial_state: None,
+            constraints: Vec::new(),
+        };
+    }
+}
+
+// Minimum-time lap of the model around the track. Errors are problems that cannot be posed,
+// while the convergence of the solver is reported by the status of the solution.
+#[allow(dead_code)]
+pub fn optimise_lap<M: ModelMetadata>(
+    track: &Track,
+    model: M,
+    options: LapOptions,
+) -> Result<Lap, String> {
+    let (nx, nu) = (model.n_x(), model.n_u());
+    if nx < 3 {
+        return Err(format!(
+            "model {} needs the pose [x, y, psi] as its first states",
+            model.name()
+        ));
+    }
+    if options.guess.0.len() != nx || options.guess.1.len() != nu {
+        return Err(String::from(
+            "the guess does not match the model dimensions",
+        ));
+    }
+    if options.intervals == 0 || options.collocation_points == 0 {
+        return Err(String::from(
+            "the mesh needs intervals and collocation points",
+        ));
+    }
+    if !track.is_closed() && options.initial_state.is_none() {
+        return Err(String::from("an open track needs a fixed initial state"));
+    }
+    if let Some(x0) = &options.initial_state {
+        if x0.len() != nx {
+            return Err(String::from("the initial state does not match the model"));
+        }
+    }
+
+    let state_bounds: Vec<(f64, f64)> = model.state_bounds();
+    let state_scales: Vec<f64> = model.state_scales();
+    let input_bounds: Vec<(f64, f64)> = model.input_bounds();
+    let input_scales: Vec<f64> = model.input_scales();
+    let spatial = Curvilinear::new(&format!("{} (spatial)", model.name()), model, track);
+
+    // Guess along the centreline at the speed of the guess, heading along the track
+    let (mut x_guess, u_guess) = options.guess;
+    x_guess[0] = 0.0;
+    x_guess[1] = 0.0;
+    x_guess[2] = 0.0;
+    let speed: f64 = spatial.model.fun(&x_guess, &u_guess, 0.0)[0];
+    if speed <= 0.0 {
+        return Err(String::from("the guess does not move along the track"));
+    }
+
+    let length: f64 = track.length();
+    let mesh: Vec<f64> = (0..=options.intervals)
+        .map(|i| length * i as f64 / options.intervals as f64)
+        .collect();
+    let mut ocp = Transcription::new(&spatial, &mesh, options.collocation_points);
+    ocp.costs.push(Box::new(MinimumTime::new(0)));
+    ocp.constraints
+        .push(Box::new(TrackLimits::new(track, 1, options.margin)));
+    ocp.constraints.extend(options.constraints);
+
+    let max_width: f64 = mesh.iter().map(|s| track.width(*s)).fold(0.0, f64::max);
+    let max_heading: f64 = std::f64::consts::FRAC_PI_2;
+    ocp.state_bounds = state_bounds;
+    ocp.state_bounds[0] = (0.0, f64::INFINITY);
+    ocp.state_bounds[1] = (f64::NEG_INFINITY, f64::INFINITY);
+    ocp.state_bounds[2] = (-max_heading, max_heading);
+    ocp.state_scales = state_scales;
+    ocp.state_scales[0] = length / speed;
+    ocp.state_scales[1] = f64::max(max_width / 2.0, 1.0);
+    ocp.state_scales[2] = 1.0;
+    ocp.input_bounds = input_bounds;
+    ocp.input_scales = input_scales;
+
+    ocp.boundary.initial[0] = Some(0.0);
+    if let Some(x0) = &options.initial_state {
+        for (fixed, x0_i) in ocp.boundary.initial.iter_mut().zip(x0.iter()).skip(1) {
+            *fixed = Some(*x0_i);
+        }
+    }
+    if track.is_closed() {
+        ocp.boundary.periodic = (1..nx).collect();
+    }
+
+    let z0: Vec<f64> = ocp.pack(|s| {
+        let mut x: Vec<f64> = x_guess.clone();
+        x[0] = s / speed;
+        return (x, u_guess.clone());
+    });
+    let solver: Box<dyn NlpSolver> = options.backend.solver(options.solver);
+    let solution: Solution = solver.solve(&ocp, &z0);
+    let trajectory: Trajectory = ocp.unpack(&solution.z);
+
+    // Racing line and speed from the wrapped model in the global and local frames
+    let mut racing_line: Vec<(f64, f64)> = Vec::with_capacity(ocp.n_nodes());
+    let mut heading: Vec<f64> = Vec::with_capacity(ocp.n_nodes());
+    let mut speed: Vec<f64> = Vec::with_capacity(ocp.n_nodes());
+    for ((s, x), u) in trajectory
+        .times
+        .iter()
+        .zip(trajectory.states.iter())
+        .zip(trajectory.inputs.iter())
+    {
+        let (px, py, psi) = spatial.global_pose(*s, x);
+        racing_line.push((px, py));
+        heading.push(psi);
+        let mut x_local: Vec<f64> = x.clone();
+        x_local[0] = 0.0;
+        x_local[1] = 0.0;
+        let dz: Vec<f64> = spatial.model.fun(&x_local, u, 0.0);
+        speed.push(f64::hypot(dz[0], dz[1]));
+    }
+
+    return Ok(Lap {
+        lap_time: trajectory.states[trajectory.states.len() - 1][0],
+        distance: trajectory.times.clone(),
+        racing_line,
+        heading,
+        speed,
+        trajectory,
+        solution,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::autodiff::{dual_jacobian, Dual, Scalar};
+    use crate::model::kinematic_bicycle::KinematicBicycle;
+    use crate::nlp::Status;
+
+    const WHEELBASE: f64 = 2.5;
+    const REAR_TO_CG: f64 = 1.2;
+
+    // Acceleration of the kinematic bicycle within a circle, (a / a_max)^2 + (a_y / a_max)^2
+    // <= 1 with the lateral acceleration a_y = v^2 sin(beta) / l_r, over its curvilinear
+    // states [t, n, xi, v] and inputs [delta, a]
+    struct Grip {
+        max_acceleration: f64, // [m/s^2]
+    }
+
+    impl Grip {
+        fn utilisation<S: Scalar>(&self, x: &[S], u: &[S]) -> S {
+            let beta: S = (u[0].tan() * (REAR_TO_CG / WHEELBASE)).atan();
+            let lateral: S = x[3].powi(2) * beta.sin() / REAR_TO_CG;
+            return (u[1] / self.max_acceleration).powi(2)
+                + (lateral / self.max_acceleration).powi(2);
+        }
+    }
+
+    impl Constraint for Grip {
+        fn n_g(&self) -> usize {
+            return 1;
+        }
+
+        fn values(&self, x: &[f64], u: &[f64], _t: f64) -> Vec<f64> {
+            return vec![self.utilisation(x, u)];
+        }
+
+        fn jac(&self, x: &[f64], u: &[f64], _t: f64) -> Vec<f64> {
+            return dual_jacobian(|x, u: &[Dual]| vec![self.utilisation(x, u)], x, u, 1);
+        }
+
+        fn bounds(&self, _t: f64) -> (Vec<f64>, Vec<f64>) {
+            return (vec![f64::NEG_INFINITY], vec![1.0]);
+        }
+    }
+
+    fn bicycle() -> KinematicBicycle {
+        return KinematicBicycle::new("Kinematic", WHEELBASE, REAR_TO_CG);
+    }
+
+    fn options<'a>(speed: f64, max_acceleration: f64) -> LapOptions<'a> {
+        let mut options = LapOptions::new((vec![0.0, 0.0, 0.0, speed], vec![0.0, 0.0]));
+        options.intervals = 20;
+        options
+            .constraints
+            .push(Box::new(Grip { max_acceleration }));
+        return options;
+    }
+
+    #[test]
+    fn test_open_straight() {
+        // Full acceleration from 10 m/s over 200 m, 10 t + 5 t^2 = 200
+        let track: Track = Track::straight(200.0, 6.0);
+        let mut options: LapOptions = options(15.0, 10.0);
+        options.initial_state = Some(vec![0.0, 0.0, 0.0, 10.0]);
+        let lap: Lap = optimise_lap(&track, bicycle(), options).unwrap();
+
+        assert_eq!(lap.solution.status, Status::Solved);
+        assert!((lap.lap_time - (41.0_f64.sqrt() - 1.0)).abs() < 1e-3);
+        assert!((lap.speed[0] - 10.0).abs() < 1e-6);
+        assert!((lap.speed[lap.speed.len() - 1] - (10.0 + 10.0 * lap.lap_time)).abs() < 1e-2);
+        let (x_end, _) = lap.racing_line[lap.racing_line.len() - 1];
+        assert!((x_end - 200.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_closed_circle() {
+        // Steady cornering on the inside edge, radius 48 m, at the grip limit v^2 / r = a_max
+        let track: Track = Track::circle(50.0, 6.0);
+        let mut options: LapOptions = options(15.0, 10.0);
+        options.margin = 1.0;
+        let lap: Lap = optimise_lap(&track, bicycle(), options).unwrap();
+
+        assert_eq!(lap.solution.status, Status::Solved);
+        let radius: f64 = 48.0;
+        let expected: f64 = 2.0 * std::f64::consts::PI * f64::sqrt(radius / 10.0);
+        assert!((lap.lap_time - expected).abs() / expected < 1e-2);
+
+        // Periodic and within the track limits
+        let (first, last) = (
+            &lap.trajectory.states[0],
+            lap.trajectory.states.last().unwrap(),
+        );
+        for (x0, xn) in first.iter().zip(last.iter()).skip(1) {
+            assert!((x0 - xn).abs() < 1e-6);
+        }
+        for (s, x) in lap.distance.iter().zip(lap.trajectory.states.iter()) {
+            assert!(x[1].abs() <= track.width(*s) / 2.0 - 1.0 + 1e-6);
+        }
+        for (x, y) in lap.racing_line.iter() {
+            assert!((f64::hypot(*x, *y) - radius).abs() < 0.5);
+        }
+        assert!(lap
+            .speed
+            .iter()
+            .all(|v| (v - f64::sqrt(radius * 10.0)).abs() < 0.5));
+    }
+
+    #[test]
+    fn test_setup_errors() {
+        let track: Track = Track::straight(200.0, 6.0);
+        let result = optimise_lap(&track, bicycle(), options(15.0, 10.0));
+        assert!(result.is_err()); // Open track without initial state
+        let result = optimise_lap(&Track::circle(50.0, 6.0), bicycle(), options(-1.0, 10.0));
+        assert!(result.is_err()); // Guess not moving along the track
+
+        // The pose of the guess is ignored
+        let mut turned: LapOptions = options(15.0, 10.0);
+        turned.guess.0[2] = std::f64::consts::FRAC_PI_2;
+        assert!(optimise_lap(&Track::circle(50.0, 6.0), bicycle(), turned).is_ok());
+    }
+}
@@ -0,0 +1,622 @@
+use crate::nlp::Nlp;
+use crate::ocp::collocation::{Collocation, IntervalCollocation};
+use crate::ocp::{Constraint, Cost};
+use simulation_toolbox::Model;
+
+// Direct collocation transcription of the optimal control problem to an NLP.
+//
+// The horizon is split by the mesh tau_0 < ... < tau_Nel into intervals, each with N_c fLGR
+// collocation points. Consecutive intervals share their boundary, giving the nodes
+// t_0 = tau_0 followed by the N_el N_c collocation points. The states are variables at every
+// node and the inputs at the collocation points, ordered per node,
+//     z = [x_0, x_1, u_1, x_2, u_2, ...],
+// so the constraint Jacobian and the KKT matrix are banded. At every collocation point the
+// constraints are the nx defects sum_l D_il x_l - f(x_i, u_i, t_i) = 0 followed by the path
+// constraints, and the periodic boundary conditions x_N - x_0 = 0 come last. Fixed initial
+// and terminal states are variable bounds.
+//
+// The Lagrange cost is integrated with the fLGR quadrature and the Mayer cost evaluated at
+// the last node. Variables are stored divided by the state and input scales, and the
+// defects divided by the state scales. Model has no second derivatives, so the Hessian of
+// the Lagrangian is one dense block per collocation point from central differences of the
+// model, cost and constraint Jacobians.
+
+const HESSIAN_STEP: f64 = 1e-6;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BoundaryConditions {
+    pub initial: Vec<Option<f64>>,  // Fixed initial states, None where free
+    pub terminal: Vec<Option<f64>>, // Fixed terminal states, None where free
+    pub periodic: Vec<usize>,       // States with x(tf) = x(t0)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trajectory {
+    pub times: Vec<f64>,
+    pub states: Vec<Vec<f64>>,
+    pub inputs: Vec<Vec<f64>>, // The first node repeats the input of the second
+}
+
+#[allow(dead_code)]
+pub struct Transcription<'a, M: Model> {
+    // Parameters
+    pub model: &'a M,
+    pub costs: Vec<Box<dyn Cost + 'a>>,
+    pub constraints: Vec<Box<dyn Constraint + 'a>>,
+    pub boundary: BoundaryConditions,
+    pub state_bounds: Vec<(f64, f64)>,
+    pub input_bounds: Vec<(f64, f64)>,
+    pub state_scales: Vec<f64>,
+    pub input_scales: Vec<f64>,
+    // Private
+    n_c: usize,
+    intervals: Vec<IntervalCollocation>,
+    times: Vec<f64>,
+}
+
+impl<'a, M: Model> Transcription<'a, M> {
+    // Transcription on the mesh boundaries with n_c collocation points per interval, without
+    // costs, constraints or bounds and with unit scales
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(model: &'a M, mesh: &[f64], n_c: usize) -> Self {
+        assert!(mesh.len() >= 2, "the mesh needs at least one interval");
+        let collocation: Collocation = Collocation::flgr(n_c);
+        let intervals: Vec<IntervalCollocation> = mesh
+            .windows(2)
+            .map(|w| collocation.interval(w[0], w[1]))
+            .collect();
+        let mut times: Vec<f64> = vec![mesh[0]];
+        for interval in intervals.iter() {
+            times.extend_from_slice(&interval.nodes[1..]);
+        }
+
+        let (nx, nu) = (model.n_x(), model.n_u());
+        return Transcription {
+            model,
+            costs: Vec::new(),
+            constraints: Vec::new(),
+            boundary: BoundaryConditions {
+                initial: vec![None; nx],
+                terminal: vec![None; nx],
+                periodic: Vec::new(),
+            },
+            state_bounds: vec![(f64::NEG_INFINITY, f64::INFINITY); nx],
+            input_bounds: vec![(f64::NEG_INFINITY, f64::INFINITY); nu],
+            state_scales: vec![1.0; nx],
+            input_scales: vec![1.0; nu],
+            n_c,
+            intervals,
+            times,
+        };
+    }
+
+    // Number of nodes, the start of the horizon and every collocation point
+    pub fn n_nodes(&self) -> usize {
+        return self.times.len();
+    }
+
+    // Independent variable at the nodes
+    #[allow(dead_code)]
+    pub fn times(&self) -> &[f64] {
+        return &self.times;
+    }
+
+    // Physical state at node j
+    pub fn state(&self, z: &[f64], j: usize) -> Vec<f64> {
+        let offset: usize = self.offset(j);
+        return z[offset..offset + self.model.n_x()]
+            .iter()
+            .zip(self.state_scales.iter())
+            .map(|(v, s)| v * s)
+            .collect();
+    }
+
+    // Physical input at collocation node j >= 1
+    pub fn input(&self, z: &[f64], j: usize) -> Vec<f64> {
+        let offset: usize = self.offset(j) + self.model.n_x();
+        return z[offset..offset + self.model.n_u()]
+            .iter()
+            .zip(self.input_scales.iter())
+            .map(|(v, s)| v * s)
+            .collect();
+    }
+
+    // Decision vector from a guess of the state and input at t
+    #[allow(dead_code)]
+    pub fn pack<F: Fn(f64) -> (Vec<f64>, Vec<f64>)>(&self, guess: F) -> Vec<f64> {
+        let mut z: Vec<f64> = Vec::with_capacity(self.n_variables());
+        for (j, t) in self.times.iter().enumerate() {
+            let (x, u) = guess(*t);
+            z.extend(x.iter().zip(self.state_scales.iter()).map(|(v, s)| v / s));
+            if j > 0 {
+                z.extend(u.iter().zip(self.input_scales.iter()).map(|(v, s)| v / s));
+            }
+        }
+        return z;
+    }
+
+    // Physical states and inputs at the nodes of a decision vector
+    #[allow(dead_code)]
+    pub fn unpack(&self, z: &[f64]) -> Trajectory {
+        let n: usize = self.n_nodes();
+        let states: Vec<Vec<f64>> = (0..n).map(|j| self.state(z, j)).collect();
+        let mut inputs: Vec<Vec<f64>> = vec![self.input(z, 1)];
+        inputs.extend((1..n).map(|j| self.input(z, j)));
+        return Trajectory {
+            times: self.times.clone(),
+            states,
+            inputs,
+        };
+    }
+
+    // Index of the first variable of node j
+    fn offset(&self, j: usize) -> usize {
+        let nx: usize = self.model.n_x();
+        return match j {
+            0 => 0,
+            _ => nx + (j - 1) * (nx + self.model.n_u()),
+        };
+    }
+
+    // Index of the first constraint of collocation node j >= 1
+    fn row(&self, j: usize) -> usize {
+        return (j - 1) * (self.model.n_x() + self.n_path());
+    }
+
+    fn n_path(&self) -> usize {
+        return self.constraints.iter().map(|c| c.n_g()).sum();
+    }
+
+    // Scale of column col of [x, u]
+    fn scale(&self, col: usize) -> f64 {
+        let nx: usize = self.model.n_x();
+        return match col < nx {
+            true => self.state_scales[col],
+            false => self.input_scales[col - nx],
+        };
+    }
+
+    // Quadrature weight of collocation node j >= 1
+    fn weight(&self, j: usize) -> f64 {
+        return self.intervals[(j - 1) / self.n_c].weights[(j - 1) % self.n_c];
+    }
+
+    // Differentiation matrix entry D_il of the interval of collocation node j >= 1, with the
+    // row i of node j and the column l of the interval node l
+    fn differentiation(&self, j: usize, l: usize) -> f64 {
+        let (k, i) = ((j - 1) / self.n_c, (j - 1) % self.n_c + 1);
+        return self.intervals[k].differentiation[i * (self.n_c + 1) + l];
+    }
+
+    // Global index of the interval node l of the interval of collocation node j >= 1
+    fn interval_node(&self, j: usize, l: usize) -> usize {
+        return (j - 1) / self.n_c * self.n_c + l;
+    }
+
+    // Constraint Jacobian entries, with zero values when z is None
+    fn jacobian_entries(&self, z: Option<&[f64]>) -> Vec<(usize, usize, f64)> {
+        let (nx, nu) = (self.model.n_x(), self.model.n_u());
+        let n_cols: usize = nx + nu;
+        let mut entries: Vec<(usize, usize, f64)> = Vec::new();
+
+        for j in 1..self.n_nodes() {
+            let (row, offset, t) = (self.row(j), self.offset(j), self.times[j]);
+            let (jac_f, jac_g): (Vec<f64>, Vec<Vec<f64>>) = match z {
+                Some(z) => {
+                    let (x, u) = (self.state(z, j), self.input(z, j));
+                    (
+                        self.model.jac(&x, &u, t),
+                        self.constraints.iter().map(|c| c.jac(&x, &u, t)).collect(),
+                    )
+                }
+                None => (
+                    vec![0.0; nx * n_cols],
+                    self.constraints
+                        .iter()
+                        .map(|c| vec![0.0; c.n_g() * n_cols])
+                        .collect(),
+                ),
+            };
+
+            // Defects, D_il on the other interval nodes and D_ii - df/dz on node j
+            for r in 0..nx {
+                for l in (0..=self.n_c).filter(|l| self.interval_node(j, *l) != j) {
+                    let col: usize = self.offset(self.interval_node(j, l)) + r;
+                    entries.push((row + r, col, self.differentiation(j, l)));
+                }
+                for col in 0..n_cols {
+                    let mut value: f64 =
+                        -jac_f[r * n_cols + col] * self.scale(col) / self.state_scales[r];
+                    if col == r {
+                        value += self.differentiation(j, (j - 1) % self.n_c + 1);
+                    }
+                    entries.push((row + r, offset + col, value));
+                }
+            }
+
+            // Path constraints
+            let mut g_row: usize = row + nx;
+            for (constraint, jac) in self.constraints.iter().zip(jac_g.iter()) {
+                for q in 0..constraint.n_g() {
+                    for col in 0..n_cols {
+                        entries.push((
+                            g_row + q,
+                            offset + col,
+                            jac[q * n_cols + col] * self.scale(col),
+                        ));
+                    }
+                }
+                g_row += constraint.n_g();
+            }
+        }
+
+        // Periodic boundary conditions
+        let (row, last) = (self.row(self.n_nodes()), self.offset(self.n_nodes() - 1));
+        for (i, r) in self.boundary.periodic.iter().enumerate() {
+            entries.push((row + i, last + r, 1.0));
+            entries.push((row + i, *r, -1.0));
+        }
+        return entries;
+    }
+
+    // Gradient of the nonlinear part of the Lagrangian with respect to the scaled variables
+    // of collocation node j >= 1, at the scaled node variables v
+    fn node_gradient(&self, j: usize, v: &[f64], sigma: f64, lambda: &[f64]) -> Vec<f64> {
+        let (nx, nu) = (self.model.n_x(), self.model.n_u());
+        let n_cols: usize = nx + nu;
+        let x: Vec<f64> = (0..nx).map(|i| v[i] * self.state_scales[i]).collect();
+        let u: Vec<f64> = (0..nu).map(|i| v[nx + i] * self.input_scales[i]).collect();
+        let t: f64 = self.times[j];
+
+        let mut grad: Vec<f64> = vec![0.0; n_cols];
+        for cost in self.costs.iter() {
+            let lagrange: Vec<f64> = cost.lagrange_grad(&x, &u, t);
+            for col in 0..n_cols {
+                grad[col] += sigma * self.weight(j) * lagrange[col];
+            }
+            if j == self.n_nodes() - 1 {
+                for (i, dphi) in cost.mayer_grad(&x).iter().enumerate() {
+                    grad[i] += sigma * dphi;
+                }
+            }
+        }
+
+        let row: usize = self.row(j);
+        let jac_f: Vec<f64> = self.model.jac(&x, &u, t);
+        for r in 0..nx {
+            for col in 0..n_cols {
+                grad[col] -= lambda[row + r] * jac_f[r * n_cols + col] / self.state_scales[r];
+            }
+        }
+        let mut g_row: usize = row + nx;
+        for constraint in self.constraints.iter() {
+            let jac: Vec<f64> = constraint.jac(&x, &u, t);
+            for q in 0..constraint.n_g() {
+                for col in 0..n_cols {
+                    grad[col] += lambda[g_row + q] * jac[q * n_cols + col];
+                }
+            }
+            g_row += constraint.n_g();
+        }
+
+        return (0..n_cols).map(|col| grad[col] * self.scale(col)).collect();
+    }
+}
+
+impl<M: Model> Nlp for Transcription<'_, M> {
+    fn n_variables(&self) -> usize {
+        return self.offset(self.n_nodes() - 1) + self.model.n_x() + self.model.n_u();
+    }
+
+    fn n_constraints(&self) -> usize {
+        return self.row(self.n_nodes()) + self.boundary.periodic.len();
+    }
+
+    fn variable_bounds(&self) -> (Vec<f64>, Vec<f64>) {
+        let n: usize = self.n_nodes();
+        let (mut lower, mut upper) = (Vec::new(), Vec::new());
+        for j in 0..n {
+            for (i, (l, u)) in self.state_bounds.iter().enumerate() {
+                let fixed: Option<f64> = match j {
+                    0 => self.boundary.initial[i],
+                    _ if j == n - 1 => self.boundary.terminal[i],
+                    _ => None,
+                };
+                let (l, u) = match fixed {
+                    Some(value) => (value, value),
+                    None => (*l, *u),
+                };
+                lower.push(l / self.state_scales[i]);
+                upper.push(u / self.state_scales[i]);
+            }
+            if j > 0 {
+                for (i, (l, u)) in self.input_bounds.iter().enumerate() {
+                    lower.push(l / self.input_scales[i]);
+                    upper.push(u / self.input_scales[i]);
+                }
+            }
+        }
+        return (lower, upper);
+    }
+
+    fn constraint_bounds(&self) -> (Vec<f64>, Vec<f64>) {
+        let (mut lower, mut upper) = (Vec::new(), Vec::new());
+        for j in 1..self.n_nodes() {
+            lower.extend(vec![0.0; self.model.n_x()]);
+            upper.extend(vec![0.0; self.model.n_x()]);
+            for constraint in self.constraints.iter() {
+                let (l, u) = constraint.bounds(self.times[j]);
+                lower.extend(l);
+                upper.extend(u);
+            }
+        }
+        lower.extend(vec![0.0; self.boundary.periodic.len()]);
+        upper.extend(vec![0.0; self.boundary.periodic.len()]);
+        return (lower, upper);
+    }
+
+    fn objective(&self, z: &[f64]) -> f64 {
+        let n: usize = self.n_nodes();
+        let mut objective: f64 = 0.0;
+        for j in 1..n {
+            let (x, u) = (self.state(z, j), self.input(z, j));
+            for cost in self.costs.iter() {
+                objective += self.weight(j) * cost.lagrange(&x, &u, self.times[j]);
+            }
+        }
+        let x_f: Vec<f64> = self.state(z, n - 1);
+        for cost in self.costs.iter() {
+            objective += cost.mayer(&x_f);
+        }
+        return objective;
+    }
+
+    fn gradient(&self, z: &[f64]) -> Vec<f64> {
+        let n: usize = self.n_nodes();
+        let n_cols: usize = self.model.n_x() + self.model.n_u();
+        let mut gradient: Vec<f64> = vec![0.0; self.n_variables()];
+        for j in 1..n {
+            let (x, u, offset) = (self.state(z, j), self.input(z, j), self.offset(j));
+            for cost in self.costs.iter() {
+                let grad: Vec<f64> = cost.lagrange_grad(&x, &u, self.times[j]);
+                for col in 0..n_cols {
+                    gradient[offset + col] += self.weight(j) * grad[col] * self.scale(col);
+                }
+            }
+        }
+        let (x_f, offset) = (self.state(z, n - 1), self.offset(n - 1));
+        for cost in self.costs.iter() {
+            for (i, dphi) in cost.mayer_grad(&x_f).iter().enumerate() {
+                gradient[offset + i] += dphi * self.state_scales[i];
+            }
+        }
+        return gradient;
+    }
+
+    fn constraints(&self, z: &[f64]) -> Vec<f64> {
+        let nx: usize = self.model.n_x();
+        let mut constraints: Vec<f64> = Vec::with_capacity(self.n_constraints());
+        for j in 1..self.n_nodes() {
+            let (x, u, t) = (self.state(z, j), self.input(z, j), self.times[j]);
+            let f: Vec<f64> = self.model.fun(&x, &u, t);
+            for r in 0..nx {
+                let derivative: f64 = (0..=self.n_c)
+                    .map(|l| {
+                        self.differentiation(j, l) * z[self.offset(self.interval_node(j, l)) + r]
+                    })
+                    .sum();
+                constraints.push(derivative - f[r] / self.state_scales[r]);
+            }
+            for constraint in self.constraints.iter() {
+                constraints.extend(constraint.values(&x, &u, t));
+            }
+        }
+        let last: usize = self.offset(self.n_nodes() - 1);
+        for r in self.boundary.periodic.iter() {
+            constraints.push(z[last + r] - z[*r]);
+        }
+        return constraints;
+    }
+
+    fn jacobian_structure(&self) -> Vec<(usize, usize)> {
+        return self
+            .jacobian_entries(None)
+            .iter()
+            .map(|(row, col, _)| (*row, *col))
+            .collect();
+    }
+
+    fn jacobian(&self, z: &[f64]) -> Vec<f64> {
+        return self
+            .jacobian_entries(Some(z))
+            .iter()
+            .map(|(_, _, value)| *value)
+            .collect();
+    }
+
+    fn hessian_structure(&self) -> Vec<(usize, usize)> {
+        let n_cols: usize = self.model.n_x() + self.model.n_u();
+        let mut structure: Vec<(usize, usize)> = Vec::new();
+        for j in 1..self.n_nodes() {
+            let offset: usize = self.offset(j);
+            for a in 0..n_cols {
+                for b in 0..=a {
+                    structure.push((offset + a, offset + b));
+                }
+            }
+        }
+        return structure;
+    }
+
+    fn hessian(&self, z: &[f64], sigma: f64, lambda: &[f64]) -> Vec<f64> {
+        let n_cols: usize = self.model.n_x() + self.model.n_u();
+        let mut values: Vec<f64> = Vec::new();
+        for j in 1..self.n_nodes() {
+            let offset: usize = self.offset(j);
+            let v: &[f64] = &z[offset..offset + n_cols];
+
+            // Row-major block from central differences of the node gradient
+            let mut block: Vec<f64> = vec![0.0; n_cols * n_cols];
+            for col in 0..n_cols {
+                let h: f64 = HESSIAN_STEP * v[col].abs().max(1.0);
+                let (mut v_p, mut v_m) = (v.to_vec(), v.to_vec());
+                v_p[col] += h;
+                v_m[col] -= h;
+                let g_p: Vec<f64> = self.node_gradient(j, &v_p, sigma, lambda);
+                let g_m: Vec<f64> = self.node_gradient(j, &v_m, sigma, lambda);
+                for row in 0..n_cols {
+                    block[row * n_cols + col] = (g_p[row] - g_m[row]) / (2.0 * h);
+                }
+            }
+            for a in 0..n_cols {
+                for b in 0..=a {
+                    values.push(0.5 * (block[a * n_cols + b] + block[b * n_cols + a]));
+                }
+            }
+        }
+        return values;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::kinematic_bicycle::KinematicBicycle;
+    use crate::model::point_mass::PointMass;
+    use crate::ocp::{ControlEffort, FrictionCircle, MinimumTime};
+
+    // Dense row-major matrix from sparse entries, summing repeats
+    fn dense(entries: &[(usize, usize)], values: &[f64], n_rows: usize, n_cols: usize) -> Vec<f64> {
+        let mut matrix: Vec<f64> = vec![0.0; n_rows * n_cols];
+        for ((row, col), value) in entries.iter().zip(values.iter()) {
+            matrix[row * n_cols + col] += value;
+        }
+        return matrix;
+    }
+
+    fn point_mass_problem(model: &PointMass) -> Transcription<'_, PointMass> {
+        let mut problem = Transcription::new(model, &[0.0, 0.5, 1.2, 2.0], 3);
+        problem
+            .costs
+            .push(Box::new(ControlEffort::new(vec![1e-3, 2e-3])));
+        problem.costs.push(Box::new(MinimumTime::new(0)));
+        problem
+            .constraints
+            .push(Box::new(FrictionCircle::new((0, 1), 10.0)));
+        problem.boundary.initial = vec![Some(0.0), Some(0.0), Some(1.0), None];
+        problem.boundary.periodic = vec![3];
+        problem.state_scales = vec![2.0, 2.0, 0.5, 0.5];
+        problem.input_scales = vec![4.0, 4.0];
+        return problem;
+    }
+
+    #[test]
+    fn test_sizes_and_bounds() {
+        let model: PointMass = PointMass::new("Mass", 2.0);
+        let problem = point_mass_problem(&model);
+        assert_eq!(problem.n_nodes(), 10);
+        assert_eq!(problem.n_variables(), 4 + 9 * 6);
+        assert_eq!(problem.n_constraints(), 9 * (4 + 1) + 1);
+
+        let (lower, upper) = problem.variable_bounds();
+        assert_eq!(lower.len(), problem.n_variables());
+        assert_eq!((lower[2], upper[2]), (2.0, 2.0)); // Initial vx of 1 scaled by 0.5
+        assert_eq!(lower[3], f64::NEG_INFINITY);
+
+        let (lower, upper) = problem.constraint_bounds();
+        assert_eq!(lower.len(), problem.n_constraints());
+        assert_eq!((lower[4], upper[4]), (f64::NEG_INFINITY, 1.0));
+        assert_eq!(
+            problem.jacobian_structure().len(),
+            problem.jacobian(&vec![0.1; 58]).len()
+        );
+    }
+
+    #[test]
+    fn test_exact_trajectory_has_no_defects() {
+        // Constant force gives a quadratic trajectory, which the collocation represents exactly
+        let model: PointMass = PointMass::new("Mass", 2.0);
+        let problem = point_mass_problem(&model);
+        let z: Vec<f64> = problem.pack(|t| {
+            let (ax, ay) = (1.5, -0.5);
+            (
+                vec![t + 0.5 * ax * t * t, 0.5 * ay * t * t, 1.0 + ax * t, ay * t],
+                vec![2.0 * ax, 2.0 * ay],
+            )
+        });
+        let constraints: Vec<f64> = problem.constraints(&z);
+        for j in 0..9 {
+            for r in 0..4 {
+                assert!(constraints[j * 5 + r].abs() < 1e-12);
+            }
+        }
+
+        // The control effort is integrated exactly and the time state is the Mayer term
+        let trajectory: Trajectory = problem.unpack(&z);
+        let x_f: f64 = trajectory.states[9][0];
+        let effort: f64 = 2.0 * (1e-3 * 9.0 + 2e-3 * 1.0);
+        assert!((problem.objective(&z) - (effort + x_f)).abs() < 1e-12);
+        assert_eq!(trajectory.times[9], 2.0);
+        assert_eq!(trajectory.inputs[0], trajectory.inputs[1]);
+    }
+
+    #[test]
+    fn test_derivatives_match_finite_differences() {
+        let model = KinematicBicycle::new("Bicycle", 2.5, 1.2);
+        let mut problem = Transcription::new(&model, &[0.0, 0.7, 1.5], 2);
+        problem
+            .costs
+            .push(Box::new(ControlEffort::new(vec![1.0, 0.1])));
+        problem
+            .constraints
+            .push(Box::new(FrictionCircle::new((0, 1), 2.0)));
+        problem.boundary.periodic = vec![2, 3];
+        problem.state_scales = vec![10.0, 10.0, 1.0, 5.0];
+        problem.input_scales = vec![0.2, 3.0];
+
+        let n: usize = problem.n_variables();
+        let m: usize = problem.n_constraints();
+        let z: Vec<f64> = (0..n).map(|i| 0.3 + 0.1 * ((i * 7) % 5) as f64).collect();
+        let lambda: Vec<f64> = (0..m).map(|i| 0.5 - 0.2 * ((i * 3) % 4) as f64).collect();
+        let sigma: f64 = 0.7;
+
+        // Lagrangian gradient sigma grad f + J^T lambda
+        let lagrangian_gradient = |z: &[f64]| -> Vec<f64> {
+            let jac: Vec<f64> = dense(&problem.jacobian_structure(), &problem.jacobian(z), m, n);
+            let grad: Vec<f64> = problem.gradient(z);
+            return (0..n)
+                .map(|i| sigma * grad[i] + (0..m).map(|r| jac[r * n + i] * lambda[r]).sum::<f64>())
+                .collect();
+        };
+
+        let jac: Vec<f64> = dense(&problem.jacobian_structure(), &problem.jacobian(&z), m, n);
+        let hess: Vec<f64> = dense(
+            &problem.hessian_structure(),
+            &problem.hessian(&z, sigma, &lambda),
+            n,
+            n,
+        );
+        let grad: Vec<f64> = problem.gradient(&z);
+        let h: f64 = 1e-6;
+        for col in 0..n {
+            let (mut z_p, mut z_m) = (z.clone(), z.clone());
+            z_p[col] += h;
+            z_m[col] -= h;
+
+            let fd: f64 = (problem.objective(&z_p) - problem.objective(&z_m)) / (2.0 * h);
+            assert!((grad[col] - fd).abs() < 1e-6 * (1.0 + fd.abs()));
+
+            let (c_p, c_m) = (problem.constraints(&z_p), problem.constraints(&z_m));
+            for row in 0..m {
+                let fd: f64 = (c_p[row] - c_m[row]) / (2.0 * h);
+                assert!((jac[row * n + col] - fd).abs() < 1e-6 * (1.0 + fd.abs()));
+            }
+
+            let (g_p, g_m) = (lagrangian_gradient(&z_p), lagrangian_gradient(&z_m));
+            for row in col..n {
+                let fd: f64 = (g_p[row] - g_m[row]) / (2.0 * h);
+                assert!((hess[row * n + col] - fd).abs() < 1e-4 * (1.0 + fd.abs()));
+            }
+        }
+    }
+}
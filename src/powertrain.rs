@@ -0,0 +1,410 @@
+pub mod battery;
+
+// Powertrain model giving the maximum tractive force at the wheels as a function of speed.
+//
+// The motor is described by a full-load speed-torque map, and the torque reaches the wheels
+// through the selected gear, the final drive and a constant driveline efficiency. The map is
+// interpolated linearly and gives no torque beyond its last speed, which acts as the rev
+// limit. Combustion engines cannot run below their lowest map speed, so at low road speed the
+// clutch slips and the torque at the lowest map speed is available. Electric motor maps must
+// start at standstill.
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MotorKind {
+    Combustion,
+    Electric,
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShiftLogic {
+    Optimal,           // Use the gear giving the largest tractive force
+    UpshiftAtRpm(f64), // Use the lowest gear with the motor speed below the given speed [rpm]
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TorqueMap {
+    // Private with getters
+    speeds: Vec<f64>,  // Motor speed [rpm], strictly increasing
+    torques: Vec<f64>, // Full-load torque [Nm]
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Powertrain {
+    pub kind: MotorKind,
+    pub torque_map: TorqueMap,
+    pub gear_ratios: Vec<f64>, // Gearbox ratio of each gear, motor speed over output speed
+    pub final_drive: f64,      // Final drive ratio
+    pub efficiency: f64,       // Driveline efficiency from motor to wheels [-]
+    pub wheel_radius: f64,     // Loaded wheel radius [m]
+    pub shift_logic: ShiftLogic,
+}
+
+const RPM_TO_RAD_S: f64 = std::f64::consts::PI / 30.0;
+
+// TORQUEMAP IMPLEMENTATION ++++++++++++++++++++++++
+impl TorqueMap {
+    pub fn new(speeds: Vec<f64>, torques: Vec<f64>) -> Result<Self, String> {
+        if speeds.len() != torques.len() {
+            return Err(format!(
+                "{} speeds but {} torques",
+                speeds.len(),
+                torques.len()
+            ));
+        }
+        if speeds.len() < 2 {
+            return Err("torque map needs at least two points".to_string());
+        }
+        if speeds.iter().chain(torques.iter()).any(|v| !v.is_finite()) {
+            return Err("torque map has a non-finite value".to_string());
+        }
+        if speeds[0] < 0.0 {
+            return Err(format!("negative motor speed {}", speeds[0]));
+        }
+        if speeds.windows(2).any(|w| w[1] <= w[0]) {
+            return Err("motor speeds must be strictly increasing".to_string());
+        }
+        return Ok(TorqueMap { speeds, torques });
+    }
+
+    // Read a map from a CSV file with the columns speed [rpm] and torque [Nm], panicking if
+    // it cannot be read or is invalid
+    #[allow(dead_code)]
+    pub fn read_from_csv(file_path: &str) -> Self {
+        let text: String = match std::fs::read_to_string(file_path) {
+            Ok(t) => t,
+            Err(e) => panic!("Failed to read torque map {}: {}", file_path, e),
+        };
+        return match Self::from_csv_str(&text) {
+            Ok(map) => map,
+            Err(e) => panic!("Invalid torque map {}: {}", file_path, e),
+        };
+    }
+
+    // Parse CSV text with the columns speed [rpm] and torque [Nm]. An optional header line,
+    // empty lines and lines starting with # are skipped.
+    pub fn from_csv_str(text: &str) -> Result<Self, String> {
+        let mut speeds: Vec<f64> = Vec::new();
+        let mut torques: Vec<f64> = Vec::new();
+
+        let lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+        for (n_data, (i, line)) in lines.enumerate() {
+            let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+            if fields.len() < 2 {
+                return Err(format!("line {} has fewer than two columns", i + 1));
+            }
+            match (fields[0].parse::<f64>(), fields[1].parse::<f64>()) {
+                (Ok(speed), Ok(torque)) => {
+                    speeds.push(speed);
+                    torques.push(torque);
+                }
+                _ if n_data == 0 => continue, // Header
+                _ => return Err(format!("line {} is not numeric", i + 1)),
+            }
+        }
+        return Self::new(speeds, torques);
+    }
+
+    // Full-load torque [Nm] and its derivative [Nm/rpm] at motor speed [rpm]. Below the
+    // first speed the first torque is held, and above the last speed there is no torque.
+    pub fn torque(&self, speed: f64) -> (f64, f64) {
+        let n: usize = self.speeds.len();
+        if speed <= self.speeds[0] {
+            return (self.torques[0], 0.0);
+        }
+        if speed > self.speeds[n - 1] {
+            return (0.0, 0.0);
+        }
+        let i: usize = self.speeds.partition_point(|&s| s < speed).max(1) - 1;
+        let slope: f64 =
+            (self.torques[i + 1] - self.torques[i]) / (self.speeds[i + 1] - self.speeds[i]);
+        return (self.torques[i] + slope * (speed - self.speeds[i]), slope);
+    }
+
+    // Getters
+    pub fn min_speed(&self) -> f64 {
+        return self.speeds[0];
+    }
+
+    pub fn max_speed(&self) -> f64 {
+        return self.speeds[self.speeds.len() - 1];
+    }
+
+    // Largest power along the map [W]
+    #[allow(dead_code)]
+    pub fn max_power(&self) -> f64 {
+        // Power is quadratic between map points, so check the vertex of every interval
+        let mut max_power: f64 = 0.0;
+        for i in 0..self.speeds.len() - 1 {
+            let (s0, s1) = (self.speeds[i], self.speeds[i + 1]);
+            let slope: f64 = (self.torques[i + 1] - self.torques[i]) / (s1 - s0);
+            let mut candidates: Vec<f64> = vec![s0, s1];
+            if slope < 0.0 {
+                let vertex: f64 = (slope * s0 - self.torques[i]) / (2.0 * slope);
+                if vertex > s0 && vertex < s1 {
+                    candidates.push(vertex);
+                }
+            }
+            for speed in candidates {
+                let (torque, _) = self.torque(speed);
+                max_power = max_power.max(torque * speed * RPM_TO_RAD_S);
+            }
+        }
+        return max_power;
+    }
+}
+
+// POWERTRAIN IMPLEMENTATION +++++++++++++++++++++++
+impl Powertrain {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(
+        kind: MotorKind,
+        torque_map: TorqueMap,
+        gear_ratios: Vec<f64>,
+        final_drive: f64,
+        efficiency: f64,
+        wheel_radius: f64,
+    ) -> Result<Self, String> {
+        if gear_ratios.is_empty() {
+            return Err("no gear ratios".to_string());
+        }
+        if gear_ratios.iter().any(|r| r.is_nan() || *r <= 0.0) {
+            return Err("gear ratios must be positive".to_string());
+        }
+        if final_drive.is_nan() || final_drive <= 0.0 {
+            return Err(format!("invalid final drive ratio {}", final_drive));
+        }
+        if !(efficiency > 0.0 && efficiency <= 1.0) {
+            return Err(format!("efficiency {} not in (0, 1]", efficiency));
+        }
+        if wheel_radius.is_nan() || wheel_radius <= 0.0 {
+            return Err(format!("invalid wheel radius {}", wheel_radius));
+        }
+        if kind == MotorKind::Electric && torque_map.min_speed() != 0.0 {
+            return Err("electric motor maps must start at 0 rpm".to_string());
+        }
+        return Ok(Powertrain {
+            kind,
+            torque_map,
+            gear_ratios,
+            final_drive,
+            efficiency,
+            wheel_radius,
+            shift_logic: ShiftLogic::Optimal,
+        });
+    }
+
+    // Overall ratio from road speed [m/s] to motor speed [rpm] in a gear
+    fn speed_ratio(&self, gear: usize) -> f64 {
+        return self.gear_ratios[gear] * self.final_drive / self.wheel_radius / RPM_TO_RAD_S;
+    }
+
+    // Motor speed [rpm] at road speed v [m/s] in a gear, without clutch slip
+    pub fn motor_speed(&self, v: f64, gear: usize) -> f64 {
+        return v.abs() * self.speed_ratio(gear);
+    }
+
+    // Maximum tractive force [N] at the wheels in a gear and its derivative with respect to
+    // the road speed [N s/m]
+    pub fn tractive_force_in_gear(&self, v: f64, gear: usize) -> (f64, f64) {
+        let ratio: f64 = self.speed_ratio(gear);
+        let (torque, dtorque) = self.torque_map.torque(self.motor_speed(v, gear));
+        let force_per_torque: f64 =
+            self.gear_ratios[gear] * self.final_drive * self.efficiency / self.wheel_radius;
+        return (
+            torque * force_per_torque,
+            dtorque * ratio * force_per_torque,
+        );
+    }
+
+    // Gear selected at road speed v [m/s], counting from zero
+    pub fn select_gear(&self, v: f64) -> usize {
+        let n_gears: usize = self.gear_ratios.len();
+        return match self.shift_logic {
+            ShiftLogic::Optimal => (0..n_gears)
+                .max_by(|&a, &b| {
+                    let force_a: f64 = self.tractive_force_in_gear(v, a).0;
+                    let force_b: f64 = self.tractive_force_in_gear(v, b).0;
+                    // Prefer the higher gear when the forces are equal
+                    force_a.total_cmp(&force_b).then(a.cmp(&b))
+                })
+                .unwrap_or(0),
+            ShiftLogic::UpshiftAtRpm(upshift) => (0..n_gears)
+                .find(|&gear| self.motor_speed(v, gear) < upshift)
+                .unwrap_or(n_gears - 1),
+        };
+    }
+
+    // Maximum tractive force [N] at road speed v [m/s] in the selected gear, and its
+    // derivative with respect to v [N s/m]. The force is zero beyond the rev limit in the
+    // highest gear and the derivative is discontinuous at gear changes.
+    pub fn max_tractive_force(&self, v: f64) -> (f64, f64) {
+        return self.tractive_force_in_gear(v, self.select_gear(v));
+    }
+
+    // Mechanical power delivered by the motor [W] for a tractive force [N] at road speed v
+    // [m/s], accounting for the driveline losses
+    #[allow(dead_code)]
+    pub fn motor_power(&self, force: f64, v: f64) -> f64 {
+        return force * v / self.efficiency;
+    }
+
+    // Top speed [m/s] limited by the rev limit in the highest gear
+    #[allow(dead_code)]
+    pub fn max_speed(&self) -> f64 {
+        return self.torque_map.max_speed() / self.speed_ratio(self.gear_ratios.len() - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMBUSTION_CSV: &str = "\
+speed_rpm,torque_nm
+# Naturally aspirated engine
+1000, 200
+4000, 300
+7000, 250
+8000, 200
+";
+
+    fn combustion() -> Powertrain {
+        let map: TorqueMap = TorqueMap::from_csv_str(COMBUSTION_CSV).unwrap();
+        return Powertrain::new(
+            MotorKind::Combustion,
+            map,
+            vec![3.0, 2.0, 1.5, 1.2, 1.0],
+            3.5,
+            0.9,
+            0.3,
+        )
+        .unwrap();
+    }
+
+    // Electric motor with 250 Nm up to 6000 rpm and constant power above
+    fn electric() -> Powertrain {
+        let mut speeds: Vec<f64> = vec![0.0];
+        let mut torques: Vec<f64> = vec![250.0];
+        for i in 0..=12 {
+            let speed: f64 = 6000.0 + 1000.0 * i as f64;
+            speeds.push(speed);
+            torques.push(250.0 * 6000.0 / speed);
+        }
+        let map: TorqueMap = TorqueMap::new(speeds, torques).unwrap();
+        return Powertrain::new(MotorKind::Electric, map, vec![9.0], 1.0, 0.95, 0.3).unwrap();
+    }
+
+    #[test]
+    fn test_parse_torque_map() {
+        let map: TorqueMap = TorqueMap::from_csv_str(COMBUSTION_CSV).unwrap();
+        assert_eq!(map.min_speed(), 1000.0);
+        assert_eq!(map.max_speed(), 8000.0);
+
+        assert!(TorqueMap::from_csv_str("1000, 200\n").is_err());
+        assert!(TorqueMap::from_csv_str("1000, 200\n900, 250\n").is_err());
+        assert!(TorqueMap::from_csv_str("1000, 200\nabc, 250\n3000, 250\n").is_err());
+        assert!(TorqueMap::from_csv_str("1000\n2000, 250\n").is_err());
+        assert!(TorqueMap::from_csv_str("1000, 200\n2000, inf\n").is_err());
+    }
+
+    #[test]
+    fn test_torque_interpolation() {
+        let map: TorqueMap = TorqueMap::from_csv_str(COMBUSTION_CSV).unwrap();
+        assert_eq!(map.torque(500.0), (200.0, 0.0));
+        assert_eq!(map.torque(1000.0), (200.0, 0.0));
+        let (torque, slope) = map.torque(2500.0);
+        assert!((torque - 250.0).abs() < 1e-12);
+        assert!((slope - 100.0 / 3000.0).abs() < 1e-12);
+        assert!((map.torque(7500.0).0 - 225.0).abs() < 1e-12);
+        assert_eq!(map.torque(8000.0).0, 200.0);
+        assert_eq!(map.torque(8001.0), (0.0, 0.0));
+
+        // Peak power at a map point, and inside an interval where T = 300 - 0.03 n
+        assert!((map.max_power() - 250.0 * 7000.0 * RPM_TO_RAD_S).abs() < 1e-6);
+        let map: TorqueMap = TorqueMap::from_csv_str("0, 300\n10000, 0\n").unwrap();
+        assert!((map.max_power() - 150.0 * 5000.0 * RPM_TO_RAD_S).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_combustion_tractive_force() {
+        let powertrain: Powertrain = combustion();
+
+        // Clutch slip at low speed gives the lowest map torque in first gear
+        let (force, dforce) = powertrain.max_tractive_force(0.5);
+        assert!((force - 200.0 * 3.0 * 3.5 * 0.9 / 0.3).abs() < 1e-9);
+        assert_eq!(dforce, 0.0);
+
+        // The optimal gear gives at least the force of every other gear
+        for v in [5.0, 20.0, 35.0, 50.0] {
+            let (force, _) = powertrain.max_tractive_force(v);
+            for gear in 0..5 {
+                assert!(force >= powertrain.tractive_force_in_gear(v, gear).0);
+            }
+        }
+
+        // No force beyond the rev limit in top gear
+        let v_max: f64 = powertrain.max_speed();
+        assert!((powertrain.motor_speed(v_max, 4) - 8000.0).abs() < 1e-9);
+        assert!(powertrain.max_tractive_force(v_max - 0.1).0 > 0.0);
+        assert_eq!(powertrain.max_tractive_force(v_max + 0.1).0, 0.0);
+    }
+
+    #[test]
+    fn test_shift_logic() {
+        let mut powertrain: Powertrain = combustion();
+        powertrain.shift_logic = ShiftLogic::UpshiftAtRpm(7000.0);
+
+        // Shift up once the motor speed passes 7000 rpm
+        let v_shift: f64 = 7000.0 / powertrain.speed_ratio(0);
+        assert_eq!(powertrain.select_gear(v_shift - 0.01), 0);
+        assert_eq!(powertrain.select_gear(v_shift + 0.01), 1);
+        assert_eq!(powertrain.select_gear(1000.0), 4);
+    }
+
+    #[test]
+    fn test_electric_tractive_force() {
+        let powertrain: Powertrain = electric();
+
+        // Constant force up to the base speed and constant power above it
+        let v_base: f64 = 6000.0 / powertrain.speed_ratio(0);
+        let f_base: f64 = 250.0 * 9.0 * 0.95 / 0.3;
+        assert!((powertrain.max_tractive_force(0.0).0 - f_base).abs() < 1e-9);
+        assert!((powertrain.max_tractive_force(0.5 * v_base).0 - f_base).abs() < 1e-9);
+        let p_wheel: f64 = f_base * v_base;
+        for v in [1.5 * v_base, 2.5 * v_base] {
+            // The map interpolates the hyperbola linearly, so allow a small error
+            let (force, _) = powertrain.max_tractive_force(v);
+            assert!((force * v - p_wheel).abs() < 0.01 * p_wheel);
+        }
+        assert!(Powertrain::new(
+            MotorKind::Electric,
+            TorqueMap::from_csv_str(COMBUSTION_CSV).unwrap(),
+            vec![9.0],
+            1.0,
+            0.95,
+            0.3
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_tractive_force_derivative() {
+        let powertrain: Powertrain = combustion();
+        let h: f64 = 1e-6;
+        for gear in 0..5 {
+            for v in [8.0, 17.0, 31.0] {
+                let (_, dforce) = powertrain.tractive_force_in_gear(v, gear);
+                let fd: f64 = (powertrain.tractive_force_in_gear(v + h, gear).0
+                    - powertrain.tractive_force_in_gear(v - h, gear).0)
+                    / (2.0 * h);
+                assert!((dforce - fd).abs() < 1e-4 * (1.0 + fd.abs()));
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+// Battery pack with an internal resistance (Rint) equivalent circuit. The open-circuit
+// voltage varies linearly with the state of charge, and the terminal voltage sags by the
+// current times the internal resistance. A lumped thermal mass is heated by the resistive
+// losses and cooled towards the ambient temperature.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Battery {
+    pub capacity: f64,              // Charge capacity [Ah]
+    pub ocv_empty: f64,             // Open-circuit voltage at SOC 0 [V]
+    pub ocv_full: f64,              // Open-circuit voltage at SOC 1 [V]
+    pub internal_resistance: f64,   // [Ohm]
+    pub max_discharge_current: f64, // [A]
+    pub max_charge_current: f64,    // Largest regeneration current, positive [A]
+    pub min_voltage: f64,           // Lowest allowed terminal voltage [V]
+    pub thermal_mass: f64,          // Heat capacity of the pack [J/K]
+    pub cooling: f64,               // Heat transfer to ambient [W/K]
+    pub ambient_temperature: f64,   // [degC]
+}
+
+// Current drawn by the pack for a given power, with derivatives
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BatteryCurrent {
+    pub current: f64, // Positive when discharging [A]
+    pub d_power: f64, // dI/dP [A/W]
+    pub d_soc: f64,   // dI/dSOC [A]
+}
+
+// BATTERY IMPLEMENTATION ++++++++++++++++++++++++++
+impl Battery {
+    // Open-circuit voltage [V] at a state of charge [-]
+    pub fn open_circuit_voltage(&self, soc: f64) -> f64 {
+        return self.ocv_empty + (self.ocv_full - self.ocv_empty) * soc;
+    }
+
+    // Energy stored between SOC 0 and soc [J], the integral of the open-circuit voltage
+    #[allow(dead_code)]
+    pub fn energy(&self, soc: f64) -> f64 {
+        let charge: f64 = self.capacity * 3600.0;
+        return charge
+            * (self.ocv_empty * soc + 0.5 * (self.ocv_full - self.ocv_empty) * soc.powi(2));
+    }
+
+    // Largest power the pack can deliver at a state of charge [W], reached when the terminal
+    // voltage has sagged to half the open-circuit voltage
+    #[allow(dead_code)]
+    pub fn max_power(&self, soc: f64) -> f64 {
+        return self.open_circuit_voltage(soc).powi(2) / (4.0 * self.internal_resistance);
+    }
+
+    // Current for a terminal power [W] at a state of charge, solving P = (V_oc - R I) I for
+    // the physical root. Powers beyond max_power have no solution, and the current is held
+    // at the value for max_power.
+    pub fn current(&self, power: f64, soc: f64) -> BatteryCurrent {
+        let r: f64 = self.internal_resistance;
+        let v_oc: f64 = self.open_circuit_voltage(soc);
+        let dv_oc: f64 = self.ocv_full - self.ocv_empty;
+        let discriminant: f64 = v_oc.powi(2) - 4.0 * r * power;
+
+        // Saturated at a small fraction of the open-circuit voltage to keep the root real
+        let floor: f64 = 1e-9 * v_oc.powi(2);
+        if discriminant < floor {
+            let root: f64 = floor.sqrt();
+            return BatteryCurrent {
+                current: (v_oc - root) / (2.0 * r),
+                d_power: 0.0,
+                d_soc: (1.0 - root / v_oc) / (2.0 * r) * dv_oc,
+            };
+        }
+
+        let root: f64 = discriminant.sqrt();
+        return BatteryCurrent {
+            current: (v_oc - root) / (2.0 * r),
+            d_power: 1.0 / root,
+            d_soc: (1.0 - v_oc / root) / (2.0 * r) * dv_oc,
+        };
+    }
+
+    // Terminal voltage [V] for a current [A] at a state of charge
+    pub fn terminal_voltage(&self, current: f64, soc: f64) -> f64 {
+        return self.open_circuit_voltage(soc) - self.internal_resistance * current;
+    }
+
+    // Rate of change of the state of charge [1/s] for a current [A]
+    pub fn soc_rate(&self, current: f64) -> f64 {
+        return -current / (self.capacity * 3600.0);
+    }
+
+    // Rate of change of the temperature [K/s] for a current [A] at a temperature [degC]
+    pub fn temperature_rate(&self, current: f64, temperature: f64) -> f64 {
+        let heat: f64 = self.internal_resistance * current.powi(2)
+            - self.cooling * (temperature - self.ambient_temperature);
+        return heat / self.thermal_mass;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_battery() -> Battery {
+        return Battery {
+            capacity: 20.0,
+            ocv_empty: 500.0,
+            ocv_full: 600.0,
+            internal_resistance: 0.1,
+            max_discharge_current: 400.0,
+            max_charge_current: 150.0,
+            min_voltage: 450.0,
+            thermal_mass: 50000.0,
+            cooling: 100.0,
+            ambient_temperature: 25.0,
+        };
+    }
+
+    #[test]
+    fn test_current_and_voltage_sag() {
+        let battery: Battery = example_battery();
+        let soc: f64 = 0.5;
+        assert_eq!(battery.open_circuit_voltage(soc), 550.0);
+
+        // The current delivers the requested power at the sagged terminal voltage
+        for power in [-40e3, 0.0, 20e3, 80e3] {
+            let current: f64 = battery.current(power, soc).current;
+            let voltage: f64 = battery.terminal_voltage(current, soc);
+            assert!((voltage * current - power).abs() < 1e-6);
+            assert!(voltage <= 550.0 || power < 0.0);
+        }
+
+        // Requests beyond the maximum power are held at the maximum
+        let i_max: f64 = battery.current(battery.max_power(soc), soc).current;
+        assert!((battery.current(2.0 * battery.max_power(soc), soc).current - i_max).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_current_derivatives() {
+        let battery: Battery = example_battery();
+        let (power, soc) = (60e3, 0.7);
+        let current: BatteryCurrent = battery.current(power, soc);
+
+        let h: f64 = 1e-3;
+        let fd_power: f64 = (battery.current(power + h, soc).current
+            - battery.current(power - h, soc).current)
+            / (2.0 * h);
+        assert!((current.d_power - fd_power).abs() < 1e-9);
+
+        let h: f64 = 1e-6;
+        let fd_soc: f64 = (battery.current(power, soc + h).current
+            - battery.current(power, soc - h).current)
+            / (2.0 * h);
+        assert!((current.d_soc - fd_soc).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_energy_and_rates() {
+        let battery: Battery = example_battery();
+
+        // 20 Ah at an average of 550 V
+        assert!((battery.energy(1.0) - 20.0 * 3600.0 * 550.0).abs() < 1e-6);
+
+        // One amp for an hour uses 1/20 of the charge
+        assert!((battery.soc_rate(1.0) * 3600.0 + 0.05).abs() < 1e-12);
+
+        // Resistive heating against cooling to ambient
+        assert!((battery.temperature_rate(100.0, 25.0) - 1000.0 / 50000.0).abs() < 1e-12);
+        assert!((battery.temperature_rate(0.0, 35.0) + 1000.0 / 50000.0).abs() < 1e-12);
+    }
+}
@@ -1,8 +1,18 @@
+pub mod cones;
+pub mod fit;
+pub mod mesh;
+pub mod metadata;
+pub mod opendrive;
+pub mod trkf;
+
 use maths_toolbox;
+use metadata::TrackMetadata;
 
 pub struct Track {
     // Public
     pub name: String,
+    pub metadata: TrackMetadata,
+    pub aero_zones: Vec<AeroZone>,
     // Private with getters
     is_closed: bool,
     length: f64,
@@ -12,12 +22,21 @@ pub struct Track {
     segments: Vec<Box<CubicBezierSegment>>, // Currently only works with CubicBezierSegment
 }
 
+// Stretch of track where DRS or active aero may be deployed, from start to end in s_lap [m].
+// On closed tracks a zone with end < start wraps over the start line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AeroZone {
+    pub start: f64,
+    pub end: f64,
+}
+
 pub struct TrackFrame {
     // Currently only 2D tracks are supported so normal is always (0, 0, 1) and thus omitted
     // Private with getters
     position: (f64, f64),
     tangent: (f64, f64), // Unit vector in "forward" direction
     lateral: (f64, f64), // Unit vector to the left of tangent
+    curvature: f64,      // Signed curvature, positive when turning left [1/m]
     width: f64,
 }
 
@@ -25,6 +44,7 @@ pub trait Segment {
     fn calc_length(&self) -> f64;
     fn eval(&self, s: f64) -> (f64, f64, f64); // Evaluate at parameter s in [0, 1]
     fn eval_ds(&self, s: f64) -> (f64, f64, f64); // Evaluate derivative wrt s at s
+    fn eval_ds2(&self, s: f64) -> (f64, f64, f64); // Evaluate second derivative wrt s at s
 }
 
 struct CubicBezierSegment {
@@ -38,18 +58,22 @@ struct CubicBezierSegment {
 // TRACK IMPLEMENTATION ++++++++++++++++++++++++++++++++
 impl std::fmt::Display for Track {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        return match self.is_closed {
+        match self.is_closed {
             true => write!(
                 f,
                 "Closed Track: {} of Length {:.2} m with {} Segments",
                 self.name, self.length, self.n_segments
-            ),
+            )?,
             false => write!(
                 f,
                 "Open Track: {} of Length {:.2} m with {} Segments",
                 self.name, self.length, self.n_segments
-            ),
+            )?,
         };
+        if !self.metadata.is_empty() {
+            write!(f, "\n{}", self.metadata)?;
+        }
+        return Ok(());
     }
 }
 
@@ -83,6 +107,8 @@ impl Track {
 
         Self {
             name,
+            metadata: TrackMetadata::default(),
+            aero_zones: Vec::new(),
             is_closed,
             n_segments,
             length,
@@ -104,6 +130,32 @@ impl Track {
         return Self::new(name, false, 1, points);
     }
 
+    // Closed circle centred on the origin, starting at (radius, 0) and driven
+    // counter-clockwise, built from four Bezier quarter circles
+    #[allow(dead_code)]
+    pub fn circle(radius: f64, width: f64) -> Self {
+        let r: f64 = radius;
+        let k: f64 = 4.0 / 3.0 * (2.0_f64.sqrt() - 1.0) * radius;
+        let points: Vec<(f64, f64, f64)> = vec![
+            (r, 0.0, width),
+            (r, k, width),
+            (k, r, width),
+            (0.0, r, width),
+            (-k, r, width),
+            (-r, k, width),
+            (-r, 0.0, width),
+            (-r, -k, width),
+            (-k, -r, width),
+            (0.0, -r, width),
+            (k, -r, width),
+            (r, -k, width),
+            (r, 0.0, width),
+        ];
+
+        let name: String = format!("{radius:.0} m Circle");
+        return Self::new(name, true, 4, points);
+    }
+
     #[allow(dead_code)]
     pub fn double_lane_change() -> Self {
         let n_segments: usize = 5;
@@ -134,106 +186,6 @@ impl Track {
         return Self::new("Double Lane Change".to_string(), false, n_segments, points);
     }
 
-    pub fn read_from_file(file_path: &str) -> Self {
-        let data: Vec<u8> = match std::fs::read(file_path) {
-            Ok(b) => b,
-            Err(e) => panic!("Failed to read track file {}: {}", file_path, e),
-        };
-        let data_len: usize = data.len();
-        if data_len < 133 {
-            panic!(
-                "Invalid track file format: file too short ({} bytes)",
-                data_len
-            );
-        }
-
-        // First four bytes should be the letters "TRKF"
-        if &data[0..4] != b"TRKF" {
-            panic!("Invalid track file format: missing TRKF header");
-        }
-        // The next two bytes should be two u8s for major and minor version
-        // This is expected to be 0.1 for now
-        let major_version: u8 = data[4];
-        let minor_version: u8 = data[5];
-        if major_version != 0 || minor_version != 1 {
-            panic!(
-                "Unsupported track file version: {}.{}",
-                major_version, minor_version
-            );
-        }
-
-        // Next 64 bytes are the name of the track as a null-terminated string
-        let name_bytes: &[u8] = &data[6..70];
-        let mut name_end: usize = 0;
-        for (i, &b) in name_bytes.iter().enumerate() {
-            if b == 0 {
-                name_end = i;
-                break;
-            }
-        }
-        if name_end == 0 {
-            panic!("Track name is missing");
-        }
-        let name: String = String::from_utf8(name_bytes[0..name_end].to_vec()).unwrap();
-
-        // The rest of the header is empty for now, so we can skip to the points
-        // The next byte is a u8 indicating if the track is closed (0) or open (1)
-        let is_closed: bool = match data[128] {
-            0 => true,
-            1 => false,
-            _ => panic!("Invalid value for is_closed in track file"),
-        };
-        // Next four bytes are a uint32 indicating the number of polynomial segments
-        let n_segments: usize =
-            u32::from_le_bytes([data[129], data[130], data[131], data[132]]) as usize;
-        let n_points: usize = n_segments * 3 + 1;
-
-        let mut points: Vec<(f64, f64, f64)> = Vec::with_capacity(n_points);
-
-        let mut offset = 133;
-        let increment: usize = 24; // Each point is 3 f64s = 24 bytes
-        for _ in 0..n_points {
-            if offset + increment > data_len {
-                panic!("Unexpected end of file while reading track points");
-            }
-
-            let x: f64 = f64::from_le_bytes([
-                data[offset],
-                data[offset + 1],
-                data[offset + 2],
-                data[offset + 3],
-                data[offset + 4],
-                data[offset + 5],
-                data[offset + 6],
-                data[offset + 7],
-            ]);
-            let y: f64 = f64::from_le_bytes([
-                data[offset + 8],
-                data[offset + 9],
-                data[offset + 10],
-                data[offset + 11],
-                data[offset + 12],
-                data[offset + 13],
-                data[offset + 14],
-                data[offset + 15],
-            ]);
-            let width: f64 = f64::from_le_bytes([
-                data[offset + 16],
-                data[offset + 17],
-                data[offset + 18],
-                data[offset + 19],
-                data[offset + 20],
-                data[offset + 21],
-                data[offset + 22],
-                data[offset + 23],
-            ]);
-            points.push((x, y, width));
-            offset += increment;
-        }
-
-        return Self::new(name, is_closed, n_segments, points);
-    }
-
     pub fn discretise(&self, s_lap_q: Vec<f64>) -> Box<Vec<TrackFrame>> {
         // Validate s_lap_q
         for &s_lap in &s_lap_q {
@@ -270,14 +222,52 @@ impl Track {
             // Evaluate the segment at s_norm
             let (x, y, width) = self.segments[segment_index].eval(s_norm);
             let (dx_ds, dy_ds, _dwidth_ds) = self.segments[segment_index].eval_ds(s_norm);
+            let (dx_ds2, dy_ds2, _dwidth_ds2) = self.segments[segment_index].eval_ds2(s_norm);
 
-            let frame: TrackFrame = TrackFrame::new((x, y), (dx_ds, dy_ds), width);
+            let frame: TrackFrame =
+                TrackFrame::from_derivatives((x, y), (dx_ds, dy_ds), (dx_ds2, dy_ds2), width);
             frames.push(frame);
         }
 
         return Box::new(frames);
     }
 
+    // Signed curvature of the centreline at s_lap [1/m]. Closed tracks wrap around and open
+    // tracks use the curvature at the nearest end outside [0, length].
+    pub fn curvature(&self, s_lap: f64) -> f64 {
+        let s_lap: f64 = match self.is_closed {
+            true => s_lap.rem_euclid(self.length),
+            false => s_lap.clamp(0.0, self.length),
+        };
+        return self.discretise(vec![s_lap])[0].curvature();
+    }
+
+    // Width of the track at s_lap [m], wrapping and clamping as for the curvature
+    #[allow(dead_code)]
+    pub fn width(&self, s_lap: f64) -> f64 {
+        let s_lap: f64 = match self.is_closed {
+            true => s_lap.rem_euclid(self.length),
+            false => s_lap.clamp(0.0, self.length),
+        };
+        return self.discretise(vec![s_lap])[0].width();
+    }
+
+    // Whether s_lap lies in one of the aero zones, wrapping around closed tracks
+    #[allow(dead_code)]
+    pub fn in_aero_zone(&self, s_lap: f64) -> bool {
+        let s_lap: f64 = match self.is_closed {
+            true => s_lap.rem_euclid(self.length),
+            false => s_lap,
+        };
+        return self
+            .aero_zones
+            .iter()
+            .any(|zone| match zone.start <= zone.end {
+                true => (zone.start..=zone.end).contains(&s_lap),
+                false => self.is_closed && (s_lap >= zone.start || s_lap <= zone.end),
+            });
+    }
+
     // Getters
     #[allow(dead_code)]
     pub fn length(&self) -> f64 {
@@ -308,7 +298,19 @@ impl std::fmt::Display for TrackFrame {
 }
 
 impl TrackFrame {
+    #[allow(dead_code)]
     pub fn new(position: (f64, f64), tangent_raw: (f64, f64), width: f64) -> Self {
+        return Self::from_derivatives(position, tangent_raw, (0.0, 0.0), width);
+    }
+
+    // Frame from the first and second derivative of the centreline with respect to any
+    // parameter, which also gives the curvature
+    pub fn from_derivatives(
+        position: (f64, f64),
+        tangent_raw: (f64, f64),
+        second_derivative_raw: (f64, f64),
+        width: f64,
+    ) -> Self {
         // Calculate lateral as a unit vector to the left of tangent under the assumption
         // that the track is in the XY plane
 
@@ -318,10 +320,16 @@ impl TrackFrame {
         // Cross product [0;0;1]x[tangent.0;tangent.1;0] = [-tangent.1; tangent.0; 0]
         let lateral: (f64, f64) = (-tangent.1, tangent.0);
 
+        // kappa = (x' y'' - y' x'') / |r'|^3
+        let curvature: f64 = (tangent_raw.0 * second_derivative_raw.1
+            - tangent_raw.1 * second_derivative_raw.0)
+            / tangent_norm.powi(3);
+
         return Self {
             position,
             tangent,
             lateral,
+            curvature,
             width,
         };
     }
@@ -345,6 +353,10 @@ impl TrackFrame {
     pub fn width(&self) -> f64 {
         return self.width;
     }
+
+    pub fn curvature(&self) -> f64 {
+        return self.curvature;
+    }
 }
 
 // SEGMENT IMPLEMENTATION for CubicBezierSegment +++++++
@@ -406,6 +418,24 @@ impl Segment for CubicBezierSegment {
 
         return (dx_ds, dy_ds, dwidth_ds);
     }
+
+    fn eval_ds2(&self, s: f64) -> (f64, f64, f64) {
+        // Validate s
+        assert!(
+            (0.0..=1.0).contains(&s),
+            "Parameter s must be in the range [0, 1], got {}",
+            s
+        );
+
+        let dx_ds2 = 6.0 * (1.0 - s) * (self.p2.0 - 2.0 * self.p1.0 + self.p0.0)
+            + 6.0 * s * (self.p3.0 - 2.0 * self.p2.0 + self.p1.0);
+        let dy_ds2 = 6.0 * (1.0 - s) * (self.p2.1 - 2.0 * self.p1.1 + self.p0.1)
+            + 6.0 * s * (self.p3.1 - 2.0 * self.p2.1 + self.p1.1);
+        let dwidth_ds2 = 6.0 * (1.0 - s) * (self.p2.2 - 2.0 * self.p1.2 + self.p0.2)
+            + 6.0 * s * (self.p3.2 - 2.0 * self.p2.2 + self.p1.2);
+
+        return (dx_ds2, dy_ds2, dwidth_ds2);
+    }
 }
 
 // CUBICBEZIERSEGMENT IMPLEMENTATION ++++++++++++++++
@@ -433,6 +463,16 @@ mod tests {
         assert_eq!(track.length(), 120.0);
     }
 
+    #[test]
+    fn test_circle_track() {
+        let track: Track = Track::circle(50.0, 6.0);
+        assert_eq!(track.name, "50 m Circle");
+        assert!(track.is_closed());
+        assert!((track.length() / (2.0 * std::f64::consts::PI * 50.0) - 1.0).abs() < 0.01);
+        assert!((track.curvature(10.0) - 1.0 / 50.0).abs() < 1e-3);
+        assert_eq!(track.width(10.0), 6.0);
+    }
+
     #[test]
     fn test_discretise_straight_track() {
         let track: Track = Track::straight(100.0, 4.0);
@@ -452,6 +492,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_track_width() {
+        let track: Track = Track::straight(100.0, 4.0);
+        assert!((track.width(50.0) - 4.0).abs() < 1e-9);
+        // Open tracks use the width at the nearest end
+        assert!((track.width(-10.0) - 4.0).abs() < 1e-9);
+        assert!((track.width(110.0) - 4.0).abs() < 1e-9);
+    }
+
     // TRACKFRAME TESTS ++++++++++++++++++++++++++++++++
     #[test]
     fn test_trackframe_xdir() {
@@ -503,6 +552,66 @@ mod tests {
         assert!((frame.lateral.0 + inv_sqrt2).abs() < 1e-6);
         assert!((frame.lateral.1 - inv_sqrt2).abs() < 1e-6);
         assert!((frame.width - 3.0).abs() < 1e-6);
+        assert_eq!(frame.curvature(), 0.0);
+    }
+
+    #[test]
+    fn test_trackframe_curvature() {
+        // Circle of radius 5 parametrised by angle, counter-clockwise at angle 0
+        let frame: TrackFrame =
+            TrackFrame::from_derivatives((5.0, 0.0), (0.0, 5.0), (-5.0, 0.0), 3.0);
+        assert!((frame.curvature() - 0.2).abs() < 1e-12);
+
+        // Clockwise turns have negative curvature
+        let frame: TrackFrame =
+            TrackFrame::from_derivatives((5.0, 0.0), (0.0, -5.0), (-5.0, 0.0), 3.0);
+        assert!((frame.curvature() + 0.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_track_curvature() {
+        // Quarter circle of radius 50 approximated by a single Bezier segment, whose
+        // curvature deviates from the circle by up to about 2 %
+        let k: f64 = 0.5523 * 50.0;
+        let points: Vec<(f64, f64, f64)> = vec![
+            (0.0, 0.0, 5.0),
+            (k, 0.0, 5.0),
+            (50.0, 50.0 - k, 5.0),
+            (50.0, 50.0, 5.0),
+        ];
+        let track: Track = Track::new("Quarter".to_string(), false, 1, points);
+        for s_lap in [0.0, 20.0, 40.0, 60.0, track.length()] {
+            assert!((track.curvature(s_lap) - 0.02).abs() < 5e-4);
+        }
+        assert_eq!(track.curvature(-5.0), track.curvature(0.0));
+
+        let straight: Track = Track::straight(100.0, 5.0);
+        assert_eq!(straight.curvature(50.0), 0.0);
+    }
+
+    #[test]
+    fn test_track_aero_zones() {
+        let mut track: Track = Track::straight(100.0, 5.0);
+        track.aero_zones.push(AeroZone {
+            start: 20.0,
+            end: 60.0,
+        });
+        assert!(!track.in_aero_zone(10.0));
+        assert!(track.in_aero_zone(20.0));
+        assert!(track.in_aero_zone(45.0));
+        assert!(!track.in_aero_zone(70.0));
+
+        // Zones over the start line only wrap on closed tracks
+        track.aero_zones = vec![AeroZone {
+            start: 90.0,
+            end: 10.0,
+        }];
+        assert!(!track.in_aero_zone(95.0));
+        track.is_closed = true;
+        assert!(track.in_aero_zone(95.0));
+        assert!(track.in_aero_zone(5.0));
+        assert!(track.in_aero_zone(105.0));
+        assert!(!track.in_aero_zone(50.0));
     }
 
     // CUBICBEZIERSEGMENT TESTS ++++++++++++++++++++++++
@@ -555,4 +664,23 @@ mod tests {
         assert!((dy1 + 6.0).abs() < 1e-6);
         assert!((dw1 - 1.5).abs() < 1e-6);
     }
+
+    #[test]
+    fn test_cubic_bezier_eval_ds2() {
+        let segment: CubicBezierSegment = CubicBezierSegment::new(
+            (0.0, 0.0, 2.0),
+            (1.0, 2.0, 2.5),
+            (2.0, 2.0, 3.0),
+            (3.0, 0.0, 3.5),
+        );
+
+        let (ddx0, ddy0, ddw0) = segment.eval_ds2(0.0);
+        assert!((ddx0 - 0.0).abs() < 1e-6);
+        assert!((ddy0 + 12.0).abs() < 1e-6);
+        assert!((ddw0 - 0.0).abs() < 1e-6);
+
+        let (ddx1, ddy1, _) = segment.eval_ds2(1.0);
+        assert!((ddx1 - 0.0).abs() < 1e-6);
+        assert!((ddy1 + 12.0).abs() < 1e-6);
+    }
 }
@@ -0,0 +1,518 @@
+// Formula Student cone-map import.
+//
+// Cone maps list the positions of blue (left) and yellow (right) boundary cones. The cones are
+// Delaunay triangulated, and every triangle edge joining a blue and a yellow cone contributes a
+// centreline point at its midpoint. These points are ordered along the driving direction, and
+// the centreline and widths are fitted with CubicBezierSegments.
+use super::fit;
+use super::Track;
+
+const EDGE_LENGTH_FACTOR: f64 = 2.5; // Maximum cross edge length relative to the median
+const MIN_POINT_SPACING: f64 = 0.25; // Closer centreline points are merged [m]
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConeColour {
+    Blue,      // Left boundary
+    Yellow,    // Right boundary
+    Orange,    // Small orange cones, e.g. at the exit of the start zone
+    BigOrange, // Big orange cones marking the start/finish line
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Cone {
+    pub x: f64,
+    pub y: f64,
+    pub colour: ConeColour,
+}
+
+// CONE IMPORT IMPLEMENTATION for Track ++++++++++++
+impl Track {
+    #[allow(dead_code)]
+    pub fn read_from_cone_csv(file_path: &str, name: &str, is_closed: bool) -> Self {
+        let text: String = match std::fs::read_to_string(file_path) {
+            Ok(t) => t,
+            Err(e) => panic!("Failed to read cone file {}: {}", file_path, e),
+        };
+        let cones: Vec<Cone> = parse_cone_csv(&text);
+        return Self::from_cones(name, &cones, is_closed);
+    }
+
+    pub fn from_cones(name: &str, cones: &[Cone], is_closed: bool) -> Self {
+        let blue: Vec<(f64, f64)> = cones_of_colour(cones, ConeColour::Blue);
+        let yellow: Vec<(f64, f64)> = cones_of_colour(cones, ConeColour::Yellow);
+        if blue.len() < 2 || yellow.len() < 2 {
+            panic!(
+                "At least two blue and two yellow cones are required, got {} and {}",
+                blue.len(),
+                yellow.len()
+            );
+        }
+
+        // Triangulate all boundary cones and keep the edges crossing the track
+        let mut points: Vec<(f64, f64)> = blue.clone();
+        points.extend(yellow.iter());
+        let n_blue: usize = blue.len();
+        let mut crossings: Vec<CrossingPoint> = Vec::new();
+        for (a, b) in delaunay_edges(&points) {
+            if a < n_blue && b >= n_blue {
+                crossings.push(CrossingPoint::new(points[a], points[b]));
+            } else if b < n_blue && a >= n_blue {
+                crossings.push(CrossingPoint::new(points[b], points[a]));
+            }
+        }
+
+        if crossings.is_empty() {
+            panic!(
+                "No triangle edge joins a blue and a yellow cone, the {} cones may be collinear \
+                 or duplicated",
+                points.len()
+            );
+        }
+
+        // Edges reaching across hairpins or infield are much longer than a normal crossing
+        let cross_length = |c: &CrossingPoint| f64::sqrt(c.across.0.powi(2) + c.across.1.powi(2));
+        let mut edge_lengths: Vec<f64> = crossings.iter().map(cross_length).collect();
+        edge_lengths.sort_by(|a, b| a.total_cmp(b));
+        let median_length: f64 = edge_lengths[edge_lengths.len() / 2];
+        crossings.retain(|c| cross_length(c) <= EDGE_LENGTH_FACTOR * median_length);
+
+        let start: (f64, f64) = start_position(cones, &blue);
+        let order: Vec<usize> = order_crossings(&crossings, start, median_length, is_closed);
+        if order.len() < 2 {
+            panic!("Could not find a centreline through the cone map");
+        }
+        let positions: Vec<(f64, f64)> = order.iter().map(|&i| crossings[i].position).collect();
+
+        // Tangents from central differences over the chord lengths
+        let n: usize = order.len();
+        let mut s: Vec<f64> = vec![0.0; n];
+        for i in 1..n {
+            s[i] = s[i - 1] + distance(positions[i - 1], positions[i]);
+        }
+        let loop_length: f64 = s[n - 1] + distance(positions[n - 1], positions[0]);
+        let neighbours = |i: usize| -> (usize, f64, usize, f64) {
+            if is_closed {
+                let prev: usize = (i + n - 1) % n;
+                let next: usize = (i + 1) % n;
+                let s_prev: f64 = if i == 0 {
+                    s[prev] - loop_length
+                } else {
+                    s[prev]
+                };
+                let s_next: f64 = if i == n - 1 { loop_length } else { s[next] };
+                return (prev, s_prev, next, s_next);
+            }
+            let prev: usize = i.saturating_sub(1);
+            let next: usize = (i + 1).min(n - 1);
+            return (prev, s[prev], next, s[next]);
+        };
+        let mut tangents: Vec<(f64, f64)> = Vec::with_capacity(n);
+        for i in 0..n {
+            let (prev, _, next, _) = neighbours(i);
+            let chord: f64 = distance(positions[prev], positions[next]);
+            tangents.push((
+                (positions[next].0 - positions[prev].0) / chord,
+                (positions[next].1 - positions[prev].1) / chord,
+            ));
+        }
+
+        // The width is the crossing edge projected onto the lateral direction
+        let widths: Vec<f64> = order
+            .iter()
+            .zip(tangents.iter())
+            .map(|(&i, t)| (-t.1 * crossings[i].across.0 + t.0 * crossings[i].across.1).abs())
+            .collect();
+
+        let mut samples: Vec<fit::HermiteSample> = Vec::with_capacity(n + 1);
+        for i in 0..n {
+            let (prev, s_prev, next, s_next) = neighbours(i);
+            let dwidth: f64 = (widths[next] - widths[prev]) / (s_next - s_prev);
+            let (x, y) = positions[i];
+            let (tx, ty) = tangents[i];
+            samples.push((s[i], (x, y, widths[i]), (tx, ty, dwidth)));
+        }
+        if is_closed {
+            let (_, point, derivative) = samples[0];
+            samples.push((loop_length, point, derivative));
+        }
+
+        let points: Vec<(f64, f64, f64)> = fit::hermite_points(&samples);
+        let n_segments: usize = (points.len() - 1) / 3;
+        return Self::new(name.to_string(), is_closed, n_segments, points);
+    }
+}
+
+// Parse a cone CSV with columns x, y, colour. A header line and blank lines are skipped.
+pub fn parse_cone_csv(text: &str) -> Vec<Cone> {
+    let mut cones: Vec<Cone> = Vec::new();
+    for (line_number, line) in text.lines().enumerate() {
+        let line: &str = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+        if fields.len() < 3 {
+            panic!(
+                "Invalid cone file: line {} has {} fields, expected x,y,colour",
+                line_number + 1,
+                fields.len()
+            );
+        }
+        let (x, y) = match (fields[0].parse::<f64>(), fields[1].parse::<f64>()) {
+            (Ok(x), Ok(y)) if x.is_finite() && y.is_finite() => (x, y),
+            _ if line_number == 0 => continue, // Header line
+            _ => panic!(
+                "Invalid cone file: bad coordinates on line {}",
+                line_number + 1
+            ),
+        };
+        let colour: ConeColour = match fields[2].to_lowercase().as_str() {
+            "blue" | "b" => ConeColour::Blue,
+            "yellow" | "y" => ConeColour::Yellow,
+            "orange" | "o" | "small_orange" => ConeColour::Orange,
+            "big_orange" | "orange_big" | "large_orange" => ConeColour::BigOrange,
+            other => panic!(
+                "Invalid cone file: unknown colour \"{}\" on line {}",
+                other,
+                line_number + 1
+            ),
+        };
+        cones.push(Cone { x, y, colour });
+    }
+    return cones;
+}
+
+// CENTRELINE ORDERING +++++++++++++++++++++++++++++
+struct CrossingPoint {
+    position: (f64, f64),
+    tangent: (f64, f64), // Driving direction, with the blue cone on the left
+    across: (f64, f64),  // From the yellow to the blue cone
+}
+
+impl CrossingPoint {
+    fn new(blue: (f64, f64), yellow: (f64, f64)) -> Self {
+        let length: f64 = distance(blue, yellow);
+        let lateral: (f64, f64) = ((blue.0 - yellow.0) / length, (blue.1 - yellow.1) / length);
+        return Self {
+            position: (0.5 * (blue.0 + yellow.0), 0.5 * (blue.1 + yellow.1)),
+            tangent: (lateral.1, -lateral.0),
+            across: (blue.0 - yellow.0, blue.1 - yellow.1),
+        };
+    }
+}
+
+// Walk from the crossing closest to the start, always stepping to the nearest crossing ahead.
+// Open tracks are also walked backwards from the start, so the start may lie anywhere on them.
+fn order_crossings(
+    crossings: &[CrossingPoint],
+    start: (f64, f64),
+    median_length: f64,
+    is_closed: bool,
+) -> Vec<usize> {
+    let mut visited: Vec<bool> = vec![false; crossings.len()];
+    let mut first: usize = 0;
+    for (i, crossing) in crossings.iter().enumerate() {
+        if distance(crossing.position, start) < distance(crossings[first].position, start) {
+            first = i;
+        }
+    }
+    visited[first] = true;
+
+    let forward: Vec<usize> = walk(crossings, &mut visited, first, 1.0, median_length);
+    if is_closed {
+        return forward;
+    }
+    let mut order: Vec<usize> = walk(crossings, &mut visited, first, -1.0, median_length);
+    order.reverse();
+    order.pop(); // The first crossing is already part of the forward walk
+    order.extend(forward);
+    return order;
+}
+
+// Visit crossings from first in the driving direction (direction = 1) or against it (-1)
+fn walk(
+    crossings: &[CrossingPoint],
+    visited: &mut [bool],
+    first: usize,
+    direction: f64,
+    median_length: f64,
+) -> Vec<usize> {
+    let max_step: f64 = EDGE_LENGTH_FACTOR * median_length;
+    let mut order: Vec<usize> = vec![first];
+    let mut current: usize = first;
+    let mut heading: (f64, f64) = (
+        direction * crossings[first].tangent.0,
+        direction * crossings[first].tangent.1,
+    );
+
+    loop {
+        let mut best: Option<usize> = None;
+        let mut best_distance: f64 = max_step;
+        let here: (f64, f64) = crossings[current].position;
+        for (i, crossing) in crossings.iter().enumerate() {
+            if visited[i] {
+                continue;
+            }
+            let step: (f64, f64) = (crossing.position.0 - here.0, crossing.position.1 - here.1);
+            let step_length: f64 = distance(crossing.position, here);
+            let ahead: bool = step.0 * heading.0 + step.1 * heading.1 > 0.0;
+            let aligned: bool =
+                direction * (crossing.tangent.0 * heading.0 + crossing.tangent.1 * heading.1) > 0.0;
+            if step_length < MIN_POINT_SPACING && aligned {
+                // Practically the same centreline point
+                visited[i] = true;
+                continue;
+            }
+            if ahead && aligned && step_length < best_distance {
+                best = Some(i);
+                best_distance = step_length;
+            }
+        }
+
+        match best {
+            Some(next) => {
+                let position: (f64, f64) = crossings[next].position;
+                heading = (
+                    (position.0 - here.0) / best_distance,
+                    (position.1 - here.1) / best_distance,
+                );
+                visited[next] = true;
+                order.push(next);
+                current = next;
+            }
+            None => break,
+        }
+    }
+
+    return order;
+}
+
+// The start line is marked by big orange cones, otherwise the first blue cone in the file is used
+fn start_position(cones: &[Cone], blue: &[(f64, f64)]) -> (f64, f64) {
+    let big_orange: Vec<(f64, f64)> = cones_of_colour(cones, ConeColour::BigOrange);
+    if big_orange.is_empty() {
+        return blue[0];
+    }
+    let n: f64 = big_orange.len() as f64;
+    let x: f64 = big_orange.iter().map(|p| p.0).sum::<f64>() / n;
+    let y: f64 = big_orange.iter().map(|p| p.1).sum::<f64>() / n;
+    return (x, y);
+}
+
+fn cones_of_colour(cones: &[Cone], colour: ConeColour) -> Vec<(f64, f64)> {
+    return cones
+        .iter()
+        .filter(|c| c.colour == colour)
+        .map(|c| (c.x, c.y))
+        .collect();
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    return f64::sqrt((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2));
+}
+
+// DELAUNAY TRIANGULATION ++++++++++++++++++++++++++
+// Bowyer-Watson algorithm, returning the unique edges (i, j) with i < j
+fn delaunay_edges(points: &[(f64, f64)]) -> Vec<(usize, usize)> {
+    let n: usize = points.len();
+
+    // Super triangle enclosing all points, stored after the real points
+    let (mut x_min, mut y_min) = (f64::INFINITY, f64::INFINITY);
+    let (mut x_max, mut y_max) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for &(x, y) in points {
+        x_min = x_min.min(x);
+        y_min = y_min.min(y);
+        x_max = x_max.max(x);
+        y_max = y_max.max(y);
+    }
+    let span: f64 = (x_max - x_min).max(y_max - y_min).max(1.0);
+    let (xc, yc) = (0.5 * (x_min + x_max), 0.5 * (y_min + y_max));
+    let mut vertices: Vec<(f64, f64)> = points.to_vec();
+    vertices.push((xc - 20.0 * span, yc - 10.0 * span));
+    vertices.push((xc + 20.0 * span, yc - 10.0 * span));
+    vertices.push((xc, yc + 20.0 * span));
+
+    let mut triangles: Vec<[usize; 3]> = vec![[n, n + 1, n + 2]];
+    for p in 0..n {
+        let point: (f64, f64) = vertices[p];
+        let (bad, good): (Vec<[usize; 3]>, Vec<[usize; 3]>) = triangles
+            .into_iter()
+            .partition(|t| in_circumcircle(&vertices, t, point));
+
+        // Edges of the cavity are those belonging to exactly one bad triangle
+        let mut cavity: Vec<(usize, usize)> = Vec::new();
+        for t in &bad {
+            for k in 0..3 {
+                let edge: (usize, usize) = ordered_edge(t[k], t[(k + 1) % 3]);
+                match cavity.iter().position(|e| *e == edge) {
+                    Some(i) => {
+                        cavity.swap_remove(i);
+                    }
+                    None => cavity.push(edge),
+                }
+            }
+        }
+
+        triangles = good;
+        for (a, b) in cavity {
+            triangles.push([a, b, p]);
+        }
+    }
+
+    let mut edges: Vec<(usize, usize)> = Vec::new();
+    for t in &triangles {
+        if t.iter().any(|&v| v >= n) {
+            continue;
+        }
+        for k in 0..3 {
+            let edge: (usize, usize) = ordered_edge(t[k], t[(k + 1) % 3]);
+            if !edges.contains(&edge) {
+                edges.push(edge);
+            }
+        }
+    }
+    return edges;
+}
+
+fn ordered_edge(a: usize, b: usize) -> (usize, usize) {
+    return if a < b { (a, b) } else { (b, a) };
+}
+
+fn in_circumcircle(vertices: &[(f64, f64)], triangle: &[usize; 3], p: (f64, f64)) -> bool {
+    let (ax, ay) = (vertices[triangle[0]].0 - p.0, vertices[triangle[0]].1 - p.1);
+    let (bx, by) = (vertices[triangle[1]].0 - p.0, vertices[triangle[1]].1 - p.1);
+    let (cx, cy) = (vertices[triangle[2]].0 - p.0, vertices[triangle[2]].1 - p.1);
+    let det: f64 = (ax * ax + ay * ay) * (bx * cy - cx * by)
+        - (bx * bx + by * by) * (ax * cy - cx * ay)
+        + (cx * cx + cy * cy) * (ax * by - bx * ay);
+
+    // The sign of the determinant depends on the orientation of the triangle
+    let orientation: f64 = (vertices[triangle[1]].0 - vertices[triangle[0]].0)
+        * (vertices[triangle[2]].1 - vertices[triangle[0]].1)
+        - (vertices[triangle[2]].0 - vertices[triangle[0]].0)
+            * (vertices[triangle[1]].1 - vertices[triangle[0]].1);
+    return det * orientation.signum() > 0.0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::track::TrackFrame;
+
+    // Counter-clockwise ring with the blue cones on the inside, staggered against the yellow ones
+    fn ring_cones(radius: f64, width: f64, n: usize) -> Vec<Cone> {
+        let mut cones: Vec<Cone> = Vec::new();
+        for i in 0..n {
+            let theta_blue: f64 = 2.0 * std::f64::consts::PI * (i as f64) / (n as f64);
+            let theta_yellow: f64 = theta_blue + std::f64::consts::PI / (n as f64);
+            cones.push(Cone {
+                x: (radius - width / 2.0) * theta_blue.cos(),
+                y: (radius - width / 2.0) * theta_blue.sin(),
+                colour: ConeColour::Blue,
+            });
+            cones.push(Cone {
+                x: (radius + width / 2.0) * theta_yellow.cos(),
+                y: (radius + width / 2.0) * theta_yellow.sin(),
+                colour: ConeColour::Yellow,
+            });
+        }
+        return cones;
+    }
+
+    #[test]
+    fn test_ring_is_closed_loop() {
+        let radius: f64 = 20.0;
+        let cones: Vec<Cone> = ring_cones(radius, 3.0, 36);
+        let track: Track = Track::from_cones("Ring", &cones, true);
+
+        assert!(track.is_closed());
+        let circumference: f64 = 2.0 * std::f64::consts::PI * radius;
+        assert!((track.length() - circumference).abs() / circumference < 0.01);
+
+        let s_lap_q: Vec<f64> = (0..100)
+            .map(|i| track.length() * i as f64 / 100.0)
+            .collect();
+        let frames: Box<Vec<TrackFrame>> = track.discretise(s_lap_q);
+        for frame in frames.iter() {
+            let (x, y) = frame.position();
+            let r: f64 = f64::sqrt(x.powi(2) + y.powi(2));
+            assert!((r - radius).abs() < 0.1);
+            assert!((frame.width() - 3.0).abs() < 0.2);
+
+            // Counter-clockwise, so the lateral direction points to the centre
+            let (nx, ny) = frame.lateral();
+            assert!(nx * x + ny * y < 0.0);
+        }
+    }
+
+    #[test]
+    fn test_straight_direction_follows_blue_on_left() {
+        let mut cones: Vec<Cone> = Vec::new();
+        for i in 0..10 {
+            cones.push(Cone {
+                x: 5.0 * i as f64,
+                y: -1.5,
+                colour: ConeColour::Blue,
+            });
+            cones.push(Cone {
+                x: 5.0 * i as f64 + 2.5,
+                y: 1.5,
+                colour: ConeColour::Yellow,
+            });
+        }
+        let track: Track = Track::from_cones("Straight", &cones, false);
+
+        assert!(!track.is_closed());
+        let frames: Box<Vec<TrackFrame>> = track.discretise(vec![0.0, track.length()]);
+        // Blue cones are at negative y, so the car drives in the negative x direction
+        assert!(frames[0].position().0 > frames[1].position().0);
+        assert!((frames[0].tangent().0 + 1.0).abs() < 1e-6);
+        assert!((frames[0].width() - 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    #[should_panic(expected = "No triangle edge joins a blue and a yellow cone")]
+    fn test_collinear_cones() {
+        let cones: Vec<Cone> = [0.0, 5.0, 10.0, 15.0]
+            .iter()
+            .enumerate()
+            .map(|(i, x)| Cone {
+                x: *x,
+                y: 0.0,
+                colour: if i < 2 {
+                    ConeColour::Blue
+                } else {
+                    ConeColour::Yellow
+                },
+            })
+            .collect();
+        Track::from_cones("Line", &cones, false);
+    }
+
+    #[test]
+    fn test_parse_cone_csv() {
+        let text: &str =
+            "x,y,colour\n0.0,1.5,blue\n0.0,-1.5,yellow\n\n# comment\n-3,0,big_orange\n";
+        let cones: Vec<Cone> = parse_cone_csv(text);
+
+        assert_eq!(cones.len(), 3);
+        assert_eq!(cones[0].colour, ConeColour::Blue);
+        assert!((cones[1].y + 1.5).abs() < 1e-12);
+        assert_eq!(cones[2].colour, ConeColour::BigOrange);
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown colour")]
+    fn test_parse_cone_csv_rejects_unknown_colour() {
+        parse_cone_csv("1.0,2.0,purple\n");
+    }
+
+    #[test]
+    fn test_delaunay_square() {
+        let points: Vec<(f64, f64)> = vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.1), (0.0, 1.0)];
+        let edges: Vec<(usize, usize)> = delaunay_edges(&points);
+        // Four sides and one diagonal
+        assert_eq!(edges.len(), 5);
+    }
+}
@@ -0,0 +1,217 @@
+// Fitting of CubicBezierSegment control points to arbitrary centreline descriptions.
+// Each piece is built as a cubic Hermite interpolant of the curve, which is then written
+// in Bezier form. Pieces are split in half until they match the curve within tolerance.
+
+const MAX_DEPTH: usize = 16;
+const FD_STEP_REL: f64 = 1e-4;
+
+// (s, (x, y, width), (dx/ds, dy/ds, dwidth/ds))
+pub type HermiteSample = (f64, (f64, f64, f64), (f64, f64, f64));
+
+// Fit cubic Bezier control points to a curve given as a function of arc length.
+//
+// curve(s) returns (x, y, width) at arc length s. breakpoints must start at 0 and end at the
+// total length, and mark every point where the derivative of the curve may be discontinuous.
+// The returned points are laid out as expected by Track::new, i.e. 3 * n_segments + 1 points.
+pub fn fit_curve<F: Fn(f64) -> (f64, f64, f64)>(
+    curve: &F,
+    breakpoints: &[f64],
+    tolerance: f64,
+) -> Vec<(f64, f64, f64)> {
+    assert!(
+        breakpoints.len() >= 2,
+        "At least two breakpoints are required to fit a curve"
+    );
+
+    let mut points: Vec<(f64, f64, f64)> = Vec::new();
+    points.push(curve(breakpoints[0]));
+    for i in 0..breakpoints.len() - 1 {
+        let s0: f64 = breakpoints[i];
+        let s1: f64 = breakpoints[i + 1];
+        if s1 <= s0 {
+            panic!(
+                "Breakpoints must be strictly increasing, got {} and {}",
+                s0, s1
+            );
+        }
+        fit_interval(curve, s0, s1, tolerance, 0, &mut points);
+    }
+
+    return points;
+}
+
+// Build control points from samples with known derivatives with respect to arc length.
+//
+// One segment is created between every pair of consecutive samples.
+pub fn hermite_points(samples: &[HermiteSample]) -> Vec<(f64, f64, f64)> {
+    assert!(
+        samples.len() >= 2,
+        "At least two samples are required to build a segment"
+    );
+
+    let mut points: Vec<(f64, f64, f64)> = Vec::with_capacity(3 * (samples.len() - 1) + 1);
+    points.push(samples[0].1);
+    for i in 0..samples.len() - 1 {
+        let (s0, p0, d0) = samples[i];
+        let (s1, p3, d1) = samples[i + 1];
+        let (p1, p2) = hermite_inner_points(p0, d0, p3, d1, s1 - s0);
+        points.push(p1);
+        points.push(p2);
+        points.push(p3);
+    }
+
+    return points;
+}
+
+fn fit_interval<F: Fn(f64) -> (f64, f64, f64)>(
+    curve: &F,
+    s0: f64,
+    s1: f64,
+    tolerance: f64,
+    depth: usize,
+    points: &mut Vec<(f64, f64, f64)>,
+) {
+    let length: f64 = s1 - s0;
+    let p0: (f64, f64, f64) = curve(s0);
+    let p3: (f64, f64, f64) = curve(s1);
+    let d0: (f64, f64, f64) = one_sided_derivative(curve, s0, FD_STEP_REL * length);
+    let d1: (f64, f64, f64) = one_sided_derivative(curve, s1, -FD_STEP_REL * length);
+    let (p1, p2) = hermite_inner_points(p0, d0, p3, d1, length);
+
+    // Compare the candidate segment with the curve at a few interior points
+    let mut max_error: f64 = 0.0;
+    for &t in &[0.25, 0.5, 0.75] {
+        let (xb, yb, wb) = bezier_point(p0, p1, p2, p3, t);
+        let (xc, yc, wc) = curve(s0 + t * length);
+        let position_error: f64 = f64::sqrt((xb - xc).powi(2) + (yb - yc).powi(2));
+        let width_error: f64 = (wb - wc).abs();
+        max_error = max_error.max(position_error).max(width_error);
+    }
+
+    if max_error > tolerance && depth < MAX_DEPTH {
+        let s_mid: f64 = 0.5 * (s0 + s1);
+        fit_interval(curve, s0, s_mid, tolerance, depth + 1, points);
+        fit_interval(curve, s_mid, s1, tolerance, depth + 1, points);
+        return;
+    }
+
+    points.push(p1);
+    points.push(p2);
+    points.push(p3);
+}
+
+// Second order one-sided finite difference, stepping into the interval with step h
+fn one_sided_derivative<F: Fn(f64) -> (f64, f64, f64)>(
+    curve: &F,
+    s: f64,
+    h: f64,
+) -> (f64, f64, f64) {
+    let f0: (f64, f64, f64) = curve(s);
+    let f1: (f64, f64, f64) = curve(s + h);
+    let f2: (f64, f64, f64) = curve(s + 2.0 * h);
+    return (
+        (-3.0 * f0.0 + 4.0 * f1.0 - f2.0) / (2.0 * h),
+        (-3.0 * f0.1 + 4.0 * f1.1 - f2.1) / (2.0 * h),
+        (-3.0 * f0.2 + 4.0 * f1.2 - f2.2) / (2.0 * h),
+    );
+}
+
+// The inner control points of the Bezier form of a cubic Hermite interpolant over a span of
+// the given length, where d0 and d1 are derivatives with respect to arc length
+fn hermite_inner_points(
+    p0: (f64, f64, f64),
+    d0: (f64, f64, f64),
+    p3: (f64, f64, f64),
+    d1: (f64, f64, f64),
+    length: f64,
+) -> ((f64, f64, f64), (f64, f64, f64)) {
+    let p1: (f64, f64, f64) = (
+        p0.0 + d0.0 * length / 3.0,
+        p0.1 + d0.1 * length / 3.0,
+        p0.2 + d0.2 * length / 3.0,
+    );
+    let p2: (f64, f64, f64) = (
+        p3.0 - d1.0 * length / 3.0,
+        p3.1 - d1.1 * length / 3.0,
+        p3.2 - d1.2 * length / 3.0,
+    );
+    return (p1, p2);
+}
+
+fn bezier_point(
+    p0: (f64, f64, f64),
+    p1: (f64, f64, f64),
+    p2: (f64, f64, f64),
+    p3: (f64, f64, f64),
+    t: f64,
+) -> (f64, f64, f64) {
+    let b0: f64 = (1.0 - t).powi(3);
+    let b1: f64 = 3.0 * t * (1.0 - t).powi(2);
+    let b2: f64 = 3.0 * t.powi(2) * (1.0 - t);
+    let b3: f64 = t.powi(3);
+    return (
+        b0 * p0.0 + b1 * p1.0 + b2 * p2.0 + b3 * p3.0,
+        b0 * p0.1 + b1 * p1.1 + b2 * p2.1 + b3 * p3.1,
+        b0 * p0.2 + b1 * p1.2 + b2 * p2.2 + b3 * p3.2,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_straight_line() {
+        let curve = |s: f64| (s, 2.0 * s, 4.0);
+        let points: Vec<(f64, f64, f64)> = fit_curve(&curve, &[0.0, 10.0], 1e-6);
+
+        // A straight line needs exactly one segment
+        assert_eq!(points.len(), 4);
+        assert!((points[3].0 - 10.0).abs() < 1e-9);
+        assert!((points[3].1 - 20.0).abs() < 1e-9);
+        assert!((points[1].2 - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_fit_circle_within_tolerance() {
+        let radius: f64 = 50.0;
+        let curve = |s: f64| {
+            let theta: f64 = s / radius;
+            (radius * theta.sin(), radius * (1.0 - theta.cos()), 6.0)
+        };
+        let length: f64 = std::f64::consts::PI * radius;
+        let tolerance: f64 = 1e-3;
+        let points: Vec<(f64, f64, f64)> = fit_curve(&curve, &[0.0, length], tolerance);
+
+        let n_segments: usize = (points.len() - 1) / 3;
+        assert!(n_segments > 1);
+        for i in 0..n_segments {
+            let (x, y, _w) = bezier_point(
+                points[3 * i],
+                points[3 * i + 1],
+                points[3 * i + 2],
+                points[3 * i + 3],
+                0.5,
+            );
+            let r: f64 = f64::sqrt(x.powi(2) + (y - radius).powi(2));
+            assert!((r - radius).abs() < 2.0 * tolerance);
+        }
+    }
+
+    #[test]
+    fn test_hermite_points_layout() {
+        let samples: Vec<HermiteSample> = vec![
+            (0.0, (0.0, 0.0, 3.0), (1.0, 0.0, 0.0)),
+            (3.0, (3.0, 0.0, 3.0), (1.0, 0.0, 0.0)),
+            (6.0, (6.0, 0.0, 3.0), (1.0, 0.0, 0.0)),
+        ];
+        let points: Vec<(f64, f64, f64)> = hermite_points(&samples);
+
+        assert_eq!(points.len(), 7);
+        for (i, point) in points.iter().enumerate() {
+            assert!((point.0 - i as f64).abs() < 1e-12);
+            assert!((point.1 - 0.0).abs() < 1e-12);
+            assert!((point.2 - 3.0).abs() < 1e-12);
+        }
+    }
+}
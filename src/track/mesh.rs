@@ -0,0 +1,406 @@
+// Triangle mesh of the track surface for 3D viewers.
+//
+// The road ribbon is built from TrackFrames sampled along the centreline, with a row of
+// vertices across the track at every station. Each vertex carries its curvilinear
+// coordinates (s, n), where n is the lateral offset from the centreline (positive to the
+// left), so results sampled along the lap can be colour-mapped onto the surface.
+use super::{Track, TrackFrame};
+
+pub struct TrackMesh {
+    // Private with getters
+    positions: Vec<(f64, f64, f64)>,
+    normals: Vec<(f64, f64, f64)>,
+    s: Vec<f64>,
+    n: Vec<f64>,
+    triangles: Vec<[u32; 3]>,
+    // Private without getters
+    attributes: Vec<(String, Vec<f64>)>, // Additional named per-vertex scalars
+}
+
+impl TrackMesh {
+    // Tessellate a flat track with stations at most resolution metres apart
+    // and n_lateral vertices across the track
+    #[allow(dead_code)]
+    pub fn tessellate(track: &Track, resolution: f64, n_lateral: usize) -> Self {
+        return Self::tessellate_with_elevation(track, resolution, n_lateral, |_s: f64| 0.0);
+    }
+
+    // Tessellate with the elevation of the centreline given as a function of s
+    pub fn tessellate_with_elevation<F: Fn(f64) -> f64>(
+        track: &Track,
+        resolution: f64,
+        n_lateral: usize,
+        elevation: F,
+    ) -> Self {
+        if resolution.is_nan() || resolution <= 0.0 {
+            panic!("Mesh resolution must be positive, got {}", resolution);
+        }
+        if n_lateral < 2 {
+            panic!("At least two vertices across the track are required");
+        }
+
+        let n_stations: usize = (track.length() / resolution).ceil() as usize + 1;
+        let s_lap_q: Vec<f64> = (0..n_stations)
+            .map(|i| track.length() * (i as f64) / ((n_stations - 1) as f64))
+            .collect();
+        let frames: Box<Vec<TrackFrame>> = track.discretise(s_lap_q.clone());
+
+        let n_vertices: usize = n_stations * n_lateral;
+        let mut positions: Vec<(f64, f64, f64)> = Vec::with_capacity(n_vertices);
+        let mut s: Vec<f64> = Vec::with_capacity(n_vertices);
+        let mut n: Vec<f64> = Vec::with_capacity(n_vertices);
+        for (frame, &s_lap) in frames.iter().zip(s_lap_q.iter()) {
+            let (xc, yc) = frame.position();
+            let (nx, ny) = frame.lateral();
+            let z: f64 = elevation(s_lap);
+            // From the left edge to the right edge
+            for j in 0..n_lateral {
+                let offset: f64 = frame.width() * (0.5 - (j as f64) / ((n_lateral - 1) as f64));
+                positions.push((xc + offset * nx, yc + offset * ny, z));
+                s.push(s_lap);
+                n.push(offset);
+            }
+        }
+
+        // Two triangles per quad, counter-clockwise when seen from above
+        let mut triangles: Vec<[u32; 3]> =
+            Vec::with_capacity(2 * (n_stations - 1) * (n_lateral - 1));
+        for i in 0..n_stations - 1 {
+            for j in 0..n_lateral - 1 {
+                let a: u32 = (i * n_lateral + j) as u32;
+                let b: u32 = (i * n_lateral + j + 1) as u32;
+                let c: u32 = ((i + 1) * n_lateral + j) as u32;
+                let d: u32 = ((i + 1) * n_lateral + j + 1) as u32;
+                triangles.push([a, b, c]);
+                triangles.push([b, d, c]);
+            }
+        }
+
+        let normals: Vec<(f64, f64, f64)> = vertex_normals(&positions, &triangles);
+
+        return Self {
+            positions,
+            normals,
+            s,
+            n,
+            triangles,
+            attributes: Vec::new(),
+        };
+    }
+
+    // Add a named per-vertex scalar evaluated at the vertex coordinates (s, n),
+    // e.g. an interpolated speed profile
+    #[allow(dead_code)]
+    pub fn add_attribute<F: Fn(f64, f64) -> f64>(&mut self, name: &str, f: F) {
+        let values: Vec<f64> = self
+            .s
+            .iter()
+            .zip(self.n.iter())
+            .map(|(&s, &n)| f(s, n))
+            .collect();
+        self.attributes.push((name.to_string(), values));
+    }
+
+    // WAVEFRONT OBJ ++++++++++++++++++++++++++++++++
+    #[allow(dead_code)]
+    pub fn write_obj(&self, file_path: &str) {
+        match std::fs::write(file_path, self.to_obj_string()) {
+            Ok(_) => {}
+            Err(e) => panic!("Failed to write OBJ file {}: {}", file_path, e),
+        }
+    }
+
+    // OBJ has no custom vertex attributes, so (s, n) are stored as texture coordinates
+    pub fn to_obj_string(&self) -> String {
+        let mut obj: String = String::new();
+        obj.push_str("# Track surface mesh, texture coordinates are (s, n) in metres\n");
+        for &(x, y, z) in &self.positions {
+            obj.push_str(&format!("v {} {} {}\n", x, y, z));
+        }
+        for (&s, &n) in self.s.iter().zip(self.n.iter()) {
+            obj.push_str(&format!("vt {} {}\n", s, n));
+        }
+        for &(x, y, z) in &self.normals {
+            obj.push_str(&format!("vn {} {} {}\n", x, y, z));
+        }
+        for triangle in &self.triangles {
+            // OBJ indices are 1-based
+            let (a, b, c) = (triangle[0] + 1, triangle[1] + 1, triangle[2] + 1);
+            obj.push_str(&format!("f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}\n"));
+        }
+        return obj;
+    }
+
+    // BINARY GLTF ++++++++++++++++++++++++++++++++++
+    #[allow(dead_code)]
+    pub fn write_glb(&self, file_path: &str) {
+        match std::fs::write(file_path, self.to_glb_bytes()) {
+            Ok(_) => {}
+            Err(e) => panic!("Failed to write glTF file {}: {}", file_path, e),
+        }
+    }
+
+    // Binary glTF 2.0 with s, n and any added attributes as custom float attributes
+    pub fn to_glb_bytes(&self) -> Vec<u8> {
+        // Binary buffer: positions, normals, s, n, added attributes, indices
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut views: Vec<(usize, usize, u32)> = Vec::new(); // (offset, length, target)
+        let mut accessors: Vec<String> = Vec::new();
+        let mut attribute_entries: Vec<String> = Vec::new();
+        let n_vertices: usize = self.positions.len();
+
+        for (name, values) in [("POSITION", &self.positions), ("NORMAL", &self.normals)] {
+            let offset: usize = buffer.len();
+            let mut min: [f32; 3] = [f32::INFINITY; 3];
+            let mut max: [f32; 3] = [f32::NEG_INFINITY; 3];
+            for &(x, y, z) in values.iter() {
+                for (k, v) in [x as f32, y as f32, z as f32].iter().enumerate() {
+                    buffer.extend_from_slice(&v.to_le_bytes());
+                    min[k] = min[k].min(*v);
+                    max[k] = max[k].max(*v);
+                }
+            }
+            views.push((offset, buffer.len() - offset, 34962)); // ARRAY_BUFFER
+            accessors.push(format!(
+                "{{\"bufferView\":{},\"componentType\":5126,\"count\":{},\"type\":\"VEC3\",\"min\":[{},{},{}],\"max\":[{},{},{}]}}",
+                views.len() - 1,
+                n_vertices,
+                min[0],
+                min[1],
+                min[2],
+                max[0],
+                max[1],
+                max[2]
+            ));
+            attribute_entries.push(format!("\"{}\":{}", name, accessors.len() - 1));
+        }
+
+        let mut scalar_views: Vec<(String, &Vec<f64>)> =
+            vec![("_S".to_string(), &self.s), ("_N".to_string(), &self.n)];
+        for (name, values) in &self.attributes {
+            scalar_views.push((format!("_{}", name.to_uppercase()), values));
+        }
+        for (name, values) in scalar_views {
+            let offset: usize = buffer.len();
+            for &v in values.iter() {
+                buffer.extend_from_slice(&(v as f32).to_le_bytes());
+            }
+            views.push((offset, buffer.len() - offset, 34962)); // ARRAY_BUFFER
+            accessors.push(format!(
+                "{{\"bufferView\":{},\"componentType\":5126,\"count\":{},\"type\":\"SCALAR\"}}",
+                views.len() - 1,
+                n_vertices
+            ));
+            attribute_entries.push(format!(
+                "\"{}\":{}",
+                escape_json(&name),
+                accessors.len() - 1
+            ));
+        }
+
+        let offset: usize = buffer.len();
+        for triangle in &self.triangles {
+            for &index in triangle {
+                buffer.extend_from_slice(&index.to_le_bytes());
+            }
+        }
+        views.push((offset, buffer.len() - offset, 34963)); // ELEMENT_ARRAY_BUFFER
+        accessors.push(format!(
+            "{{\"bufferView\":{},\"componentType\":5125,\"count\":{},\"type\":\"SCALAR\"}}",
+            views.len() - 1,
+            3 * self.triangles.len()
+        ));
+        let indices_accessor: usize = accessors.len() - 1;
+
+        // All views are 4-byte aligned since every component is 4 bytes
+        let buffer_views: Vec<String> = views
+            .iter()
+            .map(|(offset, length, target)| {
+                format!(
+                    "{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":{}}}",
+                    offset, length, target
+                )
+            })
+            .collect();
+        let json: String = format!(
+            "{{\"asset\":{{\"version\":\"2.0\",\"generator\":\"Apex\"}},\"scene\":0,\"scenes\":[{{\"nodes\":[0]}}],\"nodes\":[{{\"mesh\":0,\"name\":\"track\"}}],\"meshes\":[{{\"primitives\":[{{\"attributes\":{{{}}},\"indices\":{},\"mode\":4}}]}}],\"accessors\":[{}],\"bufferViews\":[{}],\"buffers\":[{{\"byteLength\":{}}}]}}",
+            attribute_entries.join(","),
+            indices_accessor,
+            accessors.join(","),
+            buffer_views.join(","),
+            buffer.len()
+        );
+
+        // Chunks are padded to 4 bytes, JSON with spaces and binary data with zeros
+        let mut json_bytes: Vec<u8> = json.into_bytes();
+        while !json_bytes.len().is_multiple_of(4) {
+            json_bytes.push(b' ');
+        }
+        while !buffer.len().is_multiple_of(4) {
+            buffer.push(0);
+        }
+
+        let total_length: usize = 12 + 8 + json_bytes.len() + 8 + buffer.len();
+        let mut glb: Vec<u8> = Vec::with_capacity(total_length);
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(total_length as u32).to_le_bytes());
+        glb.extend_from_slice(&(json_bytes.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json_bytes);
+        glb.extend_from_slice(&(buffer.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&buffer);
+
+        return glb;
+    }
+
+    // Getters
+    #[allow(dead_code)]
+    pub fn positions(&self) -> &Vec<(f64, f64, f64)> {
+        return &self.positions;
+    }
+
+    #[allow(dead_code)]
+    pub fn normals(&self) -> &Vec<(f64, f64, f64)> {
+        return &self.normals;
+    }
+
+    #[allow(dead_code)]
+    pub fn s(&self) -> &Vec<f64> {
+        return &self.s;
+    }
+
+    #[allow(dead_code)]
+    pub fn n(&self) -> &Vec<f64> {
+        return &self.n;
+    }
+
+    #[allow(dead_code)]
+    pub fn triangles(&self) -> &Vec<[u32; 3]> {
+        return &self.triangles;
+    }
+}
+
+// Area-weighted average of the normals of the triangles around each vertex
+fn vertex_normals(positions: &[(f64, f64, f64)], triangles: &[[u32; 3]]) -> Vec<(f64, f64, f64)> {
+    let mut normals: Vec<(f64, f64, f64)> = vec![(0.0, 0.0, 0.0); positions.len()];
+    for triangle in triangles {
+        let a: (f64, f64, f64) = positions[triangle[0] as usize];
+        let b: (f64, f64, f64) = positions[triangle[1] as usize];
+        let c: (f64, f64, f64) = positions[triangle[2] as usize];
+        let u: (f64, f64, f64) = (b.0 - a.0, b.1 - a.1, b.2 - a.2);
+        let v: (f64, f64, f64) = (c.0 - a.0, c.1 - a.1, c.2 - a.2);
+        let cross: (f64, f64, f64) = (
+            u.1 * v.2 - u.2 * v.1,
+            u.2 * v.0 - u.0 * v.2,
+            u.0 * v.1 - u.1 * v.0,
+        );
+        for &index in triangle {
+            let normal: &mut (f64, f64, f64) = &mut normals[index as usize];
+            normal.0 += cross.0;
+            normal.1 += cross.1;
+            normal.2 += cross.2;
+        }
+    }
+
+    for normal in normals.iter_mut() {
+        let norm: f64 = f64::sqrt(normal.0.powi(2) + normal.1.powi(2) + normal.2.powi(2));
+        *normal = match norm > 0.0 {
+            true => (normal.0 / norm, normal.1 / norm, normal.2 / norm),
+            false => (0.0, 0.0, 1.0),
+        };
+    }
+    return normals;
+}
+
+fn escape_json(text: &str) -> String {
+    return text.replace('\\', "\\\\").replace('"', "\\\"");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tessellate_straight() {
+        let track: Track = Track::straight(100.0, 4.0);
+        let mesh: TrackMesh = TrackMesh::tessellate(&track, 10.0, 3);
+
+        // 11 stations with 3 vertices each, and 2 triangles per quad
+        assert_eq!(mesh.positions().len(), 33);
+        assert_eq!(mesh.triangles().len(), 40);
+
+        // The first row goes from the left edge to the right edge
+        assert!((mesh.positions()[0].1 - 2.0).abs() < 1e-9);
+        assert!((mesh.positions()[1].1 - 0.0).abs() < 1e-9);
+        assert!((mesh.positions()[2].1 + 2.0).abs() < 1e-9);
+        assert!((mesh.n()[0] - 2.0).abs() < 1e-9);
+        assert!((mesh.s()[32] - 100.0).abs() < 1e-9);
+
+        for normal in mesh.normals() {
+            assert!((normal.2 - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_tessellate_with_elevation_tilts_normals() {
+        let track: Track = Track::straight(100.0, 4.0);
+        let mesh: TrackMesh =
+            TrackMesh::tessellate_with_elevation(&track, 5.0, 2, |s: f64| 0.1 * s);
+
+        assert!((mesh.positions()[41].2 - 10.0).abs() < 1e-9);
+        // Climbing in x, so normals lean backwards
+        let normal: (f64, f64, f64) = mesh.normals()[10];
+        let expected: f64 = 1.0 / f64::sqrt(1.01);
+        assert!((normal.2 - expected).abs() < 1e-9);
+        assert!((normal.0 + 0.1 * expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_obj_output() {
+        let track: Track = Track::straight(10.0, 2.0);
+        let mesh: TrackMesh = TrackMesh::tessellate(&track, 5.0, 2);
+        let obj: String = mesh.to_obj_string();
+
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 6);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("vt ")).count(), 6);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("vn ")).count(), 6);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 4);
+        assert!(obj.contains("f 1/1/1 2/2/2 3/3/3"));
+    }
+
+    #[test]
+    fn test_glb_layout() {
+        let track: Track = Track::straight(10.0, 2.0);
+        let mut mesh: TrackMesh = TrackMesh::tessellate(&track, 5.0, 2);
+        mesh.add_attribute("speed", |s: f64, _n: f64| 10.0 + s);
+        let glb: Vec<u8> = mesh.to_glb_bytes();
+
+        assert_eq!(&glb[0..4], b"glTF");
+        assert_eq!(u32::from_le_bytes([glb[4], glb[5], glb[6], glb[7]]), 2);
+        assert_eq!(
+            u32::from_le_bytes([glb[8], glb[9], glb[10], glb[11]]) as usize,
+            glb.len()
+        );
+
+        let json_length: usize = u32::from_le_bytes([glb[12], glb[13], glb[14], glb[15]]) as usize;
+        assert_eq!(&glb[16..20], b"JSON");
+        assert_eq!(json_length % 4, 0);
+        let json: &str = std::str::from_utf8(&glb[20..20 + json_length]).unwrap();
+        assert!(json.contains("\"_S\":2"));
+        assert!(json.contains("\"_N\":3"));
+        assert!(json.contains("\"_SPEED\":4"));
+
+        // 6 vertices with 3 + 3 + 1 + 1 + 1 floats each, plus 12 indices
+        let bin_start: usize = 20 + json_length;
+        let bin_length: usize = u32::from_le_bytes([
+            glb[bin_start],
+            glb[bin_start + 1],
+            glb[bin_start + 2],
+            glb[bin_start + 3],
+        ]) as usize;
+        assert_eq!(&glb[bin_start + 4..bin_start + 8], b"BIN\0");
+        assert_eq!(bin_length, 6 * 9 * 4 + 12 * 4);
+    }
+}
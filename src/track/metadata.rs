@@ -0,0 +1,418 @@
+// Descriptive metadata stored alongside the track geometry, and search over a directory of
+// track files by these fields.
+use super::Track;
+use std::ops::RangeInclusive;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrackMetadata {
+    pub author: Option<String>,
+    pub country: Option<String>,
+    pub layout: Option<String>, // Configuration or layout name, e.g. "GP Circuit"
+    pub direction: Option<DrivingDirection>,
+    pub source: Option<String>, // Surveying source, e.g. "RTK GNSS survey" or "OpenDRIVE"
+    pub created: Option<String>, // Creation date as YYYY-MM-DD
+    pub origin: Option<GeoOrigin>,
+    pub reference_lap_time: Option<f64>, // Lap time used to validate simulations [s]
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DrivingDirection {
+    Clockwise,
+    CounterClockwise,
+}
+
+// Geographic position of the local (x, y) = (0, 0) with x pointing east and y north
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GeoOrigin {
+    pub latitude: f64,  // [deg]
+    pub longitude: f64, // [deg]
+    pub altitude: f64,  // [m]
+}
+
+// Fields to match when searching. Strings match case-insensitively as substrings, ranges
+// are inclusive and fields left as None match any track. Tracks without the queried field
+// never match a range.
+#[derive(Clone, Debug, Default)]
+pub struct TrackQuery {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub country: Option<String>,
+    pub layout: Option<String>,
+    pub direction: Option<DrivingDirection>,
+    pub source: Option<String>,
+    pub is_closed: Option<bool>,
+    pub created: Option<RangeInclusive<String>>, // Dates as YYYY-MM-DD
+    pub latitude: Option<RangeInclusive<f64>>,   // Of the origin [deg]
+    pub longitude: Option<RangeInclusive<f64>>,  // Of the origin [deg]
+    pub reference_lap_time: Option<RangeInclusive<f64>>, // [s]
+}
+
+// TRACKMETADATA IMPLEMENTATION ++++++++++++++++++++
+impl std::fmt::Display for TrackMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // One line per known field, so unknown metadata adds nothing
+        let mut lines: Vec<String> = Vec::new();
+        if let Some(layout) = &self.layout {
+            lines.push(format!("Layout: {}", layout));
+        }
+        if let Some(country) = &self.country {
+            lines.push(format!("Country: {}", country));
+        }
+        if let Some(direction) = &self.direction {
+            lines.push(format!("Direction: {}", direction));
+        }
+        if let Some(author) = &self.author {
+            lines.push(format!("Author: {}", author));
+        }
+        if let Some(source) = &self.source {
+            lines.push(format!("Source: {}", source));
+        }
+        if let Some(created) = &self.created {
+            lines.push(format!("Created: {}", created));
+        }
+        if let Some(origin) = &self.origin {
+            lines.push(format!(
+                "Origin: ({:.6}, {:.6}) at {:.1} m",
+                origin.latitude, origin.longitude, origin.altitude
+            ));
+        }
+        if let Some(lap_time) = self.reference_lap_time {
+            lines.push(format!("Reference Lap Time: {:.3} s", lap_time));
+        }
+        return write!(f, "{}", lines.join("\n"));
+    }
+}
+
+impl TrackMetadata {
+    pub fn is_empty(&self) -> bool {
+        return *self == Self::default();
+    }
+
+    // Check that every numeric field is finite and within its physical range, and that the
+    // creation date is a calendar date as YYYY-MM-DD
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(created) = &self.created {
+            if !is_date(created) {
+                return Err(format!("creation date {} is not YYYY-MM-DD", created));
+            }
+        }
+        if let Some(origin) = &self.origin {
+            if !(-90.0..=90.0).contains(&origin.latitude) {
+                return Err(format!("latitude {} out of range", origin.latitude));
+            }
+            if !(-180.0..=180.0).contains(&origin.longitude) {
+                return Err(format!("longitude {} out of range", origin.longitude));
+            }
+            if !origin.altitude.is_finite() {
+                return Err("altitude is not finite".to_string());
+            }
+        }
+        if let Some(lap_time) = self.reference_lap_time {
+            if !lap_time.is_finite() || lap_time <= 0.0 {
+                return Err(format!("invalid reference lap time {}", lap_time));
+            }
+        }
+        return Ok(());
+    }
+}
+
+impl std::fmt::Display for DrivingDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            DrivingDirection::Clockwise => write!(f, "Clockwise"),
+            DrivingDirection::CounterClockwise => write!(f, "Counter-Clockwise"),
+        };
+    }
+}
+
+// TRACKQUERY IMPLEMENTATION +++++++++++++++++++++++
+impl TrackQuery {
+    pub fn matches(&self, track: &Track) -> bool {
+        let metadata: &TrackMetadata = &track.metadata;
+        return contains(&Some(track.name.clone()), &self.name)
+            && contains(&metadata.author, &self.author)
+            && contains(&metadata.country, &self.country)
+            && contains(&metadata.layout, &self.layout)
+            && contains(&metadata.source, &self.source)
+            && (self.direction.is_none() || self.direction == metadata.direction)
+            && (self.is_closed.is_none() || self.is_closed == Some(track.is_closed()))
+            && within(&metadata.created, &self.created)
+            && within(&metadata.origin.map(|o| o.latitude), &self.latitude)
+            && within(&metadata.origin.map(|o| o.longitude), &self.longitude)
+            && within(&metadata.reference_lap_time, &self.reference_lap_time);
+    }
+
+    // Check that the date bounds are YYYY-MM-DD, so that they compare in calendar order
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(created) = &self.created {
+            for date in [created.start(), created.end()] {
+                if !is_date(date) {
+                    return Err(format!("creation date {} is not YYYY-MM-DD", date));
+                }
+            }
+        }
+        return Ok(());
+    }
+}
+
+fn within<T: PartialOrd>(value: &Option<T>, range: &Option<RangeInclusive<T>>) -> bool {
+    return match (value, range) {
+        (_, None) => true,
+        (None, Some(_)) => false,
+        (Some(v), Some(r)) => r.contains(v),
+    };
+}
+
+// Whether the text is a calendar date written as YYYY-MM-DD
+pub fn is_date(text: &str) -> bool {
+    let parts: Vec<&str> = text.split('-').collect();
+    if parts.len() != 3
+        || parts[0].len() != 4
+        || parts[1].len() != 2
+        || parts[2].len() != 2
+        || !parts.iter().all(|p| p.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return false;
+    }
+
+    let year: u32 = parts[0].parse().unwrap();
+    let month: u32 = parts[1].parse().unwrap();
+    let day: u32 = parts[2].parse().unwrap();
+    let is_leap: bool =
+        (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400);
+    let days_in_month: u32 = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    return (1..=days_in_month).contains(&day);
+}
+
+fn contains(value: &Option<String>, pattern: &Option<String>) -> bool {
+    return match (value, pattern) {
+        (_, None) => true,
+        (None, Some(_)) => false,
+        (Some(v), Some(p)) => v.to_lowercase().contains(&p.to_lowercase()),
+    };
+}
+
+// Read every .trk file in a directory and return the paths and tracks matching the query,
+// sorted by path. Files that are not valid track files are skipped, and an invalid query or
+// unreadable directory is an error.
+#[allow(dead_code)]
+pub fn search_directory(
+    dir_path: &str,
+    query: &TrackQuery,
+) -> Result<Vec<(std::path::PathBuf, Track)>, String> {
+    query.validate()?;
+    let entries: std::fs::ReadDir = match std::fs::read_dir(dir_path) {
+        Ok(e) => e,
+        Err(e) => {
+            return Err(format!(
+                "Failed to read track directory {}: {}",
+                dir_path, e
+            ))
+        }
+    };
+
+    let mut paths: Vec<std::path::PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().map(|ext| ext == "trk").unwrap_or(false))
+        .collect();
+    paths.sort();
+
+    let mut found: Vec<(std::path::PathBuf, Track)> = Vec::new();
+    for path in paths {
+        let data: Vec<u8> = match std::fs::read(&path) {
+            Ok(d) => d,
+            Err(_) => continue,
+        };
+        if let Ok(track) = Track::from_trkf_bytes(&data) {
+            if query.matches(&track) {
+                found.push((path, track));
+            }
+        }
+    }
+    return Ok(found);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_metadata() -> TrackMetadata {
+        return TrackMetadata {
+            author: Some("Track Team".to_string()),
+            country: Some("Sweden".to_string()),
+            layout: Some("City Arena".to_string()),
+            direction: Some(DrivingDirection::Clockwise),
+            source: Some("RTK GNSS survey".to_string()),
+            created: Some("2025-06-01".to_string()),
+            origin: Some(GeoOrigin {
+                latitude: 57.7089,
+                longitude: 11.9746,
+                altitude: 12.0,
+            }),
+            reference_lap_time: Some(61.25),
+        };
+    }
+
+    #[test]
+    fn test_display_lists_known_fields() {
+        let text: String = format!("{}", example_metadata());
+        assert!(text.contains("Layout: City Arena"));
+        assert!(text.contains("Direction: Clockwise"));
+        assert!(text.contains("Reference Lap Time: 61.250 s"));
+        assert_eq!(format!("{}", TrackMetadata::default()), "");
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(example_metadata().validate().is_ok());
+
+        let mut metadata: TrackMetadata = example_metadata();
+        metadata.origin = Some(GeoOrigin {
+            latitude: 91.0,
+            longitude: 0.0,
+            altitude: 0.0,
+        });
+        assert!(metadata.validate().is_err());
+
+        let mut metadata: TrackMetadata = example_metadata();
+        metadata.reference_lap_time = Some(f64::NAN);
+        assert!(metadata.validate().is_err());
+
+        for created in [
+            "2025-6-1",
+            "01-06-2025",
+            "2025-06-31",
+            "2025-13-01",
+            "2025-06-01T12:00",
+        ] {
+            let mut metadata: TrackMetadata = example_metadata();
+            metadata.created = Some(created.to_string());
+            assert!(metadata.validate().is_err(), "{}", created);
+        }
+        let mut metadata: TrackMetadata = example_metadata();
+        metadata.created = Some("2024-02-29".to_string());
+        assert!(metadata.validate().is_ok());
+    }
+
+    #[test]
+    fn test_query_matches() {
+        let mut track: Track = Track::straight(100.0, 5.0);
+        track.metadata = example_metadata();
+
+        let query: TrackQuery = TrackQuery {
+            country: Some("swe".to_string()),
+            direction: Some(DrivingDirection::Clockwise),
+            ..Default::default()
+        };
+        assert!(query.matches(&track));
+
+        let query: TrackQuery = TrackQuery {
+            author: Some("someone else".to_string()),
+            ..Default::default()
+        };
+        assert!(!query.matches(&track));
+
+        let query: TrackQuery = TrackQuery {
+            is_closed: Some(true),
+            ..Default::default()
+        };
+        assert!(!query.matches(&track));
+    }
+
+    #[test]
+    fn test_query_ranges() {
+        let mut track: Track = Track::straight(100.0, 5.0);
+        track.metadata = example_metadata();
+
+        let query: TrackQuery = TrackQuery {
+            created: Some("2025-01-01".to_string()..="2025-06-01".to_string()),
+            latitude: Some(55.0..=60.0),
+            longitude: Some(10.0..=15.0),
+            reference_lap_time: Some(60.0..=62.0),
+            ..Default::default()
+        };
+        assert!(query.validate().is_ok());
+        assert!(query.matches(&track));
+
+        let query: TrackQuery = TrackQuery {
+            created: Some("2025-06-02".to_string()..="2025-12-31".to_string()),
+            ..Default::default()
+        };
+        assert!(!query.matches(&track));
+
+        let query: TrackQuery = TrackQuery {
+            longitude: Some(-5.0..=5.0),
+            ..Default::default()
+        };
+        assert!(!query.matches(&track));
+
+        let query: TrackQuery = TrackQuery {
+            reference_lap_time: Some(0.0..=60.0),
+            ..Default::default()
+        };
+        assert!(!query.matches(&track));
+
+        // Tracks without the field do not match a range
+        track.metadata = TrackMetadata::default();
+        let query: TrackQuery = TrackQuery {
+            latitude: Some(-90.0..=90.0),
+            ..Default::default()
+        };
+        assert!(!query.matches(&track));
+
+        let query: TrackQuery = TrackQuery {
+            created: Some("2025-1-1".to_string()..="2025-12-31".to_string()),
+            ..Default::default()
+        };
+        assert!(query.validate().is_err());
+    }
+
+    #[test]
+    fn test_search_directory() {
+        let dir: std::path::PathBuf =
+            std::env::temp_dir().join(format!("apex_track_search_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut swedish: Track = Track::straight(100.0, 5.0);
+        swedish.name = "Swedish".to_string();
+        swedish.metadata = example_metadata();
+        swedish.write_to_file(dir.join("a.trk").to_str().unwrap());
+
+        let mut other: Track = Track::straight(50.0, 5.0);
+        other.name = "Other".to_string();
+        other.metadata.country = Some("Germany".to_string());
+        other.write_to_file(dir.join("b.trk").to_str().unwrap());
+
+        std::fs::write(dir.join("broken.trk"), b"not a track").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"Sweden").unwrap();
+
+        let query: TrackQuery = TrackQuery {
+            country: Some("Sweden".to_string()),
+            ..Default::default()
+        };
+        let found: Vec<(std::path::PathBuf, Track)> =
+            search_directory(dir.to_str().unwrap(), &query).unwrap();
+
+        // Date bounds that are not YYYY-MM-DD would compare wrongly as strings
+        let query: TrackQuery = TrackQuery {
+            created: Some("2024-1-5".to_string()..="2025-12-31".to_string()),
+            ..Default::default()
+        };
+        let error: String = search_directory(dir.to_str().unwrap(), &query)
+            .err()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(error.contains("2024-1-5"));
+        assert!(search_directory(dir.to_str().unwrap(), &TrackQuery::default()).is_err());
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].1.name, "Swedish");
+        assert_eq!(found[0].1.metadata, example_metadata());
+    }
+}
@@ -0,0 +1,1100 @@
+// OpenDRIVE (.xodr) plan-view import and export.
+//
+// Only the plan view of a single road and the widths of its driving lanes are used. The
+// reference line and lane widths are combined into a centreline and total width, which are
+// then fitted with CubicBezierSegments. On export each segment is written as an exact
+// paramPoly3 record, with one driving lane of half the track width on either side.
+//
+// Track metadata is stored as userData records in the header, with the geographic origin also
+// written as a geoReference so other tools can place the road.
+use super::fit;
+use super::metadata::{is_date, DrivingDirection, GeoOrigin, TrackMetadata};
+use super::{Segment, Track};
+
+const FIT_TOLERANCE: f64 = 1e-3; // Maximum centreline and width deviation when importing [m]
+const CLOSED_TOLERANCE: f64 = 1e-3; // Maximum gap between start and end of a closed road [m]
+const N_INTEGRATION_STEPS: usize = 64; // Simpson steps for spirals and poly3 arc lengths
+
+// OPENDRIVE IMPLEMENTATION for Track +++++++++++++++
+impl Track {
+    #[allow(dead_code)]
+    pub fn read_from_opendrive(file_path: &str) -> Self {
+        let text: String = match std::fs::read_to_string(file_path) {
+            Ok(t) => t,
+            Err(e) => panic!("Failed to read OpenDRIVE file {}: {}", file_path, e),
+        };
+        return Self::from_opendrive_str(&text);
+    }
+
+    pub fn from_opendrive_str(xml: &str) -> Self {
+        let root: XmlElement = parse_xml(xml);
+        if root.name != "OpenDRIVE" {
+            panic!("Invalid OpenDRIVE file: root element is <{}>", root.name);
+        }
+
+        // The first road is imported, junctions and road links are ignored
+        let road_element: &XmlElement = match root.child("road") {
+            Some(r) => r,
+            None => panic!("Invalid OpenDRIVE file: no <road> element"),
+        };
+        let road: Road = Road::from_element(road_element);
+
+        let name: String = match root.child("header").and_then(|h| h.attr("name")) {
+            Some(n) if !n.is_empty() => n.to_string(),
+            _ => match road_element.attr("name") {
+                Some(n) if !n.is_empty() => n.to_string(),
+                _ => "OpenDRIVE Road".to_string(),
+            },
+        };
+
+        let curve = |s: f64| road.centreline(s);
+        let mut points: Vec<(f64, f64, f64)> =
+            fit::fit_curve(&curve, &road.breakpoints(), FIT_TOLERANCE);
+        let n_segments: usize = (points.len() - 1) / 3;
+
+        // A road whose end meets its start with the same heading is treated as a closed track
+        let (x_start, y_start, hdg_start) = road.reference_pose(0.0);
+        let (x_end, y_end, hdg_end) = road.reference_pose(road.length);
+        let gap: f64 = f64::sqrt((x_end - x_start).powi(2) + (y_end - y_start).powi(2));
+        let heading_error: f64 =
+            f64::sin(hdg_end - hdg_start).abs() + (1.0 - f64::cos(hdg_end - hdg_start)).abs();
+        let is_closed: bool = gap < CLOSED_TOLERANCE && heading_error < 1e-6;
+        if is_closed {
+            points[3 * n_segments] = points[0];
+        }
+
+        let mut track: Track = Self::new(name, is_closed, n_segments, points);
+        if let Some(header) = root.child("header") {
+            track.metadata = read_metadata(header);
+        }
+        return track;
+    }
+
+    #[allow(dead_code)]
+    pub fn write_to_opendrive(&self, file_path: &str) {
+        let xml: String = self.to_opendrive_string();
+        match std::fs::write(file_path, xml) {
+            Ok(_) => {}
+            Err(e) => panic!("Failed to write OpenDRIVE file {}: {}", file_path, e),
+        }
+    }
+
+    pub fn to_opendrive_string(&self) -> String {
+        let mut geometries: String = String::new();
+        let mut widths: String = String::new();
+        let mut s_start: f64 = 0.0;
+
+        for i in 0..self.n_segments {
+            let segment = &self.segments[i];
+            let length: f64 = self.segment_lengths[i];
+
+            // Power basis coefficients of the Bezier curve in the global frame
+            let (p0, p1, p2, p3) = (segment.p0, segment.p1, segment.p2, segment.p3);
+            let bx: f64 = 3.0 * (p1.0 - p0.0);
+            let by: f64 = 3.0 * (p1.1 - p0.1);
+            let cx: f64 = 3.0 * (p0.0 - 2.0 * p1.0 + p2.0);
+            let cy: f64 = 3.0 * (p0.1 - 2.0 * p1.1 + p2.1);
+            let dx: f64 = p3.0 - 3.0 * p2.0 + 3.0 * p1.0 - p0.0;
+            let dy: f64 = p3.1 - 3.0 * p2.1 + 3.0 * p1.1 - p0.1;
+
+            // The heading is taken from the first control point that differs from p0
+            let hdg: f64 = if bx.abs() + by.abs() > 1e-12 {
+                f64::atan2(by, bx)
+            } else if (p2.0 - p0.0).abs() + (p2.1 - p0.1).abs() > 1e-12 {
+                f64::atan2(p2.1 - p0.1, p2.0 - p0.0)
+            } else {
+                f64::atan2(p3.1 - p0.1, p3.0 - p0.0)
+            };
+
+            // Rotate into the local (u, v) frame of the geometry record
+            let (sin_h, cos_h) = hdg.sin_cos();
+            let to_u = |x: f64, y: f64| x * cos_h + y * sin_h;
+            let to_v = |x: f64, y: f64| -x * sin_h + y * cos_h;
+
+            geometries.push_str(&format!(
+                "      <geometry s=\"{}\" x=\"{}\" y=\"{}\" hdg=\"{}\" length=\"{}\">\n",
+                s_start, p0.0, p0.1, hdg, length
+            ));
+            geometries.push_str(&format!(
+                "        <paramPoly3 aU=\"0\" bU=\"{}\" cU=\"{}\" dU=\"{}\" aV=\"0\" bV=\"{}\" cV=\"{}\" dV=\"{}\" pRange=\"normalized\"/>\n",
+                to_u(bx, by),
+                to_u(cx, cy),
+                to_u(dx, dy),
+                to_v(bx, by),
+                to_v(cx, cy),
+                to_v(dx, dy)
+            ));
+            geometries.push_str("      </geometry>\n");
+
+            // Lane widths are cubic in s, so the half width is written as the Hermite cubic
+            // matching the segment's end values and end slopes with respect to arc length
+            let (dx0, dy0, dw0) = segment.eval_ds(0.0);
+            let (dx1, dy1, dw1) = segment.eval_ds(1.0);
+            let speed0: f64 = f64::sqrt(dx0.powi(2) + dy0.powi(2)).max(1e-12);
+            let speed1: f64 = f64::sqrt(dx1.powi(2) + dy1.powi(2)).max(1e-12);
+            let (a, b, c, d) = hermite_cubic(
+                p0.2 / 2.0,
+                dw0 / speed0 / 2.0,
+                p3.2 / 2.0,
+                dw1 / speed1 / 2.0,
+                length,
+            );
+            widths.push_str(&format!(
+                "            <width sOffset=\"{}\" a=\"{}\" b=\"{}\" c=\"{}\" d=\"{}\"/>\n",
+                s_start, a, b, c, d
+            ));
+
+            s_start += length;
+        }
+
+        let mut xml: String = String::new();
+        xml.push_str("<?xml version=\"1.0\" standalone=\"yes\"?>\n");
+        xml.push_str("<OpenDRIVE>\n");
+        xml.push_str(&header_xml(&self.name, &self.metadata));
+        xml.push_str(&format!(
+            "  <road name=\"{}\" length=\"{}\" id=\"1\" junction=\"-1\">\n",
+            escape_xml(&self.name),
+            self.length
+        ));
+        xml.push_str("    <planView>\n");
+        xml.push_str(&geometries);
+        xml.push_str("    </planView>\n");
+        xml.push_str("    <lanes>\n");
+        xml.push_str("      <laneSection s=\"0\">\n");
+        xml.push_str("        <left>\n");
+        xml.push_str("          <lane id=\"1\" type=\"driving\" level=\"false\">\n");
+        xml.push_str(&widths);
+        xml.push_str("          </lane>\n");
+        xml.push_str("        </left>\n");
+        xml.push_str("        <center>\n");
+        xml.push_str("          <lane id=\"0\" type=\"none\" level=\"false\"/>\n");
+        xml.push_str("        </center>\n");
+        xml.push_str("        <right>\n");
+        xml.push_str("          <lane id=\"-1\" type=\"driving\" level=\"false\">\n");
+        xml.push_str(&widths);
+        xml.push_str("          </lane>\n");
+        xml.push_str("        </right>\n");
+        xml.push_str("      </laneSection>\n");
+        xml.push_str("    </lanes>\n");
+        xml.push_str("  </road>\n");
+        xml.push_str("</OpenDRIVE>\n");
+
+        return xml;
+    }
+}
+
+// METADATA ++++++++++++++++++++++++++++++++++++++++++
+fn header_xml(name: &str, metadata: &TrackMetadata) -> String {
+    let mut header: String = format!(
+        "  <header revMajor=\"1\" revMinor=\"6\" name=\"{}\" version=\"1.00\"",
+        escape_xml(name)
+    );
+    if let Some(created) = &metadata.created {
+        header.push_str(&format!(" date=\"{}\"", escape_xml(created)));
+    }
+    if metadata.is_empty() {
+        header.push_str("/>\n");
+        return header;
+    }
+    header.push_str(">\n");
+
+    if let Some(origin) = &metadata.origin {
+        header.push_str(&format!(
+            "    <geoReference><![CDATA[+proj=tmerc +lat_0={} +lon_0={} +x_0=0 +y_0=0 +ellps=WGS84 +units=m +no_defs]]></geoReference>\n",
+            origin.latitude, origin.longitude
+        ));
+    }
+
+    let mut user_data: Vec<(&str, String)> = Vec::new();
+    let strings: [(&str, &Option<String>); 4] = [
+        ("apex:author", &metadata.author),
+        ("apex:country", &metadata.country),
+        ("apex:layout", &metadata.layout),
+        ("apex:source", &metadata.source),
+    ];
+    for (code, value) in strings {
+        if let Some(text) = value {
+            user_data.push((code, text.clone()));
+        }
+    }
+    if let Some(direction) = &metadata.direction {
+        let value: &str = match direction {
+            DrivingDirection::Clockwise => "clockwise",
+            DrivingDirection::CounterClockwise => "counterClockwise",
+        };
+        user_data.push(("apex:direction", value.to_string()));
+    }
+    if let Some(origin) = &metadata.origin {
+        user_data.push((
+            "apex:origin",
+            format!(
+                "{},{},{}",
+                origin.latitude, origin.longitude, origin.altitude
+            ),
+        ));
+    }
+    if let Some(lap_time) = metadata.reference_lap_time {
+        user_data.push(("apex:referenceLapTime", format!("{}", lap_time)));
+    }
+    for (code, value) in user_data {
+        header.push_str(&format!(
+            "    <userData code=\"{}\" value=\"{}\"/>\n",
+            code,
+            escape_xml(&value)
+        ));
+    }
+
+    header.push_str("  </header>\n");
+    return header;
+}
+
+fn read_metadata(header: &XmlElement) -> TrackMetadata {
+    // Other tools often write a full timestamp, of which only a leading YYYY-MM-DD is kept
+    let created: Option<String> = header
+        .attr("date")
+        .and_then(|d| d.get(..10))
+        .filter(|d| is_date(d))
+        .map(|d| d.to_string());
+    let mut metadata: TrackMetadata = TrackMetadata {
+        created,
+        ..Default::default()
+    };
+
+    // Other tools only write the projection, so the origin is taken from it if present
+    if let Some(geo_reference) = header.child("geoReference") {
+        let mut latitude: Option<f64> = None;
+        let mut longitude: Option<f64> = None;
+        for token in geo_reference.text.split_whitespace() {
+            if let Some(value) = token.strip_prefix("+lat_0=") {
+                latitude = value.parse::<f64>().ok();
+            } else if let Some(value) = token.strip_prefix("+lon_0=") {
+                longitude = value.parse::<f64>().ok();
+            }
+        }
+        if let (Some(latitude), Some(longitude)) = (latitude, longitude) {
+            metadata.origin = Some(GeoOrigin {
+                latitude,
+                longitude,
+                altitude: 0.0,
+            });
+        }
+    }
+
+    for user_data in header.children_named("userData") {
+        let value: String = user_data.attr("value").unwrap_or("").to_string();
+        match user_data.attr("code").unwrap_or("") {
+            "apex:author" => metadata.author = Some(value),
+            "apex:country" => metadata.country = Some(value),
+            "apex:layout" => metadata.layout = Some(value),
+            "apex:source" => metadata.source = Some(value),
+            "apex:direction" => {
+                metadata.direction = match value.as_str() {
+                    "clockwise" => Some(DrivingDirection::Clockwise),
+                    "counterClockwise" => Some(DrivingDirection::CounterClockwise),
+                    other => panic!("Invalid apex:direction value \"{}\"", other),
+                }
+            }
+            "apex:origin" => {
+                let parts: Vec<f64> = value
+                    .split(',')
+                    .map(|p| parse_f64(p, "apex:origin"))
+                    .collect();
+                if parts.len() != 3 {
+                    panic!("Invalid apex:origin value \"{}\"", value);
+                }
+                metadata.origin = Some(GeoOrigin {
+                    latitude: parts[0],
+                    longitude: parts[1],
+                    altitude: parts[2],
+                });
+            }
+            "apex:referenceLapTime" => {
+                metadata.reference_lap_time = Some(parse_f64(&value, "apex:referenceLapTime"))
+            }
+            _ => {} // userData from other tools
+        }
+    }
+
+    if let Err(e) = metadata.validate() {
+        panic!("Invalid track metadata in OpenDRIVE header: {}", e);
+    }
+    return metadata;
+}
+
+// Coefficients of the cubic a + b*ds + c*ds^2 + d*ds^3 with the given values and slopes at
+// ds = 0 and ds = length
+fn hermite_cubic(f0: f64, df0: f64, f1: f64, df1: f64, length: f64) -> (f64, f64, f64, f64) {
+    let a: f64 = f0;
+    let b: f64 = df0;
+    let c: f64 = (3.0 * (f1 - f0) / length - 2.0 * df0 - df1) / length;
+    let d: f64 = (df0 + df1 - 2.0 * (f1 - f0) / length) / length.powi(2);
+    return (a, b, c, d);
+}
+
+// PLAN VIEW +++++++++++++++++++++++++++++++++++++++++
+struct Road {
+    length: f64,
+    geometries: Vec<Geometry>,
+    lane_offsets: Vec<(f64, Poly3)>, // (s, offset polynomial in ds)
+    lane_sections: Vec<LaneSection>,
+}
+
+struct Geometry {
+    s: f64,
+    x: f64,
+    y: f64,
+    hdg: f64,
+    length: f64,
+    kind: GeometryKind,
+}
+
+enum GeometryKind {
+    Line,
+    Arc {
+        curvature: f64,
+    },
+    Spiral {
+        curv_start: f64,
+        curv_end: f64,
+    },
+    Poly3 {
+        v: Poly3,
+    },
+    ParamPoly3 {
+        u: Poly3,
+        v: Poly3,
+        normalized: bool,
+    },
+}
+
+struct LaneSection {
+    s: f64,
+    left: Vec<Vec<(f64, Poly3)>>, // Width records (sOffset, polynomial) per driving lane
+    right: Vec<Vec<(f64, Poly3)>>, // Width records (sOffset, polynomial) per driving lane
+}
+
+#[derive(Clone, Copy)]
+struct Poly3 {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+}
+
+impl Poly3 {
+    fn from_element(element: &XmlElement, prefix: &str, suffix: &str) -> Self {
+        return Self {
+            a: element.attr_f64_or(&format!("{}a{}", prefix, suffix), 0.0),
+            b: element.attr_f64_or(&format!("{}b{}", prefix, suffix), 0.0),
+            c: element.attr_f64_or(&format!("{}c{}", prefix, suffix), 0.0),
+            d: element.attr_f64_or(&format!("{}d{}", prefix, suffix), 0.0),
+        };
+    }
+
+    fn eval(&self, p: f64) -> f64 {
+        return self.a + p * (self.b + p * (self.c + p * self.d));
+    }
+
+    fn eval_dp(&self, p: f64) -> f64 {
+        return self.b + p * (2.0 * self.c + p * 3.0 * self.d);
+    }
+}
+
+impl Road {
+    fn from_element(road: &XmlElement) -> Self {
+        let plan_view: &XmlElement = match road.child("planView") {
+            Some(p) => p,
+            None => panic!("Invalid OpenDRIVE file: road has no <planView>"),
+        };
+
+        let mut geometries: Vec<Geometry> = Vec::new();
+        for element in plan_view.children_named("geometry") {
+            geometries.push(Geometry::from_element(element));
+        }
+        if geometries.is_empty() {
+            panic!("Invalid OpenDRIVE file: plan view has no geometry records");
+        }
+        geometries.sort_by(|a, b| a.s.total_cmp(&b.s));
+
+        let last: &Geometry = &geometries[geometries.len() - 1];
+        let length: f64 = last.s + last.length;
+
+        let mut lane_offsets: Vec<(f64, Poly3)> = Vec::new();
+        let mut lane_sections: Vec<LaneSection> = Vec::new();
+        if let Some(lanes) = road.child("lanes") {
+            for element in lanes.children_named("laneOffset") {
+                lane_offsets.push((
+                    element.attr_f64_or("s", 0.0),
+                    Poly3::from_element(element, "", ""),
+                ));
+            }
+            for element in lanes.children_named("laneSection") {
+                lane_sections.push(LaneSection::from_element(element));
+            }
+        }
+        lane_offsets.sort_by(|a, b| a.0.total_cmp(&b.0));
+        lane_sections.sort_by(|a, b| a.s.total_cmp(&b.s));
+        if lane_sections.is_empty() {
+            panic!("Invalid OpenDRIVE file: road has no lane sections");
+        }
+
+        return Self {
+            length,
+            geometries,
+            lane_offsets,
+            lane_sections,
+        };
+    }
+
+    // Every s at which the geometry or the width description changes
+    fn breakpoints(&self) -> Vec<f64> {
+        let mut breakpoints: Vec<f64> = vec![0.0, self.length];
+        for geometry in &self.geometries {
+            breakpoints.push(geometry.s);
+        }
+        for (s, _) in &self.lane_offsets {
+            breakpoints.push(*s);
+        }
+        for section in &self.lane_sections {
+            breakpoints.push(section.s);
+            for lane in section.left.iter().chain(section.right.iter()) {
+                for (s_offset, _) in lane {
+                    breakpoints.push(section.s + s_offset);
+                }
+            }
+        }
+
+        breakpoints.retain(|s| (0.0..=self.length).contains(s));
+        breakpoints.sort_by(|a, b| a.total_cmp(b));
+        breakpoints.dedup_by(|a, b| (*a - *b).abs() < 1e-9);
+        return breakpoints;
+    }
+
+    // Position and heading of the reference line at s
+    fn reference_pose(&self, s: f64) -> (f64, f64, f64) {
+        let mut index: usize = 0;
+        for (i, geometry) in self.geometries.iter().enumerate() {
+            if geometry.s <= s {
+                index = i;
+            }
+        }
+        let geometry: &Geometry = &self.geometries[index];
+        let ds: f64 = (s - geometry.s).clamp(0.0, geometry.length);
+        return geometry.eval(ds);
+    }
+
+    // Centreline position and total width of the driving lanes at s
+    fn centreline(&self, s: f64) -> (f64, f64, f64) {
+        let (x, y, hdg) = self.reference_pose(s);
+
+        let mut offset: f64 = 0.0;
+        for (s_offset, poly) in &self.lane_offsets {
+            if *s_offset <= s {
+                offset = poly.eval(s - s_offset);
+            }
+        }
+
+        let mut section: &LaneSection = &self.lane_sections[0];
+        for candidate in &self.lane_sections {
+            if candidate.s <= s {
+                section = candidate;
+            }
+        }
+        let ds: f64 = s - section.s;
+        let left_width: f64 = section.left.iter().map(|lane| lane_width(lane, ds)).sum();
+        let right_width: f64 = section.right.iter().map(|lane| lane_width(lane, ds)).sum();
+
+        // The centre of the driving surface is offset to the left of the reference line
+        let centre_offset: f64 = offset + (left_width - right_width) / 2.0;
+        let xc: f64 = x - centre_offset * hdg.sin();
+        let yc: f64 = y + centre_offset * hdg.cos();
+
+        return (xc, yc, left_width + right_width);
+    }
+}
+
+fn lane_width(records: &Vec<(f64, Poly3)>, ds: f64) -> f64 {
+    let mut width: f64 = 0.0;
+    for (s_offset, poly) in records {
+        if *s_offset <= ds {
+            width = poly.eval(ds - s_offset);
+        }
+    }
+    return width;
+}
+
+impl LaneSection {
+    fn from_element(element: &XmlElement) -> Self {
+        let s: f64 = element.attr_f64_or("s", 0.0);
+        let left: Vec<Vec<(f64, Poly3)>> = match element.child("left") {
+            Some(side) => Self::driving_lane_widths(side),
+            None => Vec::new(),
+        };
+        let right: Vec<Vec<(f64, Poly3)>> = match element.child("right") {
+            Some(side) => Self::driving_lane_widths(side),
+            None => Vec::new(),
+        };
+        if left.is_empty() && right.is_empty() {
+            panic!(
+                "Invalid OpenDRIVE file: lane section at s = {} has no driving lanes",
+                s
+            );
+        }
+        return Self { s, left, right };
+    }
+
+    fn driving_lane_widths(side: &XmlElement) -> Vec<Vec<(f64, Poly3)>> {
+        let mut lanes: Vec<Vec<(f64, Poly3)>> = Vec::new();
+        for lane in side.children_named("lane") {
+            if lane.attr("type").unwrap_or("driving") != "driving" {
+                continue;
+            }
+            let mut records: Vec<(f64, Poly3)> = Vec::new();
+            for width in lane.children_named("width") {
+                records.push((
+                    width.attr_f64_or("sOffset", 0.0),
+                    Poly3::from_element(width, "", ""),
+                ));
+            }
+            records.sort_by(|a, b| a.0.total_cmp(&b.0));
+            lanes.push(records);
+        }
+        return lanes;
+    }
+}
+
+impl Geometry {
+    fn from_element(element: &XmlElement) -> Self {
+        let kind_element: &XmlElement = match element.children.first() {
+            Some(k) => k,
+            None => panic!("Invalid OpenDRIVE file: geometry record without a type"),
+        };
+        let kind: GeometryKind = match kind_element.name.as_str() {
+            "line" => GeometryKind::Line,
+            "arc" => GeometryKind::Arc {
+                curvature: kind_element.attr_f64("curvature"),
+            },
+            "spiral" => GeometryKind::Spiral {
+                curv_start: kind_element.attr_f64("curvStart"),
+                curv_end: kind_element.attr_f64("curvEnd"),
+            },
+            "poly3" => GeometryKind::Poly3 {
+                v: Poly3::from_element(kind_element, "", ""),
+            },
+            "paramPoly3" => GeometryKind::ParamPoly3 {
+                u: Poly3::from_element(kind_element, "", "U"),
+                v: Poly3::from_element(kind_element, "", "V"),
+                normalized: kind_element.attr("pRange").unwrap_or("normalized") == "normalized",
+            },
+            other => panic!("Unsupported OpenDRIVE geometry type: {}", other),
+        };
+
+        let length: f64 = element.attr_f64("length");
+        if length.is_nan() || length <= 0.0 {
+            panic!("Invalid OpenDRIVE file: geometry length must be positive");
+        }
+
+        return Self {
+            s: element.attr_f64("s"),
+            x: element.attr_f64("x"),
+            y: element.attr_f64("y"),
+            hdg: element.attr_f64("hdg"),
+            length,
+            kind,
+        };
+    }
+
+    // Position and heading at distance ds along the geometry record
+    fn eval(&self, ds: f64) -> (f64, f64, f64) {
+        let (u, v, theta) = match &self.kind {
+            GeometryKind::Line => (ds, 0.0, 0.0),
+            GeometryKind::Arc { curvature } => {
+                if curvature.abs() < 1e-12 {
+                    (ds, 0.0, 0.0)
+                } else {
+                    let theta: f64 = curvature * ds;
+                    (
+                        theta.sin() / curvature,
+                        (1.0 - theta.cos()) / curvature,
+                        theta,
+                    )
+                }
+            }
+            GeometryKind::Spiral {
+                curv_start,
+                curv_end,
+            } => {
+                let rate: f64 = (curv_end - curv_start) / self.length;
+                let heading = |l: f64| curv_start * l + 0.5 * rate * l.powi(2);
+                let u: f64 = simpson(|l| heading(l).cos(), 0.0, ds);
+                let v: f64 = simpson(|l| heading(l).sin(), 0.0, ds);
+                (u, v, heading(ds))
+            }
+            GeometryKind::Poly3 { v } => {
+                // Find the local u at which the arc length along the cubic equals ds
+                let arc_length =
+                    |u_end: f64| simpson(|u| f64::sqrt(1.0 + v.eval_dp(u).powi(2)), 0.0, u_end);
+                let mut u_low: f64 = 0.0;
+                let mut u_high: f64 = ds;
+                for _ in 0..60 {
+                    let u_mid: f64 = 0.5 * (u_low + u_high);
+                    if arc_length(u_mid) < ds {
+                        u_low = u_mid;
+                    } else {
+                        u_high = u_mid;
+                    }
+                }
+                let u: f64 = 0.5 * (u_low + u_high);
+                (u, v.eval(u), f64::atan(v.eval_dp(u)))
+            }
+            GeometryKind::ParamPoly3 { u, v, normalized } => {
+                let p: f64 = match normalized {
+                    true => ds / self.length,
+                    false => ds,
+                };
+                (u.eval(p), v.eval(p), f64::atan2(v.eval_dp(p), u.eval_dp(p)))
+            }
+        };
+
+        let (sin_h, cos_h) = self.hdg.sin_cos();
+        let x: f64 = self.x + u * cos_h - v * sin_h;
+        let y: f64 = self.y + u * sin_h + v * cos_h;
+        return (x, y, self.hdg + theta);
+    }
+}
+
+fn simpson<F: Fn(f64) -> f64>(f: F, a: f64, b: f64) -> f64 {
+    let h: f64 = (b - a) / (N_INTEGRATION_STEPS as f64);
+    let mut sum: f64 = f(a) + f(b);
+    for i in 1..N_INTEGRATION_STEPS {
+        let weight: f64 = if i % 2 == 1 { 4.0 } else { 2.0 };
+        sum += weight * f(a + (i as f64) * h);
+    }
+    return sum * h / 3.0;
+}
+
+// MINIMAL XML READER ++++++++++++++++++++++++++++++++
+// Supports elements, attributes, comments, declarations, text and CDATA, which is all that is
+// needed for the header, plan view and lanes of an OpenDRIVE file.
+struct XmlElement {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<XmlElement>,
+    text: String, // Concatenated text and CDATA content directly inside the element
+}
+
+impl XmlElement {
+    fn attr(&self, key: &str) -> Option<&str> {
+        for (k, v) in &self.attributes {
+            if k == key {
+                return Some(v.as_str());
+            }
+        }
+        return None;
+    }
+
+    fn attr_f64(&self, key: &str) -> f64 {
+        return match self.attr(key) {
+            Some(v) => parse_f64(v, key),
+            None => panic!("Missing attribute {} on <{}>", key, self.name),
+        };
+    }
+
+    fn attr_f64_or(&self, key: &str, default: f64) -> f64 {
+        return match self.attr(key) {
+            Some(v) => parse_f64(v, key),
+            None => default,
+        };
+    }
+
+    fn child(&self, name: &str) -> Option<&XmlElement> {
+        return self.children.iter().find(|c| c.name == name);
+    }
+
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> {
+        return self.children.iter().filter(move |c| c.name == name);
+    }
+}
+
+fn parse_f64(value: &str, key: &str) -> f64 {
+    return match value.trim().parse::<f64>() {
+        Ok(v) if v.is_finite() => v,
+        _ => panic!("Invalid numeric value \"{}\" for attribute {}", value, key),
+    };
+}
+
+fn parse_xml(text: &str) -> XmlElement {
+    let bytes: &[u8] = text.as_bytes();
+    let mut pos: usize = 0;
+    let mut stack: Vec<XmlElement> = Vec::new();
+    let mut root: Option<XmlElement> = None;
+
+    while pos < bytes.len() {
+        if bytes[pos] != b'<' {
+            let end: usize = text[pos..]
+                .find('<')
+                .map(|i| pos + i)
+                .unwrap_or(bytes.len());
+            if let Some(element) = stack.last_mut() {
+                element.text.push_str(&unescape_xml(&text[pos..end]));
+            }
+            pos = end;
+            continue;
+        }
+        let rest: &str = &text[pos..];
+
+        if rest.starts_with("<?") {
+            pos += find_or_panic(rest, "?>") + 2;
+        } else if rest.starts_with("<!--") {
+            pos += find_or_panic(rest, "-->") + 3;
+        } else if rest.starts_with("<![CDATA[") {
+            let end: usize = find_or_panic(rest, "]]>");
+            if let Some(element) = stack.last_mut() {
+                element.text.push_str(&rest[9..end]);
+            }
+            pos += end + 3;
+        } else if rest.starts_with("<!") {
+            pos += find_or_panic(rest, ">") + 1;
+        } else if rest.starts_with("</") {
+            let end: usize = find_or_panic(rest, ">");
+            let name: &str = rest[2..end].trim();
+            let element: XmlElement = match stack.pop() {
+                Some(e) => e,
+                None => panic!("Malformed XML: unexpected closing tag </{}>", name),
+            };
+            if element.name != name {
+                panic!(
+                    "Malformed XML: expected </{}> but found </{}>",
+                    element.name, name
+                );
+            }
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(element),
+                None => root = Some(element),
+            }
+            pos += end + 1;
+        } else {
+            let end: usize = find_tag_end(rest);
+            let self_closing: bool = rest[..end].ends_with('/');
+            let inner: &str = match self_closing {
+                true => &rest[1..end - 1],
+                false => &rest[1..end],
+            };
+            let element: XmlElement = parse_tag(inner);
+            if self_closing {
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => root = Some(element),
+                }
+            } else {
+                stack.push(element);
+            }
+            pos += end + 1;
+        }
+    }
+
+    if !stack.is_empty() {
+        panic!(
+            "Malformed XML: unclosed element <{}>",
+            stack[stack.len() - 1].name
+        );
+    }
+    return match root {
+        Some(r) => r,
+        None => panic!("Malformed XML: no root element"),
+    };
+}
+
+fn find_or_panic(text: &str, pattern: &str) -> usize {
+    return match text.find(pattern) {
+        Some(i) => i,
+        None => panic!("Malformed XML: missing {}", pattern),
+    };
+}
+
+// Index of the '>' closing a start tag, skipping over quoted attribute values
+fn find_tag_end(text: &str) -> usize {
+    let mut quote: Option<char> = None;
+    for (i, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '>' => return i,
+            None => {}
+        }
+    }
+    panic!("Malformed XML: unterminated tag");
+}
+
+fn parse_tag(inner: &str) -> XmlElement {
+    let inner: &str = inner.trim();
+    let name_end: usize = inner.find(char::is_whitespace).unwrap_or(inner.len());
+    let name: String = inner[..name_end].to_string();
+    if name.is_empty() {
+        panic!("Malformed XML: element without a name");
+    }
+
+    let mut attributes: Vec<(String, String)> = Vec::new();
+    let mut rest: &str = inner[name_end..].trim_start();
+    while !rest.is_empty() {
+        let eq: usize = find_or_panic(rest, "=");
+        let key: String = rest[..eq].trim().to_string();
+        let after_eq: &str = rest[eq + 1..].trim_start();
+        let quote: char = match after_eq.chars().next() {
+            Some(q) if q == '"' || q == '\'' => q,
+            _ => panic!("Malformed XML: unquoted value for attribute {}", key),
+        };
+        let value_end: usize = match after_eq[1..].find(quote) {
+            Some(i) => i + 1,
+            None => panic!("Malformed XML: unterminated value for attribute {}", key),
+        };
+        attributes.push((key, unescape_xml(&after_eq[1..value_end])));
+        rest = after_eq[value_end + 1..].trim_start();
+    }
+
+    return XmlElement {
+        name,
+        attributes,
+        children: Vec::new(),
+        text: String::new(),
+    };
+}
+
+fn unescape_xml(text: &str) -> String {
+    return text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+}
+
+fn escape_xml(text: &str) -> String {
+    return text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::track::TrackFrame;
+
+    fn frames(track: &Track, n: usize) -> Box<Vec<TrackFrame>> {
+        let s_lap_q: Vec<f64> = (0..=n)
+            .map(|i| track.length() * (i as f64) / (n as f64))
+            .collect();
+        return track.discretise(s_lap_q);
+    }
+
+    #[test]
+    fn test_import_line_and_arc() {
+        let xml: &str = r#"<?xml version="1.0" standalone="yes"?>
+<OpenDRIVE>
+  <header revMajor="1" revMinor="6" name="Hairpin"/>
+  <road name="r" length="131.4159265" id="1" junction="-1">
+    <planView>
+      <geometry s="0" x="0" y="0" hdg="0" length="100">
+        <line/>
+      </geometry>
+      <geometry s="100" x="100" y="0" hdg="0" length="31.4159265">
+        <arc curvature="0.1"/>
+      </geometry>
+    </planView>
+    <lanes>
+      <laneSection s="0">
+        <left>
+          <lane id="1" type="driving" level="false">
+            <width sOffset="0" a="3.5" b="0" c="0" d="0"/>
+          </lane>
+        </left>
+        <center><lane id="0" type="none" level="false"/></center>
+        <right>
+          <lane id="-1" type="driving" level="false">
+            <width sOffset="0" a="3.5" b="0" c="0" d="0"/>
+          </lane>
+          <lane id="-2" type="shoulder" level="false">
+            <width sOffset="0" a="1.0" b="0" c="0" d="0"/>
+          </lane>
+        </right>
+      </laneSection>
+    </lanes>
+  </road>
+</OpenDRIVE>"#;
+
+        let track: Track = Track::from_opendrive_str(xml);
+        assert_eq!(track.name, "Hairpin");
+        assert!(!track.is_closed());
+        assert!((track.length() - 131.4159265).abs() < 1e-2);
+
+        // The end of the half circle is 20 m to the left of the start of the arc
+        let end_frames: Box<Vec<TrackFrame>> = track.discretise(vec![track.length()]);
+        let (x_end, y_end) = end_frames[0].position();
+        assert!((x_end - 100.0).abs() < 1e-2);
+        assert!((y_end - 20.0).abs() < 1e-2);
+
+        // Shoulders are not part of the driving surface
+        for frame in frames(&track, 20).iter() {
+            assert!((frame.width() - 7.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_import_asymmetric_lanes_offsets_centreline() {
+        let xml: &str = r#"<OpenDRIVE>
+  <road length="50" id="1" junction="-1">
+    <planView>
+      <geometry s="0" x="0" y="0" hdg="1.5707963267948966" length="50"><line/></geometry>
+    </planView>
+    <lanes>
+      <laneOffset s="0" a="0.5" b="0" c="0" d="0"/>
+      <laneSection s="0">
+        <left>
+          <lane id="2" type="driving"><width sOffset="0" a="3" b="0" c="0" d="0"/></lane>
+          <lane id="1" type="driving"><width sOffset="0" a="3" b="0" c="0" d="0"/></lane>
+        </left>
+        <right>
+          <lane id="-1" type="driving"><width sOffset="0" a="2" b="0.02" c="0" d="0"/></lane>
+        </right>
+      </laneSection>
+    </lanes>
+  </road>
+</OpenDRIVE>"#;
+
+        let track: Track = Track::from_opendrive_str(xml);
+        assert_eq!(track.name, "OpenDRIVE Road");
+        for frame in frames(&track, 10).iter() {
+            let (x, y) = frame.position();
+            let right_width: f64 = 2.0 + 0.02 * y;
+            // Heading north, so left is -x
+            let expected_x: f64 = -(0.5 + (6.0 - right_width) / 2.0);
+            assert!((x - expected_x).abs() < 1e-3);
+            assert!((frame.width() - (6.0 + right_width)).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_import_spiral_and_poly3() {
+        let xml: &str = r#"<OpenDRIVE>
+  <road length="40" id="1" junction="-1">
+    <planView>
+      <geometry s="0" x="0" y="0" hdg="0" length="20"><spiral curvStart="0" curvEnd="0.05"/></geometry>
+      <geometry s="20" x="19.505753764" y="3.274280947" hdg="0.5" length="20"><poly3 a="0" b="0" c="0.01" d="0"/></geometry>
+    </planView>
+    <lanes>
+      <laneSection s="0">
+        <right><lane id="-1"><width sOffset="0" a="4" b="0" c="0" d="0"/></lane></right>
+      </laneSection>
+    </lanes>
+  </road>
+</OpenDRIVE>"#;
+
+        let track: Track = Track::from_opendrive_str(xml);
+        assert!(track.n_segments >= 2);
+        // A clothoid of length 20 m ending at curvature 0.05 turns by 0.5 rad
+        let road: Road = Road::from_element(parse_xml(xml).child("road").unwrap());
+        let (_x, _y, hdg) = road.reference_pose(20.0 - 1e-9);
+        assert!((hdg - 0.5).abs() < 1e-6);
+        for frame in frames(&track, 10).iter() {
+            assert!((frame.width() - 4.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let original: Track = Track::double_lane_change();
+        let xml: String = original.to_opendrive_string();
+        let imported: Track = Track::from_opendrive_str(&xml);
+
+        assert_eq!(imported.name, original.name);
+        assert_eq!(imported.is_closed(), original.is_closed());
+        assert!((imported.length() - original.length()).abs() < 1e-2);
+
+        let original_frames: Box<Vec<TrackFrame>> = frames(&original, 50);
+        let imported_frames: Box<Vec<TrackFrame>> = frames(&imported, 50);
+        for (a, b) in original_frames.iter().zip(imported_frames.iter()) {
+            let (xa, ya) = a.position();
+            let (xb, yb) = b.position();
+            assert!(f64::sqrt((xa - xb).powi(2) + (ya - yb).powi(2)) < 5e-2);
+            assert!((a.width() - b.width()).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_closed_road_detected() {
+        let xml: &str = r#"<OpenDRIVE>
+  <header name="Ring &amp; Loop"/>
+  <road length="62.83185307179586" id="1" junction="-1">
+    <planView>
+      <geometry s="0" x="0" y="0" hdg="0" length="62.83185307179586"><arc curvature="0.1"/></geometry>
+    </planView>
+    <lanes>
+      <laneSection s="0">
+        <left><lane id="1" type="driving"><width sOffset="0" a="5" b="0" c="0" d="0"/></lane></left>
+      </laneSection>
+    </lanes>
+  </road>
+</OpenDRIVE>"#;
+
+        let track: Track = Track::from_opendrive_str(xml);
+        assert_eq!(track.name, "Ring & Loop");
+        assert!(track.is_closed());
+        let p_first: (f64, f64, f64) = track.segments[0].p0;
+        let p_last: (f64, f64, f64) = track.segments[track.n_segments - 1].p3;
+        assert_eq!(p_first, p_last);
+    }
+
+    #[test]
+    fn test_metadata_round_trip() {
+        let mut original: Track = Track::straight(100.0, 6.0);
+        original.metadata = TrackMetadata {
+            author: Some("Sim & ADAS".to_string()),
+            country: Some("Sweden".to_string()),
+            layout: Some("Short".to_string()),
+            direction: Some(DrivingDirection::CounterClockwise),
+            source: Some("Laser scan".to_string()),
+            created: Some("2025-06-01".to_string()),
+            origin: Some(GeoOrigin {
+                latitude: 57.7089,
+                longitude: 11.9746,
+                altitude: 12.5,
+            }),
+            reference_lap_time: Some(12.3),
+        };
+        let imported: Track = Track::from_opendrive_str(&original.to_opendrive_string());
+        assert_eq!(imported.metadata, original.metadata);
+    }
+
+    #[test]
+    fn test_origin_from_geo_reference() {
+        let xml: &str = r#"<OpenDRIVE>
+  <header name="Geo" date="2019-03-14T09:30:00">
+    <geoReference><![CDATA[+proj=tmerc +lat_0=48.1 +lon_0=11.5 +k=1 +ellps=WGS84]]></geoReference>
+  </header>
+  <road length="10" id="1"><planView>
+    <geometry s="0" x="0" y="0" hdg="0" length="10"><line/></geometry>
+  </planView><lanes><laneSection s="0">
+    <left><lane id="1" type="driving"><width sOffset="0" a="3" b="0" c="0" d="0"/></lane></left>
+  </laneSection></lanes></road>
+</OpenDRIVE>"#;
+        let track: Track = Track::from_opendrive_str(xml);
+        let origin: GeoOrigin = track.metadata.origin.unwrap();
+        assert!((origin.latitude - 48.1).abs() < 1e-12);
+        assert!((origin.longitude - 11.5).abs() < 1e-12);
+        assert_eq!(track.metadata.author, None);
+        assert_eq!(track.metadata.created.as_deref(), Some("2019-03-14"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported OpenDRIVE geometry type")]
+    fn test_unknown_geometry_rejected() {
+        let xml: &str = r#"<OpenDRIVE><road length="1"><planView>
+            <geometry s="0" x="0" y="0" hdg="0" length="1"><bogus/></geometry>
+        </planView></road></OpenDRIVE>"#;
+        Track::from_opendrive_str(xml);
+    }
+}
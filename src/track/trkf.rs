@@ -0,0 +1,632 @@
+// Reader and writer for the binary TRKF track format.
+//
+// Layout (little endian):
+//   0..4     "TRKF"
+//   4..6     major and minor version as u8, 0.1 or 0.2
+//   6..70    track name, NUL-terminated UTF-8
+//   70..128  reserved
+//   128      0 if the track is closed, 1 if it is open
+//   129..133 number of CubicBezierSegments as u32
+//   133..    3 * n_segments + 1 control points of (x, y, width) as f64
+//
+// Version 0.2 follows the points with a metadata block: the number of records as u32, then
+// per record a u8 key, the payload length as u32 and the payload. Unknown keys are skipped.
+//
+// Every size is checked against the actual file length before anything is allocated, and
+// the file must end exactly after the last control point or metadata record.
+use super::metadata::{DrivingDirection, GeoOrigin, TrackMetadata};
+use super::{Segment, Track};
+
+const HEADER_LENGTH: usize = 133;
+const NAME_OFFSET: usize = 6;
+const NAME_LENGTH: usize = 64;
+const POINT_LENGTH: usize = 24; // Each point is 3 f64s = 24 bytes
+const MIN_SEGMENT_LENGTH: f64 = 1e-6; // Shorter segments are treated as degenerate [m]
+const RECORD_HEADER_LENGTH: usize = 5; // u8 key and u32 payload length
+
+// Metadata record keys
+const KEY_AUTHOR: u8 = 1;
+const KEY_COUNTRY: u8 = 2;
+const KEY_LAYOUT: u8 = 3;
+const KEY_DIRECTION: u8 = 4;
+const KEY_SOURCE: u8 = 5;
+const KEY_CREATED: u8 = 6;
+const KEY_ORIGIN: u8 = 7;
+const KEY_REFERENCE_LAP_TIME: u8 = 8;
+
+// TRKF IMPLEMENTATION for Track +++++++++++++++++++
+impl Track {
+    pub fn read_from_file(file_path: &str) -> Self {
+        let data: Vec<u8> = match std::fs::read(file_path) {
+            Ok(b) => b,
+            Err(e) => panic!("Failed to read track file {}: {}", file_path, e),
+        };
+        return match Self::from_trkf_bytes(&data) {
+            Ok(track) => track,
+            Err(e) => panic!("Invalid track file {}: {}", file_path, e),
+        };
+    }
+
+    #[allow(dead_code)]
+    pub fn write_to_file(&self, file_path: &str) {
+        match std::fs::write(file_path, self.to_trkf_bytes()) {
+            Ok(_) => {}
+            Err(e) => panic!("Failed to write track file {}: {}", file_path, e),
+        }
+    }
+
+    // Serialise the track as a version 0.2 TRKF file
+    pub fn to_trkf_bytes(&self) -> Vec<u8> {
+        let name_bytes: &[u8] = self.name.as_bytes();
+        if name_bytes.is_empty() || name_bytes.len() >= NAME_LENGTH || name_bytes.contains(&0) {
+            panic!(
+                "Track name must be 1 to {} bytes without NUL characters",
+                NAME_LENGTH - 1
+            );
+        }
+        if let Err(e) = self.metadata.validate() {
+            panic!("Invalid track metadata: {}", e);
+        }
+
+        let mut data: Vec<u8> =
+            Vec::with_capacity(HEADER_LENGTH + (3 * self.n_segments + 1) * POINT_LENGTH);
+        data.extend_from_slice(b"TRKF");
+        data.push(0);
+        data.push(2);
+        data.extend_from_slice(name_bytes);
+        data.resize(128, 0); // NUL terminator, padding and reserved bytes
+        data.push(match self.is_closed {
+            true => 0,
+            false => 1,
+        });
+        data.extend_from_slice(&(self.n_segments as u32).to_le_bytes());
+
+        let mut points: Vec<(f64, f64, f64)> = vec![self.segments[0].p0];
+        for segment in &self.segments {
+            points.push(segment.p1);
+            points.push(segment.p2);
+            points.push(segment.p3);
+        }
+        for (x, y, width) in points {
+            data.extend_from_slice(&x.to_le_bytes());
+            data.extend_from_slice(&y.to_le_bytes());
+            data.extend_from_slice(&width.to_le_bytes());
+        }
+
+        write_metadata(&mut data, &self.metadata);
+        return data;
+    }
+
+    // Parse a TRKF file, returning a description of the first problem found if it is invalid
+    pub fn from_trkf_bytes(data: &[u8]) -> Result<Self, String> {
+        let data_len: usize = data.len();
+        if data_len < HEADER_LENGTH {
+            return Err(format!("file too short ({} bytes)", data_len));
+        }
+
+        // First four bytes should be the letters "TRKF"
+        if &data[0..4] != b"TRKF" {
+            return Err("missing TRKF header".to_string());
+        }
+        // The next two bytes should be two u8s for major and minor version
+        let major_version: u8 = data[4];
+        let minor_version: u8 = data[5];
+        let has_metadata: bool = minor_version >= 2;
+        if major_version != 0 || !(1..=2).contains(&minor_version) {
+            return Err(format!(
+                "unsupported version {}.{}",
+                major_version, minor_version
+            ));
+        }
+
+        // Next 64 bytes are the name of the track as a NUL-terminated string
+        let name_bytes: &[u8] = &data[NAME_OFFSET..NAME_OFFSET + NAME_LENGTH];
+        let name_end: usize = match name_bytes.iter().position(|&b| b == 0) {
+            Some(i) => i,
+            None => return Err("track name is not NUL-terminated".to_string()),
+        };
+        if name_end == 0 {
+            return Err("track name is missing".to_string());
+        }
+        let name: String = match String::from_utf8(name_bytes[0..name_end].to_vec()) {
+            Ok(n) => n,
+            Err(_) => return Err("track name is not valid UTF-8".to_string()),
+        };
+
+        // The next byte is a u8 indicating if the track is closed (0) or open (1)
+        let is_closed: bool = match data[128] {
+            0 => true,
+            1 => false,
+            v => return Err(format!("invalid value {} for is_closed", v)),
+        };
+
+        // Next four bytes are a u32 indicating the number of segments. The end of the points
+        // is computed with checked arithmetic and must match the data exactly, unless it is
+        // followed by metadata.
+        let n_segments: usize = read_u32(data, 129) as usize;
+        if n_segments == 0 {
+            return Err("track has no segments".to_string());
+        }
+        let points_end: Option<usize> = n_segments
+            .checked_mul(3)
+            .and_then(|n| n.checked_add(1))
+            .and_then(|n| n.checked_mul(POINT_LENGTH))
+            .and_then(|n| n.checked_add(HEADER_LENGTH));
+        match points_end {
+            Some(len) if len == data_len || (has_metadata && len < data_len) => {}
+            Some(len) if len > data_len => {
+                return Err(format!(
+                    "{} segments need {} bytes but the file has {}",
+                    n_segments, len, data_len
+                ))
+            }
+            Some(len) => {
+                return Err(format!(
+                    "{} bytes of trailing data after the last point",
+                    data_len - len
+                ))
+            }
+            None => return Err(format!("segment count {} is too large", n_segments)),
+        }
+        let n_points: usize = n_segments * 3 + 1;
+
+        let mut points: Vec<(f64, f64, f64)> = Vec::with_capacity(n_points);
+        for i in 0..n_points {
+            let offset: usize = HEADER_LENGTH + i * POINT_LENGTH;
+            let x: f64 = read_f64(data, offset);
+            let y: f64 = read_f64(data, offset + 8);
+            let width: f64 = read_f64(data, offset + 16);
+
+            if !x.is_finite() || !y.is_finite() {
+                return Err(format!("point {} has a non-finite coordinate", i));
+            }
+            if !width.is_finite() || width <= 0.0 {
+                return Err(format!("point {} has invalid width {}", i, width));
+            }
+            points.push((x, y, width));
+        }
+
+        let metadata: TrackMetadata = match has_metadata {
+            true => read_metadata(data, HEADER_LENGTH + n_points * POINT_LENGTH)?,
+            false => TrackMetadata::default(),
+        };
+
+        let mut track: Track = Self::new(name, is_closed, n_segments, points);
+        track.metadata = metadata;
+        for (i, segment) in track.segments.iter().enumerate() {
+            let seg_length: f64 = segment.calc_length();
+            if !seg_length.is_finite() || seg_length < MIN_SEGMENT_LENGTH {
+                return Err(format!("segment {} has invalid length {}", i, seg_length));
+            }
+        }
+        if !track.length.is_finite() {
+            return Err("track length is not finite".to_string());
+        }
+
+        return Ok(track);
+    }
+}
+
+fn read_metadata(data: &[u8], offset: usize) -> Result<TrackMetadata, String> {
+    if offset + 4 > data.len() {
+        return Err("metadata block is truncated".to_string());
+    }
+    let n_records: usize = read_u32(data, offset) as usize;
+    let mut offset: usize = offset + 4;
+    let mut metadata: TrackMetadata = TrackMetadata::default();
+    let mut seen: Vec<u8> = Vec::new();
+
+    for i in 0..n_records {
+        if data.len() - offset < RECORD_HEADER_LENGTH {
+            return Err(format!("metadata record {} is truncated", i));
+        }
+        let key: u8 = data[offset];
+        let length: usize = read_u32(data, offset + 1) as usize;
+        offset += RECORD_HEADER_LENGTH;
+        if data.len() - offset < length {
+            return Err(format!("metadata record {} is truncated", i));
+        }
+        let payload: &[u8] = &data[offset..offset + length];
+        offset += length;
+
+        if (KEY_AUTHOR..=KEY_REFERENCE_LAP_TIME).contains(&key) {
+            if seen.contains(&key) {
+                return Err(format!("duplicate metadata record with key {}", key));
+            }
+            seen.push(key);
+        }
+        match key {
+            KEY_AUTHOR => metadata.author = Some(read_string(payload, key)?),
+            KEY_COUNTRY => metadata.country = Some(read_string(payload, key)?),
+            KEY_LAYOUT => metadata.layout = Some(read_string(payload, key)?),
+            KEY_SOURCE => metadata.source = Some(read_string(payload, key)?),
+            KEY_CREATED => metadata.created = Some(read_string(payload, key)?),
+            KEY_DIRECTION => {
+                metadata.direction = match payload {
+                    [0] => Some(DrivingDirection::Clockwise),
+                    [1] => Some(DrivingDirection::CounterClockwise),
+                    _ => return Err("invalid driving direction record".to_string()),
+                }
+            }
+            KEY_ORIGIN => {
+                if payload.len() != 24 {
+                    return Err("invalid origin record".to_string());
+                }
+                metadata.origin = Some(GeoOrigin {
+                    latitude: read_f64(payload, 0),
+                    longitude: read_f64(payload, 8),
+                    altitude: read_f64(payload, 16),
+                });
+            }
+            KEY_REFERENCE_LAP_TIME => {
+                if payload.len() != 8 {
+                    return Err("invalid reference lap time record".to_string());
+                }
+                metadata.reference_lap_time = Some(read_f64(payload, 0));
+            }
+            _ => {} // Records from newer writers are skipped
+        }
+    }
+
+    if offset != data.len() {
+        return Err(format!(
+            "{} bytes of trailing data after the metadata",
+            data.len() - offset
+        ));
+    }
+    metadata.validate()?;
+    return Ok(metadata);
+}
+
+fn write_metadata(data: &mut Vec<u8>, metadata: &TrackMetadata) {
+    let mut records: Vec<(u8, Vec<u8>)> = Vec::new();
+    let strings: [(u8, &Option<String>); 5] = [
+        (KEY_AUTHOR, &metadata.author),
+        (KEY_COUNTRY, &metadata.country),
+        (KEY_LAYOUT, &metadata.layout),
+        (KEY_SOURCE, &metadata.source),
+        (KEY_CREATED, &metadata.created),
+    ];
+    for (key, value) in strings {
+        if let Some(text) = value {
+            records.push((key, text.as_bytes().to_vec()));
+        }
+    }
+    if let Some(direction) = metadata.direction {
+        let value: u8 = match direction {
+            DrivingDirection::Clockwise => 0,
+            DrivingDirection::CounterClockwise => 1,
+        };
+        records.push((KEY_DIRECTION, vec![value]));
+    }
+    if let Some(origin) = metadata.origin {
+        let mut payload: Vec<u8> = Vec::with_capacity(24);
+        payload.extend_from_slice(&origin.latitude.to_le_bytes());
+        payload.extend_from_slice(&origin.longitude.to_le_bytes());
+        payload.extend_from_slice(&origin.altitude.to_le_bytes());
+        records.push((KEY_ORIGIN, payload));
+    }
+    if let Some(lap_time) = metadata.reference_lap_time {
+        records.push((KEY_REFERENCE_LAP_TIME, lap_time.to_le_bytes().to_vec()));
+    }
+
+    data.extend_from_slice(&(records.len() as u32).to_le_bytes());
+    for (key, payload) in records {
+        data.push(key);
+        data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        data.extend_from_slice(&payload);
+    }
+}
+
+fn read_string(payload: &[u8], key: u8) -> Result<String, String> {
+    return match String::from_utf8(payload.to_vec()) {
+        Ok(s) => Ok(s),
+        Err(_) => Err(format!("metadata record {} is not valid UTF-8", key)),
+    };
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    let mut bytes: [u8; 4] = [0; 4];
+    bytes.copy_from_slice(&data[offset..offset + 4]);
+    return u32::from_le_bytes(bytes);
+}
+
+fn read_f64(data: &[u8], offset: usize) -> f64 {
+    let mut bytes: [u8; 8] = [0; 8];
+    bytes.copy_from_slice(&data[offset..offset + 8]);
+    return f64::from_le_bytes(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Build a TRKF file byte by byte
+    fn trkf_bytes(name: &str, is_closed: bool, points: &[(f64, f64, f64)]) -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::new();
+        data.extend_from_slice(b"TRKF");
+        data.push(0);
+        data.push(1);
+        let mut name_field: [u8; NAME_LENGTH] = [0; NAME_LENGTH];
+        name_field[..name.len()].copy_from_slice(name.as_bytes());
+        data.extend_from_slice(&name_field);
+        data.resize(128, 0);
+        data.push(if is_closed { 0 } else { 1 });
+        data.extend_from_slice(&(((points.len() - 1) / 3) as u32).to_le_bytes());
+        for &(x, y, width) in points {
+            data.extend_from_slice(&x.to_le_bytes());
+            data.extend_from_slice(&y.to_le_bytes());
+            data.extend_from_slice(&width.to_le_bytes());
+        }
+        return data;
+    }
+
+    fn valid_bytes() -> Vec<u8> {
+        return trkf_bytes(
+            "Test Track",
+            false,
+            &[
+                (0.0, 0.0, 4.0),
+                (10.0, 0.0, 4.0),
+                (20.0, 0.0, 4.0),
+                (30.0, 0.0, 4.0),
+                (40.0, 5.0, 4.5),
+                (50.0, 10.0, 5.0),
+                (60.0, 10.0, 5.0),
+            ],
+        );
+    }
+
+    fn set_f64(data: &mut [u8], offset: usize, value: f64) {
+        data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn metadata_bytes() -> Vec<u8> {
+        let mut track: Track = Track::from_trkf_bytes(&valid_bytes()).unwrap();
+        track.metadata = TrackMetadata {
+            author: Some("Track Team".to_string()),
+            country: Some("Sweden".to_string()),
+            direction: Some(DrivingDirection::CounterClockwise),
+            origin: Some(GeoOrigin {
+                latitude: 57.7,
+                longitude: 11.97,
+                altitude: 5.0,
+            }),
+            reference_lap_time: Some(75.5),
+            ..Default::default()
+        };
+        return track.to_trkf_bytes();
+    }
+
+    #[test]
+    fn test_write_read_round_trip() {
+        let data: Vec<u8> = metadata_bytes();
+        assert_eq!(data[5], 2);
+        let track: Track = Track::from_trkf_bytes(&data).unwrap();
+
+        assert_eq!(track.name, "Test Track");
+        assert_eq!(track.n_segments, 2);
+        assert_eq!(track.segments[1].p2, (50.0, 10.0, 5.0));
+        assert_eq!(track.metadata.country, Some("Sweden".to_string()));
+        assert_eq!(
+            track.metadata.direction,
+            Some(DrivingDirection::CounterClockwise)
+        );
+        assert_eq!(track.metadata.reference_lap_time, Some(75.5));
+        assert_eq!(track.metadata.layout, None);
+        assert_eq!(track.to_trkf_bytes(), data);
+    }
+
+    #[test]
+    fn test_unknown_metadata_record_skipped() {
+        let mut data: Vec<u8> = metadata_bytes();
+        let count_offset: usize = HEADER_LENGTH + 7 * POINT_LENGTH;
+        let n_records: u32 = read_u32(&data, count_offset);
+        data[count_offset..count_offset + 4].copy_from_slice(&(n_records + 1).to_le_bytes());
+        data.push(200);
+        data.extend_from_slice(&3u32.to_le_bytes());
+        data.extend_from_slice(b"new");
+
+        let track: Track = Track::from_trkf_bytes(&data).unwrap();
+        assert_eq!(track.metadata.author, Some("Track Team".to_string()));
+    }
+
+    #[test]
+    fn test_valid_file() {
+        let track: Track = Track::from_trkf_bytes(&valid_bytes()).unwrap();
+        assert_eq!(track.name, "Test Track");
+        assert!(!track.is_closed());
+        assert_eq!(track.n_segments, 2);
+    }
+
+    // Each entry is a malformed file and part of the expected error message
+    fn malformed_corpus() -> Vec<(Vec<u8>, &'static str)> {
+        let valid: Vec<u8> = valid_bytes();
+        let mut corpus: Vec<(Vec<u8>, &'static str)> = Vec::new();
+
+        corpus.push((Vec::new(), "file too short"));
+        corpus.push((valid[..HEADER_LENGTH - 1].to_vec(), "file too short"));
+
+        let mut bad_magic: Vec<u8> = valid.clone();
+        bad_magic[0] = b'X';
+        corpus.push((bad_magic, "missing TRKF header"));
+
+        let mut bad_version: Vec<u8> = valid.clone();
+        bad_version[5] = 3;
+        corpus.push((bad_version, "unsupported version"));
+
+        let mut unterminated_name: Vec<u8> = valid.clone();
+        for b in &mut unterminated_name[NAME_OFFSET..NAME_OFFSET + NAME_LENGTH] {
+            *b = b'a';
+        }
+        corpus.push((unterminated_name, "not NUL-terminated"));
+
+        let mut empty_name: Vec<u8> = valid.clone();
+        empty_name[NAME_OFFSET] = 0;
+        corpus.push((empty_name, "name is missing"));
+
+        let mut invalid_utf8: Vec<u8> = valid.clone();
+        invalid_utf8[NAME_OFFSET] = 0xFF;
+        corpus.push((invalid_utf8, "not valid UTF-8"));
+
+        let mut bad_closed: Vec<u8> = valid.clone();
+        bad_closed[128] = 7;
+        corpus.push((bad_closed, "is_closed"));
+
+        let mut no_segments: Vec<u8> = valid.clone();
+        no_segments[129..133].copy_from_slice(&0u32.to_le_bytes());
+        corpus.push((no_segments, "no segments"));
+
+        let mut huge_count: Vec<u8> = valid.clone();
+        huge_count[129..133].copy_from_slice(&u32::MAX.to_le_bytes());
+        corpus.push((huge_count, "segments need"));
+
+        let mut truncated: Vec<u8> = valid.clone();
+        truncated.truncate(valid.len() - 1);
+        corpus.push((truncated, "segments need"));
+
+        let mut trailing: Vec<u8> = valid.clone();
+        trailing.extend_from_slice(b"garbage");
+        corpus.push((trailing, "trailing data"));
+
+        let mut nan_x: Vec<u8> = valid.clone();
+        set_f64(&mut nan_x, HEADER_LENGTH + 2 * POINT_LENGTH, f64::NAN);
+        corpus.push((nan_x, "non-finite coordinate"));
+
+        let mut infinite_y: Vec<u8> = valid.clone();
+        set_f64(&mut infinite_y, HEADER_LENGTH + 8, f64::INFINITY);
+        corpus.push((infinite_y, "non-finite coordinate"));
+
+        let mut negative_width: Vec<u8> = valid.clone();
+        set_f64(
+            &mut negative_width,
+            HEADER_LENGTH + 3 * POINT_LENGTH + 16,
+            -1.0,
+        );
+        corpus.push((negative_width, "invalid width"));
+
+        let mut nan_width: Vec<u8> = valid.clone();
+        set_f64(&mut nan_width, HEADER_LENGTH + 16, f64::NAN);
+        corpus.push((nan_width, "invalid width"));
+
+        let degenerate: Vec<u8> = trkf_bytes("Point", false, &[(1.0, 1.0, 3.0); 4]);
+        corpus.push((degenerate, "invalid length"));
+
+        let overflowing: Vec<u8> = trkf_bytes(
+            "Overflow",
+            false,
+            &[
+                (-1e308, 0.0, 3.0),
+                (1e308, 0.0, 3.0),
+                (-1e308, 0.0, 3.0),
+                (1e308, 0.0, 3.0),
+            ],
+        );
+        corpus.push((overflowing, "invalid length"));
+
+        // Version 0.2 metadata block
+        let with_metadata: Vec<u8> = metadata_bytes();
+        let count_offset: usize = HEADER_LENGTH + 7 * POINT_LENGTH;
+
+        let mut no_block: Vec<u8> = with_metadata[..count_offset].to_vec();
+        no_block[5] = 2;
+        corpus.push((no_block, "metadata block is truncated"));
+
+        let mut short_block: Vec<u8> = with_metadata[..count_offset + 2].to_vec();
+        short_block[5] = 2;
+        corpus.push((short_block, "metadata block is truncated"));
+
+        let mut too_many_records: Vec<u8> = with_metadata.clone();
+        too_many_records[count_offset..count_offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        corpus.push((too_many_records, "is truncated"));
+
+        let mut huge_record: Vec<u8> = with_metadata.clone();
+        huge_record[count_offset + 5..count_offset + 9].copy_from_slice(&u32::MAX.to_le_bytes());
+        corpus.push((huge_record, "is truncated"));
+
+        let mut metadata_trailing: Vec<u8> = with_metadata.clone();
+        metadata_trailing.push(0);
+        corpus.push((metadata_trailing, "trailing data after the metadata"));
+
+        let mut bad_author: Vec<u8> = with_metadata.clone();
+        bad_author[count_offset + 9] = 0xFF;
+        corpus.push((bad_author, "not valid UTF-8"));
+
+        let lap_time_offset: usize = with_metadata.len() - 8;
+        let mut negative_lap_time: Vec<u8> = with_metadata.clone();
+        set_f64(&mut negative_lap_time, lap_time_offset, -3.0);
+        corpus.push((negative_lap_time, "invalid reference lap time"));
+
+        let latitude_offset: usize = lap_time_offset - RECORD_HEADER_LENGTH - 24;
+        let mut bad_latitude: Vec<u8> = with_metadata.clone();
+        set_f64(&mut bad_latitude, latitude_offset, f64::NAN);
+        corpus.push((bad_latitude, "latitude"));
+
+        let mut duplicate: Vec<u8> = with_metadata.clone();
+        let n_records: u32 = read_u32(&duplicate, count_offset);
+        duplicate[count_offset..count_offset + 4].copy_from_slice(&(n_records + 1).to_le_bytes());
+        duplicate.push(KEY_COUNTRY);
+        duplicate.extend_from_slice(&2u32.to_le_bytes());
+        duplicate.extend_from_slice(b"SE");
+        corpus.push((duplicate, "duplicate metadata record"));
+
+        return corpus;
+    }
+
+    #[test]
+    fn test_malformed_corpus_rejected() {
+        for (i, (data, expected)) in malformed_corpus().iter().enumerate() {
+            match Track::from_trkf_bytes(data) {
+                Ok(_) => panic!("Corpus entry {} was accepted", i),
+                Err(e) => assert!(
+                    e.contains(expected),
+                    "Corpus entry {}: expected \"{}\" in \"{}\"",
+                    i,
+                    expected,
+                    e
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_truncation_rejected() {
+        for valid in [valid_bytes(), metadata_bytes()] {
+            for len in 0..valid.len() {
+                assert!(Track::from_trkf_bytes(&valid[..len]).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_mutations_never_panic() {
+        // Deterministic linear congruential generator so failures are reproducible
+        let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut next = || {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            return (state >> 33) as usize;
+        };
+
+        let valid_files: [Vec<u8>; 2] = [valid_bytes(), metadata_bytes()];
+        for iteration in 0..4000 {
+            let mut data: Vec<u8> = valid_files[iteration % 2].clone();
+            let n_mutations: usize = 1 + next() % 4;
+            for _ in 0..n_mutations {
+                let index: usize = next() % data.len();
+                data[index] = (next() % 256) as u8;
+            }
+            if next() % 4 == 0 {
+                let new_len: usize = next() % (data.len() + 16);
+                data.resize(new_len, (next() % 256) as u8);
+            }
+
+            // Any outcome is fine as long as the parser does not panic, and accepted
+            // tracks must have sane geometry
+            if let Ok(track) = Track::from_trkf_bytes(&data) {
+                assert!(track.length().is_finite() && track.length() > 0.0);
+            }
+        }
+    }
+}
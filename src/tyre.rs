@@ -0,0 +1,130 @@
+pub mod condition;
+pub mod pacejka;
+
+// Tyre force laws used by the vehicle models.
+//
+// A TyreLaw gives the lateral force of a tyre (or a lumped axle) as a function of slip angle
+// and normal load, together with the derivatives needed for analytic model Jacobians.
+// Forces follow the convention that a positive slip angle gives a positive lateral force.
+
+pub trait TyreLaw {
+    // Lateral force [N] at slip angle alpha [rad] and normal load fz [N]
+    fn lateral_force(&self, alpha: f64, fz: f64) -> f64;
+    // Derivative of the lateral force with respect to the slip angle [N/rad]
+    fn lateral_force_dalpha(&self, alpha: f64, fz: f64) -> f64;
+    // Derivative of the lateral force with respect to the normal load [N/N]
+    fn lateral_force_dfz(&self, alpha: f64, fz: f64) -> f64;
+}
+
+// Linear tyre, F = C * alpha, independent of the normal load
+pub struct LinearTyre {
+    // Parameters
+    pub cornering_stiffness: f64, // [N/rad]
+}
+
+// Saturating tyre, F = mu * Fz * tanh(C * alpha / (mu * Fz)), which is linear for small slip
+// angles and saturates smoothly at the friction limit mu * Fz
+pub struct SaturatingTyre {
+    // Parameters
+    pub cornering_stiffness: f64, // [N/rad]
+    pub mu: f64,                  // Friction coefficient [-]
+}
+
+// LINEARTYRE IMPLEMENTATION +++++++++++++++++++++++
+impl LinearTyre {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(cornering_stiffness: f64) -> Self {
+        return LinearTyre {
+            cornering_stiffness,
+        };
+    }
+}
+
+impl TyreLaw for LinearTyre {
+    fn lateral_force(&self, alpha: f64, _fz: f64) -> f64 {
+        return self.cornering_stiffness * alpha;
+    }
+
+    fn lateral_force_dalpha(&self, _alpha: f64, _fz: f64) -> f64 {
+        return self.cornering_stiffness;
+    }
+
+    fn lateral_force_dfz(&self, _alpha: f64, _fz: f64) -> f64 {
+        return 0.0;
+    }
+}
+
+// SATURATINGTYRE IMPLEMENTATION +++++++++++++++++++
+impl SaturatingTyre {
+    #[allow(dead_code)] // Allow unused constructor
+    pub fn new(cornering_stiffness: f64, mu: f64) -> Self {
+        return SaturatingTyre {
+            cornering_stiffness,
+            mu,
+        };
+    }
+}
+
+impl TyreLaw for SaturatingTyre {
+    fn lateral_force(&self, alpha: f64, fz: f64) -> f64 {
+        let f_max: f64 = self.mu * fz;
+        return f_max * f64::tanh(self.cornering_stiffness * alpha / f_max);
+    }
+
+    fn lateral_force_dalpha(&self, alpha: f64, fz: f64) -> f64 {
+        let f_max: f64 = self.mu * fz;
+        let tanh: f64 = f64::tanh(self.cornering_stiffness * alpha / f_max);
+        return self.cornering_stiffness * (1.0 - tanh.powi(2));
+    }
+
+    fn lateral_force_dfz(&self, alpha: f64, fz: f64) -> f64 {
+        let f_max: f64 = self.mu * fz;
+        let z: f64 = self.cornering_stiffness * alpha / f_max;
+        let tanh: f64 = z.tanh();
+        // d/dFz [mu Fz tanh(z)] with dz/dFz = -z / Fz
+        return self.mu * (tanh - z * (1.0 - tanh.powi(2)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linear_tyre() {
+        let tyre = LinearTyre::new(80000.0);
+        assert!((tyre.lateral_force(0.05, 4000.0) - 4000.0).abs() < 1e-9);
+        assert!((tyre.lateral_force_dalpha(0.3, 4000.0) - 80000.0).abs() < 1e-9);
+        assert_eq!(tyre.lateral_force_dfz(0.3, 4000.0), 0.0);
+    }
+
+    #[test]
+    fn test_saturating_tyre_limits() {
+        let tyre = SaturatingTyre::new(80000.0, 1.2);
+        let fz: f64 = 4000.0;
+
+        // Linear for small slip angles
+        let alpha: f64 = 1e-4;
+        assert!((tyre.lateral_force(alpha, fz) - 80000.0 * alpha).abs() < 1e-3);
+        // Saturates at the friction limit
+        assert!((tyre.lateral_force(1.0, fz) - 1.2 * fz).abs() < 1e-3);
+        assert!((tyre.lateral_force(-1.0, fz) + 1.2 * fz).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_saturating_tyre_derivatives() {
+        let tyre = SaturatingTyre::new(60000.0, 1.1);
+        let (alpha, fz) = (0.04, 3500.0);
+        let h: f64 = 1e-6;
+
+        let dalpha_fd: f64 =
+            (tyre.lateral_force(alpha + h, fz) - tyre.lateral_force(alpha - h, fz)) / (2.0 * h);
+        assert!((tyre.lateral_force_dalpha(alpha, fz) - dalpha_fd).abs() < 1e-3);
+
+        let h_fz: f64 = 1e-3;
+        let dfz_fd: f64 = (tyre.lateral_force(alpha, fz + h_fz)
+            - tyre.lateral_force(alpha, fz - h_fz))
+            / (2.0 * h_fz);
+        assert!((tyre.lateral_force_dfz(alpha, fz) - dfz_fd).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,113 @@
+// Energy-based tyre temperature and wear laws giving a grip multiplier for the tyre or
+// friction model.
+//
+// The tyres are lumped into one thermal mass heated by a share of the power dissipated by
+// sliding and cooled towards the ambient temperature. Wear grows with the sliding energy.
+// The grip peaks at the optimal temperature, falls off smoothly outside the temperature
+// window towards a floor, and drops linearly with wear.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TyreCondition {
+    pub heat_capacity: f64,       // Lumped heat capacity of the tyres [J/K]
+    pub heating_fraction: f64,    // Share of the sliding power heating the tyres [-]
+    pub cooling: f64,             // Heat transfer to ambient [W/K]
+    pub ambient_temperature: f64, // [degC]
+    pub optimal_temperature: f64, // Temperature of peak grip [degC]
+    pub temperature_window: f64,  // Offset from the optimum losing half the thermal grip [K]
+    pub max_thermal_loss: f64,    // Grip lost far outside the window [-]
+    pub wear_rate: f64,           // Wear per unit sliding energy [1/J]
+    pub wear_grip_loss: f64,      // Grip lost when fully worn, at wear 1 [-]
+}
+
+// TYRECONDITION IMPLEMENTATION ++++++++++++++++++++++++++
+impl TyreCondition {
+    // Rate of change of the temperature [K/s] for a sliding power [W] at a temperature [degC]
+    pub fn temperature_rate(&self, sliding_power: f64, temperature: f64) -> f64 {
+        let heat: f64 = self.heating_fraction * sliding_power
+            - self.cooling * (temperature - self.ambient_temperature);
+        return heat / self.heat_capacity;
+    }
+
+    // Rate of change of the wear [1/s] for a sliding power [W]
+    pub fn wear_rate(&self, sliding_power: f64) -> f64 {
+        return self.wear_rate * sliding_power;
+    }
+
+    // Grip multiplier [-] at a temperature [degC] and wear [-], with its derivatives with
+    // respect to the temperature and the wear
+    pub fn grip(&self, temperature: f64, wear: f64) -> (f64, f64, f64) {
+        let d: f64 = (temperature - self.optimal_temperature) / self.temperature_window;
+        let thermal: f64 = 1.0 - self.max_thermal_loss * d.powi(2) / (1.0 + d.powi(2));
+        let dthermal_dt: f64 =
+            -self.max_thermal_loss * 2.0 * d / (1.0 + d.powi(2)).powi(2) / self.temperature_window;
+        let worn: f64 = 1.0 - self.wear_grip_loss * wear;
+        return (
+            thermal * worn,
+            dthermal_dt * worn,
+            -thermal * self.wear_grip_loss,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_condition() -> TyreCondition {
+        return TyreCondition {
+            heat_capacity: 20000.0,
+            heating_fraction: 0.5,
+            cooling: 50.0,
+            ambient_temperature: 25.0,
+            optimal_temperature: 90.0,
+            temperature_window: 30.0,
+            max_thermal_loss: 0.4,
+            wear_rate: 1e-8,
+            wear_grip_loss: 0.2,
+        };
+    }
+
+    #[test]
+    fn test_grip_window() {
+        let condition: TyreCondition = example_condition();
+
+        // Full grip at the optimum, half the thermal loss at the edge of the window
+        assert_eq!(condition.grip(90.0, 0.0).0, 1.0);
+        assert!((condition.grip(60.0, 0.0).0 - 0.8).abs() < 1e-12);
+        assert!((condition.grip(120.0, 0.0).0 - 0.8).abs() < 1e-12);
+        assert!(condition.grip(-500.0, 0.0).0 > 0.6);
+
+        // Wear scales the grip
+        assert!((condition.grip(90.0, 0.5).0 - 0.9).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_grip_derivatives() {
+        let condition: TyreCondition = example_condition();
+        let h: f64 = 1e-6;
+        for (temperature, wear) in [(40.0, 0.1), (95.0, 0.3), (150.0, 0.0)] {
+            let (_, dg_dt, dg_dw) = condition.grip(temperature, wear);
+            let fd_t: f64 = (condition.grip(temperature + h, wear).0
+                - condition.grip(temperature - h, wear).0)
+                / (2.0 * h);
+            let fd_w: f64 = (condition.grip(temperature, wear + h).0
+                - condition.grip(temperature, wear - h).0)
+                / (2.0 * h);
+            assert!((dg_dt - fd_t).abs() < 1e-8);
+            assert!((dg_dw - fd_w).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn test_heating_and_wear() {
+        let condition: TyreCondition = example_condition();
+
+        // Equilibrium where half the sliding power is removed by cooling
+        assert!(condition.temperature_rate(6500.0, 90.0).abs() < 1e-12);
+        assert!(condition.temperature_rate(10000.0, 90.0) > 0.0);
+        assert!(condition.temperature_rate(0.0, 90.0) < 0.0);
+
+        assert_eq!(condition.wear_rate(0.0), 0.0);
+        assert!((condition.wear_rate(5000.0) - 5e-5).abs() < 1e-15);
+    }
+}
@@ -0,0 +1,708 @@
+// Pacejka Magic Formula tyre model, following the MF 5.2 pure and combined slip equations
+// without camber, turn slip or inflation pressure effects and with all scaling factors at one.
+// Coefficients are read from the sections of a .tir property file.
+use super::TyreLaw;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MagicFormula {
+    pub nominal_load: f64, // FNOMIN [N]
+    // Pure longitudinal slip
+    pub pcx1: f64, // Shape factor
+    pub pdx1: f64, // Peak friction
+    pub pdx2: f64, // Load variation of peak friction
+    pub pex1: f64, // Curvature factor
+    pub pex2: f64, // Load variation of curvature
+    pub pex3: f64, // Quadratic load variation of curvature
+    pub pex4: f64, // Curvature asymmetry between traction and braking
+    pub pkx1: f64, // Slip stiffness per unit load
+    pub pkx2: f64, // Load variation of slip stiffness
+    pub pkx3: f64, // Exponential load variation of slip stiffness
+    pub phx1: f64, // Horizontal shift
+    pub phx2: f64, // Load variation of horizontal shift
+    pub pvx1: f64, // Vertical shift per unit load
+    pub pvx2: f64, // Load variation of vertical shift
+    // Pure lateral slip
+    pub pcy1: f64, // Shape factor
+    pub pdy1: f64, // Peak friction
+    pub pdy2: f64, // Load variation of peak friction
+    pub pey1: f64, // Curvature factor
+    pub pey2: f64, // Load variation of curvature
+    pub pey3: f64, // Curvature asymmetry between positive and negative slip
+    pub pky1: f64, // Maximum cornering stiffness per unit nominal load
+    pub pky2: f64, // Load at maximum cornering stiffness per unit nominal load
+    pub phy1: f64, // Horizontal shift
+    pub phy2: f64, // Load variation of horizontal shift
+    pub pvy1: f64, // Vertical shift per unit load
+    pub pvy2: f64, // Load variation of vertical shift
+    // Combined slip
+    pub rbx1: f64, // Slope factor of the longitudinal weighting
+    pub rbx2: f64, // Variation of the slope factor with slip ratio
+    pub rcx1: f64, // Shape factor of the longitudinal weighting
+    pub rhx1: f64, // Horizontal shift of the longitudinal weighting
+    pub rby1: f64, // Slope factor of the lateral weighting
+    pub rby2: f64, // Variation of the slope factor with slip angle
+    pub rby3: f64, // Slip angle shift of the slope factor
+    pub rcy1: f64, // Shape factor of the lateral weighting
+    pub rhy1: f64, // Horizontal shift of the lateral weighting
+    pub rhy2: f64, // Load variation of the horizontal shift
+}
+
+// A force and its derivatives with respect to the slip quantity and the normal load
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SlipForce {
+    pub force: f64,  // [N]
+    pub d_slip: f64, // [N/-] or [N/rad]
+    pub d_load: f64, // [N/N]
+}
+
+// Combined slip forces with derivatives with respect to [kappa, alpha, fz]
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CombinedForces {
+    pub fx: f64,
+    pub fy: f64,
+    pub dfx: [f64; 3],
+    pub dfy: [f64; 3],
+}
+
+// Value of y = D sin(C atan(B x - E (B x - atan(B x)))) and its partial derivatives
+struct MagicFormulaValue {
+    y: f64,
+    dy_dx: f64,
+    dy_db: f64,
+    dy_dd: f64,
+    dy_de: f64,
+}
+
+// Value of G = cos(C atan(B (s + S))) / cos(C atan(B S)) and its partial derivatives with
+// respect to the slip s, the shift S and the slope factor B
+struct WeightingValue {
+    g: f64,
+    dg_ds: f64,
+    dg_dshift: f64,
+    dg_db: f64,
+}
+
+// Force of an unloaded wheel. At fz = 0 the stiffness factors B = K / (C D) are 0 / 0, and
+// below it the peak factor D changes sign, so a wheel without load carries no force.
+const NO_FORCE: SlipForce = SlipForce {
+    force: 0.0,
+    d_slip: 0.0,
+    d_load: 0.0,
+};
+
+// Coefficients a property file must give, FNOMIN first
+const REQUIRED_COEFFICIENTS: [&str; 8] = [
+    "FNOMIN", "PCX1", "PDX1", "PKX1", "PCY1", "PDY1", "PKY1", "PKY2",
+];
+
+// MAGICFORMULA IMPLEMENTATION +++++++++++++++++++++
+impl MagicFormula {
+    // Read the coefficients from a .tir file, panicking if it cannot be read or is invalid
+    #[allow(dead_code)]
+    pub fn read_from_tir(file_path: &str) -> Self {
+        let text: String = match std::fs::read_to_string(file_path) {
+            Ok(t) => t,
+            Err(e) => panic!("Failed to read tyre file {}: {}", file_path, e),
+        };
+        return match Self::from_tir_str(&text) {
+            Ok(tyre) => tyre,
+            Err(e) => panic!("Invalid tyre file {}: {}", file_path, e),
+        };
+    }
+
+    // Parse the contents of a .tir file. Lines have the form KEY = value with optional
+    // $ comments, and section headers, string values and unknown keys are ignored.
+    // Coefficients that are not given default to zero, except FNOMIN and the shape, peak
+    // friction and stiffness coefficients, which are required and non-zero since the
+    // stiffness factors B = K / (C D) are undefined without them.
+    pub fn from_tir_str(text: &str) -> Result<Self, String> {
+        let mut tyre: MagicFormula = MagicFormula::default();
+        let mut given: Vec<String> = Vec::new();
+
+        for (i, raw_line) in text.lines().enumerate() {
+            let line: &str = raw_line.split(['$', '!']).next().unwrap_or("").trim();
+            if line.is_empty() || line.starts_with('[') {
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((k, v)) => (k.trim().to_uppercase(), v.trim()),
+                None => return Err(format!("line {} is not a KEY = value pair", i + 1)),
+            };
+            if value.starts_with('\'') || value.starts_with('"') {
+                continue;
+            }
+
+            let field: &mut f64 = match tyre.coefficient_mut(&key) {
+                Some(f) => f,
+                None => continue,
+            };
+            *field = match value.parse::<f64>() {
+                Ok(v) if v.is_finite() => v,
+                _ => return Err(format!("invalid value '{}' for {}", value, key)),
+            };
+            given.push(key);
+        }
+
+        for key in REQUIRED_COEFFICIENTS {
+            if !given.iter().any(|k| k == key) {
+                return Err(format!("{} is missing", key));
+            }
+        }
+        if tyre.nominal_load <= 0.0 {
+            return Err(format!("invalid nominal load {}", tyre.nominal_load));
+        }
+        for key in REQUIRED_COEFFICIENTS.iter().skip(1) {
+            if *tyre.coefficient_mut(key).unwrap() == 0.0 {
+                return Err(format!("{} must be non-zero", key));
+            }
+        }
+        return Ok(tyre);
+    }
+
+    fn coefficient_mut(&mut self, key: &str) -> Option<&mut f64> {
+        return match key {
+            "FNOMIN" => Some(&mut self.nominal_load),
+            "PCX1" => Some(&mut self.pcx1),
+            "PDX1" => Some(&mut self.pdx1),
+            "PDX2" => Some(&mut self.pdx2),
+            "PEX1" => Some(&mut self.pex1),
+            "PEX2" => Some(&mut self.pex2),
+            "PEX3" => Some(&mut self.pex3),
+            "PEX4" => Some(&mut self.pex4),
+            "PKX1" => Some(&mut self.pkx1),
+            "PKX2" => Some(&mut self.pkx2),
+            "PKX3" => Some(&mut self.pkx3),
+            "PHX1" => Some(&mut self.phx1),
+            "PHX2" => Some(&mut self.phx2),
+            "PVX1" => Some(&mut self.pvx1),
+            "PVX2" => Some(&mut self.pvx2),
+            "PCY1" => Some(&mut self.pcy1),
+            "PDY1" => Some(&mut self.pdy1),
+            "PDY2" => Some(&mut self.pdy2),
+            "PEY1" => Some(&mut self.pey1),
+            "PEY2" => Some(&mut self.pey2),
+            "PEY3" => Some(&mut self.pey3),
+            "PKY1" => Some(&mut self.pky1),
+            "PKY2" => Some(&mut self.pky2),
+            "PHY1" => Some(&mut self.phy1),
+            "PHY2" => Some(&mut self.phy2),
+            "PVY1" => Some(&mut self.pvy1),
+            "PVY2" => Some(&mut self.pvy2),
+            "RBX1" => Some(&mut self.rbx1),
+            "RBX2" => Some(&mut self.rbx2),
+            "RCX1" => Some(&mut self.rcx1),
+            "RHX1" => Some(&mut self.rhx1),
+            "RBY1" => Some(&mut self.rby1),
+            "RBY2" => Some(&mut self.rby2),
+            "RBY3" => Some(&mut self.rby3),
+            "RCY1" => Some(&mut self.rcy1),
+            "RHY1" => Some(&mut self.rhy1),
+            "RHY2" => Some(&mut self.rhy2),
+            _ => None,
+        };
+    }
+
+    // Normalised load increment dfz = (fz - Fz0) / Fz0
+    fn load_increment(&self, fz: f64) -> f64 {
+        return (fz - self.nominal_load) / self.nominal_load;
+    }
+
+    // Pure longitudinal force Fx0 at slip ratio kappa [-] and normal load fz [N]
+    #[allow(dead_code)]
+    pub fn longitudinal_force(&self, kappa: f64, fz: f64) -> SlipForce {
+        if fz <= 0.0 {
+            return NO_FORCE;
+        }
+        let fz0: f64 = self.nominal_load;
+        let dfz: f64 = self.load_increment(fz);
+        let ddfz: f64 = 1.0 / fz0; // d(dfz)/dfz
+
+        let shx: f64 = self.phx1 + self.phx2 * dfz;
+        let kappa_x: f64 = kappa + shx;
+
+        let cx: f64 = self.pcx1;
+        let mu_x: f64 = self.pdx1 + self.pdx2 * dfz;
+        let dx: f64 = mu_x * fz;
+        let d_dx: f64 = mu_x + fz * self.pdx2 * ddfz;
+
+        let stiffness: f64 = self.pkx1 + self.pkx2 * dfz;
+        let exponential: f64 = f64::exp(self.pkx3 * dfz);
+        let kx: f64 = fz * stiffness * exponential;
+        let d_kx: f64 =
+            exponential * (stiffness + fz * self.pkx2 * ddfz + fz * stiffness * self.pkx3 * ddfz);
+
+        let bx: f64 = kx / (cx * dx);
+        let d_bx: f64 = (d_kx * dx - kx * d_dx) / (cx * dx.powi(2));
+
+        let asymmetry: f64 = 1.0 - self.pex4 * kappa_x.signum();
+        let ex: f64 = (self.pex1 + self.pex2 * dfz + self.pex3 * dfz.powi(2)) * asymmetry;
+        let d_ex: f64 = (self.pex2 + 2.0 * self.pex3 * dfz) * ddfz * asymmetry;
+
+        let svx: f64 = fz * (self.pvx1 + self.pvx2 * dfz);
+        let d_svx: f64 = self.pvx1 + self.pvx2 * dfz + fz * self.pvx2 * ddfz;
+
+        let mf: MagicFormulaValue = magic_formula(bx, cx, dx, ex, kappa_x);
+        return SlipForce {
+            force: mf.y + svx,
+            d_slip: mf.dy_dx,
+            d_load: mf.dy_dd * d_dx
+                + mf.dy_db * d_bx
+                + mf.dy_de * d_ex
+                + mf.dy_dx * self.phx2 * ddfz
+                + d_svx,
+        };
+    }
+
+    // Pure lateral force Fy0 at slip angle alpha [rad] and normal load fz [N]
+    pub fn lateral_force(&self, alpha: f64, fz: f64) -> SlipForce {
+        if fz <= 0.0 {
+            return NO_FORCE;
+        }
+        let fz0: f64 = self.nominal_load;
+        let dfz: f64 = self.load_increment(fz);
+        let ddfz: f64 = 1.0 / fz0; // d(dfz)/dfz
+
+        let shy: f64 = self.phy1 + self.phy2 * dfz;
+        let alpha_y: f64 = alpha + shy;
+
+        let cy: f64 = self.pcy1;
+        let mu_y: f64 = self.pdy1 + self.pdy2 * dfz;
+        let dy: f64 = mu_y * fz;
+        let d_dy: f64 = mu_y + fz * self.pdy2 * ddfz;
+
+        // Cornering stiffness saturates with load, peaking at fz = PKY2 * Fz0
+        let q: f64 = fz / (self.pky2 * fz0);
+        let ky: f64 = self.pky1 * fz0 * f64::sin(2.0 * q.atan());
+        let d_ky: f64 = self.pky1 * fz0 * f64::cos(2.0 * q.atan()) * 2.0
+            / (1.0 + q.powi(2))
+            / (self.pky2 * fz0);
+
+        let by: f64 = ky / (cy * dy);
+        let d_by: f64 = (d_ky * dy - ky * d_dy) / (cy * dy.powi(2));
+
+        let asymmetry: f64 = 1.0 - self.pey3 * alpha_y.signum();
+        let ey: f64 = (self.pey1 + self.pey2 * dfz) * asymmetry;
+        let d_ey: f64 = self.pey2 * ddfz * asymmetry;
+
+        let svy: f64 = fz * (self.pvy1 + self.pvy2 * dfz);
+        let d_svy: f64 = self.pvy1 + self.pvy2 * dfz + fz * self.pvy2 * ddfz;
+
+        let mf: MagicFormulaValue = magic_formula(by, cy, dy, ey, alpha_y);
+        return SlipForce {
+            force: mf.y + svy,
+            d_slip: mf.dy_dx,
+            d_load: mf.dy_dd * d_dy
+                + mf.dy_db * d_by
+                + mf.dy_de * d_ey
+                + mf.dy_dx * self.phy2 * ddfz
+                + d_svy,
+        };
+    }
+
+    // Combined slip forces, the pure slip forces scaled by the cosine weighting functions
+    // G_x(alpha, kappa) and G_y(kappa, alpha, fz)
+    #[allow(dead_code)]
+    pub fn combined_forces(&self, kappa: f64, alpha: f64, fz: f64) -> CombinedForces {
+        let fx0: SlipForce = self.longitudinal_force(kappa, fz);
+        let fy0: SlipForce = self.lateral_force(alpha, fz);
+        let ddfz: f64 = 1.0 / self.nominal_load;
+
+        // Longitudinal force reduced by the slip angle
+        let z: f64 = self.rbx2 * kappa;
+        let bx_alpha: f64 = self.rbx1 * z.atan().cos();
+        let d_bx_alpha: f64 = -self.rbx1 * z.atan().sin() * self.rbx2 / (1.0 + z.powi(2));
+        let gx: WeightingValue = weighting(bx_alpha, self.rcx1, self.rhx1, alpha);
+
+        // Lateral force reduced by the slip ratio
+        let w: f64 = self.rby2 * (alpha - self.rby3);
+        let by_kappa: f64 = self.rby1 * w.atan().cos();
+        let d_by_kappa: f64 = -self.rby1 * w.atan().sin() * self.rby2 / (1.0 + w.powi(2));
+        let shy_kappa: f64 = self.rhy1 + self.rhy2 * self.load_increment(fz);
+        let gy: WeightingValue = weighting(by_kappa, self.rcy1, shy_kappa, kappa);
+
+        return CombinedForces {
+            fx: gx.g * fx0.force,
+            fy: gy.g * fy0.force,
+            dfx: [
+                gx.dg_db * d_bx_alpha * fx0.force + gx.g * fx0.d_slip,
+                gx.dg_ds * fx0.force,
+                gx.g * fx0.d_load,
+            ],
+            dfy: [
+                gy.dg_ds * fy0.force,
+                gy.dg_db * d_by_kappa * fy0.force + gy.g * fy0.d_slip,
+                gy.dg_dshift * self.rhy2 * ddfz * fy0.force + gy.g * fy0.d_load,
+            ],
+        };
+    }
+
+    // Sign mapping a slip angle in the TyreLaw convention to the convention of the
+    // coefficients. Property files in the ISO convention have PKY1 < 0, meaning that their
+    // slip angle is measured opposite to the TyreLaw slip angle.
+    fn slip_angle_sign(&self) -> f64 {
+        return if self.pky1 < 0.0 { -1.0 } else { 1.0 };
+    }
+}
+
+impl TyreLaw for MagicFormula {
+    fn lateral_force(&self, alpha: f64, fz: f64) -> f64 {
+        return MagicFormula::lateral_force(self, self.slip_angle_sign() * alpha, fz).force;
+    }
+
+    fn lateral_force_dalpha(&self, alpha: f64, fz: f64) -> f64 {
+        let sign: f64 = self.slip_angle_sign();
+        return sign * MagicFormula::lateral_force(self, sign * alpha, fz).d_slip;
+    }
+
+    fn lateral_force_dfz(&self, alpha: f64, fz: f64) -> f64 {
+        let sign: f64 = self.slip_angle_sign();
+        return MagicFormula::lateral_force(self, sign * alpha, fz).d_load;
+    }
+}
+
+fn magic_formula(b: f64, c: f64, d: f64, e: f64, x: f64) -> MagicFormulaValue {
+    let bx: f64 = b * x;
+    let t: f64 = bx - e * (bx - bx.atan());
+    let phi: f64 = c * t.atan();
+
+    // dy/dt and dt/d(bx)
+    let dy_dt: f64 = d * c * phi.cos() / (1.0 + t.powi(2));
+    let dt_dbx: f64 = 1.0 - e * bx.powi(2) / (1.0 + bx.powi(2));
+
+    return MagicFormulaValue {
+        y: d * phi.sin(),
+        dy_dx: dy_dt * dt_dbx * b,
+        dy_db: dy_dt * dt_dbx * x,
+        dy_dd: phi.sin(),
+        dy_de: -dy_dt * (bx - bx.atan()),
+    };
+}
+
+fn weighting(b: f64, c: f64, shift: f64, slip: f64) -> WeightingValue {
+    let (x_num, x_den) = (b * (slip + shift), b * shift);
+    let num: f64 = f64::cos(c * x_num.atan());
+    let den: f64 = f64::cos(c * x_den.atan());
+
+    // Derivatives of the numerator and denominator with respect to their arguments
+    let dnum: f64 = -f64::sin(c * x_num.atan()) * c / (1.0 + x_num.powi(2));
+    let dden: f64 = -f64::sin(c * x_den.atan()) * c / (1.0 + x_den.powi(2));
+
+    return WeightingValue {
+        g: num / den,
+        dg_ds: dnum * b / den,
+        dg_dshift: (dnum * b * den - num * dden * b) / den.powi(2),
+        dg_db: (dnum * (slip + shift) * den - num * dden * shift) / den.powi(2),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Passenger car coefficients in the layout of an MF 5.2 property file
+    const EXAMPLE_TIR: &str = "\
+[MDI_HEADER]
+FILE_TYPE                ='tir'
+FILE_VERSION             =3.0
+[MODEL]
+PROPERTY_FILE_FORMAT     ='MF_05'
+[VERTICAL]
+FNOMIN                   = 4000         $Nominal wheel load
+[LONGITUDINAL_COEFFICIENTS]
+PCX1                     = 1.6411       $Shape factor Cfx for longitudinal force
+PDX1                     = 1.1739       $Longitudinal friction Mux at Fznom
+PDX2                     = -0.16395     $Variation of friction Mux with load
+PEX1                     = 0.46403      $Longitudinal curvature Efx at Fznom
+PEX2                     = 0.25022      $Variation of curvature Efx with load
+PEX3                     = 0.067842     $Variation of curvature Efx with load squared
+PEX4                     = -3.7604e-05  $Factor in curvature Efx while driving
+PKX1                     = 22.303       $Longitudinal slip stiffness Kfx/Fz at Fznom
+PKX2                     = 0.48896      $Variation of slip stiffness Kfx/Fz with load
+PKX3                     = 0.21253      $Exponent in slip stiffness Kfx/Fz with load
+PHX1                     = 0.00021484   $Horizontal shift Shx at Fznom
+PHX2                     = 0.00057532   $Variation of shift Shx with load
+PVX1                     = -1.2903e-05  $Vertical shift Svx/Fz at Fznom
+PVX2                     = -2.4573e-05  $Variation of shift Svx/Fz with load
+RBX1                     = 13.276       $Slope factor for combined slip Fx reduction
+RBX2                     = -13.778      $Variation of slope Fx reduction with kappa
+RCX1                     = 1.2568       $Shape factor for combined slip Fx reduction
+RHX1                     = 0            $Shift factor for combined slip Fx reduction
+[LATERAL_COEFFICIENTS]
+PCY1                     = 1.3507       $Shape factor Cfy for lateral forces
+PDY1                     = 1.0489       $Lateral friction Muy
+PDY2                     = -0.18033     $Variation of friction Muy with load
+PEY1                     = -0.0074722   $Lateral curvature Efy at Fznom
+PEY2                     = -0.0063208   $Variation of curvature Efy with load
+PEY3                     = -9.9935      $Zero order camber dependency of curvature Efy
+PKY1                     = -21.92       $Maximum value of stiffness Kfy/Fznom
+PKY2                     = 2.0012       $Load at which Kfy reaches maximum value
+PHY1                     = 0.0026747    $Horizontal shift Shy at Fznom
+PHY2                     = 8.9094e-05   $Variation of shift Shy with load
+PVY1                     = 0.037318     $Vertical shift in Svy/Fz at Fznom
+PVY2                     = -0.010049    $Variation of shift Svy/Fz with load
+RBY1                     = 7.1433       $Slope factor for combined Fy reduction
+RBY2                     = 9.1916       $Variation of slope Fy reduction with alpha
+RBY3                     = -0.027856    $Shift term for alpha in slope Fy reduction
+RCY1                     = 1.0719       $Shape factor for combined Fy reduction
+RHY1                     = 5.7448e-06   $Shift factor for combined Fy reduction
+RHY2                     = -3.1368e-05  $Shift factor for combined Fy reduction with load
+";
+
+    // Only the required coefficients
+    const BASIC_TIR: &str = "\
+FNOMIN = 4000
+PCX1 = 1.6
+PDX1 = 1.2
+PKX1 = 22
+PCY1 = 1.3
+PDY1 = 1.0
+PKY1 = 20
+PKY2 = 2
+";
+
+    fn example_tyre() -> MagicFormula {
+        return MagicFormula::from_tir_str(EXAMPLE_TIR).unwrap();
+    }
+
+    fn central_difference<F: Fn(f64) -> f64>(f: F, x: f64, h: f64) -> f64 {
+        return (f(x + h) - f(x - h)) / (2.0 * h);
+    }
+
+    #[test]
+    fn test_parse_tir() {
+        let tyre: MagicFormula = example_tyre();
+        assert_eq!(tyre.nominal_load, 4000.0);
+        assert_eq!(tyre.pcx1, 1.6411);
+        assert_eq!(tyre.pex4, -3.7604e-05);
+        assert_eq!(tyre.pky1, -21.92);
+        assert_eq!(tyre.rhy2, -3.1368e-05);
+
+        let parse = |extra: &str| MagicFormula::from_tir_str(&format!("{}{}", BASIC_TIR, extra));
+        assert!(parse("").is_ok());
+        assert!(parse("pcx1 = 1.6 $ lower case\n").is_ok());
+        assert!(parse("PCX1 1.6\n").is_err());
+        assert!(parse("PCX1 = abc\n").is_err());
+        assert!(parse("FNOMIN = -1\n").is_err());
+        assert!(MagicFormula::from_tir_str("PCX1 = 1.6\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_tir_required_coefficients() {
+        // Every required coefficient must be given, and all but FNOMIN must be non-zero
+        for key in REQUIRED_COEFFICIENTS {
+            let without: String = BASIC_TIR
+                .lines()
+                .filter(|line| !line.starts_with(&format!("{} ", key)))
+                .map(|line| format!("{}\n", line))
+                .collect();
+            let error: String = MagicFormula::from_tir_str(&without).unwrap_err();
+            assert_eq!(error, format!("{} is missing", key));
+        }
+        for key in REQUIRED_COEFFICIENTS.iter().skip(1) {
+            let zero: String = format!("{}{} = 0\n", BASIC_TIR, key);
+            let error: String = MagicFormula::from_tir_str(&zero).unwrap_err();
+            assert_eq!(error, format!("{} must be non-zero", key));
+        }
+        assert!(MagicFormula::from_tir_str("FNOMIN = 4000\nPKY2 = 2\n").is_err());
+
+        // The forces of a minimal file are finite
+        let tyre: MagicFormula = MagicFormula::from_tir_str(BASIC_TIR).unwrap();
+        let forces: CombinedForces = tyre.combined_forces(0.05, 0.05, 4000.0);
+        assert!(forces.fx.is_finite() && forces.fy.is_finite());
+        assert!(forces
+            .dfx
+            .iter()
+            .chain(forces.dfy.iter())
+            .all(|d| d.is_finite()));
+    }
+
+    #[test]
+    fn test_basic_curve() {
+        // With the shifts, curvature load terms and combined slip coefficients absent, the
+        // forces at nominal load reduce to y = D sin(C atan(B x - E (B x - atan(B x)))) with
+        // D = mu Fz, B C D = K and the cornering stiffness K = PKY1 Fz0 sin(2 atan(1 / PKY2))
+        let tir: String = format!("{}PEX1 = 0.5\nPEY1 = -1.0\n", BASIC_TIR);
+        let tyre: MagicFormula = MagicFormula::from_tir_str(&tir).unwrap();
+        let fz: f64 = 4000.0;
+        let curve = |k: f64, c: f64, d: f64, e: f64, x: f64| -> f64 {
+            let b: f64 = k / (c * d);
+            let t: f64 = b * x - e * (b * x - f64::atan(b * x));
+            return d * f64::sin(c * t.atan());
+        };
+        let ky: f64 = 20.0 * fz * f64::sin(2.0 * f64::atan(0.5));
+        for i in 0..=20 {
+            let slip: f64 = -0.3 + 0.03 * i as f64;
+            let fx: f64 = curve(22.0 * fz, 1.6, 1.2 * fz, 0.5, slip);
+            let fy: f64 = curve(ky, 1.3, 1.0 * fz, -1.0, slip);
+            assert!((tyre.longitudinal_force(slip, fz).force - fx).abs() < 1e-9);
+            assert!((tyre.lateral_force(slip, fz).force - fy).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_reference_values() {
+        // Forces of the example coefficients from a separate evaluation of the MF 5.2
+        // equations, including the shifts, load variations and curvature asymmetry
+        let tyre: MagicFormula = example_tyre();
+        let close = |value: f64, reference: f64| (value - reference).abs() < 1e-6 * reference.abs();
+        for &(kappa, fz, fx) in &[
+            (0.02, 4000.0, 1716.6920372987488),
+            (0.1, 4000.0, 4531.47099366782),
+            (-0.1, 6000.0, -6437.582001746141),
+            (0.3, 2000.0, 2373.0701424862705),
+        ] {
+            assert!(close(tyre.longitudinal_force(kappa, fz).force, fx));
+        }
+        for &(alpha, fz, fy) in &[
+            (0.02, 4000.0, -1368.605873993727),
+            (-0.1, 4000.0, 4017.9160551973223),
+            (0.15, 6000.0, -5487.096091430456),
+            (-0.05, 2000.0, 1656.52332815582),
+        ] {
+            assert!(close(tyre.lateral_force(alpha, fz).force, fy));
+        }
+        for &(kappa, alpha, fz, fx, fy) in &[
+            (0.05, 0.05, 4000.0, 2809.223728309338, -2690.5995051091536),
+            (-0.08, -0.1, 3000.0, -1971.9447718359847, 2833.220352498204),
+        ] {
+            let forces: CombinedForces = tyre.combined_forces(kappa, alpha, fz);
+            assert!(close(forces.fx, fx) && close(forces.fy, fy));
+        }
+    }
+
+    #[test]
+    fn test_unloaded_wheel() {
+        // Without load the forces and their derivatives vanish instead of becoming NaN
+        let tyre: MagicFormula = example_tyre();
+        for fz in [0.0, -500.0] {
+            assert_eq!(tyre.longitudinal_force(0.1, fz), NO_FORCE);
+            assert_eq!(tyre.lateral_force(0.1, fz), NO_FORCE);
+            let forces: CombinedForces = tyre.combined_forces(0.1, 0.1, fz);
+            assert_eq!((forces.fx, forces.fy), (0.0, 0.0));
+            assert!(forces
+                .dfx
+                .iter()
+                .chain(forces.dfy.iter())
+                .all(|d| *d == 0.0));
+            assert_eq!(TyreLaw::lateral_force_dfz(&tyre, 0.1, fz), 0.0);
+        }
+
+        // The force goes to zero with the load, so the cut-off is continuous
+        assert!(tyre.lateral_force(0.1, 1e-3).force.abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_pure_slip_characteristics() {
+        let tyre: MagicFormula = example_tyre();
+        let fz: f64 = 4000.0;
+
+        // Slope through the shifted origin equals the slip and cornering stiffness
+        let kx: f64 = fz * 22.303;
+        let fx: SlipForce = tyre.longitudinal_force(-0.00021484, fz);
+        assert!((fx.d_slip - kx).abs() < 1e-6 * kx);
+        let ky: f64 = -21.92 * fz * f64::sin(2.0 * f64::atan(1.0 / 2.0012));
+        let fy: SlipForce = tyre.lateral_force(-0.0026747, fz);
+        assert!((fy.d_slip - ky).abs() < 1e-6 * ky.abs());
+
+        // Peak force equals mu Fz above the vertical shift
+        let svx: f64 = fz * -1.2903e-05;
+        let peak_x: f64 = (0..2000)
+            .map(|i| tyre.longitudinal_force(i as f64 * 1e-3, fz).force - svx)
+            .fold(0.0, f64::max);
+        assert!((peak_x - 1.1739 * fz).abs() < 1e-3 * fz);
+        let svy: f64 = fz * 0.037318;
+        let peak_y: f64 = (0..2000)
+            .map(|i| -(tyre.lateral_force(i as f64 * 1e-3, fz).force - svy))
+            .fold(0.0, f64::max);
+        assert!((peak_y - 1.0489 * fz).abs() < 1e-3 * fz);
+
+        // Large slip approaches the asymptote D sin(C pi / 2)
+        let asymptote: f64 = 1.1739 * fz * f64::sin(1.6411 * std::f64::consts::FRAC_PI_2);
+        let sliding: f64 = tyre.longitudinal_force(1e6, fz).force - svx;
+        assert!((sliding - asymptote).abs() < 1e-2 * fz);
+
+        // Load sensitivity: the friction coefficient drops with load
+        let fz_high: f64 = 8000.0;
+        let peak_high: f64 = (0..2000)
+            .map(|i| tyre.longitudinal_force(i as f64 * 1e-3, fz_high).force)
+            .fold(0.0, f64::max);
+        assert!(peak_high / fz_high < peak_x / fz);
+    }
+
+    #[test]
+    fn test_pure_slip_derivatives() {
+        let tyre: MagicFormula = example_tyre();
+        for &(slip, fz) in &[(0.05, 4000.0), (-0.12, 2500.0), (0.4, 6500.0)] {
+            let fx: SlipForce = tyre.longitudinal_force(slip, fz);
+            let fd_slip: f64 =
+                central_difference(|k| tyre.longitudinal_force(k, fz).force, slip, 1e-7);
+            let fd_load: f64 =
+                central_difference(|f| tyre.longitudinal_force(slip, f).force, fz, 1e-3);
+            assert!((fx.d_slip - fd_slip).abs() < 1e-5 * (1.0 + fx.d_slip.abs()));
+            assert!((fx.d_load - fd_load).abs() < 1e-6 * (1.0 + fx.d_load.abs()));
+
+            let fy: SlipForce = tyre.lateral_force(slip, fz);
+            let fd_slip: f64 = central_difference(|a| tyre.lateral_force(a, fz).force, slip, 1e-7);
+            let fd_load: f64 = central_difference(|f| tyre.lateral_force(slip, f).force, fz, 1e-3);
+            assert!((fy.d_slip - fd_slip).abs() < 1e-5 * (1.0 + fy.d_slip.abs()));
+            assert!((fy.d_load - fd_load).abs() < 1e-6 * (1.0 + fy.d_load.abs()));
+        }
+    }
+
+    #[test]
+    fn test_combined_slip() {
+        let tyre: MagicFormula = example_tyre();
+        let fz: f64 = 4000.0;
+
+        // Without the other slip quantity the combined forces reduce to pure slip
+        let combined: CombinedForces = tyre.combined_forces(0.1, 0.0, fz);
+        assert!((combined.fx - tyre.longitudinal_force(0.1, fz).force).abs() < 1e-9);
+
+        // Adding slip in the other direction reduces both forces
+        let combined: CombinedForces = tyre.combined_forces(0.1, 0.1, fz);
+        assert!(combined.fx.abs() < tyre.longitudinal_force(0.1, fz).force.abs());
+        assert!(combined.fy.abs() < tyre.lateral_force(0.1, fz).force.abs());
+    }
+
+    #[test]
+    fn test_combined_slip_derivatives() {
+        let tyre: MagicFormula = example_tyre();
+        for &(kappa, alpha, fz) in &[(0.05, 0.03, 4000.0), (-0.08, -0.1, 3000.0)] {
+            let forces: CombinedForces = tyre.combined_forces(kappa, alpha, fz);
+            let fd: [[f64; 2]; 3] = [
+                [
+                    central_difference(|k| tyre.combined_forces(k, alpha, fz).fx, kappa, 1e-7),
+                    central_difference(|k| tyre.combined_forces(k, alpha, fz).fy, kappa, 1e-7),
+                ],
+                [
+                    central_difference(|a| tyre.combined_forces(kappa, a, fz).fx, alpha, 1e-7),
+                    central_difference(|a| tyre.combined_forces(kappa, a, fz).fy, alpha, 1e-7),
+                ],
+                [
+                    central_difference(|f| tyre.combined_forces(kappa, alpha, f).fx, fz, 1e-3),
+                    central_difference(|f| tyre.combined_forces(kappa, alpha, f).fy, fz, 1e-3),
+                ],
+            ];
+            for (j, [fd_x, fd_y]) in fd.iter().enumerate() {
+                assert!((forces.dfx[j] - fd_x).abs() < 1e-5 * (1.0 + fd_x.abs()));
+                assert!((forces.dfy[j] - fd_y).abs() < 1e-5 * (1.0 + fd_y.abs()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_tyre_law_convention() {
+        // The ISO property file is mapped so that a positive slip angle gives a positive force
+        let tyre: MagicFormula = example_tyre();
+        let fz: f64 = 4000.0;
+        assert!(TyreLaw::lateral_force(&tyre, 0.05, fz) > 0.0);
+        assert!(TyreLaw::lateral_force(&tyre, -0.05, fz) < 0.0);
+        assert!(TyreLaw::lateral_force_dalpha(&tyre, 0.0, fz) > 0.0);
+
+        let fd: f64 = central_difference(|a| TyreLaw::lateral_force(&tyre, a, fz), 0.05, 1e-7);
+        assert!((TyreLaw::lateral_force_dalpha(&tyre, 0.05, fz) - fd).abs() < 1e-3);
+        let fd: f64 = central_difference(|f| TyreLaw::lateral_force(&tyre, 0.05, f), fz, 1e-3);
+        assert!((TyreLaw::lateral_force_dfz(&tyre, 0.05, fz) - fd).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,689 @@
+// Vehicle definition files, constructing a vehicle model from its parameters.
+//
+// The files use a subset of TOML: top-level and [section] keys with numbers, "strings" and
+// arrays of numbers as values, and # comments. The top level holds the name and the model
+// type, one of point_mass, extended_point_mass, kinematic_bicycle, dynamic_bicycle or
+// double_track. The [mass], [inertia], [geometry], [tyre], [aero] and [powertrain] sections
+// hold the parameters, in SI units with angles in radians and motor speeds in rpm. Every
+// required field is checked for presence and range, and fields the model does not use are
+// rejected so typos do not go unnoticed. Tyre property and torque map files are resolved
+// relative to the vehicle file.
+//
+//     name = "Formula Student"
+//     model = "dynamic_bicycle"
+//
+//     [mass]
+//     mass = 280.0 # [kg]
+//     ...
+use crate::aero::{Aero, AeroState, Drs};
+use crate::model::double_track::{DoubleTrack, DoubleTrackParameters};
+use crate::model::dynamic_bicycle::DynamicBicycle;
+use crate::model::extended_point_mass::{ExtendedPointMass, ExtendedPointMassParameters};
+use crate::model::kinematic_bicycle::KinematicBicycle;
+use crate::model::metadata::{generic_variables, ModelMetadata, Variable};
+use crate::model::point_mass::PointMass;
+use crate::powertrain::{MotorKind, Powertrain, TorqueMap};
+use crate::tyre::pacejka::MagicFormula;
+use crate::tyre::{LinearTyre, SaturatingTyre, TyreLaw};
+use simulation_toolbox::Model;
+use std::path::Path;
+
+// Only one vehicle is built per run, so the variant sizes do not matter
+#[allow(clippy::large_enum_variant)]
+pub enum Vehicle {
+    PointMass(PointMass),
+    ExtendedPointMass(ExtendedPointMass),
+    KinematicBicycle(KinematicBicycle),
+    DynamicBicycle(DynamicBicycle),
+    DoubleTrack(DoubleTrack),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Number(f64),
+    Text(String),
+    Numbers(Vec<f64>),
+}
+
+// Front and rear tyre laws
+type AxleTyres = (Box<dyn TyreLaw>, Box<dyn TyreLaw>);
+
+// Parsed fields keyed by section, with the section "" for the top level
+struct Fields {
+    entries: Vec<(String, String, Value)>,
+    used: Vec<bool>,
+}
+
+// VEHICLE IMPLEMENTATION ++++++++++++++++++++++++++
+impl Vehicle {
+    #[allow(dead_code)]
+    pub fn read_from_file(file_path: &str) -> Self {
+        let text: String = match std::fs::read_to_string(file_path) {
+            Ok(t) => t,
+            Err(e) => panic!("Failed to read vehicle file {}: {}", file_path, e),
+        };
+        let base_dir: &Path = Path::new(file_path).parent().unwrap_or(Path::new(""));
+        return match Self::from_toml_str(&text, base_dir) {
+            Ok(vehicle) => vehicle,
+            Err(e) => panic!("Invalid vehicle file {}: {}", file_path, e),
+        };
+    }
+
+    // Build the vehicle from the contents of a definition file, with referenced files
+    // resolved relative to base_dir
+    pub fn from_toml_str(text: &str, base_dir: &Path) -> Result<Self, String> {
+        let mut fields: Fields = Fields::parse(text)?;
+        let name: String = fields.text("", "name")?;
+        let model: String = fields.text("", "model")?;
+
+        let vehicle: Vehicle = match model.as_str() {
+            "point_mass" => {
+                let mass: f64 = fields.positive("mass", "mass", "kg")?;
+                Vehicle::PointMass(PointMass::new(&name, mass))
+            }
+            "extended_point_mass" => {
+                Vehicle::ExtendedPointMass(extended_point_mass(&name, &mut fields, base_dir)?)
+            }
+            "kinematic_bicycle" => {
+                let wheelbase: f64 = fields.positive("geometry", "wheelbase", "m")?;
+                let rear_to_cg: f64 = fields.positive("geometry", "rear_to_cg", "m")?;
+                if rear_to_cg > wheelbase {
+                    return Err(format!(
+                        "geometry.rear_to_cg [m] must not exceed geometry.wheelbase, got {}",
+                        rear_to_cg
+                    ));
+                }
+                Vehicle::KinematicBicycle(KinematicBicycle::new(&name, wheelbase, rear_to_cg))
+            }
+            "dynamic_bicycle" => {
+                let mass: f64 = fields.positive("mass", "mass", "kg")?;
+                let yaw_inertia: f64 = fields.positive("inertia", "yaw_inertia", "kg m^2")?;
+                let front_to_cg: f64 = fields.positive("geometry", "front_to_cg", "m")?;
+                let rear_to_cg: f64 = fields.positive("geometry", "rear_to_cg", "m")?;
+                let (front_tyre, rear_tyre) = tyres(&mut fields, base_dir)?;
+                Vehicle::DynamicBicycle(DynamicBicycle::new(
+                    &name,
+                    mass,
+                    yaw_inertia,
+                    front_to_cg,
+                    rear_to_cg,
+                    front_tyre,
+                    rear_tyre,
+                ))
+            }
+            "double_track" => {
+                let parameters = DoubleTrackParameters {
+                    mass: fields.positive("mass", "mass", "kg")?,
+                    yaw_inertia: fields.positive("inertia", "yaw_inertia", "kg m^2")?,
+                    front_to_cg: fields.positive("geometry", "front_to_cg", "m")?,
+                    rear_to_cg: fields.positive("geometry", "rear_to_cg", "m")?,
+                    front_track: fields.positive("geometry", "front_track", "m")?,
+                    rear_track: fields.positive("geometry", "rear_track", "m")?,
+                    cg_height: fields.positive("geometry", "cg_height", "m")?,
+                    front_roll_share: fields.fraction("geometry", "front_roll_share")?,
+                    wheel_radius: fields.positive("geometry", "wheel_radius", "m")?,
+                };
+                let (front_tyre, rear_tyre) = tyres(&mut fields, base_dir)?;
+                Vehicle::DoubleTrack(DoubleTrack::new(&name, parameters, front_tyre, rear_tyre))
+            }
+            _ => {
+                return Err(format!(
+                    "unknown model '{}', expected point_mass, extended_point_mass, \
+                     kinematic_bicycle, dynamic_bicycle or double_track",
+                    model
+                ))
+            }
+        };
+
+        fields.check_all_used(&model)?;
+        return Ok(vehicle);
+    }
+}
+
+impl Model for Vehicle {
+    fn name(&self) -> &str {
+        return match self {
+            Vehicle::PointMass(m) => m.name(),
+            Vehicle::ExtendedPointMass(m) => m.name(),
+            Vehicle::KinematicBicycle(m) => m.name(),
+            Vehicle::DynamicBicycle(m) => m.name(),
+            Vehicle::DoubleTrack(m) => m.name(),
+        };
+    }
+
+    fn n_x(&self) -> usize {
+        return match self {
+            Vehicle::PointMass(m) => m.n_x(),
+            Vehicle::ExtendedPointMass(m) => m.n_x(),
+            Vehicle::KinematicBicycle(m) => m.n_x(),
+            Vehicle::DynamicBicycle(m) => m.n_x(),
+            Vehicle::DoubleTrack(m) => m.n_x(),
+        };
+    }
+
+    fn n_u(&self) -> usize {
+        return match self {
+            Vehicle::PointMass(m) => m.n_u(),
+            Vehicle::ExtendedPointMass(m) => m.n_u(),
+            Vehicle::KinematicBicycle(m) => m.n_u(),
+            Vehicle::DynamicBicycle(m) => m.n_u(),
+            Vehicle::DoubleTrack(m) => m.n_u(),
+        };
+    }
+
+    fn fun(&self, x: &Vec<f64>, u: &Vec<f64>, t: f64) -> Vec<f64> {
+        return match self {
+            Vehicle::PointMass(m) => m.fun(x, u, t),
+            Vehicle::ExtendedPointMass(m) => m.fun(x, u, t),
+            Vehicle::KinematicBicycle(m) => m.fun(x, u, t),
+            Vehicle::DynamicBicycle(m) => m.fun(x, u, t),
+            Vehicle::DoubleTrack(m) => m.fun(x, u, t),
+        };
+    }
+
+    fn jac(&self, x: &Vec<f64>, u: &Vec<f64>, t: f64) -> Vec<f64> {
+        return match self {
+            Vehicle::PointMass(m) => m.jac(x, u, t),
+            Vehicle::ExtendedPointMass(m) => m.jac(x, u, t),
+            Vehicle::KinematicBicycle(m) => m.jac(x, u, t),
+            Vehicle::DynamicBicycle(m) => m.jac(x, u, t),
+            Vehicle::DoubleTrack(m) => m.jac(x, u, t),
+        };
+    }
+}
+
+// Models without metadata are described by generic names
+impl ModelMetadata for Vehicle {
+    fn states(&self) -> Vec<Variable> {
+        return match self {
+            Vehicle::PointMass(m) => m.states(),
+            Vehicle::KinematicBicycle(m) => m.states(),
+            _ => generic_variables("x", self.n_x()),
+        };
+    }
+
+    fn inputs(&self) -> Vec<Variable> {
+        return match self {
+            Vehicle::PointMass(m) => m.inputs(),
+            Vehicle::KinematicBicycle(m) => m.inputs(),
+            _ => generic_variables("u", self.n_u()),
+        };
+    }
+}
+
+fn extended_point_mass(
+    name: &str,
+    fields: &mut Fields,
+    base_dir: &Path,
+) -> Result<ExtendedPointMass, String> {
+    let aero: Option<Aero> = match fields.has_section("aero") {
+        true => Some(aero(fields)?),
+        false => None,
+    };
+    let (drag_factor, downforce_factor) = match &aero {
+        Some(a) => (
+            a.drag_factor(&AeroState::default()),
+            a.downforce_factor(&AeroState::default()),
+        ),
+        None => (0.0, 0.0),
+    };
+    let parameters = ExtendedPointMassParameters {
+        mass: fields.positive("mass", "mass", "kg")?,
+        drag_factor,
+        downforce_factor,
+        rolling_resistance: fields.non_negative("tyre", "rolling_resistance", "-")?,
+        max_power: fields.positive("powertrain", "max_power", "W")?,
+        mu_longitudinal: fields.positive("tyre", "mu_longitudinal", "-")?,
+        mu_lateral: fields.positive("tyre", "mu_lateral", "-")?,
+    };
+
+    let mut model: ExtendedPointMass = ExtendedPointMass::new(name, parameters);
+    model.aero = aero;
+    if fields.has_field("powertrain", "motor") {
+        let wheel_radius: f64 = fields.positive("geometry", "wheel_radius", "m")?;
+        model.powertrain = Some(powertrain(fields, base_dir, wheel_radius)?);
+    }
+    return Ok(model);
+}
+
+fn aero(fields: &mut Fields) -> Result<Aero, String> {
+    let mut aero: Aero = Aero::new(
+        fields.positive("aero", "air_density", "kg/m^3")?,
+        fields.positive("aero", "frontal_area", "m^2")?,
+        fields.non_negative("aero", "drag_coefficient", "-")?,
+        fields.number("aero", "lift_coefficient", "-")?,
+        fields.fraction("aero", "balance")?,
+    );
+    if fields.has_field("aero", "drs_drag_reduction") {
+        aero.drs = Some(Drs {
+            drag_reduction: fields.fraction("aero", "drs_drag_reduction")?,
+            downforce_reduction: fields.fraction("aero", "drs_downforce_reduction")?,
+            balance_shift: fields.number("aero", "drs_balance_shift", "-")?,
+        });
+    }
+    return Ok(aero);
+}
+
+fn powertrain(
+    fields: &mut Fields,
+    base_dir: &Path,
+    wheel_radius: f64,
+) -> Result<Powertrain, String> {
+    let kind: MotorKind = match fields.text("powertrain", "motor")?.as_str() {
+        "combustion" => MotorKind::Combustion,
+        "electric" => MotorKind::Electric,
+        other => {
+            return Err(format!(
+                "[powertrain] motor must be combustion or electric, got '{}'",
+                other
+            ))
+        }
+    };
+    let torque_map: TorqueMap = match fields.has_field("powertrain", "torque_map") {
+        true => {
+            let path: String = fields.text("powertrain", "torque_map")?;
+            TorqueMap::from_csv_str(&read_relative(base_dir, &path)?)
+                .map_err(|e| format!("torque map {}: {}", path, e))?
+        }
+        false => TorqueMap::new(
+            fields.numbers("powertrain", "speeds", "rpm")?,
+            fields.numbers("powertrain", "torques", "Nm")?,
+        )
+        .map_err(|e| format!("[powertrain] torque map: {}", e))?,
+    };
+    return Powertrain::new(
+        kind,
+        torque_map,
+        fields.numbers("powertrain", "gear_ratios", "-")?,
+        fields.positive("powertrain", "final_drive", "-")?,
+        fields.fraction("powertrain", "efficiency")?,
+        wheel_radius,
+    )
+    .map_err(|e| format!("[powertrain] {}", e));
+}
+
+// Front and rear tyre laws from the [tyre] section
+fn tyres(fields: &mut Fields, base_dir: &Path) -> Result<AxleTyres, String> {
+    return match fields.text("tyre", "kind")?.as_str() {
+        "linear" => Ok((
+            Box::new(LinearTyre::new(fields.positive(
+                "tyre",
+                "front_cornering_stiffness",
+                "N/rad",
+            )?)),
+            Box::new(LinearTyre::new(fields.positive(
+                "tyre",
+                "rear_cornering_stiffness",
+                "N/rad",
+            )?)),
+        )),
+        "saturating" => {
+            let mu: f64 = fields.positive("tyre", "mu", "-")?;
+            Ok((
+                Box::new(SaturatingTyre::new(
+                    fields.positive("tyre", "front_cornering_stiffness", "N/rad")?,
+                    mu,
+                )),
+                Box::new(SaturatingTyre::new(
+                    fields.positive("tyre", "rear_cornering_stiffness", "N/rad")?,
+                    mu,
+                )),
+            ))
+        }
+        "pacejka" => {
+            let mut load = |key: &str| -> Result<Box<dyn TyreLaw>, String> {
+                let path: String = fields.text("tyre", key)?;
+                let tyre: MagicFormula =
+                    MagicFormula::from_tir_str(&read_relative(base_dir, &path)?)
+                        .map_err(|e| format!("tyre file {}: {}", path, e))?;
+                return Ok(Box::new(tyre));
+            };
+            Ok((load("front_file")?, load("rear_file")?))
+        }
+        other => Err(format!(
+            "[tyre] kind must be linear, saturating or pacejka, got '{}'",
+            other
+        )),
+    };
+}
+
+fn read_relative(base_dir: &Path, path: &str) -> Result<String, String> {
+    let full_path = base_dir.join(path);
+    return std::fs::read_to_string(&full_path)
+        .map_err(|e| format!("failed to read {}: {}", full_path.display(), e));
+}
+
+// FIELDS IMPLEMENTATION ++++++++++++++++++++++++++
+impl Fields {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut entries: Vec<(String, String, Value)> = Vec::new();
+        let mut section: String = String::new();
+
+        for (i, raw_line) in text.lines().enumerate() {
+            let line: &str = strip_comment(raw_line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') {
+                section = match line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                    Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+                    _ => return Err(format!("line {}: invalid section header", i + 1)),
+                };
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((k, v)) => (k.trim().to_string(), v.trim()),
+                None => return Err(format!("line {}: expected key = value", i + 1)),
+            };
+            if entries.iter().any(|(s, k, _)| *s == section && *k == key) {
+                return Err(format!(
+                    "line {}: duplicate field {}",
+                    i + 1,
+                    describe(&section, &key)
+                ));
+            }
+            let value: Value = parse_value(value).map_err(|e| format!("line {}: {}", i + 1, e))?;
+            entries.push((section.clone(), key, value));
+        }
+
+        let used: Vec<bool> = vec![false; entries.len()];
+        return Ok(Fields { entries, used });
+    }
+
+    fn has_section(&self, section: &str) -> bool {
+        return self.entries.iter().any(|(s, _, _)| s == section);
+    }
+
+    fn has_field(&self, section: &str, key: &str) -> bool {
+        return self
+            .entries
+            .iter()
+            .any(|(s, k, _)| s == section && k == key);
+    }
+
+    fn get(&mut self, section: &str, key: &str, unit: &str) -> Result<Value, String> {
+        for (i, (s, k, value)) in self.entries.iter().enumerate() {
+            if s == section && k == key {
+                self.used[i] = true;
+                return Ok(value.clone());
+            }
+        }
+        return Err(format!(
+            "missing field {} [{}]",
+            describe(section, key),
+            unit
+        ));
+    }
+
+    fn text(&mut self, section: &str, key: &str) -> Result<String, String> {
+        return match self.get(section, key, "text")? {
+            Value::Text(text) => Ok(text),
+            _ => Err(format!(
+                "{} must be a quoted string",
+                describe(section, key)
+            )),
+        };
+    }
+
+    fn number(&mut self, section: &str, key: &str, unit: &str) -> Result<f64, String> {
+        return match self.get(section, key, unit)? {
+            Value::Number(value) => Ok(value),
+            _ => Err(format!(
+                "{} [{}] must be a number",
+                describe(section, key),
+                unit
+            )),
+        };
+    }
+
+    fn numbers(&mut self, section: &str, key: &str, unit: &str) -> Result<Vec<f64>, String> {
+        return match self.get(section, key, unit)? {
+            Value::Numbers(values) => Ok(values),
+            _ => Err(format!(
+                "{} [{}] must be an array of numbers",
+                describe(section, key),
+                unit
+            )),
+        };
+    }
+
+    fn positive(&mut self, section: &str, key: &str, unit: &str) -> Result<f64, String> {
+        let value: f64 = self.number(section, key, unit)?;
+        if value <= 0.0 {
+            return Err(format!(
+                "{} [{}] must be positive, got {}",
+                describe(section, key),
+                unit,
+                value
+            ));
+        }
+        return Ok(value);
+    }
+
+    fn non_negative(&mut self, section: &str, key: &str, unit: &str) -> Result<f64, String> {
+        let value: f64 = self.number(section, key, unit)?;
+        if value < 0.0 {
+            return Err(format!(
+                "{} [{}] must not be negative, got {}",
+                describe(section, key),
+                unit,
+                value
+            ));
+        }
+        return Ok(value);
+    }
+
+    fn fraction(&mut self, section: &str, key: &str) -> Result<f64, String> {
+        let value: f64 = self.number(section, key, "-")?;
+        if !(0.0..=1.0).contains(&value) {
+            return Err(format!(
+                "{} [-] must be in [0, 1], got {}",
+                describe(section, key),
+                value
+            ));
+        }
+        return Ok(value);
+    }
+
+    fn check_all_used(&self, model: &str) -> Result<(), String> {
+        for ((section, key, _), used) in self.entries.iter().zip(self.used.iter()) {
+            if !used {
+                return Err(format!(
+                    "unknown field {} for a {} model",
+                    describe(section, key),
+                    model
+                ));
+            }
+        }
+        return Ok(());
+    }
+}
+
+fn describe(section: &str, key: &str) -> String {
+    return match section.is_empty() {
+        true => key.to_string(),
+        false => format!("{}.{}", section, key),
+    };
+}
+
+// Remove a # comment that is not inside a string
+fn strip_comment(line: &str) -> &str {
+    let mut in_string: bool = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    return line;
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    if let Some(inner) = text.strip_prefix('"') {
+        return match inner.strip_suffix('"') {
+            Some(s) if !s.contains('"') => Ok(Value::Text(s.to_string())),
+            _ => Err(format!("invalid string {}", text)),
+        };
+    }
+    if let Some(inner) = text.strip_prefix('[') {
+        let inner: &str = match inner.strip_suffix(']') {
+            Some(s) => s,
+            None => return Err(format!("unterminated array {}", text)),
+        };
+        let mut values: Vec<f64> = Vec::new();
+        for item in inner.split(',').map(|s| s.trim()) {
+            if item.is_empty() {
+                continue;
+            }
+            values.push(parse_number(item)?);
+        }
+        return Ok(Value::Numbers(values));
+    }
+    return Ok(Value::Number(parse_number(text)?));
+}
+
+fn parse_number(text: &str) -> Result<f64, String> {
+    return match text.replace('_', "").parse::<f64>() {
+        Ok(v) if v.is_finite() => Ok(v),
+        _ => Err(format!("invalid number {}", text)),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOUBLE_TRACK: &str = include_str!("../vehicles/formula_student.toml");
+
+    fn parse(text: &str) -> Result<Vehicle, String> {
+        return Vehicle::from_toml_str(text, Path::new("vehicles"));
+    }
+
+    #[test]
+    fn test_point_mass() {
+        let vehicle: Vehicle = parse(include_str!("../vehicles/point_mass.toml")).unwrap();
+        assert_eq!(vehicle.name(), "PointMass1");
+        assert_eq!(vehicle.n_x(), 4);
+        let dx = vehicle.fun(&vec![0.0, 0.0, 1.0, 0.0], &vec![1.0, 0.0], 0.0);
+        assert_eq!(dx, vec![1.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_kinematic_bicycle() {
+        let text: &str = "
+            name = \"Kart\"
+            model = \"kinematic_bicycle\"
+
+            [geometry]
+            wheelbase = 1.05
+            rear_to_cg = 0.45
+        ";
+        let vehicle: Vehicle = parse(text).unwrap();
+        let model: &KinematicBicycle = match &vehicle {
+            Vehicle::KinematicBicycle(m) => m,
+            _ => panic!("expected a kinematic bicycle model"),
+        };
+        assert_eq!(model.wheelbase, 1.05);
+        assert_eq!(model.rear_to_cg, 0.45);
+        assert_eq!(vehicle.state_names(), vec!["x", "y", "psi", "v"]);
+
+        let behind: String = text.replace("0.45", "1.5");
+        assert!(parse(&behind).err().unwrap().contains("rear_to_cg"));
+    }
+
+    #[test]
+    fn test_double_track() {
+        let vehicle: Vehicle = parse(DOUBLE_TRACK).unwrap();
+        let model: &DoubleTrack = match &vehicle {
+            Vehicle::DoubleTrack(m) => m,
+            _ => panic!("expected a double track model"),
+        };
+        assert_eq!(model.parameters.mass, 280.0);
+        assert_eq!(model.parameters.front_roll_share, 0.55);
+        assert_eq!(vehicle.n_u(), 3);
+    }
+
+    #[test]
+    fn test_extended_point_mass_with_aero_and_powertrain() {
+        let text: &str = "
+            name = \"EV\" # Electric car
+            model = \"extended_point_mass\"
+
+            [mass]
+            mass = 1_200.0
+
+            [geometry]
+            wheel_radius = 0.33
+
+            [tyre]
+            rolling_resistance = 0.012
+            mu_longitudinal = 1.4
+            mu_lateral = 1.5
+
+            [aero]
+            air_density = 1.2
+            frontal_area = 2.0
+            drag_coefficient = 0.5
+            lift_coefficient = 1.0
+            balance = 0.45
+            drs_drag_reduction = 0.2
+            drs_downforce_reduction = 0.3
+            drs_balance_shift = 0.0
+
+            [powertrain]
+            max_power = 200e3
+            motor = \"electric\"
+            speeds = [0, 6000, 12000]
+            torques = [320, 320, 150]
+            gear_ratios = [1.0]
+            final_drive = 9.0
+            efficiency = 0.92
+        ";
+        let model: ExtendedPointMass = match parse(text).unwrap() {
+            Vehicle::ExtendedPointMass(m) => m,
+            _ => panic!("expected an extended point mass model"),
+        };
+        assert!((model.parameters.drag_factor - 0.6).abs() < 1e-12);
+        assert!((model.parameters.downforce_factor - 1.2).abs() < 1e-12);
+        assert!(model.aero.unwrap().drs.is_some());
+        assert_eq!(model.powertrain.unwrap().final_drive, 9.0);
+    }
+
+    #[test]
+    fn test_errors() {
+        let missing: String = DOUBLE_TRACK.replace("yaw_inertia", "# yaw_inertia");
+        assert_eq!(
+            parse(&missing).err().unwrap(),
+            "missing field inertia.yaw_inertia [kg m^2]"
+        );
+
+        let negative: String = DOUBLE_TRACK.replace("mass = 280.0", "mass = -280.0");
+        assert!(parse(&negative)
+            .err()
+            .unwrap()
+            .contains("mass.mass [kg] must be positive"));
+
+        let typo: String = DOUBLE_TRACK.replace("cg_height", "cg_hieght");
+        assert!(parse(&typo).err().unwrap().contains("geometry.cg_height"));
+
+        let unknown: String = format!("{}\n[aero]\nair_density = 1.2\n", DOUBLE_TRACK);
+        assert_eq!(
+            parse(&unknown).err().unwrap(),
+            "unknown field aero.air_density for a double_track model"
+        );
+
+        let model: String = DOUBLE_TRACK.replace("double_track", "triple_track");
+        assert!(parse(&model)
+            .err()
+            .unwrap()
+            .starts_with("unknown model 'triple_track'"));
+
+        assert!(parse("name = \"A\"\nmodel = point_mass\n").is_err());
+        assert!(parse("name = \"A\"\nname = \"B\"\n")
+            .err()
+            .unwrap()
+            .contains("duplicate"));
+        assert!(parse("[mass\n").err().unwrap().contains("line 1"));
+    }
+}